@@ -0,0 +1,365 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use crate::backend::Backend;
+use crate::frame::RespFrame;
+
+/// Tries to serve a blocked client from `key`, returning its reply when there was data.
+pub type ServeFn = Box<dyn Fn(&Backend, &str) -> Option<RespFrame> + Send + Sync>;
+
+/// Puts back what a [`ServeFn`] took from `key` for a reply that never reached its client.
+pub type RestoreFn = Box<dyn Fn(&Backend, &str, RespFrame) + Send + Sync>;
+
+/// Clients parked by blocking commands, queued FIFO per key.
+///
+/// Writers call [`Backend::signal_key_ready`] after adding data to a key; like redis' ready
+/// keys list, the queued clients are then served in arrival order.
+#[derive(Default)]
+pub struct BlockingState {
+    waiters: Mutex<HashMap<String, VecDeque<Arc<Waiter>>>>,
+    ready: Mutex<VecDeque<String>>,
+    serving: Mutex<()>,
+    next_id: AtomicU64,
+}
+
+struct Waiter {
+    id: u64,
+    keys: Vec<String>,
+    serve: ServeFn,
+    restore: Option<RestoreFn>,
+    tx: Mutex<Option<oneshot::Sender<(String, RespFrame)>>>,
+}
+
+/// Keeps a waiter registered while its client is parked and unregisters it on drop, which
+/// covers replies, timeouts and dropped connections alike.
+struct WaiterGuard<'a> {
+    backend: &'a Backend,
+    waiter: Arc<Waiter>,
+    /// Owned by the guard so that it outlives the registration: a reply is either received
+    /// or still here to be restored when the client goes away.
+    rx: oneshot::Receiver<(String, RespFrame)>,
+}
+
+impl Backend {
+    /// Serves `keys` in order with `serve`, parking the caller until another client adds data
+    /// to one of them or `timeout` elapses. `None` means the wait timed out.
+    ///
+    /// When the caller is dropped after being served, e.g. on a closed connection, `restore`
+    /// gets the unsent reply so that popped data isn't lost.
+    pub async fn block_on(
+        &self,
+        keys: Vec<String>,
+        timeout: Option<Duration>,
+        serve: ServeFn,
+        restore: Option<RestoreFn>,
+    ) -> Option<RespFrame> {
+        let mut guard = {
+            // parked clients are served by writers, which already hold the lock
+            let _shared = self.exec_lock.read().await;
+            for key in &keys {
+                if let Some(frame) = serve(self, key) {
+                    return Some(frame);
+                }
+            }
+
+            let (tx, rx) = oneshot::channel();
+            let waiter = Arc::new(Waiter {
+                id: self.blocking.next_id.fetch_add(1, Ordering::Relaxed),
+                keys,
+                serve,
+                restore,
+                tx: Mutex::new(Some(tx)),
+            });
+            let guard = WaiterGuard::register(self, waiter, rx);
+            // data may have arrived between the first attempt and registering
+            for key in &guard.waiter.keys {
+                self.signal_key_ready(key);
+            }
+            guard
+        };
+
+        let received = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, &mut guard.rx).await.ok(),
+            None => Some((&mut guard.rx).await),
+        };
+        match received {
+            Some(Ok((_, frame))) => Some(frame),
+            _ => {
+                // a reply may race with the timeout, it must not be lost
+                guard.waiter.cancel();
+                guard.rx.try_recv().ok().map(|(_, frame)| frame)
+            }
+        }
+    }
+
+    /// Marks `key` as having new data so that clients blocked on it get served.
+    pub fn signal_key_ready(&self, key: &str) {
+        let state = &self.blocking;
+        if !state.waiters.lock().unwrap().contains_key(key) {
+            return;
+        }
+        state.ready.lock().unwrap().push_back(key.to_string());
+
+        loop {
+            // serving can push to other keys, which are queued and handled by the same loop
+            let Ok(serving) = state.serving.try_lock() else {
+                return;
+            };
+            loop {
+                let key = state.ready.lock().unwrap().pop_front();
+                match key {
+                    Some(key) => self.serve_waiters(&key),
+                    None => break,
+                }
+            }
+            drop(serving);
+            if state.ready.lock().unwrap().is_empty() {
+                return;
+            }
+        }
+    }
+
+    pub fn blocked_clients(&self) -> usize {
+        let waiters = self.blocking.waiters.lock().unwrap();
+        let mut ids: Vec<u64> = waiters.values().flatten().map(|w| w.id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.len()
+    }
+
+    fn serve_waiters(&self, key: &str) {
+        // a client that can't be served doesn't hold back the ones queued behind it, e.g.
+        // readers of different consumer groups see different data on the same stream
+        let queue: Vec<Arc<Waiter>> = {
+            let waiters = self.blocking.waiters.lock().unwrap();
+            match waiters.get(key) {
+                Some(queue) => queue.iter().cloned().collect(),
+                None => return,
+            }
+        };
+        for waiter in queue {
+            if waiter.try_serve(self, key) {
+                self.unregister(&waiter);
+            }
+        }
+    }
+
+    fn unregister(&self, waiter: &Waiter) {
+        let mut waiters = self.blocking.waiters.lock().unwrap();
+        for key in &waiter.keys {
+            if let Some(queue) = waiters.get_mut(key) {
+                queue.retain(|w| w.id != waiter.id);
+                if queue.is_empty() {
+                    waiters.remove(key);
+                }
+            }
+        }
+    }
+}
+
+impl Waiter {
+    /// Returns false only when the client is still waiting and `key` had nothing to give.
+    fn try_serve(&self, backend: &Backend, key: &str) -> bool {
+        let mut tx = self.tx.lock().unwrap();
+        if tx.as_ref().is_none_or(|tx| tx.is_closed()) {
+            return true;
+        }
+        match (self.serve)(backend, key) {
+            Some(frame) => {
+                // the guard drops the receiver only after cancelling, which waits for this lock
+                if let Some(tx) = tx.take() {
+                    let _ = tx.send((key.to_string(), frame));
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn cancel(&self) {
+        self.tx.lock().unwrap().take();
+    }
+
+    /// Hands a reply that was served but never received to the restore hook. Like any write
+    /// it runs under the execution lock, later on when a transaction holds it.
+    fn restore(self: &Arc<Self>, backend: &Backend, key: String, frame: RespFrame) {
+        let Some(restore) = &self.restore else {
+            return;
+        };
+        if let Ok(_shared) = backend.exec_lock.try_read() {
+            restore(backend, &key, frame);
+            return;
+        }
+        let (backend, waiter) = (backend.clone(), self.clone());
+        tokio::spawn(async move {
+            let _shared = backend.exec_lock.read().await;
+            if let Some(restore) = &waiter.restore {
+                restore(&backend, &key, frame);
+            }
+        });
+    }
+}
+
+impl<'a> WaiterGuard<'a> {
+    fn register(
+        backend: &'a Backend,
+        waiter: Arc<Waiter>,
+        rx: oneshot::Receiver<(String, RespFrame)>,
+    ) -> Self {
+        let mut waiters = backend.blocking.waiters.lock().unwrap();
+        for key in &waiter.keys {
+            waiters
+                .entry(key.clone())
+                .or_default()
+                .push_back(waiter.clone());
+        }
+        drop(waiters);
+        Self {
+            backend,
+            waiter,
+            rx,
+        }
+    }
+}
+
+impl Drop for WaiterGuard<'_> {
+    fn drop(&mut self) {
+        self.waiter.cancel();
+        self.backend.unregister(&self.waiter);
+        if let Ok((key, frame)) = self.rx.try_recv() {
+            self.waiter.restore(self.backend, key, frame);
+        }
+    }
+}
+
+impl fmt::Debug for BlockingState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingState")
+            .field("keys", &self.waiters.lock().unwrap().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::backend::{Backend, ListSide};
+    use crate::frame::RespFrame;
+
+    use super::{RestoreFn, ServeFn};
+
+    fn pop_left() -> ServeFn {
+        Box::new(|backend, key| {
+            backend
+                .list_pop(key, ListSide::Left, 1)
+                .and_then(|mut v| v.pop())
+        })
+    }
+
+    fn push_left() -> RestoreFn {
+        Box::new(|backend, key, frame| {
+            backend.list_push(key, ListSide::Left, vec![frame]);
+        })
+    }
+
+    #[tokio::test]
+    async fn test_block_on_times_out() {
+        let backend = Backend::new();
+        let ret = backend
+            .block_on(
+                vec!["queue".to_string()],
+                Some(Duration::from_millis(20)),
+                pop_left(),
+                None,
+            )
+            .await;
+        assert_eq!(ret, None);
+        assert_eq!(backend.blocked_clients(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_block_on_serves_clients_in_order() {
+        let backend = Backend::new();
+        let first = tokio::spawn({
+            let backend = backend.clone();
+            async move {
+                backend
+                    .block_on(vec!["queue".to_string()], None, pop_left(), None)
+                    .await
+            }
+        });
+        while backend.blocked_clients() < 1 {
+            tokio::task::yield_now().await;
+        }
+        let second = tokio::spawn({
+            let backend = backend.clone();
+            async move {
+                backend
+                    .block_on(vec!["queue".to_string()], None, pop_left(), None)
+                    .await
+            }
+        });
+        while backend.blocked_clients() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        backend.list_push("queue", ListSide::Right, vec![b"a".into(), b"b".into()]);
+        assert_eq!(first.await.unwrap(), Some(RespFrame::from(b"a")));
+        assert_eq!(second.await.unwrap(), Some(RespFrame::from(b"b")));
+        assert!(!backend.list.contains_key("queue"));
+    }
+
+    #[tokio::test]
+    async fn test_block_on_disconnect_during_push() {
+        let backend = Backend::new();
+        let client = |backend: &Backend| {
+            let backend = backend.clone();
+            tokio::spawn(async move {
+                backend
+                    .block_on(
+                        vec!["queue".to_string()],
+                        None,
+                        pop_left(),
+                        Some(push_left()),
+                    )
+                    .await
+            })
+        };
+
+        // gone before the push: not served at all
+        let gone = client(&backend);
+        while backend.blocked_clients() < 1 {
+            tokio::task::yield_now().await;
+        }
+        let waiting = client(&backend);
+        while backend.blocked_clients() < 2 {
+            tokio::task::yield_now().await;
+        }
+        gone.abort();
+        assert!(gone.await.unwrap_err().is_cancelled());
+        backend.list_push("queue", ListSide::Right, vec![b"a".into()]);
+        assert_eq!(waiting.await.unwrap(), Some(RespFrame::from(b"a")));
+
+        // gone after being served: the element is pushed back and goes to the next client
+        let gone = client(&backend);
+        while backend.blocked_clients() < 1 {
+            tokio::task::yield_now().await;
+        }
+        let waiting = client(&backend);
+        while backend.blocked_clients() < 2 {
+            tokio::task::yield_now().await;
+        }
+        backend.list_push("queue", ListSide::Right, vec![b"b".into()]);
+        gone.abort();
+        assert!(gone.await.unwrap_err().is_cancelled());
+        assert_eq!(waiting.await.unwrap(), Some(RespFrame::from(b"b")));
+        assert!(!backend.list.contains_key("queue"));
+        assert_eq!(backend.blocked_clients(), 0);
+    }
+}
@@ -0,0 +1,178 @@
+use std::f64::consts::LN_2;
+
+use crate::backend::hyperloglog::murmur_hash64a;
+
+pub const BLOOM_DEFAULT_ERROR_RATE: f64 = 0.01;
+pub const BLOOM_DEFAULT_CAPACITY: u64 = 100;
+pub const BLOOM_DEFAULT_EXPANSION: u64 = 2;
+/// Every new sub-filter halves the error rate so the compound rate stays bounded.
+const BLOOM_TIGHTENING_RATIO: f64 = 0.5;
+/// The most bits a sub-filter may take, 512MB worth.
+pub const BLOOM_MAX_BITS: u64 = 1 << 32;
+
+/// A scalable bloom filter: once a sub-filter holds as many items as it was sized for, a
+/// larger one with a tighter error rate is stacked on top.
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    filters: Vec<SubFilter>,
+    /// `None` when the filter must not grow
+    expansion: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+struct SubFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    hashes: u32,
+    capacity: u64,
+    error_rate: f64,
+    items: u64,
+}
+
+impl BloomFilter {
+    /// `expansion` is how much larger every new sub-filter is, `None` to never grow.
+    pub fn new(error_rate: f64, capacity: u64, expansion: Option<u64>) -> Self {
+        Self {
+            filters: vec![SubFilter::new(error_rate, capacity)],
+            expansion,
+        }
+    }
+
+    /// Adds an item, returns false when it may have been added before. `Err` when the filter is
+    /// full and can't grow.
+    pub fn add(&mut self, item: &[u8]) -> Result<bool, String> {
+        let hash = hash_item(item);
+        if self.contains_hash(hash) {
+            return Ok(false);
+        }
+        let last = self.filters.last().filter(|f| f.items < f.capacity);
+        if last.is_none() {
+            let Some(expansion) = self.expansion else {
+                return Err("non scaling filter is full".to_string());
+            };
+            let last = &self.filters[self.filters.len() - 1];
+            let error_rate = last.error_rate * BLOOM_TIGHTENING_RATIO;
+            let capacity = last.capacity.saturating_mul(expansion);
+            if bloom_bits(error_rate, capacity).is_none() {
+                return Err("Maximum expansion reached".to_string());
+            }
+            self.filters.push(SubFilter::new(error_rate, capacity));
+        }
+        let len = self.filters.len();
+        self.filters[len - 1].insert(hash);
+        Ok(true)
+    }
+
+    pub fn contains(&self, item: &[u8]) -> bool {
+        self.contains_hash(hash_item(item))
+    }
+
+    /// The number of items the filter can hold before growing.
+    pub fn capacity(&self) -> u64 {
+        self.filters.iter().map(|f| f.capacity).sum()
+    }
+
+    /// The memory taken by the bit arrays in bytes.
+    pub fn size(&self) -> usize {
+        self.filters.iter().map(|f| f.bits.len() * 8).sum()
+    }
+
+    pub fn num_filters(&self) -> usize {
+        self.filters.len()
+    }
+
+    pub fn items(&self) -> u64 {
+        self.filters.iter().map(|f| f.items).sum()
+    }
+
+    pub fn expansion(&self) -> Option<u64> {
+        self.expansion
+    }
+
+    fn contains_hash(&self, hash: (u64, u64)) -> bool {
+        self.filters.iter().any(|f| f.contains(hash))
+    }
+}
+
+/// The number of bits a sub-filter for `capacity` items at `error_rate` takes, `None` past
+/// [`BLOOM_MAX_BITS`].
+pub fn bloom_bits(error_rate: f64, capacity: u64) -> Option<u64> {
+    let bits = (capacity as f64 * bits_per_item(error_rate)).ceil();
+    (bits <= BLOOM_MAX_BITS as f64).then(|| (bits as u64).max(64))
+}
+
+fn bits_per_item(error_rate: f64) -> f64 {
+    -error_rate.ln() / (LN_2 * LN_2)
+}
+
+impl SubFilter {
+    fn new(error_rate: f64, capacity: u64) -> Self {
+        let bits_per_item = bits_per_item(error_rate);
+        let num_bits = ((capacity as f64 * bits_per_item).ceil() as u64).max(64);
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            hashes: (bits_per_item * LN_2).ceil().max(1.0) as u32,
+            capacity,
+            error_rate,
+            items: 0,
+        }
+    }
+
+    fn insert(&mut self, hash: (u64, u64)) {
+        for bit in self.positions(hash).collect::<Vec<_>>() {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+        self.items += 1;
+    }
+
+    fn contains(&self, hash: (u64, u64)) -> bool {
+        self.positions(hash)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Double hashing derives every probe from two hashes.
+    fn positions(&self, (h1, h2): (u64, u64)) -> impl Iterator<Item = u64> + '_ {
+        (0..self.hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+}
+
+fn hash_item(item: &[u8]) -> (u64, u64) {
+    let h1 = murmur_hash64a(item, 0xc6a4a7935bd1e995);
+    let h2 = murmur_hash64a(item, h1);
+    (h1, h2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter_scales() {
+        let mut bloom = BloomFilter::new(0.01, 100, Some(2));
+        for i in 0..1000 {
+            bloom.add(format!("item:{}", i).as_bytes()).unwrap();
+        }
+        assert!((0..1000).all(|i| bloom.contains(format!("item:{}", i).as_bytes())));
+        assert!(bloom.num_filters() > 1);
+        assert!(bloom.capacity() >= 1000);
+        let false_positives = (0..10_000)
+            .filter(|i| bloom.contains(format!("other:{}", i).as_bytes()))
+            .count();
+        assert!(false_positives < 200, "{}", false_positives);
+        assert_eq!(bloom.add(b"item:1"), Ok(false));
+
+        let mut bloom = BloomFilter::new(0.01, 2, None);
+        bloom.add(b"a").unwrap();
+        bloom.add(b"b").unwrap();
+        assert!(bloom.add(b"c").is_err());
+
+        // the next sub-filter would be too large to allocate
+        let mut bloom = BloomFilter::new(0.01, 1, Some(u64::MAX));
+        bloom.add(b"a").unwrap();
+        assert_eq!(
+            bloom.add(b"b"),
+            Err("Maximum expansion reached".to_string())
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::backend::hyperloglog::murmur_hash64a;
+
+/// The most counters of a sketch, 512MB worth.
+pub const CMS_MAX_COUNTERS: usize = 1 << 26;
+
+/// A Count-Min sketch: `depth` rows of `width` counters, each row indexed by its own hash. An
+/// item's count is the smallest of its counters, which may overestimate but never
+/// underestimates.
+#[derive(Debug, Clone)]
+pub struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    counters: Vec<u64>,
+    count: u64,
+}
+
+impl CountMinSketch {
+    pub fn new(width: usize, depth: usize) -> Self {
+        Self {
+            width,
+            depth,
+            counters: vec![0; width * depth],
+            count: 0,
+        }
+    }
+
+    /// Sizes the sketch so that the overestimate stays below `error` times the total count
+    /// with a probability of `1 - probability`.
+    pub fn with_error(error: f64, probability: f64) -> Self {
+        let (width, depth) = Self::dimensions(error, probability);
+        Self::new(width, depth)
+    }
+
+    /// The width and depth [`CountMinSketch::with_error`] sizes the sketch with.
+    pub fn dimensions(error: f64, probability: f64) -> (usize, usize) {
+        let width = (2.0 / error).ceil() as usize;
+        let depth = (probability.ln() / 0.5f64.ln()).ceil().max(1.0) as usize;
+        (width, depth)
+    }
+
+    /// Adds `incr` to the item, returns its new count.
+    pub fn incr_by(&mut self, item: &[u8], incr: u64) -> u64 {
+        let mut min = u64::MAX;
+        for row in 0..self.depth {
+            let index = self.index(row, item);
+            let counter = &mut self.counters[index];
+            *counter = counter.saturating_add(incr);
+            min = min.min(*counter);
+        }
+        self.count = self.count.saturating_add(incr);
+        min
+    }
+
+    pub fn query(&self, item: &[u8]) -> u64 {
+        (0..self.depth)
+            .map(|row| self.counters[self.index(row, item)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The sum of all increments.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn index(&self, row: usize, item: &[u8]) -> usize {
+        row * self.width + (murmur_hash64a(item, row as u64) % self.width as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_min_sketch() {
+        let mut cms = CountMinSketch::with_error(0.001, 0.01);
+        assert_eq!((cms.width(), cms.depth()), (2000, 7));
+        for i in 0..1000u64 {
+            cms.incr_by(format!("item:{}", i % 10).as_bytes(), 1);
+        }
+        assert_eq!(cms.incr_by(b"item:1", 5), 105);
+        assert_eq!(cms.query(b"item:1"), 105);
+        assert_eq!(cms.query(b"missing"), 0);
+        assert_eq!(cms.count(), 1005);
+    }
+}
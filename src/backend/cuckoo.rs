@@ -0,0 +1,259 @@
+use rand::Rng;
+
+use crate::backend::hyperloglog::murmur_hash64a;
+
+pub const CUCKOO_DEFAULT_CAPACITY: u64 = 1024;
+pub const CUCKOO_DEFAULT_BUCKET_SIZE: usize = 2;
+pub const CUCKOO_DEFAULT_MAX_ITERATIONS: usize = 20;
+pub const CUCKOO_DEFAULT_EXPANSION: u64 = 1;
+/// The most slots a sub-filter may take, 512MB worth.
+pub const CUCKOO_MAX_SLOTS: u64 = 1 << 29;
+
+/// A cuckoo filter keeps an 8 bit fingerprint of every item in one of two candidate buckets,
+/// which unlike a bloom filter allows deleting items. When an insertion can't find room after
+/// `max_iterations` relocations a larger sub-filter is added.
+#[derive(Debug, Clone)]
+pub struct CuckooFilter {
+    filters: Vec<SubFilter>,
+    bucket_size: usize,
+    max_iterations: usize,
+    /// how much larger every new sub-filter is, 0 to never grow
+    expansion: u64,
+    items: u64,
+    deletes: u64,
+}
+
+#[derive(Debug, Clone)]
+struct SubFilter {
+    /// `num_buckets * bucket_size` slots, 0 marks an empty one
+    slots: Vec<u8>,
+    /// a power of two so that the alternate bucket can be found with a xor
+    num_buckets: u64,
+}
+
+impl CuckooFilter {
+    /// `capacity` and `bucket_size` must fit in [`CUCKOO_MAX_SLOTS`], see [`cuckoo_buckets`].
+    pub fn new(capacity: u64, bucket_size: usize, max_iterations: usize, expansion: u64) -> Self {
+        let num_buckets = cuckoo_buckets(capacity, bucket_size).expect("the filter is too large");
+        Self {
+            filters: vec![SubFilter::new(num_buckets, bucket_size)],
+            bucket_size,
+            max_iterations,
+            expansion,
+            items: 0,
+            deletes: 0,
+        }
+    }
+
+    /// Adds an item, the same one may be added several times. `Err` when the filter is full.
+    pub fn add(&mut self, item: &[u8]) -> Result<(), String> {
+        let (fp, hash) = fingerprint(item);
+        let inserted = self
+            .filters
+            .iter_mut()
+            .rev()
+            .any(|filter| filter.insert(fp, hash, self.bucket_size, self.max_iterations));
+        if !inserted {
+            if self.expansion == 0 {
+                return Err("Filter is full".to_string());
+            }
+            let last = &self.filters[self.filters.len() - 1];
+            let num_buckets = last
+                .num_buckets
+                .checked_mul(self.expansion)
+                .and_then(u64::checked_next_power_of_two)
+                .filter(|buckets| fits(*buckets, self.bucket_size))
+                .ok_or_else(|| "Maximum expansion reached".to_string())?;
+            let mut filter = SubFilter::new(num_buckets, self.bucket_size);
+            filter.insert(fp, hash, self.bucket_size, self.max_iterations);
+            self.filters.push(filter);
+        }
+        self.items += 1;
+        Ok(())
+    }
+
+    pub fn contains(&self, item: &[u8]) -> bool {
+        let (fp, hash) = fingerprint(item);
+        self.filters
+            .iter()
+            .any(|filter| filter.find(fp, hash, self.bucket_size).is_some())
+    }
+
+    /// Removes one copy of an item, returns whether one was found.
+    pub fn delete(&mut self, item: &[u8]) -> bool {
+        let (fp, hash) = fingerprint(item);
+        for filter in self.filters.iter_mut().rev() {
+            if let Some(slot) = filter.find(fp, hash, self.bucket_size) {
+                filter.slots[slot] = 0;
+                self.items -= 1;
+                self.deletes += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn items(&self) -> u64 {
+        self.items
+    }
+
+    pub fn deletes(&self) -> u64 {
+        self.deletes
+    }
+
+    pub fn num_buckets(&self) -> u64 {
+        self.filters.iter().map(|f| f.num_buckets).sum()
+    }
+
+    pub fn num_filters(&self) -> usize {
+        self.filters.len()
+    }
+
+    pub fn bucket_size(&self) -> usize {
+        self.bucket_size
+    }
+
+    pub fn max_iterations(&self) -> usize {
+        self.max_iterations
+    }
+
+    pub fn expansion(&self) -> u64 {
+        self.expansion
+    }
+
+    /// The memory taken by the slots in bytes.
+    pub fn size(&self) -> usize {
+        self.filters.iter().map(|f| f.slots.len()).sum()
+    }
+}
+
+/// The number of buckets of a sub-filter for `capacity` items, `None` when its slots would
+/// outgrow [`CUCKOO_MAX_SLOTS`].
+pub fn cuckoo_buckets(capacity: u64, bucket_size: usize) -> Option<u64> {
+    capacity
+        .div_ceil(bucket_size as u64)
+        .max(1)
+        .checked_next_power_of_two()
+        .filter(|buckets| fits(*buckets, bucket_size))
+}
+
+fn fits(num_buckets: u64, bucket_size: usize) -> bool {
+    num_buckets
+        .checked_mul(bucket_size as u64)
+        .is_some_and(|slots| slots <= CUCKOO_MAX_SLOTS)
+}
+
+impl SubFilter {
+    fn new(num_buckets: u64, bucket_size: usize) -> Self {
+        Self {
+            slots: vec![0; num_buckets as usize * bucket_size],
+            num_buckets,
+        }
+    }
+
+    fn buckets(&self, fp: u8, hash: u64) -> (u64, u64) {
+        let first = hash & (self.num_buckets - 1);
+        (first, self.alternate(first, fp))
+    }
+
+    fn alternate(&self, bucket: u64, fp: u8) -> u64 {
+        (bucket ^ murmur_hash64a(&[fp], 0)) & (self.num_buckets - 1)
+    }
+
+    fn find(&self, fp: u8, hash: u64, bucket_size: usize) -> Option<usize> {
+        let (first, second) = self.buckets(fp, hash);
+        [first, second].into_iter().find_map(|bucket| {
+            let start = bucket as usize * bucket_size;
+            (start..start + bucket_size).find(|slot| self.slots[*slot] == fp)
+        })
+    }
+
+    fn insert(&mut self, fp: u8, hash: u64, bucket_size: usize, max_iterations: usize) -> bool {
+        let (first, second) = self.buckets(fp, hash);
+        for bucket in [first, second] {
+            if self.insert_into(bucket, fp, bucket_size) {
+                return true;
+            }
+        }
+
+        // kick fingerprints around, undoing the moves if no room turns up
+        let mut rng = rand::thread_rng();
+        let mut bucket = if rng.gen() { first } else { second };
+        let mut fp = fp;
+        let mut moves = vec![];
+        for _ in 0..max_iterations {
+            let slot = bucket as usize * bucket_size + rng.gen_range(0..bucket_size);
+            let evicted = std::mem::replace(&mut self.slots[slot], fp);
+            moves.push((slot, evicted));
+            fp = evicted;
+            bucket = self.alternate(bucket, fp);
+            if self.insert_into(bucket, fp, bucket_size) {
+                return true;
+            }
+        }
+        for (slot, evicted) in moves.into_iter().rev() {
+            self.slots[slot] = evicted;
+        }
+        false
+    }
+
+    fn insert_into(&mut self, bucket: u64, fp: u8, bucket_size: usize) -> bool {
+        let start = bucket as usize * bucket_size;
+        match self.slots[start..start + bucket_size]
+            .iter_mut()
+            .find(|slot| **slot == 0)
+        {
+            Some(slot) => {
+                *slot = fp;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A fingerprint that is never 0, along with the hash that picks the first bucket.
+fn fingerprint(item: &[u8]) -> (u8, u64) {
+    let hash = murmur_hash64a(item, 0);
+    let fp = (hash >> 56) as u8;
+    (fp.max(1), hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cuckoo_filter_add_delete() {
+        let mut cuckoo = CuckooFilter::new(64, 2, 20, 1);
+        for i in 0..200 {
+            cuckoo.add(format!("item:{}", i).as_bytes()).unwrap();
+        }
+        assert!(cuckoo.num_filters() > 1);
+        assert!((0..200).all(|i| cuckoo.contains(format!("item:{}", i).as_bytes())));
+        assert_eq!(cuckoo.items(), 200);
+
+        let mut cuckoo = CuckooFilter::new(64, 2, 20, 1);
+        cuckoo.add(b"dup").unwrap();
+        cuckoo.add(b"dup").unwrap();
+        assert!(cuckoo.delete(b"dup"));
+        assert!(cuckoo.contains(b"dup"));
+        assert!(cuckoo.delete(b"dup"));
+        assert!(!cuckoo.delete(b"dup"));
+
+        let mut cuckoo = CuckooFilter::new(2, 1, 0, 0);
+        let results: Vec<bool> = (0..10)
+            .map(|i| cuckoo.add(format!("item:{}", i).as_bytes()).is_ok())
+            .collect();
+        assert!(results.contains(&false));
+
+        // the next sub-filter would be too large to allocate
+        let mut cuckoo = CuckooFilter::new(1, 1, 0, u64::MAX);
+        cuckoo.add(b"a").unwrap();
+        assert_eq!(
+            cuckoo.add(b"b"),
+            Err("Maximum expansion reached".to_string())
+        );
+        assert_eq!(cuckoo_buckets(u64::MAX, 1), None);
+    }
+}
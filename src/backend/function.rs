@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::array::RespArray;
+use crate::backend::Backend;
+use crate::bulk_string::BulkString;
+use crate::encode::RespEncode;
+use crate::frame::RespFrame;
+
+/// A library of functions loaded with FUNCTION LOAD.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionLibrary {
+    pub name: String,
+    /// the code, metadata line included, which registers the functions when it runs
+    pub code: String,
+    /// the flags of each function, by name
+    pub functions: BTreeMap<String, Vec<String>>,
+}
+
+/// How FUNCTION RESTORE treats the libraries that already exist.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestorePolicy {
+    /// fail when a library already exists
+    Append,
+    /// replace the libraries with the same name
+    Replace,
+    /// delete every library first
+    Flush,
+}
+
+/// The function libraries, by name. They are part of the server's state rather than of the
+/// dataset, so FLUSHALL keeps them.
+#[derive(Debug, Default)]
+pub struct FunctionState {
+    libraries: Mutex<BTreeMap<String, FunctionLibrary>>,
+    /// held by changes while they are saved, so that FCALL only waits on `libraries` for the
+    /// swap and the file is always written in the order the changes apply
+    updating: Mutex<()>,
+    /// where the libraries are saved after every change, set at startup
+    file: OnceLock<PathBuf>,
+}
+
+impl Backend {
+    /// Adds `library`, or replaces the one with the same name when `replace` is set. No two
+    /// libraries may register the same function.
+    pub fn function_load(&self, library: FunctionLibrary, replace: bool) -> Result<(), String> {
+        self.function_update(|libraries| insert_library(libraries, library, replace))
+    }
+
+    /// The code of the library registering `function` and the flags of the function.
+    pub fn function_find(&self, function: &str) -> Option<(String, Vec<String>)> {
+        let libraries = self.functions.libraries.lock().unwrap();
+        libraries.values().find_map(|library| {
+            let flags = library.functions.get(function)?;
+            Some((library.code.clone(), flags.clone()))
+        })
+    }
+
+    /// Removes a library with its functions, returns whether it existed.
+    pub fn function_delete(&self, library: &str) -> Result<bool, String> {
+        self.function_update(|libraries| Ok(libraries.remove(library).is_some()))
+    }
+
+    pub fn function_flush(&self) -> Result<(), String> {
+        self.function_update(|libraries| {
+            libraries.clear();
+            Ok(())
+        })
+    }
+
+    /// Every library, sorted by name.
+    pub fn function_libraries(&self) -> Vec<FunctionLibrary> {
+        let libraries = self.functions.libraries.lock().unwrap();
+        libraries.values().cloned().collect()
+    }
+
+    /// Adds dumped libraries all at once, or none of them when one can't be added.
+    pub fn function_restore(
+        &self,
+        restored: Vec<FunctionLibrary>,
+        policy: RestorePolicy,
+    ) -> Result<(), String> {
+        self.function_update(|libraries| {
+            if policy == RestorePolicy::Flush {
+                libraries.clear();
+            }
+            for library in restored {
+                insert_library(libraries, library, policy == RestorePolicy::Replace)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// The code of every library as the payload of FUNCTION DUMP, a RESP array.
+    pub fn function_dump(&self) -> Vec<u8> {
+        dump_payload(&self.functions.libraries.lock().unwrap())
+    }
+
+    /// Saves the libraries to `path` after every change from now on.
+    pub fn function_save_to(&self, path: PathBuf) {
+        let _ = self.functions.file.set(path);
+    }
+
+    /// Applies `update` to a copy of the libraries, which replaces them once it succeeded and
+    /// was saved. A library change is never lost when the server restarts.
+    fn function_update<T>(
+        &self,
+        update: impl FnOnce(&mut BTreeMap<String, FunctionLibrary>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let _updating = self.functions.updating.lock().unwrap();
+        let mut next = self.functions.libraries.lock().unwrap().clone();
+        let updated = update(&mut next)?;
+        if let Some(path) = self.functions.file.get() {
+            save(path, &dump_payload(&next))
+                .map_err(|e| format!("Failed to save the function libraries: {}", e))?;
+        }
+        *self.functions.libraries.lock().unwrap() = next;
+        Ok(updated)
+    }
+}
+
+fn dump_payload(libraries: &BTreeMap<String, FunctionLibrary>) -> Vec<u8> {
+    let codes = libraries
+        .values()
+        .map(|library| BulkString::new(library.code.as_str()).into())
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(codes).encode()
+}
+
+/// Writes a temporary file next to `path` and moves it over, so that a crash leaves either
+/// the old libraries or the new ones.
+fn save(path: &Path, payload: &[u8]) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, payload)?;
+    std::fs::rename(&tmp, path)
+}
+
+fn insert_library(
+    libraries: &mut BTreeMap<String, FunctionLibrary>,
+    library: FunctionLibrary,
+    replace: bool,
+) -> Result<(), String> {
+    if !replace && libraries.contains_key(&library.name) {
+        return Err(format!("Library '{}' already exists", library.name));
+    }
+    for other in libraries.values() {
+        if other.name == library.name {
+            continue;
+        }
+        if let Some(function) = library
+            .functions
+            .keys()
+            .find(|function| other.functions.contains_key(*function))
+        {
+            return Err(format!("Function {} already exists", function));
+        }
+    }
+    libraries.insert(library.name.clone(), library);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(name: &str, functions: &[&str]) -> FunctionLibrary {
+        FunctionLibrary {
+            name: name.to_string(),
+            code: format!("#!lua name={}", name),
+            functions: functions
+                .iter()
+                .map(|function| (function.to_string(), vec![]))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_function_libraries() {
+        let backend = Backend::new();
+        backend
+            .function_load(library("a", &["f", "g"]), false)
+            .unwrap();
+        assert!(backend.function_load(library("a", &["h"]), false).is_err());
+        assert!(backend.function_load(library("b", &["g"]), false).is_err());
+        backend.function_load(library("a", &["h"]), true).unwrap();
+        assert!(backend.function_find("f").is_none());
+        assert_eq!(
+            backend.function_find("h"),
+            Some(("#!lua name=a".to_string(), vec![]))
+        );
+
+        let restored = vec![library("b", &["g"]), library("c", &["h"])];
+        assert!(backend
+            .function_restore(restored.clone(), RestorePolicy::Append)
+            .is_err());
+        // nothing is restored when one library fails
+        assert!(backend.function_find("g").is_none());
+        backend
+            .function_restore(restored.clone(), RestorePolicy::Flush)
+            .unwrap();
+        assert_eq!(backend.function_libraries(), restored);
+
+        assert_eq!(backend.function_delete("b"), Ok(true));
+        assert_eq!(backend.function_delete("b"), Ok(false));
+        backend.function_flush().unwrap();
+        assert!(backend.function_libraries().is_empty());
+    }
+
+    #[test]
+    fn test_function_save() {
+        let backend = Backend::new();
+        let path = std::env::temp_dir().join(format!(
+            "simple-redis-functions-{}.resp",
+            std::process::id()
+        ));
+        backend.function_save_to(path.clone());
+        backend.function_load(library("a", &["f"]), false).unwrap();
+        backend.function_load(library("b", &["g"]), false).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), backend.function_dump());
+        backend.function_delete("a").unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            b"*1\r\n$12\r\n#!lua name=b\r\n"
+        );
+        // nothing changes when the libraries can't be saved
+        std::fs::remove_file(&path).unwrap();
+        std::fs::create_dir(&path).unwrap();
+        assert!(backend.function_flush().is_err());
+        assert_eq!(backend.function_libraries(), vec![library("b", &["g"])]);
+        std::fs::remove_dir(&path).unwrap();
+        std::fs::remove_file(path.with_extension("resp.tmp")).unwrap();
+    }
+
+    #[test]
+    fn test_function_save_concurrent() {
+        let backend = Backend::new();
+        let path = std::env::temp_dir().join(format!(
+            "simple-redis-functions-concurrent-{}.resp",
+            std::process::id()
+        ));
+        backend.function_save_to(path.clone());
+        let loaders: Vec<_> = (0..8)
+            .map(|i| {
+                let backend = backend.clone();
+                std::thread::spawn(move || {
+                    let (name, function) = (format!("lib{}", i), format!("f{}", i));
+                    backend
+                        .function_load(library(&name, &[&function]), false)
+                        .unwrap();
+                })
+            })
+            .collect();
+        for loader in loaders {
+            loader.join().unwrap();
+        }
+        // the last save holds every library
+        assert_eq!(backend.function_libraries().len(), 8);
+        assert_eq!(std::fs::read(&path).unwrap(), backend.function_dump());
+
+        // a change being saved doesn't hold back readers
+        let _updating = backend.functions.updating.lock().unwrap();
+        assert!(backend.function_find("f0").is_some());
+        assert_eq!(backend.function_libraries().len(), 8);
+        std::fs::remove_file(path).unwrap();
+    }
+}
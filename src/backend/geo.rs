@@ -0,0 +1,332 @@
+use crate::backend::{Backend, ScoreBound};
+
+/// Geohashes are 52 bits long, 26 for each coordinate, which a score holds exactly.
+const GEO_STEP_MAX: u32 = 26;
+/// The latitudes reachable by the web mercator projection.
+pub const GEO_LAT_MIN: f64 = -85.05112878;
+pub const GEO_LAT_MAX: f64 = 85.05112878;
+pub const GEO_LON_MIN: f64 = -180.0;
+pub const GEO_LON_MAX: f64 = 180.0;
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+const MERCATOR_MAX: f64 = 20037726.37;
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    pub lon: f64,
+    pub lat: f64,
+}
+
+/// The area a GEOSEARCH looks into, in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
+}
+
+/// A member found by a GEOSEARCH.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoMatch {
+    pub member: String,
+    /// distance from the center in meters
+    pub dist: f64,
+    /// the geohash, as stored in the score
+    pub score: f64,
+    pub point: GeoPoint,
+}
+
+impl GeoPoint {
+    /// `None` when the coordinates can't be indexed.
+    pub fn new(lon: f64, lat: f64) -> Option<Self> {
+        ((GEO_LON_MIN..=GEO_LON_MAX).contains(&lon) && (GEO_LAT_MIN..=GEO_LAT_MAX).contains(&lat))
+            .then_some(Self { lon, lat })
+    }
+
+    /// The 52 bit geohash this point is stored with.
+    pub fn encode(&self) -> u64 {
+        let (lat, lon) = cell_of(*self, GEO_STEP_MAX);
+        interleave(lat, lon)
+    }
+
+    /// The center of the cell of a 52 bit geohash.
+    pub fn decode(bits: u64) -> Self {
+        let (lat, lon) = deinterleave(bits);
+        let (lat_min, lat_max) = cell_bounds(lat, GEO_STEP_MAX, GEO_LAT_MIN, GEO_LAT_MAX);
+        let (lon_min, lon_max) = cell_bounds(lon, GEO_STEP_MAX, GEO_LON_MIN, GEO_LON_MAX);
+        Self {
+            lon: ((lon_min + lon_max) / 2.0).clamp(GEO_LON_MIN, GEO_LON_MAX),
+            lat: ((lat_min + lat_max) / 2.0).clamp(GEO_LAT_MIN, GEO_LAT_MAX),
+        }
+    }
+
+    /// The haversine distance in meters.
+    pub fn distance(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let u = ((lat2 - lat1) / 2.0).sin();
+        let v = ((other.lon.to_radians() - self.lon.to_radians()) / 2.0).sin();
+        let a = u * u + lat1.cos() * lat2.cos() * v * v;
+        2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+    }
+
+    /// The standard 11 characters geohash, which unlike the stored one spans latitudes
+    /// -90..90.
+    pub fn geohash(&self) -> String {
+        let scale = |value: f64, min: f64, max: f64| {
+            let offset = (value - min) / (max - min) * (1u64 << GEO_STEP_MAX) as f64;
+            (offset as u64).min((1 << GEO_STEP_MAX) - 1)
+        };
+        let bits = interleave(
+            scale(self.lat, -90.0, 90.0),
+            scale(self.lon, GEO_LON_MIN, GEO_LON_MAX),
+        );
+        (0..11)
+            .map(|i| {
+                // 52 bits only fill 10 characters and a half
+                let index = if i == 10 {
+                    0
+                } else {
+                    (bits >> (52 - (i + 1) * 5)) & 0x1f
+                };
+                GEOHASH_ALPHABET[index as usize] as char
+            })
+            .collect()
+    }
+}
+
+impl GeoShape {
+    /// The distance of `point` from `center` when it lies within the shape.
+    pub fn contains(&self, center: &GeoPoint, point: &GeoPoint) -> Option<f64> {
+        match self {
+            GeoShape::Radius(radius) => {
+                let dist = center.distance(point);
+                (dist <= *radius).then_some(dist)
+            }
+            GeoShape::Box { width, height } => {
+                let lat_dist = EARTH_RADIUS_IN_METERS
+                    * (point.lat.to_radians() - center.lat.to_radians()).abs();
+                if lat_dist > height / 2.0 {
+                    return None;
+                }
+                let lon_dist = GeoPoint {
+                    lon: center.lon,
+                    lat: point.lat,
+                }
+                .distance(point);
+                if lon_dist > width / 2.0 {
+                    return None;
+                }
+                Some(center.distance(point))
+            }
+        }
+    }
+
+    /// `(lon_min, lat_min, lon_max, lat_max)` of a box enclosing the shape.
+    fn bounding_box(&self, center: &GeoPoint) -> (f64, f64, f64, f64) {
+        let (width, height) = match self {
+            GeoShape::Radius(radius) => (radius * 2.0, radius * 2.0),
+            GeoShape::Box { width, height } => (*width, *height),
+        };
+        let lat_delta = (height / 2.0 / EARTH_RADIUS_IN_METERS).to_degrees();
+        // meridians converge, the box is the widest on the side closer to the equator
+        let lon_delta =
+            |lat: f64| (width / 2.0 / EARTH_RADIUS_IN_METERS / lat.to_radians().cos()).to_degrees();
+        let lon_delta = lon_delta(center.lat + lat_delta).max(lon_delta(center.lat - lat_delta));
+        (
+            center.lon - lon_delta,
+            center.lat - lat_delta,
+            center.lon + lon_delta,
+            center.lat + lat_delta,
+        )
+    }
+
+    fn radius(&self) -> f64 {
+        match self {
+            GeoShape::Radius(radius) => *radius,
+            GeoShape::Box { width, height } => (width / 2.0).hypot(height / 2.0),
+        }
+    }
+}
+
+impl Backend {
+    /// The members of `key` within `shape` around `center`, in geohash order. With `limit` the
+    /// search stops as soon as that many were found.
+    pub fn geo_search(
+        &self,
+        key: &str,
+        center: GeoPoint,
+        shape: GeoShape,
+        limit: Option<usize>,
+    ) -> Vec<GeoMatch> {
+        let Some(zset) = self.zset.get(key) else {
+            return vec![];
+        };
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut found = vec![];
+        for (min, max) in search_ranges(center, shape) {
+            let (start, end) =
+                zset.score_range(ScoreBound::Inclusive(min), ScoreBound::Exclusive(max));
+            for (member, score) in zset.range(start, end, false) {
+                if found.len() >= limit {
+                    return found;
+                }
+                let point = GeoPoint::decode(score as u64);
+                if let Some(dist) = shape.contains(&center, &point) {
+                    found.push(GeoMatch {
+                        member: member.to_string(),
+                        dist,
+                        score,
+                        point,
+                    });
+                }
+            }
+        }
+        found
+    }
+}
+
+/// The score ranges of the cell holding `center` and its 8 neighbours, at the finest step whose
+/// cells still cover the whole shape.
+fn search_ranges(center: GeoPoint, shape: GeoShape) -> Vec<(f64, f64)> {
+    let (lon_min, lat_min, lon_max, lat_max) = shape.bounding_box(&center);
+    let mut step = estimate_step(shape.radius(), center.lat);
+    let (lat, lon) = loop {
+        let (lat, lon) = cell_of(center, step);
+        let (cell_lat_min, cell_lat_max) = cell_bounds(lat, step, GEO_LAT_MIN, GEO_LAT_MAX);
+        let (cell_lon_min, cell_lon_max) = cell_bounds(lon, step, GEO_LON_MIN, GEO_LON_MAX);
+        let lat_size = cell_lat_max - cell_lat_min;
+        let lon_size = cell_lon_max - cell_lon_min;
+        let covered = cell_lat_min - lat_size <= lat_min
+            && cell_lat_max + lat_size >= lat_max
+            && cell_lon_min - lon_size <= lon_min
+            && cell_lon_max + lon_size >= lon_max;
+        if covered || step == 1 {
+            break (lat, lon);
+        }
+        step -= 1;
+    };
+
+    let cells = 1i64 << step;
+    let shift = 2 * (GEO_STEP_MAX - step);
+    let mut ranges = vec![];
+    for lat_offset in -1..=1 {
+        let lat = lat as i64 + lat_offset;
+        if !(0..cells).contains(&lat) {
+            continue;
+        }
+        for lon_offset in -1..=1 {
+            // longitudes wrap around the antimeridian
+            let lon = (lon as i64 + lon_offset).rem_euclid(cells);
+            let bits = interleave(lat as u64, lon as u64);
+            let range = ((bits << shift) as f64, ((bits + 1) << shift) as f64);
+            if !ranges.contains(&range) {
+                ranges.push(range);
+            }
+        }
+    }
+    ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+    ranges
+}
+
+/// The step whose cells are about as large as `radius`, cells shrink towards the poles.
+fn estimate_step(radius: f64, lat: f64) -> u32 {
+    if radius == 0.0 {
+        return GEO_STEP_MAX;
+    }
+    let mut step: i32 = 1;
+    let mut range = radius;
+    while range < MERCATOR_MAX {
+        range *= 2.0;
+        step += 1;
+    }
+    step -= 2;
+    if !(-66.0..=66.0).contains(&lat) {
+        step -= 1;
+        if !(-80.0..=80.0).contains(&lat) {
+            step -= 1;
+        }
+    }
+    step.clamp(1, GEO_STEP_MAX as i32) as u32
+}
+
+/// The `(latitude, longitude)` indexes of the cell holding `point` at `step`.
+fn cell_of(point: GeoPoint, step: u32) -> (u64, u64) {
+    let scale = |value: f64, min: f64, max: f64| {
+        let offset = (value - min) / (max - min) * (1u64 << step) as f64;
+        (offset as u64).min((1 << step) - 1)
+    };
+    (
+        scale(point.lat, GEO_LAT_MIN, GEO_LAT_MAX),
+        scale(point.lon, GEO_LON_MIN, GEO_LON_MAX),
+    )
+}
+
+fn cell_bounds(index: u64, step: u32, min: f64, max: f64) -> (f64, f64) {
+    let size = (max - min) / (1u64 << step) as f64;
+    (min + index as f64 * size, min + (index + 1) as f64 * size)
+}
+
+/// Interleaves the bits of both indexes, the longitude taking the odd positions.
+fn interleave(lat: u64, lon: u64) -> u64 {
+    (0..GEO_STEP_MAX).fold(0, |bits, i| {
+        bits | ((lat >> i) & 1) << (2 * i) | ((lon >> i) & 1) << (2 * i + 1)
+    })
+}
+
+fn deinterleave(bits: u64) -> (u64, u64) {
+    (0..GEO_STEP_MAX).fold((0, 0), |(lat, lon), i| {
+        (
+            lat | ((bits >> (2 * i)) & 1) << i,
+            lon | ((bits >> (2 * i + 1)) & 1) << i,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::SortedSet;
+
+    #[test]
+    fn test_geohash_encoding() {
+        let palermo = GeoPoint::new(13.361389, 38.115556).unwrap();
+        let decoded = GeoPoint::decode(palermo.encode());
+        assert!((decoded.lon - palermo.lon).abs() < 1e-5);
+        assert!((decoded.lat - palermo.lat).abs() < 1e-5);
+        assert_eq!(palermo.encode(), 3479099956230698);
+        assert_eq!(decoded.geohash(), "sqc8b49rny0");
+        assert!(GeoPoint::new(0.0, 86.0).is_none());
+
+        // distances are measured between the stored cell centers
+        let catania = GeoPoint::new(15.087269, 37.502669).unwrap();
+        let catania = GeoPoint::decode(catania.encode());
+        assert!((decoded.distance(&catania) - 166274.1516).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_geo_search() {
+        let backend = Backend::new();
+        let mut zset = SortedSet::new();
+        for (member, lon, lat) in [
+            ("palermo", 13.361389, 38.115556),
+            ("catania", 15.087269, 37.502669),
+            ("rome", 12.496366, 41.902782),
+        ] {
+            let point = GeoPoint::new(lon, lat).unwrap();
+            zset.insert(member.to_string(), point.encode() as f64);
+        }
+        backend.zset_store("sicily", zset, "zadd");
+
+        let center = GeoPoint::new(15.0, 37.0).unwrap();
+        let found = backend.geo_search("sicily", center, GeoShape::Radius(200_000.0), None);
+        let mut members: Vec<&str> = found.iter().map(|m| m.member.as_str()).collect();
+        members.sort();
+        assert_eq!(members, vec!["catania", "palermo"]);
+
+        let shape = GeoShape::Box {
+            width: 400_000.0,
+            height: 400_000.0,
+        };
+        let found = backend.geo_search("sicily", center, shape, Some(1));
+        assert_eq!(found.len(), 1);
+    }
+}
@@ -0,0 +1,337 @@
+use std::time::Duration;
+
+use dashmap::DashMap;
+
+use crate::backend::{now_millis, Backend, NOTIFY_GENERIC, NOTIFY_HASH};
+use crate::frame::RespFrame;
+
+/// How often the background task reclaims expired hash fields.
+const ACTIVE_EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What a write does to the TTL of the fields it touches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldTtl {
+    /// the fields don't expire anymore, like a plain HSET
+    Clear,
+    /// the fields keep whatever TTL they had
+    Keep,
+    /// the fields expire at the given unix time in milliseconds
+    ExpireAt(u64),
+}
+
+/// The NX | XX | GT | LT flags of HEXPIRE and its siblings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExpireCondition {
+    Always,
+    /// only when the field has no TTL
+    Nx,
+    /// only when the field has a TTL
+    Xx,
+    /// only when the new expiry is later, a field without TTL never expires so it never applies
+    Gt,
+    /// only when the new expiry is earlier, which always holds for a field without TTL
+    Lt,
+}
+
+/// The reply codes of HEXPIRE for a single field.
+pub const EXPIRE_NO_FIELD: i64 = -2;
+pub const EXPIRE_NOT_SET: i64 = 0;
+pub const EXPIRE_SET: i64 = 1;
+pub const EXPIRE_DELETED: i64 = 2;
+
+// Lock order: an `hmap` guard may be held while locking `hmap_expires`, never the other way
+// around. Writes re-index the hash once both are released.
+impl Backend {
+    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
+        self.hmap_purge_expired(key);
+        self.hmap
+            .get(key)
+            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+    }
+
+    /// Sets every field to its value, returns how many of them were new. Overwritten fields lose
+    /// their TTL.
+    pub fn hset(&self, key: &str, fields: Vec<(String, RespFrame)>) -> usize {
+        self.hsetex(key, fields, FieldTtl::Clear)
+    }
+
+    /// Like [`Backend::hset`] but with control over the TTL of the written fields.
+    pub fn hsetex(&self, key: &str, fields: Vec<(String, RespFrame)>, ttl: FieldTtl) -> usize {
+        self.hmap_purge_expired(key);
+        let mut added = 0;
+        {
+            let hmap = self.hmap.entry(key.to_string()).or_default();
+            for (field, value) in fields {
+                if hmap.insert(field.clone(), value).is_none() {
+                    added += 1;
+                }
+                self.set_field_ttl(key, field, ttl);
+            }
+        }
+        self.ft_reindex(key);
+        added
+    }
+
+    /// Replaces a single field with the value computed by `update` from the current one, keeping
+    /// its TTL. Nothing is written when `update` fails.
+    pub fn hupdate<T, E>(
+        &self,
+        key: &str,
+        field: &str,
+        update: impl FnOnce(Option<&RespFrame>) -> Result<(RespFrame, T), E>,
+    ) -> Result<T, E> {
+        self.hmap_purge_expired(key);
+        let ret = {
+            let hmap = self.hmap.entry(key.to_string()).or_default();
+            let current = hmap.get(field).map(|v| v.value().clone());
+            update(current.as_ref()).map(|(value, ret)| {
+                hmap.insert(field.to_string(), value);
+                ret
+            })
+        };
+        self.hmap_remove_if_empty(key);
+        if ret.is_ok() {
+            self.ft_reindex(key);
+        }
+        ret
+    }
+
+    /// Deletes the given fields, returns the values of those that existed. Hashes left empty are
+    /// removed.
+    pub fn hgetdel(&self, key: &str, fields: &[String]) -> Vec<Option<RespFrame>> {
+        self.hmap_purge_expired(key);
+        let removed = match self.hmap.get(key) {
+            Some(hmap) => fields
+                .iter()
+                .map(|field| {
+                    let value = hmap.remove(field).map(|(_, value)| value);
+                    if value.is_some() {
+                        self.set_field_ttl(key, field.clone(), FieldTtl::Clear);
+                    }
+                    value
+                })
+                .collect(),
+            None => vec![None; fields.len()],
+        };
+        self.hmap_remove_if_empty(key);
+        if removed.iter().any(Option::is_some) {
+            self.ft_reindex(key);
+        }
+        removed
+    }
+
+    /// Deletes the given fields, returns how many existed. Hashes left empty are removed.
+    pub fn hdel(&self, key: &str, fields: &[String]) -> usize {
+        self.hgetdel(key, fields).into_iter().flatten().count()
+    }
+
+    pub fn hlen(&self, key: &str) -> usize {
+        self.hmap_purge_expired(key);
+        self.hmap.get(key).map(|hmap| hmap.len()).unwrap_or(0)
+    }
+
+    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
+        self.hmap_purge_expired(key);
+        self.hmap.get(key).map(|v| v.value().clone())
+    }
+
+    /// Sets when `field` expires, returns one of the `EXPIRE_*` codes. An expiry that is already
+    /// in the past deletes the field.
+    pub fn hexpire_at(&self, key: &str, field: &str, at: u64, cond: ExpireCondition) -> i64 {
+        self.hmap_purge_expired(key);
+        let code = {
+            let Some(hmap) = self.hmap.get(key) else {
+                return EXPIRE_NO_FIELD;
+            };
+            if !hmap.contains_key(field) {
+                return EXPIRE_NO_FIELD;
+            }
+            let current = self.field_expire_at(key, field);
+            let applies = match (cond, current) {
+                (ExpireCondition::Always, _) => true,
+                (ExpireCondition::Nx, current) => current.is_none(),
+                (ExpireCondition::Xx, current) => current.is_some(),
+                (ExpireCondition::Gt, current) => current.is_some_and(|current| at > current),
+                (ExpireCondition::Lt, current) => current.is_none_or(|current| at < current),
+            };
+            if !applies {
+                EXPIRE_NOT_SET
+            } else if at <= now_millis() {
+                hmap.remove(field);
+                self.set_field_ttl(key, field.to_string(), FieldTtl::Clear);
+                EXPIRE_DELETED
+            } else {
+                self.set_field_ttl(key, field.to_string(), FieldTtl::ExpireAt(at));
+                EXPIRE_SET
+            }
+        };
+        self.hmap_remove_if_empty(key);
+        if code == EXPIRE_DELETED {
+            self.ft_reindex(key);
+        }
+        code
+    }
+
+    /// The unix time in milliseconds at which `field` expires: `None` when the field doesn't
+    /// exist, `Some(None)` when it has no TTL.
+    pub fn hexpire_time(&self, key: &str, field: &str) -> Option<Option<u64>> {
+        self.hmap_purge_expired(key);
+        let hmap = self.hmap.get(key)?;
+        hmap.contains_key(field)
+            .then(|| self.field_expire_at(key, field))
+    }
+
+    /// Removes the TTL of `field`, returns whether it had one or `None` when it doesn't exist.
+    pub fn hpersist(&self, key: &str, field: &str) -> Option<bool> {
+        self.hmap_purge_expired(key);
+        let hmap = self.hmap.get(key)?;
+        if !hmap.contains_key(field) {
+            return None;
+        }
+        let had_ttl = self.field_expire_at(key, field).is_some();
+        if had_ttl {
+            self.set_field_ttl(key, field.to_string(), FieldTtl::Clear);
+        }
+        Some(had_ttl)
+    }
+
+    /// Drops the fields of `key` whose TTL has passed, along with the hash if nothing is left,
+    /// notifying `hexpired`.
+    pub(crate) fn hmap_purge_expired(&self, key: &str) {
+        if !self.hmap_expires.contains_key(key) {
+            return;
+        }
+        let now = now_millis();
+        let mut purged = false;
+        {
+            let Some(hmap) = self.hmap.get(key) else {
+                return;
+            };
+            let Some(mut expires) = self.hmap_expires.get_mut(key) else {
+                return;
+            };
+            expires.retain(|field, at| {
+                let expired = *at <= now;
+                if expired {
+                    hmap.remove(field);
+                    purged = true;
+                }
+                !expired
+            });
+        }
+        self.hmap_expires
+            .remove_if(key, |_, expires| expires.is_empty());
+        self.hmap_remove_if_empty(key);
+        if purged {
+            self.ft_reindex(key);
+            self.notify(NOTIFY_HASH, "hexpired", key);
+            if !self.hmap.contains_key(key) {
+                self.notify(NOTIFY_GENERIC, "del", key);
+            }
+        }
+    }
+
+    /// Periodically reclaims expired hash fields that nobody reads anymore.
+    pub async fn hmap_active_expire(self) {
+        let mut interval = tokio::time::interval(ACTIVE_EXPIRE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let now = now_millis();
+            let keys: Vec<String> = self
+                .hmap_expires
+                .iter()
+                .filter(|expires| expires.values().any(|at| *at <= now))
+                .map(|expires| expires.key().clone())
+                .collect();
+            for key in keys {
+                let _shared = self.exec_lock.read().await;
+                self.hmap_purge_expired(&key);
+            }
+        }
+    }
+
+    pub(crate) fn hmap_remove_if_empty(&self, key: &str) {
+        self.hmap.remove_if(key, |_, hmap| {
+            if hmap.is_empty() {
+                self.hmap_expires.remove(key);
+            }
+            hmap.is_empty()
+        });
+    }
+
+    fn field_expire_at(&self, key: &str, field: &str) -> Option<u64> {
+        self.hmap_expires
+            .get(key)
+            .and_then(|expires| expires.get(field).copied())
+    }
+
+    /// Callers hold a guard on the hash in `hmap` so the TTL can't outlive its field.
+    fn set_field_ttl(&self, key: &str, field: String, ttl: FieldTtl) {
+        match ttl {
+            FieldTtl::Keep => {}
+            FieldTtl::Clear => {
+                if let Some(mut expires) = self.hmap_expires.get_mut(key) {
+                    expires.remove(&field);
+                }
+                self.hmap_expires
+                    .remove_if(key, |_, expires| expires.is_empty());
+            }
+            FieldTtl::ExpireAt(at) => {
+                self.hmap_expires
+                    .entry(key.to_string())
+                    .or_default()
+                    .insert(field, at);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_hash_field_expiry() {
+        let backend = Backend::new();
+        let fields = ["a", "b"]
+            .iter()
+            .map(|f| (f.to_string(), RespFrame::Integer(1)))
+            .collect();
+        backend.hset("h", fields);
+
+        let past = now_millis() - 1;
+        let future = now_millis() + 60_000;
+        assert_eq!(
+            backend.hexpire_at("h", "a", future, ExpireCondition::Gt),
+            EXPIRE_NOT_SET
+        );
+        assert_eq!(
+            backend.hexpire_at("h", "a", future, ExpireCondition::Nx),
+            EXPIRE_SET
+        );
+        assert_eq!(backend.hexpire_time("h", "a"), Some(Some(future)));
+        assert_eq!(backend.hexpire_time("h", "b"), Some(None));
+        assert_eq!(backend.hexpire_time("h", "c"), None);
+
+        // overwriting a field clears its TTL
+        backend.hset("h", vec![("a".to_string(), RespFrame::Integer(2))]);
+        assert_eq!(backend.hexpire_time("h", "a"), Some(None));
+        assert!(!backend.hmap_expires.contains_key("h"));
+
+        assert_eq!(
+            backend.hexpire_at("h", "b", past, ExpireCondition::Always),
+            EXPIRE_DELETED
+        );
+        assert_eq!(backend.hlen("h"), 1);
+
+        // an expired field disappears on the next read, taking the empty hash with it
+        backend
+            .hmap_expires
+            .insert("h".to_string(), HashMap::from([("a".to_string(), past)]));
+        assert_eq!(backend.hget("h", "a"), None);
+        assert!(!backend.hmap.contains_key("h"));
+        assert!(!backend.hmap_expires.contains_key("h"));
+    }
+}
@@ -0,0 +1,452 @@
+use dashmap::mapref::entry::Entry;
+
+use crate::backend::Backend;
+use crate::bulk_string::BulkString;
+use crate::frame::RespFrame;
+
+/// Bits of the hash that pick a register, 2^14 registers give a standard error of 0.81%.
+const HLL_P: u32 = 14;
+/// Bits of the hash left to count the run of zeroes in.
+const HLL_Q: u32 = 64 - HLL_P;
+const HLL_REGISTERS: usize = 1 << HLL_P;
+const HLL_REGISTER_BITS: usize = 6;
+const HLL_REGISTER_MAX: u8 = (1 << HLL_REGISTER_BITS) - 1;
+const HLL_HEADER_SIZE: usize = 16;
+const HLL_DENSE_SIZE: usize = HLL_HEADER_SIZE + (HLL_REGISTERS * HLL_REGISTER_BITS).div_ceil(8);
+/// Past this size a sparse HyperLogLog is promoted to the dense representation.
+const HLL_SPARSE_MAX_BYTES: usize = 3000;
+const HLL_SPARSE_VAL_MAX: u8 = 32;
+const HLL_MAGIC: &[u8; 4] = b"HYLL";
+const HLL_DENSE: u8 = 0;
+const HLL_SPARSE: u8 = 1;
+const HLL_HASH_SEED: u64 = 0xadc83b19;
+
+/// A HyperLogLog with the same binary layout as redis' `HYLL` strings, so its bytes can be
+/// read back with GET and restored with SET.
+///
+/// The layout is a 16 bytes header (magic, encoding, 3 unused bytes and the cached cardinality
+/// in little endian, whose most significant bit flags it as stale) followed by the registers:
+/// 16384 packed 6 bit counters when dense, or a run length encoding of them when sparse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+    sparse: bool,
+    cached: Option<u64>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; HLL_REGISTERS],
+            sparse: true,
+            cached: Some(0),
+        }
+    }
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes a `HYLL` string, `None` when the bytes are not a valid HyperLogLog.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HLL_HEADER_SIZE || &bytes[..4] != HLL_MAGIC {
+            return None;
+        }
+        let mut card = [0; 8];
+        card.copy_from_slice(&bytes[8..16]);
+        let card = u64::from_le_bytes(card);
+        let cached = (card & (1 << 63) == 0).then_some(card);
+        let body = &bytes[HLL_HEADER_SIZE..];
+        let (registers, sparse) = match bytes[4] {
+            HLL_DENSE if bytes.len() == HLL_DENSE_SIZE => (dense_registers(body)?, false),
+            HLL_SPARSE => (sparse_registers(body)?, true),
+            _ => return None,
+        };
+        Some(Self {
+            registers,
+            sparse,
+            cached,
+        })
+    }
+
+    /// Encodes the HyperLogLog, sparse for as long as it fits.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let sparse = self.sparse.then(|| sparse_bytes(&self.registers)).flatten();
+        let mut bytes = Vec::with_capacity(HLL_DENSE_SIZE);
+        bytes.extend_from_slice(HLL_MAGIC);
+        bytes.push(if sparse.is_some() {
+            HLL_SPARSE
+        } else {
+            HLL_DENSE
+        });
+        bytes.extend_from_slice(&[0; 3]);
+        bytes.extend_from_slice(&self.cached.unwrap_or(1 << 63).to_le_bytes());
+        match sparse {
+            Some(sparse) => bytes.extend_from_slice(&sparse),
+            None => bytes.extend_from_slice(&dense_bytes(&self.registers)),
+        }
+        bytes
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        self.sparse && sparse_bytes(&self.registers).is_some()
+    }
+
+    /// Adds an element, returns whether a register changed.
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let hash = murmur_hash64a(element, HLL_HASH_SEED);
+        let index = (hash & (HLL_REGISTERS as u64 - 1)) as usize;
+        // the sentinel bit bounds the run to Q + 1
+        let count = ((hash >> HLL_P) | (1 << HLL_Q)).trailing_zeros() as u8 + 1;
+        if count <= self.registers[index] {
+            return false;
+        }
+        self.registers[index] = count;
+        self.cached = None;
+        true
+    }
+
+    /// Takes the largest value of every register, which makes this the union of both.
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            if *other > *register {
+                *register = *other;
+                self.cached = None;
+            }
+        }
+        self.sparse &= other.sparse;
+    }
+
+    /// The estimated cardinality, cached until the next change.
+    pub fn count(&mut self) -> u64 {
+        if let Some(card) = self.cached {
+            return card;
+        }
+        let card = estimate(&self.registers);
+        self.cached = Some(card);
+        card
+    }
+}
+
+/// The estimator of "New cardinality estimation algorithms for HyperLogLog sketches" by Otmar
+/// Ertl, the one redis uses.
+fn estimate(registers: &[u8]) -> u64 {
+    let m = HLL_REGISTERS as f64;
+    let mut histogram = [0u32; HLL_Q as usize + 2];
+    for register in registers {
+        histogram[*register as usize] += 1;
+    }
+
+    let q = HLL_Q as usize;
+    let mut z = m * tau((m - histogram[q + 1] as f64) / m);
+    for count in histogram[1..=q].iter().rev() {
+        z += *count as f64;
+        z *= 0.5;
+    }
+    z += m * sigma(histogram[0] as f64 / m);
+    let alpha_inf = 0.5 / std::f64::consts::LN_2;
+    (alpha_inf * m * m / z).round() as u64
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let prev = z;
+        z += x * y;
+        y += y;
+        if prev == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let prev = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if prev == z {
+            return z / 3.0;
+        }
+    }
+}
+
+/// Registers are packed 6 bits at a time, least significant bits first. `None` when a register
+/// holds a count no hash can produce, which would overflow the histogram of [`estimate`].
+fn dense_registers(body: &[u8]) -> Option<Vec<u8>> {
+    (0..HLL_REGISTERS)
+        .map(|i| {
+            let bit = i * HLL_REGISTER_BITS;
+            let (byte, shift) = (bit / 8, bit % 8);
+            let word = body[byte] as u16 | (body.get(byte + 1).copied().unwrap_or(0) as u16) << 8;
+            let register = (word >> shift) as u8 & HLL_REGISTER_MAX;
+            (register as u32 <= HLL_Q + 1).then_some(register)
+        })
+        .collect()
+}
+
+fn dense_bytes(registers: &[u8]) -> Vec<u8> {
+    let mut body = vec![0u8; HLL_DENSE_SIZE - HLL_HEADER_SIZE];
+    for (i, register) in registers.iter().enumerate() {
+        let bit = i * HLL_REGISTER_BITS;
+        let (byte, shift) = (bit / 8, bit % 8);
+        let value = (*register & HLL_REGISTER_MAX) as u16;
+        body[byte] |= (value << shift) as u8;
+        if shift > 8 - HLL_REGISTER_BITS {
+            body[byte + 1] |= (value >> (8 - shift)) as u8;
+        }
+    }
+    body
+}
+
+/// Decodes the sparse opcodes: `00xxxxxx` is a run of up to 64 zero registers, `01xxxxxx
+/// yyyyyyyy` a run of up to 16384 and `1vvvvvxx` a run of up to 4 registers set to 1..=32.
+fn sparse_registers(body: &[u8]) -> Option<Vec<u8>> {
+    let mut registers = Vec::with_capacity(HLL_REGISTERS);
+    let mut bytes = body.iter();
+    while let Some(op) = bytes.next() {
+        let (value, len) = match op >> 6 {
+            0b00 => (0, (op & 0x3f) as usize + 1),
+            0b01 => {
+                let low = *bytes.next()? as usize;
+                (0, (((op & 0x3f) as usize) << 8 | low) + 1)
+            }
+            _ => (((op >> 2) & 0x1f) + 1, (op & 0x03) as usize + 1),
+        };
+        if registers.len() + len > HLL_REGISTERS {
+            return None;
+        }
+        registers.resize(registers.len() + len, value);
+    }
+    (registers.len() == HLL_REGISTERS).then_some(registers)
+}
+
+/// Encodes the registers as sparse opcodes, `None` when a register is too large for them or
+/// the encoding outgrows the sparse limit.
+fn sparse_bytes(registers: &[u8]) -> Option<Vec<u8>> {
+    let mut body = vec![];
+    let mut i = 0;
+    while i < registers.len() {
+        let value = registers[i];
+        if value > HLL_SPARSE_VAL_MAX {
+            return None;
+        }
+        let run = registers[i..].iter().take_while(|r| **r == value).count();
+        if value == 0 {
+            let mut left = run;
+            while left > 0 {
+                let len = left.min(HLL_REGISTERS);
+                if len > 64 {
+                    body.push(0x40 | ((len - 1) >> 8) as u8);
+                    body.push(((len - 1) & 0xff) as u8);
+                } else {
+                    body.push((len - 1) as u8);
+                }
+                left -= len;
+            }
+        } else {
+            let mut left = run;
+            while left > 0 {
+                let len = left.min(4);
+                body.push(0x80 | ((value - 1) << 2) | (len - 1) as u8);
+                left -= len;
+            }
+        }
+        if HLL_HEADER_SIZE + body.len() > HLL_SPARSE_MAX_BYTES {
+            return None;
+        }
+        i += run;
+    }
+    Some(body)
+}
+
+/// MurmurHash64A, the 64 bit hash redis feeds its HyperLogLogs with.
+pub(crate) fn murmur_hash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+
+    let mut chunks = key.chunks_exact(8);
+    for chunk in chunks.by_ref() {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap_or_default());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate() {
+            h ^= (*byte as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+impl Backend {
+    /// The HyperLogLog stored at `key`, `Err` when the string there is something else.
+    pub fn hll_get(&self, key: &str) -> Result<Option<HyperLogLog>, String> {
+        match self.map.get(key) {
+            Some(value) => match value.value() {
+                RespFrame::BulkString(bytes) => HyperLogLog::from_bytes(bytes)
+                    .map(Some)
+                    .ok_or_else(invalid_hll),
+                _ => Err(invalid_hll()),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Adds elements to the HyperLogLog at `key`, creating it if needed. Returns whether it
+    /// changed.
+    pub fn pfadd(&self, key: &str, elements: &[Vec<u8>]) -> Result<bool, String> {
+        match self.map.entry(key.to_string()) {
+            Entry::Occupied(mut entry) => {
+                let RespFrame::BulkString(bytes) = entry.get() else {
+                    return Err(invalid_hll());
+                };
+                let mut hll = HyperLogLog::from_bytes(bytes).ok_or_else(invalid_hll)?;
+                let mut changed = false;
+                for element in elements {
+                    changed |= hll.add(element);
+                }
+                if changed {
+                    entry.insert(BulkString::new(hll.to_bytes()).into());
+                }
+                Ok(changed)
+            }
+            Entry::Vacant(entry) => {
+                let mut hll = HyperLogLog::new();
+                for element in elements {
+                    hll.add(element);
+                }
+                entry.insert(BulkString::new(hll.to_bytes()).into());
+                Ok(true)
+            }
+        }
+    }
+
+    /// The estimated cardinality of `key`, which caches it in the stored string.
+    pub fn pfcount(&self, key: &str) -> Result<u64, String> {
+        let Some(mut entry) = self.map.get_mut(key) else {
+            return Ok(0);
+        };
+        let RespFrame::BulkString(bytes) = entry.value() else {
+            return Err(invalid_hll());
+        };
+        let mut hll = HyperLogLog::from_bytes(bytes).ok_or_else(invalid_hll)?;
+        let stale = hll.cached.is_none();
+        let card = hll.count();
+        if stale {
+            *entry.value_mut() = BulkString::new(hll.to_bytes()).into();
+        }
+        Ok(card)
+    }
+
+    /// The estimated cardinality of the union of `keys`, missing keys count as empty.
+    pub fn pfcount_union(&self, keys: &[String]) -> Result<u64, String> {
+        let mut union = HyperLogLog::new();
+        for key in keys {
+            if let Some(hll) = self.hll_get(key)? {
+                union.merge(&hll);
+            }
+        }
+        Ok(union.count())
+    }
+
+    /// Stores the union of `dest` and `sources` at `dest`. The result stays sparse only when
+    /// every input was.
+    pub fn pfmerge(&self, dest: &str, sources: &[String]) -> Result<(), String> {
+        let mut merged = self.hll_get(dest)?.unwrap_or_default();
+        for key in sources {
+            if let Some(hll) = self.hll_get(key)? {
+                merged.merge(&hll);
+            }
+        }
+        self.map
+            .insert(dest.to_string(), BulkString::new(merged.to_bytes()).into());
+        Ok(())
+    }
+}
+
+fn invalid_hll() -> String {
+    "WRONGTYPE Key is not a valid HyperLogLog string value.".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyperloglog_estimate_and_encoding() {
+        let mut hll = HyperLogLog::new();
+        assert_eq!(hll.count(), 0);
+        for i in 0..1000 {
+            hll.add(format!("item:{}", i).as_bytes());
+        }
+        assert!(hll.is_sparse());
+        let bytes = hll.to_bytes();
+        assert_eq!(&bytes[..5], b"HYLL\x01");
+        let mut decoded = HyperLogLog::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.registers, hll.registers);
+        let card = decoded.count() as f64;
+        assert!((card - 1000.0).abs() < 1000.0 * 0.05, "{}", card);
+
+        for i in 0..100_000 {
+            hll.add(format!("item:{}", i).as_bytes());
+        }
+        let bytes = hll.to_bytes();
+        assert_eq!(bytes.len(), HLL_DENSE_SIZE);
+        assert_eq!(bytes[4], HLL_DENSE);
+        let mut decoded = HyperLogLog::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.registers, hll.registers);
+        let card = decoded.count() as f64;
+        assert!((card - 100_000.0).abs() < 100_000.0 * 0.05, "{}", card);
+        assert!(HyperLogLog::from_bytes(b"HYLL").is_none());
+    }
+
+    #[test]
+    fn test_corrupted_dense_registers() {
+        let mut hll = HyperLogLog::new();
+        hll.registers[0] = HLL_Q as u8 + 1;
+        hll.sparse = false;
+        let mut bytes = hll.to_bytes();
+        assert!(HyperLogLog::from_bytes(&bytes).is_some());
+        // the first register set to 63, past the histogram of the estimate
+        bytes[HLL_HEADER_SIZE] |= HLL_REGISTER_MAX;
+        assert!(HyperLogLog::from_bytes(&bytes).is_none());
+    }
+
+    #[test]
+    fn test_murmur_hash64a() {
+        // the empty key only goes through the final mix
+        assert_eq!(murmur_hash64a(b"", 0), 0);
+        assert_ne!(
+            murmur_hash64a(b"a", HLL_HASH_SEED),
+            murmur_hash64a(b"b", HLL_HASH_SEED)
+        );
+        assert_ne!(
+            murmur_hash64a(b"abcdefgh", HLL_HASH_SEED),
+            murmur_hash64a(b"abcdefgi", HLL_HASH_SEED)
+        );
+    }
+}
@@ -0,0 +1,700 @@
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+use crate::array::RespArray;
+use crate::bulk_string::BulkString;
+use crate::frame::RespFrame;
+use crate::map::RespMap;
+use crate::null::RespNull;
+
+/// A path into a JSON document. Paths starting with `$` are JSONPath and may match any number
+/// of values; anything else is a legacy path such as `.a.b[0]`, which names a single value.
+///
+/// The supported JSONPath subset covers member access (`.a`, `['a','b']`), wildcards, recursive
+/// descent (`..a`), indexes and slices (`[0,-1]`, `[1:5:2]`) and filters such as
+/// `[?(@.price < 10 && @.tags)]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+    legacy: bool,
+}
+
+/// One step from the root to a matched value.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JsonStep {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    /// `..`: the selector applies to the value and all of its descendants
+    recursive: bool,
+    selector: Selector,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Keys(Vec<String>),
+    Indexes(Vec<i64>),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: i64,
+    },
+    Wildcard,
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Or(Box<Filter>, Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Exists(Operand),
+    Compare(Operand, CompareOp, Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    /// `@...`, relative to the value being filtered
+    Current(Vec<Segment>),
+    /// `$...`, relative to the document root
+    Root(Vec<Segment>),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let (legacy, rest) = match path.strip_prefix('$') {
+            Some(rest) => (false, rest.to_string()),
+            None if path == "." => (true, String::new()),
+            None if path.starts_with('.') || path.starts_with('[') => (true, path.to_string()),
+            None => (true, format!(".{}", path)),
+        };
+        let mut parser = PathParser {
+            input: rest.as_bytes(),
+            pos: 0,
+        };
+        let segments = parser.segments()?;
+        if parser.pos < parser.input.len() {
+            return Err(parser.error());
+        }
+        Ok(Self { segments, legacy })
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.legacy
+    }
+
+    /// Whether the path names the whole document.
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Where the path matches in `root`, in document order.
+    pub fn locate(&self, root: &Value) -> Vec<Vec<JsonStep>> {
+        select(root, &self.segments, vec![(vec![], root)])
+            .into_iter()
+            .map(|(location, _)| location)
+            .collect()
+    }
+
+    /// The values the path matches in `root`.
+    pub fn select<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        select(root, &self.segments, vec![(vec![], root)])
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// For a path ending with a plain member name, the path of its parent and that name, which
+    /// is where a new member gets added.
+    pub fn split_last_key(&self) -> Option<(JsonPath, &str)> {
+        let (last, parent) = self.segments.split_last()?;
+        match &last.selector {
+            Selector::Keys(keys) if keys.len() == 1 && !last.recursive => Some((
+                JsonPath {
+                    segments: parent.to_vec(),
+                    legacy: self.legacy,
+                },
+                &keys[0],
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// The value at `location`, as returned by [`JsonPath::locate`].
+pub fn json_get_mut<'a>(root: &'a mut Value, location: &[JsonStep]) -> Option<&'a mut Value> {
+    location.iter().try_fold(root, |value, step| match step {
+        JsonStep::Key(key) => value.as_object_mut()?.get_mut(key),
+        JsonStep::Index(index) => value.as_array_mut()?.get_mut(*index),
+    })
+}
+
+/// Removes the value at `location`, the root can't be removed.
+pub fn json_remove(root: &mut Value, location: &[JsonStep]) -> Option<Value> {
+    let (last, parent) = location.split_last()?;
+    match (json_get_mut(root, parent)?, last) {
+        (Value::Object(object), JsonStep::Key(key)) => object.shift_remove(key),
+        (Value::Array(array), JsonStep::Index(index)) if *index < array.len() => {
+            Some(array.remove(*index))
+        }
+        _ => None,
+    }
+}
+
+/// The type name JSON.TYPE reports.
+pub fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Maps a document onto the RESP types, objects become maps so RESP3 clients get them natively.
+impl From<&Value> for RespFrame {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => RespFrame::Null(RespNull),
+            Value::Bool(b) => RespFrame::Boolean(*b),
+            Value::Number(n) => match n.as_i64() {
+                Some(n) => RespFrame::Integer(n),
+                None => RespFrame::Double(n.as_f64().unwrap_or_default()),
+            },
+            Value::String(s) => BulkString::new(s.as_str()).into(),
+            Value::Array(array) => {
+                RespArray::new(array.iter().map(RespFrame::from).collect::<Vec<_>>()).into()
+            }
+            Value::Object(object) => {
+                let mut map = RespMap::new();
+                for (key, value) in object {
+                    map.insert(key.clone(), value.into());
+                }
+                map.into()
+            }
+        }
+    }
+}
+
+type Matches<'a> = Vec<(Vec<JsonStep>, &'a Value)>;
+
+fn select<'a>(root: &'a Value, segments: &[Segment], mut current: Matches<'a>) -> Matches<'a> {
+    for segment in segments {
+        let mut next = vec![];
+        for (location, value) in current {
+            if segment.recursive {
+                let mut descendants = vec![];
+                descend(location, value, &mut descendants);
+                for (location, value) in descendants {
+                    apply(root, &segment.selector, location, value, &mut next);
+                }
+            } else {
+                apply(root, &segment.selector, location, value, &mut next);
+            }
+        }
+        current = next;
+    }
+    current
+}
+
+/// The value followed by all of its descendants, depth first.
+fn descend<'a>(location: Vec<JsonStep>, value: &'a Value, out: &mut Matches<'a>) {
+    out.push((location.clone(), value));
+    for (step, child) in children(value) {
+        descend(child_location(&location, step), child, out);
+    }
+}
+
+fn children(value: &Value) -> Vec<(JsonStep, &Value)> {
+    match value {
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (JsonStep::Index(i), v))
+            .collect(),
+        Value::Object(object) => object
+            .iter()
+            .map(|(k, v)| (JsonStep::Key(k.clone()), v))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn child_location(location: &[JsonStep], step: JsonStep) -> Vec<JsonStep> {
+    let mut location = location.to_vec();
+    location.push(step);
+    location
+}
+
+fn apply<'a>(
+    root: &'a Value,
+    selector: &Selector,
+    location: Vec<JsonStep>,
+    value: &'a Value,
+    out: &mut Matches<'a>,
+) {
+    match (selector, value) {
+        (Selector::Keys(keys), Value::Object(object)) => {
+            for key in keys {
+                if let Some(child) = object.get(key) {
+                    out.push((child_location(&location, JsonStep::Key(key.clone())), child));
+                }
+            }
+        }
+        (Selector::Indexes(indexes), Value::Array(array)) => {
+            for index in indexes {
+                let index = if *index < 0 {
+                    array.len() as i64 + index
+                } else {
+                    *index
+                };
+                if let Some(child) = usize::try_from(index).ok().and_then(|i| array.get(i)) {
+                    out.push((
+                        child_location(&location, JsonStep::Index(index as usize)),
+                        child,
+                    ));
+                }
+            }
+        }
+        (Selector::Slice { start, end, step }, Value::Array(array)) => {
+            let len = array.len() as i64;
+            let bound = |i: i64| if i < 0 { (len + i).max(0) } else { i.min(len) };
+            let start = start.map_or(0, bound);
+            let end = end.map_or(len, bound);
+            for index in (start..end).step_by(*step as usize) {
+                out.push((
+                    child_location(&location, JsonStep::Index(index as usize)),
+                    &array[index as usize],
+                ));
+            }
+        }
+        (Selector::Wildcard, _) => {
+            for (step, child) in children(value) {
+                out.push((child_location(&location, step), child));
+            }
+        }
+        (Selector::Filter(filter), _) => {
+            for (step, child) in children(value) {
+                if filter.matches(root, child) {
+                    out.push((child_location(&location, step), child));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Filter {
+    fn matches(&self, root: &Value, current: &Value) -> bool {
+        match self {
+            Filter::Or(a, b) => a.matches(root, current) || b.matches(root, current),
+            Filter::And(a, b) => a.matches(root, current) && b.matches(root, current),
+            Filter::Not(filter) => !filter.matches(root, current),
+            Filter::Exists(operand) => operand.resolve(root, current).is_some(),
+            Filter::Compare(a, op, b) => {
+                match (a.resolve(root, current), b.resolve(root, current)) {
+                    (Some(a), Some(b)) => op.holds(&a, &b),
+                    (None, None) => matches!(op, CompareOp::Eq | CompareOp::Le | CompareOp::Ge),
+                    _ => *op == CompareOp::Ne,
+                }
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn resolve(&self, root: &Value, current: &Value) -> Option<Value> {
+        let (segments, start) = match self {
+            Operand::Literal(value) => return Some(value.clone()),
+            Operand::Current(segments) => (segments, current),
+            Operand::Root(segments) => (segments, root),
+        };
+        select(root, segments, vec![(vec![], start)])
+            .into_iter()
+            .next()
+            .map(|(_, value)| value.clone())
+    }
+}
+
+impl CompareOp {
+    fn holds(self, a: &Value, b: &Value) -> bool {
+        let ordering = match (a, b) {
+            (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            _ if a == b => Some(Ordering::Equal),
+            _ => None,
+        };
+        match (self, ordering) {
+            (CompareOp::Ne, ordering) => ordering != Some(Ordering::Equal),
+            (_, None) => false,
+            (CompareOp::Eq, Some(o)) => o.is_eq(),
+            (CompareOp::Lt, Some(o)) => o.is_lt(),
+            (CompareOp::Le, Some(o)) => o.is_le(),
+            (CompareOp::Gt, Some(o)) => o.is_gt(),
+            (CompareOp::Ge, Some(o)) => o.is_ge(),
+        }
+    }
+}
+
+struct PathParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl PathParser<'_> {
+    fn error(&self) -> String {
+        format!("invalid JSONPath at position {}", self.pos)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.input[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn segments(&mut self) -> Result<Vec<Segment>, String> {
+        let mut segments = vec![];
+        loop {
+            let recursive = if self.eat("..") {
+                true
+            } else if self.eat(".") || self.peek() == Some(b'[') {
+                false
+            } else {
+                return Ok(segments);
+            };
+            let selector = if self.eat("[") {
+                self.bracket()?
+            } else if self.eat("*") {
+                Selector::Wildcard
+            } else {
+                Selector::Keys(vec![self.name()?])
+            };
+            segments.push(Segment {
+                recursive,
+                selector,
+            });
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_ascii_whitespace() && !b".[]()=!<>&|,'\"".contains(&c))
+        {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.error());
+        }
+        Ok(String::from_utf8_lossy(&self.input[start..self.pos]).into_owned())
+    }
+
+    /// The selector inside `[...]`, past the opening bracket.
+    fn bracket(&mut self) -> Result<Selector, String> {
+        self.skip_whitespace();
+        let selector = match self.peek() {
+            Some(b'*') => {
+                self.pos += 1;
+                Selector::Wildcard
+            }
+            Some(b'?') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                let filter = if self.eat("(") {
+                    let filter = self.or()?;
+                    self.expect(")")?;
+                    filter
+                } else {
+                    self.or()?
+                };
+                Selector::Filter(filter)
+            }
+            Some(b'\'' | b'"') => {
+                let mut keys = vec![self.quoted()?];
+                while self.comma() {
+                    keys.push(self.quoted()?);
+                }
+                Selector::Keys(keys)
+            }
+            _ => {
+                let first = self.integer();
+                self.skip_whitespace();
+                if self.eat(":") {
+                    let end = self.integer();
+                    self.skip_whitespace();
+                    let step = if self.eat(":") {
+                        self.integer().unwrap_or(1)
+                    } else {
+                        1
+                    };
+                    if step <= 0 {
+                        return Err(self.error());
+                    }
+                    Selector::Slice {
+                        start: first,
+                        end,
+                        step,
+                    }
+                } else {
+                    let mut indexes = vec![first.ok_or_else(|| self.error())?];
+                    while self.comma() {
+                        indexes.push(self.integer().ok_or_else(|| self.error())?);
+                    }
+                    Selector::Indexes(indexes)
+                }
+            }
+        };
+        self.expect("]")?;
+        Ok(selector)
+    }
+
+    fn comma(&mut self) -> bool {
+        self.skip_whitespace();
+        let comma = self.eat(",");
+        self.skip_whitespace();
+        comma
+    }
+
+    fn integer(&mut self) -> Option<i64> {
+        self.skip_whitespace();
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let parsed = std::str::from_utf8(&self.input[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok());
+        if parsed.is_none() {
+            self.pos = start;
+        }
+        parsed
+    }
+
+    fn quoted(&mut self) -> Result<String, String> {
+        let quote = self.peek().ok_or_else(|| self.error())?;
+        self.pos += 1;
+        let mut value = vec![];
+        loop {
+            match self.peek() {
+                Some(b'\\') if self.pos + 1 < self.input.len() => {
+                    value.push(self.input[self.pos + 1]);
+                    self.pos += 2;
+                }
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(String::from_utf8_lossy(&value).into_owned());
+                }
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+                None => return Err(self.error()),
+            }
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.and()?;
+        self.skip_whitespace();
+        while self.eat("||") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+            self.skip_whitespace();
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.unary()?;
+        self.skip_whitespace();
+        while self.eat("&&") {
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+            self.skip_whitespace();
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<Filter, String> {
+        self.skip_whitespace();
+        if self.eat("!") {
+            return Ok(Filter::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let filter = self.or()?;
+            self.expect(")")?;
+            return Ok(filter);
+        }
+        let left = self.operand()?;
+        self.skip_whitespace();
+        let op = [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ]
+        .into_iter()
+        .find(|(token, _)| self.eat(token));
+        match op {
+            Some((_, op)) => Ok(Filter::Compare(left, op, self.operand()?)),
+            None => Ok(Filter::Exists(left)),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'@') => {
+                self.pos += 1;
+                Ok(Operand::Current(self.segments()?))
+            }
+            Some(b'$') => {
+                self.pos += 1;
+                Ok(Operand::Root(self.segments()?))
+            }
+            Some(b'\'' | b'"') => Ok(Operand::Literal(Value::String(self.quoted()?))),
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || b"-+.".contains(&c))
+                {
+                    self.pos += 1;
+                }
+                let literal = std::str::from_utf8(&self.input[start..self.pos]).unwrap_or("");
+                match serde_json::from_str::<Value>(literal) {
+                    Ok(value @ (Value::Number(_) | Value::Bool(_) | Value::Null)) => {
+                        Ok(Operand::Literal(value))
+                    }
+                    _ => {
+                        self.pos = start;
+                        Err(self.error())
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn select(path: &str, doc: &Value) -> Vec<Value> {
+        JsonPath::parse(path)
+            .unwrap()
+            .select(doc)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_json_path_select() {
+        let doc = json!({
+            "a": 1,
+            "store": {
+                "books": [
+                    {"title": "x", "price": 8, "a": 2},
+                    {"title": "y", "price": 12.5, "tags": ["new"]},
+                    {"title": "z", "price": 30}
+                ]
+            }
+        });
+        assert_eq!(select("$..a", &doc), vec![json!(1), json!(2)]);
+        assert_eq!(
+            select("$.store.books[*].title", &doc),
+            vec![json!("x"), json!("y"), json!("z")]
+        );
+        assert_eq!(select("$.store.books[-1].title", &doc), vec![json!("z")]);
+        assert_eq!(
+            select("$.store.books[0:3:2].price", &doc),
+            vec![json!(8), json!(30)]
+        );
+        assert_eq!(
+            select("$.store.books[?(@.price < 20 && @.price > 10)].title", &doc),
+            vec![json!("y")]
+        );
+        assert_eq!(
+            select("$.store.books[?@.tags || @.title == 'z'].title", &doc),
+            vec![json!("y"), json!("z")]
+        );
+        assert_eq!(select("$['a','missing']", &doc), vec![json!(1)]);
+        assert_eq!(select(".store.books[1].price", &doc), vec![json!(12.5)]);
+        assert_eq!(select(".", &doc), vec![doc.clone()]);
+        assert!(JsonPath::parse("$.store[").is_err());
+        assert!(JsonPath::parse("$.a b").is_err());
+    }
+
+    #[test]
+    fn test_json_path_locate_and_mutate() {
+        let mut doc = json!({"a": [1, {"b": 2}], "b": 3});
+        let path = JsonPath::parse("$..b").unwrap();
+        let locations = path.locate(&doc);
+        assert_eq!(
+            locations,
+            vec![
+                vec![JsonStep::Key("b".into())],
+                vec![
+                    JsonStep::Key("a".into()),
+                    JsonStep::Index(1),
+                    JsonStep::Key("b".into())
+                ],
+            ]
+        );
+        *json_get_mut(&mut doc, &locations[1]).unwrap() = json!(5);
+        assert_eq!(json_remove(&mut doc, &locations[0]), Some(json!(3)));
+        assert_eq!(doc, json!({"a": [1, {"b": 5}]}));
+
+        let path = JsonPath::parse("$.a[1].c").unwrap();
+        let (parent, key) = path.split_last_key().unwrap();
+        assert_eq!(key, "c");
+        assert_eq!(parent.select(&doc), vec![&json!({"b": 5})]);
+    }
+}
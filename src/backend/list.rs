@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use crate::backend::{move_between, Backend, NOTIFY_GENERIC, NOTIFY_LIST};
+use crate::frame::RespFrame;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListSide {
+    Left,
+    Right,
+}
+
+impl Backend {
+    /// Pushes `values` one by one at `side`, creating the list if needed. Returns the new length.
+    pub fn list_push(&self, key: &str, side: ListSide, values: Vec<RespFrame>) -> usize {
+        let (len, created) = {
+            let mut list = self.list.entry(key.to_string()).or_default();
+            let created = list.is_empty();
+            push_all(&mut list, side, values);
+            (list.len(), created)
+        };
+        self.notify_new(created, key);
+        self.notify(NOTIFY_LIST, side.push_event(), key);
+        self.signal_key_ready(key);
+        len
+    }
+
+    /// Like [`Backend::list_push`] but only pushes when the list already exists, returns 0 otherwise.
+    pub fn list_push_existing(&self, key: &str, side: ListSide, values: Vec<RespFrame>) -> usize {
+        let len = match self.list.get_mut(key) {
+            Some(mut list) => {
+                push_all(&mut list, side, values);
+                list.len()
+            }
+            None => return 0,
+        };
+        self.notify(NOTIFY_LIST, side.push_event(), key);
+        self.signal_key_ready(key);
+        len
+    }
+
+    /// Pops at most `count` elements from `side`. Lists left empty are removed from the keyspace.
+    pub fn list_pop(&self, key: &str, side: ListSide, count: usize) -> Option<Vec<RespFrame>> {
+        let popped = {
+            let mut list = self.list.get_mut(key)?;
+            let count = count.min(list.len());
+            match side {
+                ListSide::Left => list.drain(..count).collect::<Vec<_>>(),
+                ListSide::Right => (0..count).filter_map(|_| list.pop_back()).collect(),
+            }
+        };
+        if !popped.is_empty() {
+            self.notify(NOTIFY_LIST, side.pop_event(), key);
+        }
+        self.list_remove_if_empty(key);
+        Some(popped)
+    }
+
+    /// Pops an element from `src` at `from` and pushes it to `dst` at `to`, atomically.
+    pub fn list_move(
+        &self,
+        src: &str,
+        dst: &str,
+        from: ListSide,
+        to: ListSide,
+    ) -> Option<RespFrame> {
+        if src == dst {
+            let mut list = self.list.get_mut(src)?;
+            let value = pop_one(&mut list, from)?;
+            push_all(&mut list, to, vec![value.clone()]);
+            drop(list);
+            self.notify(NOTIFY_LIST, from.pop_event(), src);
+            self.notify(NOTIFY_LIST, to.push_event(), src);
+            return Some(value);
+        }
+
+        let moved = move_between(
+            &self.list,
+            src,
+            dst,
+            VecDeque::is_empty,
+            |from_list, to_list| {
+                let value = pop_one(from_list, from)?;
+                push_all(to_list, to, vec![value.clone()]);
+                Some(value)
+            },
+        )?;
+        self.notify(NOTIFY_LIST, from.pop_event(), src);
+        if moved.emptied {
+            self.notify(NOTIFY_GENERIC, "del", src);
+        }
+        self.notify_new(moved.created, dst);
+        self.notify(NOTIFY_LIST, to.push_event(), dst);
+        self.signal_key_ready(dst);
+        Some(moved.value)
+    }
+
+    pub fn list_len(&self, key: &str) -> usize {
+        self.list.get(key).map(|list| list.len()).unwrap_or(0)
+    }
+
+    /// Removes the list when a write left it empty, notifying `del`.
+    pub(crate) fn list_remove_if_empty(&self, key: &str) {
+        if self
+            .list
+            .remove_if(key, |_, list| list.is_empty())
+            .is_some()
+        {
+            self.notify(NOTIFY_GENERIC, "del", key);
+        }
+    }
+}
+
+impl ListSide {
+    fn push_event(self) -> &'static str {
+        match self {
+            ListSide::Left => "lpush",
+            ListSide::Right => "rpush",
+        }
+    }
+
+    fn pop_event(self) -> &'static str {
+        match self {
+            ListSide::Left => "lpop",
+            ListSide::Right => "rpop",
+        }
+    }
+}
+
+fn push_all(list: &mut VecDeque<RespFrame>, side: ListSide, values: Vec<RespFrame>) {
+    match side {
+        ListSide::Left => values.into_iter().for_each(|v| list.push_front(v)),
+        ListSide::Right => list.extend(values),
+    }
+}
+
+fn pop_one(list: &mut VecDeque<RespFrame>, side: ListSide) -> Option<RespFrame> {
+    match side {
+        ListSide::Left => list.pop_front(),
+        ListSide::Right => list.pop_back(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_move_is_atomic() {
+        let backend = Backend::new();
+        let values = (0..2000).map(RespFrame::Integer).collect();
+        backend.list_push("src", ListSide::Right, values);
+        let mover = std::thread::spawn({
+            let backend = backend.clone();
+            move || {
+                while backend
+                    .list_move("src", "dst", ListSide::Left, ListSide::Right)
+                    .is_some()
+                {}
+            }
+        });
+        // elements only go from src to dst, so reading src first can count one twice but
+        // never miss it
+        loop {
+            let src = backend.list_len("src");
+            let dst = backend.list_len("dst");
+            assert!(src + dst >= 2000, "{} + {}", src, dst);
+            if src == 0 {
+                break;
+            }
+        }
+        mover.join().unwrap();
+        assert_eq!(backend.list_len("dst"), 2000);
+        assert!(!backend.list.contains_key("src"));
+    }
+}
@@ -1,10 +1,87 @@
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ops::Deref;
+use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use dashmap::DashMap;
+use dashmap::{DashMap, SharedValue};
+use serde_json::Value;
+use tokio::sync::RwLock;
 
 use crate::frame::RespFrame;
 
+mod blocking;
+mod bloom;
+mod cms;
+mod cuckoo;
+mod function;
+mod geo;
+mod hmap;
+mod hyperloglog;
+mod json;
+mod list;
+mod notify;
+mod plugin;
+mod pubsub;
+mod scripting;
+mod search;
+mod set;
+mod skiplist;
+mod slot;
+mod stream;
+mod timeseries;
+mod topk;
+mod vectorset;
+mod watch;
+mod zset;
+
+pub use blocking::{BlockingState, RestoreFn, ServeFn};
+pub use bloom::{
+    bloom_bits, BloomFilter, BLOOM_DEFAULT_CAPACITY, BLOOM_DEFAULT_ERROR_RATE,
+    BLOOM_DEFAULT_EXPANSION,
+};
+pub use cms::{CountMinSketch, CMS_MAX_COUNTERS};
+pub use cuckoo::{
+    cuckoo_buckets, CuckooFilter, CUCKOO_DEFAULT_BUCKET_SIZE, CUCKOO_DEFAULT_CAPACITY,
+    CUCKOO_DEFAULT_EXPANSION, CUCKOO_DEFAULT_MAX_ITERATIONS,
+};
+pub use function::{FunctionLibrary, FunctionState, RestorePolicy};
+pub use geo::{GeoMatch, GeoPoint, GeoShape};
+pub use hmap::{
+    ExpireCondition, FieldTtl, EXPIRE_DELETED, EXPIRE_NOT_SET, EXPIRE_NO_FIELD, EXPIRE_SET,
+};
+pub use hyperloglog::HyperLogLog;
+pub use json::{json_get_mut, json_remove, json_type, JsonPath, JsonStep};
+pub use list::ListSide;
+pub use notify::{
+    format_notify_flags, parse_notify_flags, NOTIFY_ALL, NOTIFY_EVICTED, NOTIFY_EXPIRED,
+    NOTIFY_GENERIC, NOTIFY_HASH, NOTIFY_KEYEVENT, NOTIFY_KEYSPACE, NOTIFY_KEY_MISS, NOTIFY_LIST,
+    NOTIFY_MODULE, NOTIFY_NEW, NOTIFY_SET, NOTIFY_STREAM, NOTIFY_STRING, NOTIFY_ZSET,
+};
+pub use plugin::{PluginModule, PluginState, PLUGIN_DEFAULT_MAX_FUEL, PLUGIN_DEFAULT_MAX_MEMORY};
+pub use pubsub::{PubSubEvent, PubSubState, Subscriber};
+pub use scripting::{
+    script_sha, RunningScript, ScriptState, BUSY_ERROR, SCRIPT_DEFAULT_BUSY_REPLY_THRESHOLD,
+};
+pub use search::{stem, FieldKind, FieldSpec, Query, SearchIndex, DEFAULT_STOPWORDS};
+pub use slot::{key_hash_slot, SlotState, SLOT_COUNT};
+pub use stream::{
+    AutoClaim, ClaimOptions, Consumer, ConsumerGroup, PendingEntry, Stream, StreamId, StreamIdSpec,
+    StreamTrim,
+};
+pub use timeseries::{
+    bucket_start, Aggregation, BucketTimestamp, CompactionRule, DuplicatePolicy, LabelFilter,
+    RangeAggregation, RangeQuery, TimeSeries,
+};
+pub use topk::{
+    TopK, TOPK_DEFAULT_DECAY, TOPK_DEFAULT_DEPTH, TOPK_DEFAULT_WIDTH, TOPK_MAX_BUCKETS, TOPK_MAX_K,
+};
+pub use vectorset::{
+    CompareOp, Metric, VectorFilter, VectorSet, VSET_DEFAULT_EF_CONSTRUCTION, VSET_DEFAULT_M,
+};
+pub use watch::{WatchState, Watcher};
+pub use zset::{LexBound, ScoreBound, SortedSet};
+
 #[derive(Debug, Clone)]
 pub struct Backend(Arc<BackendInner>);
 
@@ -12,6 +89,32 @@ pub struct Backend(Arc<BackendInner>);
 pub struct BackendInner {
     pub map: DashMap<String, RespFrame>,
     pub hmap: DashMap<String, DashMap<String, RespFrame>>,
+    /// unix time in milliseconds at which hash fields expire, only for fields that have a TTL
+    pub hmap_expires: DashMap<String, HashMap<String, u64>>,
+    pub list: DashMap<String, VecDeque<RespFrame>>,
+    pub set: DashMap<String, HashSet<String>>,
+    pub zset: DashMap<String, SortedSet>,
+    pub stream: DashMap<String, Stream>,
+    pub bloom: DashMap<String, BloomFilter>,
+    pub cuckoo: DashMap<String, CuckooFilter>,
+    pub cms: DashMap<String, CountMinSketch>,
+    pub topk: DashMap<String, TopK>,
+    pub json: DashMap<String, Value>,
+    pub timeseries: DashMap<String, TimeSeries>,
+    pub search: DashMap<String, SearchIndex>,
+    pub vset: DashMap<String, VectorSet>,
+    pub blocking: BlockingState,
+    pub pubsub: PubSubState,
+    pub slots: SlotState,
+    /// the `notify-keyspace-events` flags, see [`parse_notify_flags`]
+    pub notify_keyspace_events: AtomicU32,
+    /// taken shared by every command and exclusively by EXEC, so that the commands of a
+    /// transaction or a script never interleave with other clients'
+    pub exec_lock: Arc<RwLock<()>>,
+    pub watch: WatchState,
+    pub scripts: ScriptState,
+    pub functions: FunctionState,
+    pub plugins: PluginState,
 }
 
 impl Deref for Backend {
@@ -27,6 +130,28 @@ impl Default for BackendInner {
         Self {
             map: DashMap::new(),
             hmap: DashMap::new(),
+            hmap_expires: DashMap::new(),
+            list: DashMap::new(),
+            set: DashMap::new(),
+            zset: DashMap::new(),
+            stream: DashMap::new(),
+            bloom: DashMap::new(),
+            cuckoo: DashMap::new(),
+            cms: DashMap::new(),
+            topk: DashMap::new(),
+            json: DashMap::new(),
+            timeseries: DashMap::new(),
+            search: DashMap::new(),
+            vset: DashMap::new(),
+            blocking: BlockingState::default(),
+            pubsub: PubSubState::default(),
+            slots: SlotState::default(),
+            notify_keyspace_events: AtomicU32::new(0),
+            exec_lock: Arc::new(RwLock::new(())),
+            watch: WatchState::default(),
+            scripts: ScriptState::default(),
+            functions: FunctionState::default(),
+            plugins: PluginState::default(),
         }
     }
 }
@@ -47,19 +172,120 @@ impl Backend {
         self.map.insert(key.to_string(), value);
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+    /// Removes `key` whatever type it holds, returns whether it existed.
+    pub fn del(&self, key: &str) -> bool {
+        let hash = self.hmap.remove(key).is_some();
+        if hash {
+            self.hmap_expires.remove(key);
+            self.ft_reindex(key);
+        }
+        hash | self.map.remove(key).is_some()
+            | self.list.remove(key).is_some()
+            | self.set.remove(key).is_some()
+            | self.zset.remove(key).is_some()
+            | self.stream.remove(key).is_some()
+            | self.bloom.remove(key).is_some()
+            | self.cuckoo.remove(key).is_some()
+            | self.cms.remove(key).is_some()
+            | self.topk.remove(key).is_some()
+            | self.json.remove(key).is_some()
+            | self.timeseries.remove(key).is_some()
+            | self.vset.remove(key).is_some()
     }
-    pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        self.hmap
-            .entry(key.to_string())
-            .or_default()
-            .insert(field.to_string(), value);
+
+    /// Removes every key and search index, for FLUSHALL and FLUSHDB.
+    pub fn flush(&self) {
+        self.map.clear();
+        self.hmap.clear();
+        self.hmap_expires.clear();
+        self.list.clear();
+        self.set.clear();
+        self.zset.clear();
+        self.stream.clear();
+        self.bloom.clear();
+        self.cuckoo.clear();
+        self.cms.clear();
+        self.topk.clear();
+        self.json.clear();
+        self.timeseries.clear();
+        self.search.clear();
+        self.vset.clear();
+        self.touch_all_keys();
     }
+}
+
+/// What [`move_between`] did besides running the move.
+pub(crate) struct Moved<R> {
+    pub value: R,
+    /// `dst` didn't exist before
+    pub created: bool,
+    /// `src` was left empty and removed
+    pub emptied: bool,
+}
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.value().clone())
+/// Runs `move_fn` on the values at `src` and `dst` as one critical section, for LMOVE and SMOVE:
+/// the shards of both keys stay locked, in index order so that two moves can't deadlock.
+/// `dst` starts from the default value when missing, and either key is removed when
+/// `is_empty` holds afterwards. Returns `None` when `src` doesn't exist or `move_fn` moved
+/// nothing. `src` and `dst` must differ.
+pub(crate) fn move_between<V: Default, R>(
+    map: &DashMap<String, V>,
+    src: &str,
+    dst: &str,
+    is_empty: impl Fn(&V) -> bool,
+    move_fn: impl FnOnce(&mut V, &mut V) -> Option<R>,
+) -> Option<Moved<R>> {
+    let shards = map.shards();
+    let (s, d) = (map.determine_map(src), map.determine_map(dst));
+    let mut first = shards[s.min(d)].write();
+    let mut second = (s != d).then(|| shards[s.max(d)].write());
+    let (src_map, dst_map) = match second.as_deref_mut() {
+        None => (&mut *first, None),
+        Some(second) if s < d => (&mut *first, Some(second)),
+        Some(second) => (second, Some(&mut *first)),
+    };
+    if !src_map.contains_key(src) {
+        return None;
+    }
+    let (value, created) = match dst_map {
+        Some(dst_map) => {
+            let created = !dst_map.contains_key(dst);
+            let to = dst_map
+                .entry(dst.to_string())
+                .or_insert_with(|| SharedValue::new(V::default()));
+            let value = move_fn(src_map.get_mut(src)?.get_mut(), to.get_mut());
+            if dst_map.get(dst).is_some_and(|v| is_empty(v.get())) {
+                dst_map.remove(dst);
+            }
+            (value, created)
+        }
+        None => {
+            let created = !src_map.contains_key(dst);
+            src_map
+                .entry(dst.to_string())
+                .or_insert_with(|| SharedValue::new(V::default()));
+            let [from, to] = src_map.get_many_mut([src, dst])?;
+            let value = move_fn(from.get_mut(), to.get_mut());
+            if src_map.get(dst).is_some_and(|v| is_empty(v.get())) {
+                src_map.remove(dst);
+            }
+            (value, created)
+        }
+    };
+    let emptied = src_map.get(src).is_some_and(|v| is_empty(v.get()));
+    if emptied {
+        src_map.remove(src);
     }
+    Some(Moved {
+        value: value?,
+        created,
+        emptied,
+    })
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
 }
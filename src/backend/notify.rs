@@ -0,0 +1,187 @@
+use std::sync::atomic::Ordering;
+
+use crate::backend::Backend;
+
+/// `K`, publish `__keyspace@0__:<key>` messages carrying the event
+pub const NOTIFY_KEYSPACE: u32 = 1 << 0;
+/// `E`, publish `__keyevent@0__:<event>` messages carrying the key
+pub const NOTIFY_KEYEVENT: u32 = 1 << 1;
+/// `g`, type independent commands like `del`
+pub const NOTIFY_GENERIC: u32 = 1 << 2;
+/// `$`
+pub const NOTIFY_STRING: u32 = 1 << 3;
+/// `l`
+pub const NOTIFY_LIST: u32 = 1 << 4;
+/// `s`
+pub const NOTIFY_SET: u32 = 1 << 5;
+/// `h`, including expired hash fields
+pub const NOTIFY_HASH: u32 = 1 << 6;
+/// `z`
+pub const NOTIFY_ZSET: u32 = 1 << 7;
+/// `x`, keys removed because they expired
+pub const NOTIFY_EXPIRED: u32 = 1 << 8;
+/// `e`, keys removed to free memory
+pub const NOTIFY_EVICTED: u32 = 1 << 9;
+/// `t`
+pub const NOTIFY_STREAM: u32 = 1 << 10;
+/// `m`, reads of keys that don't exist
+pub const NOTIFY_KEY_MISS: u32 = 1 << 11;
+/// `d`, the module types: bloom and cuckoo filters, count-min sketches, top-k, JSON, time
+/// series and vector sets
+pub const NOTIFY_MODULE: u32 = 1 << 12;
+/// `n`, keys being created
+pub const NOTIFY_NEW: u32 = 1 << 13;
+/// `A`, every class but `m` and `n`
+pub const NOTIFY_ALL: u32 = NOTIFY_GENERIC
+    | NOTIFY_STRING
+    | NOTIFY_LIST
+    | NOTIFY_SET
+    | NOTIFY_HASH
+    | NOTIFY_ZSET
+    | NOTIFY_EXPIRED
+    | NOTIFY_EVICTED
+    | NOTIFY_STREAM
+    | NOTIFY_MODULE;
+
+/// The flags in the order `notify-keyspace-events` prints them.
+const FLAG_CHARS: [(char, u32); 13] = [
+    ('g', NOTIFY_GENERIC),
+    ('$', NOTIFY_STRING),
+    ('l', NOTIFY_LIST),
+    ('s', NOTIFY_SET),
+    ('h', NOTIFY_HASH),
+    ('z', NOTIFY_ZSET),
+    ('x', NOTIFY_EXPIRED),
+    ('e', NOTIFY_EVICTED),
+    ('t', NOTIFY_STREAM),
+    ('d', NOTIFY_MODULE),
+    ('K', NOTIFY_KEYSPACE),
+    ('E', NOTIFY_KEYEVENT),
+    ('m', NOTIFY_KEY_MISS),
+];
+
+/// Parses `notify-keyspace-events` flags like `KEA` or `Kgx`, `None` for unknown ones.
+pub fn parse_notify_flags(flags: &str) -> Option<u32> {
+    flags.chars().try_fold(0, |acc, c| {
+        let flag = match c {
+            'A' => NOTIFY_ALL,
+            'n' => NOTIFY_NEW,
+            _ => FLAG_CHARS.iter().find(|(ch, _)| *ch == c)?.1,
+        };
+        Some(acc | flag)
+    })
+}
+
+/// The inverse of [`parse_notify_flags`], using `A` when every class is set.
+pub fn format_notify_flags(flags: u32) -> String {
+    let mut out = String::new();
+    if flags & NOTIFY_ALL == NOTIFY_ALL {
+        out.push('A');
+    }
+    for (c, flag) in FLAG_CHARS {
+        let covered = flags & NOTIFY_ALL == NOTIFY_ALL && NOTIFY_ALL & flag != 0;
+        if flags & flag != 0 && !covered {
+            out.push(c);
+        }
+    }
+    if flags & NOTIFY_NEW != 0 {
+        out.push('n');
+    }
+    out
+}
+
+impl Backend {
+    pub fn notify_flags(&self) -> u32 {
+        self.notify_keyspace_events.load(Ordering::Relaxed)
+    }
+
+    pub fn set_notify_flags(&self, flags: u32) {
+        self.notify_keyspace_events.store(flags, Ordering::Relaxed);
+    }
+
+    /// Publishes `event` on `key` when `class` is enabled, on the keyspace channel of the key,
+    /// the keyevent channel of the event or both. Every write notifies, so the clients
+    /// watching `key` are flagged here too.
+    pub fn notify(&self, class: u32, event: &str, key: &str) {
+        if class != NOTIFY_KEY_MISS {
+            self.touch_key(key);
+        }
+        let flags = self.notify_flags();
+        if flags & class == 0 {
+            return;
+        }
+        if flags & NOTIFY_KEYSPACE != 0 {
+            self.publish(&format!("__keyspace@0__:{}", key), event.as_bytes());
+        }
+        if flags & NOTIFY_KEYEVENT != 0 {
+            self.publish(&format!("__keyevent@0__:{}", event), key.as_bytes());
+        }
+    }
+
+    /// Notifies a `new` key when `created`, which comes before the event of the write that
+    /// created it.
+    pub fn notify_new(&self, created: bool, key: &str) {
+        if created {
+            self.notify(NOTIFY_NEW, "new", key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{PubSubEvent, Subscriber};
+    use crate::bulk_string::BulkString;
+    use crate::push::RespPush;
+
+    use super::*;
+
+    #[test]
+    fn test_notify_flags() {
+        assert_eq!(
+            parse_notify_flags("KEA"),
+            Some(NOTIFY_ALL | NOTIFY_KEYSPACE | NOTIFY_KEYEVENT)
+        );
+        assert_eq!(
+            parse_notify_flags("Kl$"),
+            Some(NOTIFY_KEYSPACE | NOTIFY_LIST | NOTIFY_STRING)
+        );
+        assert_eq!(parse_notify_flags(""), Some(0));
+        assert_eq!(parse_notify_flags("Kq"), None);
+        assert_eq!(format_notify_flags(parse_notify_flags("EA").unwrap()), "AE");
+        assert_eq!(
+            format_notify_flags(parse_notify_flags("nmhK").unwrap()),
+            "hKmn"
+        );
+        assert_eq!(format_notify_flags(0), "");
+    }
+
+    #[test]
+    fn test_notify() {
+        let backend = Backend::new();
+        let (mut subscriber, mut rx) = Subscriber::new(&backend);
+        subscriber.psubscribe("__key*__:*");
+        backend.notify(NOTIFY_LIST, "lpush", "l");
+        assert!(rx.try_recv().is_err());
+
+        backend.set_notify_flags(parse_notify_flags("Kl").unwrap());
+        backend.notify(NOTIFY_HASH, "hset", "h");
+        backend.notify(NOTIFY_LIST, "lpush", "l");
+        let message = |channel: &str, payload: &str| {
+            PubSubEvent::Message(
+                RespPush::new([
+                    BulkString::new("pmessage").into(),
+                    BulkString::new("__key*__:*").into(),
+                    BulkString::new(channel).into(),
+                    BulkString::new(payload).into(),
+                ])
+                .into(),
+            )
+        };
+        assert_eq!(rx.try_recv().unwrap(), message("__keyspace@0__:l", "lpush"));
+        assert!(rx.try_recv().is_err());
+
+        backend.set_notify_flags(parse_notify_flags("En").unwrap());
+        backend.notify_new(true, "l");
+        assert_eq!(rx.try_recv().unwrap(), message("__keyevent@0__:new", "l"));
+    }
+}
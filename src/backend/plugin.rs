@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use wasmi::{Config, Engine, Module};
+
+use crate::backend::Backend;
+
+/// The default `plugin-max-fuel`, roughly the number of wasm instructions a plugin command may
+/// execute before it's aborted.
+pub const PLUGIN_DEFAULT_MAX_FUEL: u64 = 10_000_000;
+
+/// The default `plugin-max-memory`, in bytes, a plugin instance may grow its memory to.
+pub const PLUGIN_DEFAULT_MAX_MEMORY: u64 = 16 * 1024 * 1024;
+
+/// A WebAssembly module loaded with PLUGIN LOAD.
+#[derive(Debug, Clone)]
+pub struct PluginModule {
+    pub name: String,
+    /// the file the module was loaded from
+    pub path: String,
+    pub module: Arc<Module>,
+    /// the commands the module registered, and whether each one writes
+    pub commands: BTreeMap<String, bool>,
+}
+
+/// The loaded plugins, by name, and the limits their commands run with.
+#[derive(Debug)]
+pub struct PluginState {
+    /// compiles the modules, with fuel metering so commands can't run forever
+    engine: Engine,
+    plugins: Mutex<BTreeMap<String, PluginModule>>,
+    max_fuel: AtomicU64,
+    max_memory: AtomicU64,
+}
+
+impl Default for PluginState {
+    fn default() -> Self {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        Self {
+            engine: Engine::new(&config),
+            plugins: Mutex::new(BTreeMap::new()),
+            max_fuel: AtomicU64::new(PLUGIN_DEFAULT_MAX_FUEL),
+            max_memory: AtomicU64::new(PLUGIN_DEFAULT_MAX_MEMORY),
+        }
+    }
+}
+
+impl Backend {
+    pub fn plugin_engine(&self) -> &Engine {
+        &self.plugins.engine
+    }
+
+    /// Adds a plugin, unless one with the same name is loaded or registers one of its
+    /// commands.
+    pub fn plugin_add(&self, plugin: PluginModule) -> Result<(), String> {
+        let mut plugins = self.plugins.plugins.lock().unwrap();
+        if plugins.contains_key(&plugin.name) {
+            return Err(format!("Plugin '{}' is already loaded", plugin.name));
+        }
+        for other in plugins.values() {
+            if let Some(command) = plugin
+                .commands
+                .keys()
+                .find(|command| other.commands.contains_key(*command))
+            {
+                return Err(format!(
+                    "Command '{}' is already registered by plugin '{}'",
+                    command, other.name
+                ));
+            }
+        }
+        plugins.insert(plugin.name.clone(), plugin);
+        Ok(())
+    }
+
+    /// Removes a plugin with its commands, returns whether it was loaded.
+    pub fn plugin_remove(&self, name: &str) -> bool {
+        self.plugins.plugins.lock().unwrap().remove(name).is_some()
+    }
+
+    /// Every plugin, sorted by name.
+    pub fn plugin_list(&self) -> Vec<PluginModule> {
+        let plugins = self.plugins.plugins.lock().unwrap();
+        plugins.values().cloned().collect()
+    }
+
+    /// The module registering `command`, and whether the command writes.
+    pub fn plugin_command(&self, command: &str) -> Option<(Arc<Module>, bool)> {
+        let plugins = self.plugins.plugins.lock().unwrap();
+        plugins.values().find_map(|plugin| {
+            let write = plugin.commands.get(command)?;
+            Some((plugin.module.clone(), *write))
+        })
+    }
+
+    pub fn plugin_max_fuel(&self) -> u64 {
+        self.plugins.max_fuel.load(Ordering::Relaxed)
+    }
+
+    pub fn set_plugin_max_fuel(&self, fuel: u64) {
+        self.plugins.max_fuel.store(fuel, Ordering::Relaxed);
+    }
+
+    pub fn plugin_max_memory(&self) -> u64 {
+        self.plugins.max_memory.load(Ordering::Relaxed)
+    }
+
+    pub fn set_plugin_max_memory(&self, bytes: u64) {
+        self.plugins.max_memory.store(bytes, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(backend: &Backend, name: &str, commands: &[&str]) -> PluginModule {
+        PluginModule {
+            name: name.to_string(),
+            path: format!("{}.wasm", name),
+            module: Arc::new(Module::new(backend.plugin_engine(), b"\0asm\x01\0\0\0").unwrap()),
+            commands: commands
+                .iter()
+                .map(|command| (command.to_string(), false))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_plugins() {
+        let backend = Backend::new();
+        backend.plugin_add(plugin(&backend, "a", &["x"])).unwrap();
+        assert!(backend.plugin_add(plugin(&backend, "a", &["y"])).is_err());
+        assert!(backend.plugin_add(plugin(&backend, "b", &["x"])).is_err());
+        backend.plugin_add(plugin(&backend, "b", &["y"])).unwrap();
+        assert_eq!(
+            backend.plugin_command("y").map(|(_, write)| write),
+            Some(false)
+        );
+        assert_eq!(backend.plugin_list().len(), 2);
+
+        assert!(backend.plugin_remove("b"));
+        assert!(!backend.plugin_remove("b"));
+        assert!(backend.plugin_command("y").is_none());
+    }
+}
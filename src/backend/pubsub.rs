@@ -0,0 +1,422 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use tokio::sync::mpsc;
+
+use crate::backend::{key_hash_slot, Backend};
+use crate::bulk_string::BulkString;
+use crate::cmd::glob_match;
+use crate::frame::RespFrame;
+use crate::push::RespPush;
+
+type PushSender = mpsc::UnboundedSender<PubSubEvent>;
+
+/// Subscribers by channel or pattern, then by client id.
+type Subscriptions = HashMap<String, HashMap<u64, PushSender>>;
+
+/// The pub/sub subscriptions of all clients. Events are pushed into a channel per client
+/// that its connection drains into the socket.
+#[derive(Default)]
+pub struct PubSubState {
+    channels: Mutex<Subscriptions>,
+    patterns: Mutex<Subscriptions>,
+    shard_channels: Mutex<Subscriptions>,
+    next_id: AtomicU64,
+}
+
+/// What a connection receives from other clients.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PubSubEvent {
+    /// a message to push to the client as is
+    Message(RespFrame),
+    /// the slot of a shard channel the client subscribed to moved away, the subscription
+    /// is gone
+    ShardChannelMoved(String),
+}
+
+/// The subscriptions of one connection, which are dropped with it.
+pub struct Subscriber {
+    backend: Backend,
+    id: u64,
+    tx: PushSender,
+    channels: BTreeSet<String>,
+    patterns: BTreeSet<String>,
+    shard_channels: BTreeSet<String>,
+}
+
+impl Subscriber {
+    /// A client without subscriptions and the receiving end of the messages it will get.
+    pub fn new(backend: &Backend) -> (Self, mpsc::UnboundedReceiver<PubSubEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let subscriber = Self {
+            backend: backend.clone(),
+            id: backend.pubsub.next_id.fetch_add(1, Ordering::Relaxed),
+            tx,
+            channels: BTreeSet::new(),
+            patterns: BTreeSet::new(),
+            shard_channels: BTreeSet::new(),
+        };
+        (subscriber, rx)
+    }
+
+    /// The number of channels and patterns the client is subscribed to.
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    /// The number of shard channels the client is subscribed to, which are counted apart.
+    pub fn shard_count(&self) -> usize {
+        self.shard_channels.len()
+    }
+
+    pub fn is_subscribed(&self) -> bool {
+        self.count() + self.shard_count() > 0
+    }
+
+    pub fn channels(&self) -> Vec<String> {
+        self.channels.iter().cloned().collect()
+    }
+
+    pub fn patterns(&self) -> Vec<String> {
+        self.patterns.iter().cloned().collect()
+    }
+
+    pub fn shard_channels(&self) -> Vec<String> {
+        self.shard_channels.iter().cloned().collect()
+    }
+
+    /// Subscribes to `channel`, returns the subscription count afterwards.
+    pub fn subscribe(&mut self, channel: &str) -> usize {
+        if self.channels.insert(channel.to_string()) {
+            self.register(&self.backend.pubsub.channels, channel);
+        }
+        self.count()
+    }
+
+    pub fn unsubscribe(&mut self, channel: &str) -> usize {
+        if self.channels.remove(channel) {
+            self.unregister(&self.backend.pubsub.channels, channel);
+        }
+        self.count()
+    }
+
+    pub fn psubscribe(&mut self, pattern: &str) -> usize {
+        if self.patterns.insert(pattern.to_string()) {
+            self.register(&self.backend.pubsub.patterns, pattern);
+        }
+        self.count()
+    }
+
+    pub fn punsubscribe(&mut self, pattern: &str) -> usize {
+        if self.patterns.remove(pattern) {
+            self.unregister(&self.backend.pubsub.patterns, pattern);
+        }
+        self.count()
+    }
+
+    /// Subscribes to the shard channel `channel`, returns the shard subscription count
+    /// afterwards.
+    pub fn ssubscribe(&mut self, channel: &str) -> usize {
+        if self.shard_channels.insert(channel.to_string()) {
+            self.register(&self.backend.pubsub.shard_channels, channel);
+        }
+        self.shard_count()
+    }
+
+    pub fn sunsubscribe(&mut self, channel: &str) -> usize {
+        if self.shard_channels.remove(channel) {
+            self.unregister(&self.backend.pubsub.shard_channels, channel);
+        }
+        self.shard_count()
+    }
+
+    /// Drops every subscription, as RESET does.
+    pub fn unsubscribe_all(&mut self) {
+        for channel in std::mem::take(&mut self.channels) {
+            self.unregister(&self.backend.pubsub.channels, &channel);
+        }
+        for pattern in std::mem::take(&mut self.patterns) {
+            self.unregister(&self.backend.pubsub.patterns, &pattern);
+        }
+        for channel in std::mem::take(&mut self.shard_channels) {
+            self.unregister(&self.backend.pubsub.shard_channels, &channel);
+        }
+    }
+
+    /// Turns an event into the frame pushed to the client. A moved shard channel is dropped
+    /// from the subscriptions and the client told with an unsolicited `sunsubscribe`.
+    pub fn receive(&mut self, event: PubSubEvent) -> RespFrame {
+        match event {
+            PubSubEvent::Message(frame) => frame,
+            PubSubEvent::ShardChannelMoved(channel) => {
+                let count = self.sunsubscribe(&channel);
+                RespPush::new([
+                    BulkString::new("sunsubscribe").into(),
+                    BulkString::new(channel).into(),
+                    RespFrame::Integer(count as i64),
+                ])
+                .into()
+            }
+        }
+    }
+
+    fn register(&self, subscriptions: &Mutex<Subscriptions>, name: &str) {
+        subscriptions
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default()
+            .insert(self.id, self.tx.clone());
+    }
+
+    fn unregister(&self, subscriptions: &Mutex<Subscriptions>, name: &str) {
+        let mut subscriptions = subscriptions.lock().unwrap();
+        if let Some(clients) = subscriptions.get_mut(name) {
+            clients.remove(&self.id);
+            if clients.is_empty() {
+                subscriptions.remove(name);
+            }
+        }
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        self.unsubscribe_all();
+    }
+}
+
+impl Backend {
+    /// Sends `message` to the subscribers of `channel` and of the patterns matching it,
+    /// returns how many clients received it.
+    pub fn publish(&self, channel: &str, message: &[u8]) -> usize {
+        let mut received = 0;
+        if let Some(clients) = self.pubsub.channels.lock().unwrap().get(channel) {
+            let push = RespPush::new([
+                BulkString::new("message").into(),
+                BulkString::new(channel).into(),
+                BulkString::new(message).into(),
+            ]);
+            for tx in clients.values() {
+                if tx.send(PubSubEvent::Message(push.clone().into())).is_ok() {
+                    received += 1;
+                }
+            }
+        }
+        for (pattern, clients) in self.pubsub.patterns.lock().unwrap().iter() {
+            if !glob_match(pattern.as_bytes(), channel.as_bytes()) {
+                continue;
+            }
+            let push = RespPush::new([
+                BulkString::new("pmessage").into(),
+                BulkString::new(pattern.as_str()).into(),
+                BulkString::new(channel).into(),
+                BulkString::new(message).into(),
+            ]);
+            for tx in clients.values() {
+                if tx.send(PubSubEvent::Message(push.clone().into())).is_ok() {
+                    received += 1;
+                }
+            }
+        }
+        received
+    }
+
+    /// Sends `message` to the subscribers of the shard channel `channel`. A standalone server
+    /// owns every slot, so the message is always delivered here.
+    pub fn spublish(&self, channel: &str, message: &[u8]) -> usize {
+        let shard_channels = self.pubsub.shard_channels.lock().unwrap();
+        let Some(clients) = shard_channels.get(channel) else {
+            return 0;
+        };
+        let push = RespPush::new([
+            BulkString::new("smessage").into(),
+            BulkString::new(channel).into(),
+            BulkString::new(message).into(),
+        ]);
+        clients
+            .values()
+            .filter(|tx| tx.send(PubSubEvent::Message(push.clone().into())).is_ok())
+            .count()
+    }
+
+    /// Drops the subscriptions to shard channels in `slot` once it moved to another node,
+    /// telling their subscribers. Returns the affected channels.
+    pub fn release_slot(&self, slot: u16) -> Vec<String> {
+        let mut shard_channels = self.pubsub.shard_channels.lock().unwrap();
+        let mut moved: Vec<String> = shard_channels
+            .keys()
+            .filter(|channel| key_hash_slot(channel.as_bytes()) == slot)
+            .cloned()
+            .collect();
+        moved.sort();
+        for channel in &moved {
+            if let Some(clients) = shard_channels.remove(channel) {
+                for tx in clients.values() {
+                    let _ = tx.send(PubSubEvent::ShardChannelMoved(channel.clone()));
+                }
+            }
+        }
+        moved
+    }
+
+    /// The channels with at least one subscriber, optionally only those matching `pattern`.
+    pub fn pubsub_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        subscribed(&self.pubsub.channels, pattern)
+    }
+
+    /// Like [`Backend::pubsub_channels`] for shard channels.
+    pub fn pubsub_shard_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        subscribed(&self.pubsub.shard_channels, pattern)
+    }
+
+    /// The number of subscribers of `channel`, not counting pattern subscribers.
+    pub fn pubsub_numsub(&self, channel: &str) -> usize {
+        subscriber_count(&self.pubsub.channels, channel)
+    }
+
+    pub fn pubsub_shard_numsub(&self, channel: &str) -> usize {
+        subscriber_count(&self.pubsub.shard_channels, channel)
+    }
+
+    /// The number of patterns subscribed to by any client.
+    pub fn pubsub_numpat(&self) -> usize {
+        self.pubsub.patterns.lock().unwrap().len()
+    }
+}
+
+fn subscribed(subscriptions: &Mutex<Subscriptions>, pattern: Option<&str>) -> Vec<String> {
+    let mut channels: Vec<String> = subscriptions
+        .lock()
+        .unwrap()
+        .keys()
+        .filter(|channel| {
+            pattern.is_none_or(|pattern| glob_match(pattern.as_bytes(), channel.as_bytes()))
+        })
+        .cloned()
+        .collect();
+    channels.sort();
+    channels
+}
+
+fn subscriber_count(subscriptions: &Mutex<Subscriptions>, channel: &str) -> usize {
+    subscriptions
+        .lock()
+        .unwrap()
+        .get(channel)
+        .map_or(0, HashMap::len)
+}
+
+impl fmt::Debug for PubSubState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PubSubState")
+            .field("channels", &self.channels.lock().unwrap().len())
+            .field("patterns", &self.patterns.lock().unwrap().len())
+            .field("shard_channels", &self.shard_channels.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl fmt::Debug for Subscriber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscriber")
+            .field("id", &self.id)
+            .field("channels", &self.channels)
+            .field("patterns", &self.patterns)
+            .field("shard_channels", &self.shard_channels)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish() {
+        let backend = Backend::new();
+        let (mut news, mut news_rx) = Subscriber::new(&backend);
+        let (mut all, mut all_rx) = Subscriber::new(&backend);
+        assert_eq!(news.subscribe("news"), 1);
+        assert_eq!(news.subscribe("news"), 1);
+        assert_eq!(all.psubscribe("n*"), 1);
+        assert_eq!(all.subscribe("news"), 2);
+
+        assert_eq!(backend.publish("news", b"hi"), 3);
+        assert_eq!(backend.publish("nope", b"hi"), 1);
+        assert_eq!(backend.publish("sports", b"hi"), 0);
+        assert_eq!(
+            news_rx.try_recv().unwrap(),
+            PubSubEvent::Message(
+                RespPush::new([b"message".into(), b"news".into(), b"hi".into()]).into()
+            )
+        );
+        assert!(news_rx.try_recv().is_err());
+        let received: Vec<RespFrame> = std::iter::from_fn(|| all_rx.try_recv().ok())
+            .map(|event| all.receive(event))
+            .collect();
+        assert_eq!(received.len(), 3);
+        assert!(received.contains(
+            &RespPush::new([
+                b"pmessage".into(),
+                b"n*".into(),
+                b"nope".into(),
+                b"hi".into()
+            ])
+            .into()
+        ));
+
+        assert_eq!(backend.pubsub_channels(None), ["news"]);
+        assert_eq!(backend.pubsub_numsub("news"), 2);
+        assert_eq!(backend.pubsub_numpat(), 1);
+        assert_eq!(news.unsubscribe("news"), 0);
+        assert_eq!(backend.pubsub_numsub("news"), 1);
+        drop(all);
+        assert!(backend.pubsub_channels(None).is_empty());
+        assert_eq!(backend.pubsub_numpat(), 0);
+        assert_eq!(backend.publish("news", b"hi"), 0);
+    }
+
+    #[test]
+    fn test_shard_channels() {
+        let backend = Backend::new();
+        let (mut subscriber, mut rx) = Subscriber::new(&backend);
+        let (mut other, _other_rx) = Subscriber::new(&backend);
+        assert_eq!(subscriber.ssubscribe("{user1}.a"), 1);
+        assert_eq!(subscriber.ssubscribe("{user1}.b"), 2);
+        assert_eq!(subscriber.subscribe("{user1}.a"), 1);
+        other.ssubscribe("news");
+
+        assert_eq!(backend.spublish("{user1}.a", b"hi"), 1);
+        assert_eq!(
+            subscriber.receive(rx.try_recv().unwrap()),
+            RespPush::new([b"smessage".into(), b"{user1}.a".into(), b"hi".into()]).into()
+        );
+        assert!(rx.try_recv().is_err());
+        assert_eq!(
+            backend.pubsub_shard_channels(None),
+            ["news", "{user1}.a", "{user1}.b"]
+        );
+        assert_eq!(backend.pubsub_shard_numsub("news"), 1);
+        assert_eq!(backend.pubsub_numsub("news"), 0);
+
+        let moved = backend.release_slot(key_hash_slot(b"user1"));
+        assert_eq!(moved, ["{user1}.a", "{user1}.b"]);
+        assert_eq!(
+            subscriber.receive(rx.try_recv().unwrap()),
+            RespPush::new([
+                b"sunsubscribe".into(),
+                b"{user1}.a".into(),
+                RespFrame::Integer(1)
+            ])
+            .into()
+        );
+        subscriber.receive(rx.try_recv().unwrap());
+        assert_eq!(subscriber.shard_count(), 0);
+        assert!(subscriber.is_subscribed());
+        assert_eq!(backend.pubsub_shard_channels(None), ["news"]);
+        assert_eq!(backend.spublish("{user1}.a", b"hi"), 0);
+    }
+}
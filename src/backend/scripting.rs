@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedRwLockWriteGuard, RwLockReadGuard};
+
+use crate::backend::Backend;
+
+/// The default `busy-reply-threshold`, in milliseconds.
+pub const SCRIPT_DEFAULT_BUSY_REPLY_THRESHOLD: u64 = 5000;
+
+pub const BUSY_ERROR: &str = "BUSY Redis is busy running a script. You can only call SCRIPT KILL.";
+
+/// How often clients waiting for a script check whether it became busy.
+const BUSY_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The script cache of EVALSHA and the state of the script running, if any. Scripts run while
+/// holding `exec_lock` exclusively, so there is at most one.
+#[derive(Debug)]
+pub struct ScriptState {
+    /// script bodies by the lowercase hex SHA1 of the body
+    cache: Mutex<HashMap<String, String>>,
+    /// when the running script started
+    running: Mutex<Option<Instant>>,
+    /// the running script called a write command, killing it would leave them half done
+    wrote: AtomicBool,
+    /// set by SCRIPT KILL, the running script checks it while it executes
+    kill: AtomicBool,
+    /// milliseconds a script runs before other clients are refused with BUSY
+    busy_reply_threshold: AtomicU64,
+}
+
+/// Marks a script as running until it's dropped.
+pub struct RunningScript<'a> {
+    backend: &'a Backend,
+}
+
+impl Default for ScriptState {
+    fn default() -> Self {
+        Self {
+            cache: Mutex::new(HashMap::new()),
+            running: Mutex::new(None),
+            wrote: AtomicBool::new(false),
+            kill: AtomicBool::new(false),
+            busy_reply_threshold: AtomicU64::new(SCRIPT_DEFAULT_BUSY_REPLY_THRESHOLD),
+        }
+    }
+}
+
+/// The lowercase hex SHA1 scripts are cached under.
+pub fn script_sha(body: &str) -> String {
+    sha1_smol::Sha1::from(body).digest().to_string()
+}
+
+impl Backend {
+    /// Caches `body` for EVALSHA, returns its SHA1.
+    pub fn script_load(&self, body: String) -> String {
+        let sha = script_sha(&body);
+        self.scripts.cache.lock().unwrap().insert(sha.clone(), body);
+        sha
+    }
+
+    pub fn script_get(&self, sha: &str) -> Option<String> {
+        let sha = sha.to_ascii_lowercase();
+        self.scripts.cache.lock().unwrap().get(&sha).cloned()
+    }
+
+    pub fn script_exists(&self, sha: &str) -> bool {
+        let sha = sha.to_ascii_lowercase();
+        self.scripts.cache.lock().unwrap().contains_key(&sha)
+    }
+
+    pub fn script_flush(&self) {
+        self.scripts.cache.lock().unwrap().clear();
+    }
+
+    pub fn busy_reply_threshold(&self) -> u64 {
+        self.scripts.busy_reply_threshold.load(Ordering::Relaxed)
+    }
+
+    pub fn set_busy_reply_threshold(&self, millis: u64) {
+        self.scripts
+            .busy_reply_threshold
+            .store(millis, Ordering::Relaxed);
+    }
+
+    /// Marks a script as running, callers hold `exec_lock` exclusively.
+    pub fn script_start(&self) -> RunningScript<'_> {
+        self.scripts.wrote.store(false, Ordering::Relaxed);
+        self.scripts.kill.store(false, Ordering::Relaxed);
+        *self.scripts.running.lock().unwrap() = Some(Instant::now());
+        RunningScript { backend: self }
+    }
+
+    /// Records that the running script called a write command.
+    pub fn script_wrote(&self) {
+        self.scripts.wrote.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether SCRIPT KILL asked the running script to stop.
+    pub fn script_killed(&self) -> bool {
+        self.scripts.kill.load(Ordering::Relaxed)
+    }
+
+    /// Whether a script has been running for longer than the busy reply threshold.
+    pub fn script_busy(&self) -> bool {
+        self.scripts.running.lock().unwrap().is_some_and(|started| {
+            started.elapsed() >= Duration::from_millis(self.busy_reply_threshold())
+        })
+    }
+
+    /// Asks the running script to stop, unless it already wrote something.
+    pub fn script_kill(&self) -> Result<(), String> {
+        if self.scripts.running.lock().unwrap().is_none() {
+            return Err("NOTBUSY No scripts in execution right now.".to_string());
+        }
+        if self.scripts.wrote.load(Ordering::Relaxed) {
+            return Err("UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or kill the server in a hard way.".to_string());
+        }
+        self.scripts.kill.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Takes `exec_lock` shared to execute a command. A client waiting for a script that runs
+    /// past the busy reply threshold is refused with BUSY instead.
+    pub async fn exec_shared(&self) -> Result<RwLockReadGuard<'_, ()>, String> {
+        tokio::select! {
+            biased;
+            guard = self.exec_lock.read() => Ok(guard),
+            _ = self.script_became_busy() => Err(BUSY_ERROR.to_string()),
+        }
+    }
+
+    /// Like [`Backend::exec_shared`], but exclusively for transactions and scripts. The guard
+    /// is owned so that scripts can run on the blocking pool.
+    pub async fn exec_exclusive(&self) -> Result<OwnedRwLockWriteGuard<()>, String> {
+        tokio::select! {
+            biased;
+            guard = self.exec_lock.clone().write_owned() => Ok(guard),
+            _ = self.script_became_busy() => Err(BUSY_ERROR.to_string()),
+        }
+    }
+
+    async fn script_became_busy(&self) {
+        while !self.script_busy() {
+            tokio::time::sleep(BUSY_CHECK_INTERVAL).await;
+        }
+    }
+}
+
+impl Drop for RunningScript<'_> {
+    fn drop(&mut self) {
+        let scripts = &self.backend.scripts;
+        *scripts.running.lock().unwrap() = None;
+        scripts.kill.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_cache() {
+        let backend = Backend::new();
+        let sha = backend.script_load("return 1".to_string());
+        assert_eq!(sha, "e0e1f9fabfc9d4800c877a703b823ac0578ff8db");
+        assert!(backend.script_exists(&sha.to_ascii_uppercase()));
+        assert_eq!(backend.script_get(&sha).as_deref(), Some("return 1"));
+        backend.script_flush();
+        assert!(!backend.script_exists(&sha));
+    }
+
+    #[test]
+    fn test_script_kill() {
+        let backend = Backend::new();
+        assert!(backend.script_kill().unwrap_err().starts_with("NOTBUSY"));
+
+        let running = backend.script_start();
+        backend.set_busy_reply_threshold(0);
+        assert!(backend.script_busy());
+        assert!(backend.script_kill().is_ok());
+        assert!(backend.script_killed());
+        drop(running);
+        assert!(!backend.script_busy());
+        assert!(!backend.script_killed());
+
+        let _running = backend.script_start();
+        backend.script_wrote();
+        assert!(backend.script_kill().unwrap_err().starts_with("UNKILLABLE"));
+    }
+}
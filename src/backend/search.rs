@@ -0,0 +1,1078 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{Bound, RangeBounds};
+
+use dashmap::mapref::entry::Entry;
+
+use crate::backend::Backend;
+use crate::frame::{format_double, RespFrame};
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+/// The stopwords an index skips unless FT.CREATE sets its own.
+pub const DEFAULT_STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it",
+    "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldKind {
+    /// tokenized, stemmed unless `nostem`, and scored with BM25
+    Text {
+        weight: f64,
+        nostem: bool,
+    },
+    /// split on `separator`, matched exactly
+    Tag {
+        separator: char,
+        case_sensitive: bool,
+    },
+    Numeric,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSpec {
+    /// the hash field that is indexed
+    pub name: String,
+    /// how queries and replies refer to the field, the name itself unless set with AS
+    pub alias: String,
+    pub kind: FieldKind,
+    pub sortable: bool,
+}
+
+/// A parsed FT.SEARCH query, fields are positions in the schema of the index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    All,
+    /// a lowercased word, stemmed against the fields that are
+    Term {
+        field: Option<usize>,
+        term: String,
+    },
+    Prefix {
+        field: Option<usize>,
+        prefix: String,
+    },
+    /// words that follow each other in the same field
+    Phrase {
+        field: Option<usize>,
+        terms: Vec<String>,
+    },
+    Tag {
+        field: usize,
+        tags: Vec<String>,
+    },
+    Numeric {
+        field: usize,
+        min: Bound<f64>,
+        max: Bound<f64>,
+    },
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+}
+
+/// The text fields of a document that hold a term, with the positions of the term in each.
+type Postings = Vec<(usize, Vec<u32>)>;
+
+#[derive(Debug)]
+struct Document {
+    /// the raw value of each schema field, by position in the schema
+    values: Vec<Option<String>>,
+    /// how many terms the text fields hold, the length BM25 normalizes with
+    len: usize,
+}
+
+/// A secondary index over the hashes whose key starts with one of its prefixes.
+#[derive(Debug)]
+pub struct SearchIndex {
+    prefixes: Vec<String>,
+    stopwords: HashSet<String>,
+    schema: Vec<FieldSpec>,
+    docs: HashMap<String, Document>,
+    /// term -> document -> the positions of the term in each text field
+    terms: BTreeMap<String, HashMap<String, Postings>>,
+    /// (field, tag) -> documents
+    tags: HashMap<(usize, String), HashSet<String>>,
+    total_len: usize,
+}
+
+impl FieldSpec {
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            FieldKind::Text { .. } => "TEXT",
+            FieldKind::Tag { .. } => "TAG",
+            FieldKind::Numeric => "NUMERIC",
+        }
+    }
+
+    /// The form a word of a query or a document is indexed under for this field.
+    fn term(&self, word: &str) -> String {
+        match self.kind {
+            FieldKind::Text { nostem: false, .. } => stem(word),
+            _ => word.to_string(),
+        }
+    }
+
+    fn weight(&self) -> f64 {
+        match self.kind {
+            FieldKind::Text { weight, .. } => weight,
+            _ => 0.0,
+        }
+    }
+
+    fn tags(&self, value: &str) -> Vec<String> {
+        let FieldKind::Tag {
+            separator,
+            case_sensitive,
+        } = self.kind
+        else {
+            return vec![];
+        };
+        value
+            .split(separator)
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| {
+                if case_sensitive {
+                    tag.to_string()
+                } else {
+                    tag.to_lowercase()
+                }
+            })
+            .collect()
+    }
+}
+
+impl SearchIndex {
+    /// An empty prefix list covers every key, `stopwords` defaults to [`DEFAULT_STOPWORDS`].
+    pub fn new(
+        prefixes: Vec<String>,
+        stopwords: Option<Vec<String>>,
+        schema: Vec<FieldSpec>,
+    ) -> Self {
+        let stopwords = match stopwords {
+            Some(words) => words.into_iter().map(|word| word.to_lowercase()).collect(),
+            None => DEFAULT_STOPWORDS
+                .iter()
+                .map(|word| word.to_string())
+                .collect(),
+        };
+        Self {
+            prefixes,
+            stopwords,
+            schema,
+            docs: HashMap::new(),
+            terms: BTreeMap::new(),
+            tags: HashMap::new(),
+            total_len: 0,
+        }
+    }
+
+    pub fn prefixes(&self) -> &[String] {
+        &self.prefixes
+    }
+
+    pub fn schema(&self) -> &[FieldSpec] {
+        &self.schema
+    }
+
+    pub fn num_docs(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn num_terms(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// How many (term, document) pairs the inverted index holds.
+    pub fn num_records(&self) -> usize {
+        self.terms.values().map(HashMap::len).sum()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.docs.keys()
+    }
+
+    /// The position in the schema of the field called `alias`.
+    pub fn field(&self, alias: &str) -> Option<usize> {
+        self.schema.iter().position(|spec| spec.alias == alias)
+    }
+
+    pub fn covers(&self, key: &str) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|prefix| key.starts_with(prefix))
+    }
+
+    /// Re-indexes `key` from the string fields of its hash, `None` when the hash is gone.
+    pub fn update(&mut self, key: &str, fields: Option<&HashMap<String, String>>) {
+        self.remove(key);
+        if let Some(fields) = fields {
+            self.add(key, fields);
+        }
+    }
+
+    fn add(&mut self, key: &str, fields: &HashMap<String, String>) {
+        let mut doc = Document {
+            values: vec![None; self.schema.len()],
+            len: 0,
+        };
+        for (i, spec) in self.schema.iter().enumerate() {
+            let Some(value) = fields.get(&spec.name) else {
+                continue;
+            };
+            match spec.kind {
+                FieldKind::Text { .. } => {
+                    let words = tokenize(value, &self.stopwords);
+                    doc.len += words.len();
+                    for (position, word) in words.iter().enumerate() {
+                        let postings = self
+                            .terms
+                            .entry(spec.term(word))
+                            .or_default()
+                            .entry(key.to_string())
+                            .or_default();
+                        if postings.last().map(|(field, _)| *field) != Some(i) {
+                            postings.push((i, vec![]));
+                        }
+                        if let Some((_, positions)) = postings.last_mut() {
+                            positions.push(position as u32);
+                        }
+                    }
+                }
+                FieldKind::Tag { .. } => {
+                    for tag in spec.tags(value) {
+                        self.tags
+                            .entry((i, tag))
+                            .or_default()
+                            .insert(key.to_string());
+                    }
+                }
+                FieldKind::Numeric => {}
+            }
+            doc.values[i] = Some(value.clone());
+        }
+        self.total_len += doc.len;
+        self.docs.insert(key.to_string(), doc);
+    }
+
+    fn remove(&mut self, key: &str) {
+        let Some(doc) = self.docs.remove(key) else {
+            return;
+        };
+        self.total_len -= doc.len;
+        for (i, spec) in self.schema.iter().enumerate() {
+            let Some(value) = &doc.values[i] else {
+                continue;
+            };
+            match spec.kind {
+                FieldKind::Text { .. } => {
+                    for word in tokenize(value, &self.stopwords) {
+                        let term = spec.term(&word);
+                        if let Some(postings) = self.terms.get_mut(&term) {
+                            postings.remove(key);
+                            if postings.is_empty() {
+                                self.terms.remove(&term);
+                            }
+                        }
+                    }
+                }
+                FieldKind::Tag { .. } => {
+                    for tag in spec.tags(value) {
+                        let tag = (i, tag);
+                        if let Some(keys) = self.tags.get_mut(&tag) {
+                            keys.remove(key);
+                            if keys.is_empty() {
+                                self.tags.remove(&tag);
+                            }
+                        }
+                    }
+                }
+                FieldKind::Numeric => {}
+            }
+        }
+    }
+
+    /// The raw value of a schema field of an indexed document.
+    pub fn value(&self, key: &str, field: usize) -> Option<&str> {
+        self.docs.get(key)?.values.get(field)?.as_deref()
+    }
+
+    /// The position of the field SORTBY orders by.
+    pub fn sortable(&self, alias: &str) -> Result<usize, String> {
+        let field = self
+            .field(alias)
+            .ok_or_else(|| format!("Property `{}` not loaded nor in schema", alias))?;
+        if !self.schema[field].sortable {
+            return Err(format!("Property `{}` is not sortable", alias));
+        }
+        Ok(field)
+    }
+
+    /// Orders two documents by a field, numerically for NUMERIC fields and case-insensitively
+    /// otherwise. Documents without the field come last.
+    pub fn compare(&self, a: &str, b: &str, field: usize) -> Ordering {
+        match (self.value(a, field), self.value(b, field)) {
+            (Some(a), Some(b)) => match self.schema[field].kind {
+                FieldKind::Numeric => parse_number(a).total_cmp(&parse_number(b)),
+                _ => a.to_lowercase().cmp(&b.to_lowercase()),
+            },
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    /// The documents matching `query` with their score, best first.
+    pub fn search(&self, query: &Query) -> Vec<(String, f64)> {
+        let mut hits: Vec<(String, f64)> = self
+            .eval(query)
+            .into_iter()
+            .map(|(key, score)| (key.to_string(), score))
+            .collect();
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        hits
+    }
+
+    fn eval(&self, query: &Query) -> HashMap<&str, f64> {
+        match query {
+            Query::All => self.docs.keys().map(|key| (key.as_str(), 1.0)).collect(),
+            Query::Term { field, term } => self.bm25(self.phrase_frequencies(*field, &[term])),
+            Query::Phrase { field, terms } => {
+                let terms: Vec<&String> = terms.iter().collect();
+                self.bm25(self.phrase_frequencies(*field, &terms))
+            }
+            Query::Prefix { field, prefix } => {
+                let mut frequencies = HashMap::new();
+                let terms = self
+                    .terms
+                    .range(prefix.clone()..)
+                    .take_while(|(term, _)| term.starts_with(prefix.as_str()));
+                for (_, postings) in terms {
+                    for (key, fields) in postings {
+                        for (i, positions) in fields {
+                            if field.is_none_or(|field| field == *i) {
+                                *frequencies.entry(key.as_str()).or_default() +=
+                                    self.schema[*i].weight() * positions.len() as f64;
+                            }
+                        }
+                    }
+                }
+                self.bm25(frequencies)
+            }
+            Query::Tag { field, tags } => tags
+                .iter()
+                .filter_map(|tag| self.tags.get(&(*field, tag.clone())))
+                .flatten()
+                .map(|key| (key.as_str(), 0.0))
+                .collect(),
+            Query::Numeric { field, min, max } => self
+                .docs
+                .iter()
+                .filter(|(_, doc)| {
+                    doc.values[*field]
+                        .as_deref()
+                        .and_then(|value| value.trim().parse::<f64>().ok())
+                        .is_some_and(|value| (*min, *max).contains(&value))
+                })
+                .map(|(key, _)| (key.as_str(), 0.0))
+                .collect(),
+            Query::And(queries) => {
+                let mut queries = queries.iter();
+                let Some(first) = queries.next() else {
+                    return HashMap::new();
+                };
+                let mut matched = self.eval(first);
+                for query in queries {
+                    let other = self.eval(query);
+                    matched.retain(|key, score| match other.get(key) {
+                        Some(other) => {
+                            *score += other;
+                            true
+                        }
+                        None => false,
+                    });
+                }
+                matched
+            }
+            Query::Or(queries) => {
+                let mut matched: HashMap<&str, f64> = HashMap::new();
+                for query in queries {
+                    for (key, score) in self.eval(query) {
+                        *matched.entry(key).or_default() += score;
+                    }
+                }
+                matched
+            }
+            Query::Not(query) => {
+                let excluded = self.eval(query);
+                self.docs
+                    .keys()
+                    .filter(|key| !excluded.contains_key(key.as_str()))
+                    .map(|key| (key.as_str(), 0.0))
+                    .collect()
+            }
+        }
+    }
+
+    /// How often, weighted by field, each document holds `words` one after the other.
+    fn phrase_frequencies(&self, field: Option<usize>, words: &[&String]) -> HashMap<&str, f64> {
+        let mut frequencies = HashMap::new();
+        let Some((first, rest)) = words.split_first() else {
+            return frequencies;
+        };
+        for (i, spec) in self.schema.iter().enumerate() {
+            if !matches!(spec.kind, FieldKind::Text { .. }) || field.is_some_and(|f| f != i) {
+                continue;
+            }
+            let Some(postings) = self.terms.get(&spec.term(first)) else {
+                continue;
+            };
+            let rest: Vec<String> = rest.iter().map(|word| spec.term(word)).collect();
+            for (key, fields) in postings {
+                let Some((_, positions)) = fields.iter().find(|(f, _)| *f == i) else {
+                    continue;
+                };
+                let count = positions
+                    .iter()
+                    .filter(|position| {
+                        rest.iter().enumerate().all(|(offset, term)| {
+                            self.positions(term, key, i).is_some_and(|next| {
+                                next.contains(&(**position + offset as u32 + 1))
+                            })
+                        })
+                    })
+                    .count();
+                if count > 0 {
+                    *frequencies.entry(key.as_str()).or_default() += spec.weight() * count as f64;
+                }
+            }
+        }
+        frequencies
+    }
+
+    fn positions(&self, term: &str, key: &str, field: usize) -> Option<&Vec<u32>> {
+        self.terms
+            .get(term)?
+            .get(key)?
+            .iter()
+            .find(|(f, _)| *f == field)
+            .map(|(_, positions)| positions)
+    }
+
+    fn bm25<'a>(&self, frequencies: HashMap<&'a str, f64>) -> HashMap<&'a str, f64> {
+        let n = self.docs.len() as f64;
+        let df = frequencies.len() as f64;
+        let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+        let avg_len = (self.total_len as f64 / n.max(1.0)).max(1.0);
+        frequencies
+            .into_iter()
+            .map(|(key, tf)| {
+                let len = self.docs.get(key).map_or(0, |doc| doc.len) as f64;
+                let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * len / avg_len);
+                (key, idf * tf * (BM25_K1 + 1.0) / (tf + norm))
+            })
+            .collect()
+    }
+
+    /// Parses the FT.SEARCH query syntax: words, `"phrases"`, `prefix*`, `-negation`,
+    /// `a | b`, `(groups)`, and `@field:` followed by a word, a group, `{tag | tag}` or
+    /// `[min max]` where `(` makes a bound exclusive. `*` matches every document.
+    pub fn parse_query(&self, query: &str) -> Result<Query, String> {
+        let mut parser = QueryParser {
+            chars: query.chars().collect(),
+            pos: 0,
+            index: self,
+        };
+        let parsed = parser.parse_union(None)?;
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(format!("Syntax error at offset {}", parser.pos));
+        }
+        // a query made only of stopwords matches nothing
+        Ok(parsed.unwrap_or(Query::Or(vec![])))
+    }
+}
+
+struct QueryParser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    index: &'a SearchIndex,
+}
+
+impl QueryParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() != Some(c) {
+            return Err(format!(
+                "Syntax error at offset {}: expected `{}`",
+                self.pos, c
+            ));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Reads up to the unescaped `end`, which is consumed.
+    fn until(&mut self, end: char) -> Result<String, String> {
+        let start = self.pos;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(format!("Syntax error at offset {}: unclosed", start)),
+                Some('\\') => {
+                    text.push('\\');
+                    self.pos += 1;
+                    if let Some(c) = self.peek() {
+                        text.push(c);
+                        self.pos += 1;
+                    }
+                }
+                Some(c) if c == end => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(is_word_char) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn parse_union(&mut self, field: Option<usize>) -> Result<Option<Query>, String> {
+        let mut alternatives = vec![];
+        loop {
+            if let Some(query) = self.parse_intersect(field)? {
+                alternatives.push(query);
+            }
+            self.skip_whitespace();
+            if self.peek() != Some('|') {
+                break;
+            }
+            self.pos += 1;
+        }
+        Ok(match alternatives.len() {
+            0 => None,
+            1 => alternatives.pop(),
+            _ => Some(Query::Or(alternatives)),
+        })
+    }
+
+    fn parse_intersect(&mut self, field: Option<usize>) -> Result<Option<Query>, String> {
+        let mut queries = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some(')') | Some('|') => break,
+                _ => {
+                    if let Some(query) = self.parse_unary(field)? {
+                        queries.push(query);
+                    }
+                }
+            }
+        }
+        Ok(match queries.len() {
+            0 => None,
+            1 => queries.pop(),
+            _ => Some(Query::And(queries)),
+        })
+    }
+
+    fn parse_unary(&mut self, field: Option<usize>) -> Result<Option<Query>, String> {
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(self
+                    .parse_unary(field)?
+                    .map(|query| Query::Not(Box::new(query))))
+            }
+            Some('(') => {
+                self.pos += 1;
+                let query = self.parse_union(field)?;
+                self.expect(')')?;
+                Ok(query)
+            }
+            Some('@') => {
+                self.pos += 1;
+                let name = self.word();
+                self.expect(':')?;
+                let i = self
+                    .index
+                    .field(&name)
+                    .ok_or_else(|| format!("Unknown field `{}`", name))?;
+                self.skip_whitespace();
+                let spec = &self.index.schema[i];
+                match (&spec.kind, self.peek()) {
+                    (FieldKind::Tag { .. }, Some('{')) => {
+                        self.pos += 1;
+                        let tags = split_unescaped(&self.until('}')?, '|')
+                            .iter()
+                            .flat_map(|tag| spec.tags(tag))
+                            .collect();
+                        Ok(Some(Query::Tag { field: i, tags }))
+                    }
+                    (FieldKind::Numeric, Some('[')) => {
+                        self.pos += 1;
+                        let range = self.until(']')?.replace(',', " ");
+                        let bounds: Vec<&str> = range.split_whitespace().collect();
+                        let [min, max] = bounds[..] else {
+                            return Err(format!("Bad numeric range `{}`", range));
+                        };
+                        Ok(Some(Query::Numeric {
+                            field: i,
+                            min: parse_bound(min)?,
+                            max: parse_bound(max)?,
+                        }))
+                    }
+                    (FieldKind::Text { .. }, _) => self.parse_unary(Some(i)),
+                    _ => Err(format!(
+                        "Syntax error at offset {}: unexpected query for {} field `{}`",
+                        self.pos,
+                        spec.type_name(),
+                        name
+                    )),
+                }
+            }
+            Some('"') => {
+                self.pos += 1;
+                let mut terms = tokenize(&self.until('"')?, &self.index.stopwords);
+                Ok(match terms.len() {
+                    0 => None,
+                    1 => terms.pop().map(|term| Query::Term { field, term }),
+                    _ => Some(Query::Phrase { field, terms }),
+                })
+            }
+            Some('*') => {
+                self.pos += 1;
+                Ok(Some(Query::All))
+            }
+            Some(c) if is_word_char(c) => {
+                let word = self.word().to_lowercase();
+                if self.peek() == Some('*') {
+                    self.pos += 1;
+                    if word.chars().count() < 2 {
+                        return Err("Prefix queries need at least two characters".to_string());
+                    }
+                    return Ok(Some(Query::Prefix {
+                        field,
+                        prefix: word,
+                    }));
+                }
+                if self.index.stopwords.contains(&word) {
+                    return Ok(None);
+                }
+                Ok(Some(Query::Term { field, term: word }))
+            }
+            // other punctuation separates words, like it does when indexing
+            _ => {
+                self.pos += 1;
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Splits text into lowercased words, leaving out stopwords.
+fn tokenize(text: &str, stopwords: &HashSet<String>) -> Vec<String> {
+    text.split(|c: char| !is_word_char(c))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .filter(|word| !stopwords.contains(word))
+        .collect()
+}
+
+fn split_unescaped(text: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let (Some(next), Some(part)) = (chars.next(), parts.last_mut()) {
+                    part.push(next);
+                }
+            }
+            c if c == separator => parts.push(String::new()),
+            c => {
+                if let Some(part) = parts.last_mut() {
+                    part.push(c);
+                }
+            }
+        }
+    }
+    parts
+}
+
+fn parse_bound(bound: &str) -> Result<Bound<f64>, String> {
+    let (exclusive, value) = match bound.strip_prefix('(') {
+        Some(value) => (true, value),
+        None => (false, bound),
+    };
+    let value: f64 = value
+        .parse()
+        .map_err(|_| format!("Bad numeric bound `{}`", bound))?;
+    Ok(if value.is_infinite() {
+        Bound::Unbounded
+    } else if exclusive {
+        Bound::Excluded(value)
+    } else {
+        Bound::Included(value)
+    })
+}
+
+fn parse_number(value: &str) -> f64 {
+    value.trim().parse().unwrap_or(f64::NAN)
+}
+
+/// Reduces an English word to its stem with the first steps of the Porter algorithm, enough
+/// for plurals, `-ed`, `-ing` and the common derivational suffixes.
+pub fn stem(word: &str) -> String {
+    if word.len() <= 2 || !word.bytes().all(|b| b.is_ascii_lowercase()) {
+        return word.to_string();
+    }
+    let mut w = word.as_bytes().to_vec();
+
+    // step 1a: plurals
+    if w.ends_with(b"sses") || w.ends_with(b"ies") {
+        w.truncate(w.len() - 2);
+    } else if !w.ends_with(b"ss") && w.ends_with(b"s") {
+        w.pop();
+    }
+
+    // step 1b: past tense and gerunds
+    if w.ends_with(b"eed") {
+        if measure(&w[..w.len() - 3]) > 0 {
+            w.pop();
+        }
+    } else if let Some(len) = [&b"ed"[..], b"ing"]
+        .iter()
+        .find(|suffix| w.ends_with(suffix) && has_vowel(&w[..w.len() - suffix.len()]))
+        .map(|suffix| w.len() - suffix.len())
+    {
+        w.truncate(len);
+        if w.ends_with(b"at") || w.ends_with(b"bl") || w.ends_with(b"iz") {
+            w.push(b'e');
+        } else if ends_double_consonant(&w) && !matches!(w.last(), Some(b'l' | b's' | b'z')) {
+            w.pop();
+        } else if measure(&w) == 1 && ends_cvc(&w) {
+            w.push(b'e');
+        }
+    }
+
+    // step 1c
+    if w.ends_with(b"y") && has_vowel(&w[..w.len() - 1]) {
+        w.pop();
+        w.push(b'i');
+    }
+
+    // steps 2 and 3: derivational suffixes
+    const SUFFIXES: &[(&str, &str)] = &[
+        ("ational", "ate"),
+        ("tional", "tion"),
+        ("enci", "ence"),
+        ("anci", "ance"),
+        ("izer", "ize"),
+        ("alli", "al"),
+        ("entli", "ent"),
+        ("eli", "e"),
+        ("ousli", "ous"),
+        ("ization", "ize"),
+        ("ation", "ate"),
+        ("ator", "ate"),
+        ("alism", "al"),
+        ("iveness", "ive"),
+        ("fulness", "ful"),
+        ("ousness", "ous"),
+        ("aliti", "al"),
+        ("iviti", "ive"),
+        ("biliti", "ble"),
+        ("icate", "ic"),
+        ("ative", ""),
+        ("alize", "al"),
+        ("iciti", "ic"),
+        ("ical", "ic"),
+        ("ful", ""),
+        ("ness", ""),
+    ];
+    for (suffix, replacement) in SUFFIXES {
+        if w.ends_with(suffix.as_bytes()) {
+            let len = w.len() - suffix.len();
+            if measure(&w[..len]) > 0 {
+                w.truncate(len);
+                w.extend_from_slice(replacement.as_bytes());
+            }
+            break;
+        }
+    }
+
+    String::from_utf8(w).unwrap_or_else(|_| word.to_string())
+}
+
+fn is_consonant(w: &[u8], i: usize) -> bool {
+    match w[i] {
+        b'a' | b'e' | b'i' | b'o' | b'u' => false,
+        b'y' => i == 0 || !is_consonant(w, i - 1),
+        _ => true,
+    }
+}
+
+/// The number of vowel-consonant sequences in `w`.
+fn measure(w: &[u8]) -> usize {
+    let mut m = 0;
+    let mut after_vowel = false;
+    for i in 0..w.len() {
+        let consonant = is_consonant(w, i);
+        if consonant && after_vowel {
+            m += 1;
+        }
+        after_vowel = !consonant;
+    }
+    m
+}
+
+fn has_vowel(w: &[u8]) -> bool {
+    (0..w.len()).any(|i| !is_consonant(w, i))
+}
+
+fn ends_double_consonant(w: &[u8]) -> bool {
+    let n = w.len();
+    n >= 2 && w[n - 1] == w[n - 2] && is_consonant(w, n - 1)
+}
+
+fn ends_cvc(w: &[u8]) -> bool {
+    let n = w.len();
+    n >= 3
+        && is_consonant(w, n - 3)
+        && !is_consonant(w, n - 2)
+        && is_consonant(w, n - 1)
+        && !matches!(w[n - 1], b'w' | b'x' | b'y')
+}
+
+// Lock order: `search` is only locked once the `hmap` guards are released, the hash commands
+// call `ft_reindex` after they are done with the hash.
+impl Backend {
+    /// Registers a new index and indexes the hashes it already covers.
+    pub fn ft_create(&self, name: String, index: SearchIndex) -> Result<(), String> {
+        let keys: Vec<String> = self
+            .hmap
+            .iter()
+            .map(|hmap| hmap.key().clone())
+            .filter(|key| index.covers(key))
+            .collect();
+        match self.search.entry(name.clone()) {
+            Entry::Occupied(_) => return Err("Index already exists".to_string()),
+            Entry::Vacant(entry) => {
+                entry.insert(index);
+            }
+        }
+        for key in keys {
+            let fields = self.hash_strings(&key);
+            if let Some(mut index) = self.search.get_mut(&name) {
+                index.update(&key, fields.as_ref());
+            }
+        }
+        Ok(())
+    }
+
+    /// Brings the indexes covering `key` in line with the hash stored there, or drops the key
+    /// from them when the hash is gone.
+    pub(crate) fn ft_reindex(&self, key: &str) {
+        if !self.search.iter().any(|index| index.covers(key)) {
+            return;
+        }
+        let fields = self.hash_strings(key);
+        for mut index in self.search.iter_mut() {
+            if index.covers(key) {
+                index.update(key, fields.as_ref());
+            }
+        }
+    }
+
+    /// The fields of the hash at `key` that hold text or numbers, as strings.
+    fn hash_strings(&self, key: &str) -> Option<HashMap<String, String>> {
+        self.hmap.get(key).map(|hmap| {
+            hmap.iter()
+                .filter_map(|field| {
+                    let value = match field.value() {
+                        RespFrame::BulkString(s) => String::from_utf8(s.0.clone()).ok()?,
+                        RespFrame::SimpleString(s) => s.0.clone(),
+                        RespFrame::Integer(i) => i.to_string(),
+                        RespFrame::Double(f) => format_double(*f),
+                        _ => return None,
+                    };
+                    Some((field.key().clone(), value))
+                })
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> SearchIndex {
+        let schema = vec![
+            FieldSpec {
+                name: "title".to_string(),
+                alias: "title".to_string(),
+                kind: FieldKind::Text {
+                    weight: 2.0,
+                    nostem: false,
+                },
+                sortable: true,
+            },
+            FieldSpec {
+                name: "body".to_string(),
+                alias: "body".to_string(),
+                kind: FieldKind::Text {
+                    weight: 1.0,
+                    nostem: false,
+                },
+                sortable: false,
+            },
+            FieldSpec {
+                name: "tags".to_string(),
+                alias: "tags".to_string(),
+                kind: FieldKind::Tag {
+                    separator: ',',
+                    case_sensitive: false,
+                },
+                sortable: false,
+            },
+            FieldSpec {
+                name: "price".to_string(),
+                alias: "price".to_string(),
+                kind: FieldKind::Numeric,
+                sortable: true,
+            },
+        ];
+        let mut index = SearchIndex::new(vec!["doc:".to_string()], None, schema);
+        for (key, fields) in [
+            (
+                "doc:1",
+                [
+                    ("title", "Running shoes"),
+                    ("body", "light shoes for the long runs"),
+                    ("tags", "Sport, Outdoor"),
+                    ("price", "80"),
+                ],
+            ),
+            (
+                "doc:2",
+                [
+                    ("title", "Hiking boots"),
+                    ("body", "boots that run through mud and snow"),
+                    ("tags", "outdoor"),
+                    ("price", "120"),
+                ],
+            ),
+            (
+                "doc:3",
+                [
+                    ("title", "Reading lamp"),
+                    ("body", "a lamp for long evenings"),
+                    ("tags", "home"),
+                    ("price", "35.5"),
+                ],
+            ),
+        ] {
+            let fields = fields
+                .iter()
+                .map(|(field, value)| (field.to_string(), value.to_string()))
+                .collect();
+            index.update(key, Some(&fields));
+        }
+        index
+    }
+
+    fn keys(index: &SearchIndex, query: &str) -> Vec<String> {
+        let mut keys: Vec<String> = index
+            .search(&index.parse_query(query).unwrap())
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_stem() {
+        assert_eq!(stem("running"), "run");
+        assert_eq!(stem("runs"), "run");
+        assert_eq!(stem("ponies"), "poni");
+        assert_eq!(stem("caresses"), "caress");
+        assert_eq!(stem("hopping"), "hop");
+        assert_eq!(stem("hoping"), "hope");
+        assert_eq!(stem("relational"), "relate");
+        assert_eq!(stem("is"), "is");
+    }
+
+    #[test]
+    fn test_search_index_queries() {
+        let index = index();
+        assert_eq!(keys(&index, "run"), ["doc:1", "doc:2"]);
+        assert_eq!(keys(&index, "@title:run"), ["doc:1"]);
+        assert_eq!(keys(&index, "shoes -@tags:{sport}"), Vec::<String>::new());
+        assert_eq!(keys(&index, "lamp | boots"), ["doc:2", "doc:3"]);
+        assert_eq!(keys(&index, "\"long evenings\""), ["doc:3"]);
+        assert_eq!(keys(&index, "\"evenings long\""), Vec::<String>::new());
+        assert_eq!(
+            keys(&index, "@tags:{outdoor | HOME}"),
+            ["doc:1", "doc:2", "doc:3"]
+        );
+        assert_eq!(keys(&index, "@price:[(35.5 +inf]"), ["doc:1", "doc:2"]);
+        assert_eq!(
+            keys(&index, "@price:[-inf 100] @body:(long)"),
+            ["doc:1", "doc:3"]
+        );
+        assert_eq!(keys(&index, "hik*"), ["doc:2"]);
+        assert_eq!(keys(&index, "the"), Vec::<String>::new());
+        assert_eq!(keys(&index, "*").len(), 3);
+        assert!(index.parse_query("@missing:foo").is_err());
+        assert!(index.parse_query("@price:{foo}").is_err());
+
+        // a match in the heavier title field ranks higher
+        let hits = index.search(&index.parse_query("run").unwrap());
+        assert_eq!(hits[0].0, "doc:1");
+        assert!(hits[0].1 > hits[1].1);
+    }
+
+    #[test]
+    fn test_search_index_update() {
+        let mut index = index();
+        let fields = HashMap::from([("title".to_string(), "Trail shoes".to_string())]);
+        index.update("doc:1", Some(&fields));
+        assert_eq!(keys(&index, "running"), ["doc:2"]);
+        assert_eq!(keys(&index, "trail"), ["doc:1"]);
+        assert_eq!(keys(&index, "@tags:{sport}"), Vec::<String>::new());
+
+        index.update("doc:2", None);
+        assert_eq!(index.num_docs(), 2);
+        assert_eq!(keys(&index, "boots"), Vec::<String>::new());
+        assert!(!index.terms.contains_key("boot"));
+    }
+}
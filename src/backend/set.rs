@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+
+use crate::backend::{move_between, Backend, NOTIFY_GENERIC, NOTIFY_SET};
+
+impl Backend {
+    pub fn set_members(&self, key: &str) -> HashSet<String> {
+        self.set
+            .get(key)
+            .map(|v| v.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// Adds `members`, returning how many of them were not in the set yet.
+    pub fn set_add(&self, key: &str, members: Vec<String>) -> usize {
+        let (added, created) = {
+            let mut set = self.set.entry(key.to_string()).or_default();
+            let created = set.is_empty();
+            let added = members
+                .into_iter()
+                .filter(|m| set.insert(m.clone()))
+                .count();
+            (added, created)
+        };
+        if added > 0 {
+            self.notify_new(created, key);
+            self.notify(NOTIFY_SET, "sadd", key);
+        }
+        added
+    }
+
+    /// Removes `members`, dropping the set once it is empty. Returns how many were removed.
+    pub fn set_remove(&self, key: &str, members: &[String]) -> usize {
+        let removed = match self.set.get_mut(key) {
+            Some(mut set) => members.iter().filter(|m| set.remove(*m)).count(),
+            None => 0,
+        };
+        if removed > 0 {
+            self.notify(NOTIFY_SET, "srem", key);
+        }
+        self.set_remove_if_empty(key);
+        removed
+    }
+
+    /// Moves `member` from `src` to `dst` as one step, for SMOVE. Returns whether it was in `src`.
+    /// `src` and `dst` must differ.
+    pub fn set_move(&self, src: &str, dst: &str, member: &str) -> bool {
+        let moved = move_between(&self.set, src, dst, HashSet::is_empty, |from, to| {
+            let member = from.take(member)?;
+            Some(to.insert(member))
+        });
+        let Some(moved) = moved else {
+            return false;
+        };
+        self.notify(NOTIFY_SET, "srem", src);
+        if moved.emptied {
+            self.notify(NOTIFY_GENERIC, "del", src);
+        }
+        if moved.value {
+            self.notify_new(moved.created, dst);
+            self.notify(NOTIFY_SET, "sadd", dst);
+        }
+        true
+    }
+
+    /// Replaces `key` with `members`, an empty result deletes the key. Returns the cardinality.
+    /// `event` is notified for the stored key, `del` when an existing key was deleted instead.
+    pub fn set_store(&self, key: &str, members: HashSet<String>, event: &str) -> usize {
+        let len = members.len();
+        if members.is_empty() {
+            if self.set.remove(key).is_some() {
+                self.notify(NOTIFY_GENERIC, "del", key);
+            }
+        } else {
+            let created = self.set.insert(key.to_string(), members).is_none();
+            self.notify_new(created, key);
+            self.notify(NOTIFY_SET, event, key);
+        }
+        len
+    }
+
+    pub fn set_inter(&self, keys: &[String]) -> HashSet<String> {
+        let mut keys = keys.iter();
+        let mut ret = match keys.next() {
+            Some(key) => self.set_members(key),
+            None => return HashSet::new(),
+        };
+        for key in keys {
+            if ret.is_empty() {
+                break;
+            }
+            match self.set.get(key) {
+                Some(set) => ret.retain(|m| set.contains(m)),
+                None => ret.clear(),
+            }
+        }
+        ret
+    }
+
+    pub fn set_union(&self, keys: &[String]) -> HashSet<String> {
+        let mut ret = HashSet::new();
+        for key in keys {
+            if let Some(set) = self.set.get(key) {
+                ret.extend(set.iter().cloned());
+            }
+        }
+        ret
+    }
+
+    pub fn set_diff(&self, keys: &[String]) -> HashSet<String> {
+        let mut keys = keys.iter();
+        let mut ret = match keys.next() {
+            Some(key) => self.set_members(key),
+            None => return HashSet::new(),
+        };
+        for key in keys {
+            if let Some(set) = self.set.get(key) {
+                ret.retain(|m| !set.contains(m));
+            }
+        }
+        ret
+    }
+
+    pub(crate) fn set_remove_if_empty(&self, key: &str) {
+        if self.set.remove_if(key, |_, set| set.is_empty()).is_some() {
+            self.notify(NOTIFY_GENERIC, "del", key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_move_is_atomic() {
+        let backend = Backend::new();
+        let members: Vec<String> = (0..2000).map(|i| i.to_string()).collect();
+        backend.set_add("src", members.clone());
+        let mover = std::thread::spawn({
+            let backend = backend.clone();
+            move || {
+                for member in &members {
+                    assert!(backend.set_move("src", "dst", member));
+                }
+            }
+        });
+        let card = |key: &str| backend.set.get(key).map(|set| set.len()).unwrap_or(0);
+        // members only go from src to dst, so reading src first can count one twice but
+        // never miss it
+        loop {
+            let src = card("src");
+            let dst = card("dst");
+            assert!(src + dst >= 2000, "{} + {}", src, dst);
+            if src == 0 {
+                break;
+            }
+        }
+        mover.join().unwrap();
+        assert_eq!(card("dst"), 2000);
+        assert!(!backend.set.contains_key("src"));
+        assert!(!backend.set_move("src", "dst", "0"));
+    }
+}
@@ -0,0 +1,353 @@
+use std::cmp::Ordering;
+
+use rand::Rng;
+
+const MAX_LEVEL: usize = 32;
+const HEAD: usize = 0;
+
+/// A skiplist ordered by `(score, member)` whose links also record how many elements they skip,
+/// like redis' `zskiplist`. That makes ranks as cheap as lookups.
+///
+/// Nodes live in an arena and link to each other by index; ranks are 0-based.
+#[derive(Debug, Clone)]
+pub struct SkipList {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    level: usize,
+    len: usize,
+    tail: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: String,
+    score: f64,
+    backward: Option<usize>,
+    levels: Vec<Link>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Link {
+    forward: Option<usize>,
+    span: usize,
+}
+
+pub struct Iter<'a> {
+    list: &'a SkipList,
+    next: Option<usize>,
+    remaining: usize,
+    rev: bool,
+}
+
+impl SkipList {
+    pub fn new() -> Self {
+        let head = Node {
+            member: String::new(),
+            score: 0.0,
+            backward: None,
+            levels: vec![Link::default(); MAX_LEVEL],
+        };
+        Self {
+            nodes: vec![head],
+            free: vec![],
+            level: 1,
+            len: 0,
+            tail: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts an element, the caller makes sure `member` isn't in the list yet.
+    pub fn insert(&mut self, score: f64, member: String) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0usize; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i == self.level - 1 { 0 } else { rank[i + 1] };
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if self.cmp_node(next, score, &member) != Ordering::Less {
+                    break;
+                }
+                rank[i] += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            update[i] = x;
+        }
+
+        let level = random_level();
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].levels[i].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let x = self.alloc(Node {
+            member,
+            score,
+            backward: None,
+            levels: vec![Link::default(); level],
+        });
+        for i in 0..level {
+            let prev = self.nodes[update[i]].levels[i];
+            self.nodes[x].levels[i] = Link {
+                forward: prev.forward,
+                span: prev.span - (rank[0] - rank[i]),
+            };
+            self.nodes[update[i]].levels[i] = Link {
+                forward: Some(x),
+                span: rank[0] - rank[i] + 1,
+            };
+        }
+        for (i, prev) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[*prev].levels[i].span += 1;
+        }
+
+        self.nodes[x].backward = (update[0] != HEAD).then_some(update[0]);
+        match self.nodes[x].levels[0].forward {
+            Some(next) => self.nodes[next].backward = Some(x),
+            None => self.tail = Some(x),
+        }
+        self.len += 1;
+    }
+
+    /// Removes the element with exactly this score and member, returns whether it was found.
+    pub fn remove(&mut self, score: f64, member: &str) -> bool {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if self.cmp_node(next, score, member) != Ordering::Less {
+                    break;
+                }
+                x = next;
+            }
+            update[i] = x;
+        }
+
+        match self.nodes[x].levels[0].forward {
+            Some(x) if self.cmp_node(x, score, member) == Ordering::Equal => {
+                self.unlink(x, &update);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Number of leading elements for which `before` holds. `before` must be true for a prefix
+    /// of the list and false afterwards, e.g. "score is below the range minimum".
+    pub fn count_while(&self, before: impl Fn(f64, &str) -> bool) -> usize {
+        let mut traversed = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                let node = &self.nodes[next];
+                if !before(node.score, &node.member) {
+                    break;
+                }
+                traversed += self.nodes[x].levels[i].span;
+                x = next;
+            }
+        }
+        traversed
+    }
+
+    /// 0-based rank of an element that is known to be in the list.
+    pub fn rank(&self, score: f64, member: &str) -> usize {
+        self.count_while(|s, m| cmp_entry(s, m, score, member) == Ordering::Less)
+    }
+
+    pub fn get_by_rank(&self, rank: usize) -> Option<(&str, f64)> {
+        self.node_by_rank(rank).map(|x| {
+            let node = &self.nodes[x];
+            (node.member.as_str(), node.score)
+        })
+    }
+
+    /// Iterates over the elements with ranks in `start..end`, lowest score first.
+    pub fn range(&self, start: usize, end: usize) -> Iter<'_> {
+        let end = end.min(self.len);
+        Iter {
+            list: self,
+            next: if start < end {
+                self.node_by_rank(start)
+            } else {
+                None
+            },
+            remaining: end.saturating_sub(start),
+            rev: false,
+        }
+    }
+
+    /// Iterates over the elements with ranks in `start..end`, highest score first.
+    pub fn rev_range(&self, start: usize, end: usize) -> Iter<'_> {
+        let end = end.min(self.len);
+        Iter {
+            list: self,
+            next: if start < end {
+                self.node_by_rank(end - 1)
+            } else {
+                None
+            },
+            remaining: end.saturating_sub(start),
+            rev: true,
+        }
+    }
+
+    fn node_by_rank(&self, rank: usize) -> Option<usize> {
+        if rank >= self.len {
+            return None;
+        }
+        // spans count the header as rank 0, so elements are 1-based here
+        let target = rank + 1;
+        let mut traversed = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if traversed + self.nodes[x].levels[i].span > target {
+                    break;
+                }
+                traversed += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            if traversed == target {
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    fn unlink(&mut self, x: usize, update: &[usize; MAX_LEVEL]) {
+        for (i, prev) in update.iter().enumerate().take(self.level) {
+            let link = self.nodes[*prev].levels[i];
+            if link.forward == Some(x) {
+                let removed = self.nodes[x].levels[i];
+                self.nodes[*prev].levels[i] = Link {
+                    forward: removed.forward,
+                    span: link.span + removed.span - 1,
+                };
+            } else {
+                self.nodes[*prev].levels[i].span -= 1;
+            }
+        }
+        match self.nodes[x].levels[0].forward {
+            Some(next) => self.nodes[next].backward = self.nodes[x].backward,
+            None => self.tail = self.nodes[x].backward,
+        }
+        while self.level > 1 && self.nodes[HEAD].levels[self.level - 1].forward.is_none() {
+            self.level -= 1;
+        }
+        self.len -= 1;
+        self.nodes[x].member = String::new();
+        self.nodes[x].levels.clear();
+        self.free.push(x);
+    }
+
+    fn alloc(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(x) => {
+                self.nodes[x] = node;
+                x
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn cmp_node(&self, x: usize, score: f64, member: &str) -> Ordering {
+        let node = &self.nodes[x];
+        cmp_entry(node.score, &node.member, score, member)
+    }
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let x = self.next?;
+        let node = &self.list.nodes[x];
+        self.remaining -= 1;
+        self.next = if self.rev {
+            node.backward
+        } else {
+            node.levels[0].forward
+        };
+        Some((node.member.as_str(), node.score))
+    }
+}
+
+fn cmp_entry(score_a: f64, member_a: &str, score_b: f64, member_b: &str) -> Ordering {
+    score_a
+        .total_cmp(&score_b)
+        .then_with(|| member_a.as_bytes().cmp(member_b.as_bytes()))
+}
+
+fn random_level() -> usize {
+    let mut rng = rand::thread_rng();
+    let mut level = 1;
+    while level < MAX_LEVEL && rng.gen_ratio(1, 4) {
+        level += 1;
+    }
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skiplist_rank_and_range() {
+        let mut list = SkipList::new();
+        for i in (0..100).rev() {
+            list.insert(i as f64, format!("m{:03}", i));
+        }
+        assert_eq!(list.len(), 100);
+        assert_eq!(list.rank(42.0, "m042"), 42);
+        assert_eq!(list.get_by_rank(7), Some(("m007", 7.0)));
+        assert_eq!(list.count_while(|score, _| score < 10.5), 11);
+
+        assert!(list.remove(7.0, "m007"));
+        assert!(!list.remove(7.0, "m007"));
+        assert_eq!(list.get_by_rank(7), Some(("m008", 8.0)));
+
+        let members: Vec<&str> = list.range(97, 200).map(|(m, _)| m).collect();
+        assert_eq!(members, vec!["m098", "m099"]);
+        let members: Vec<&str> = list.rev_range(0, 3).map(|(m, _)| m).collect();
+        assert_eq!(members, vec!["m002", "m001", "m000"]);
+    }
+
+    #[test]
+    fn test_skiplist_orders_equal_scores_by_member() {
+        let mut list = SkipList::new();
+        list.insert(1.0, "b".to_string());
+        list.insert(1.0, "a".to_string());
+        list.insert(0.5, "c".to_string());
+        let members: Vec<&str> = list.range(0, 3).map(|(m, _)| m).collect();
+        assert_eq!(members, vec!["c", "a", "b"]);
+        for (m, s) in [("a", 1.0), ("c", 0.5), ("b", 1.0)] {
+            assert!(list.remove(s, m));
+        }
+        assert!(list.is_empty());
+        assert_eq!(list.get_by_rank(0), None);
+    }
+}
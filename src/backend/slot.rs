@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use rand::Rng;
+
+use crate::backend::Backend;
+
+/// The number of hash slots keys and shard channels are routed by.
+pub const SLOT_COUNT: u16 = 16384;
+
+/// The slot of `key`, CRC16 of the key modulo [`SLOT_COUNT`]. Only the part between the first
+/// `{` and the next `}` is hashed when it isn't empty, so `{user1}.name` and `{user1}.age`
+/// share a slot.
+pub fn key_hash_slot(key: &[u8]) -> u16 {
+    let hashed = key
+        .iter()
+        .position(|b| *b == b'{')
+        .and_then(|open| {
+            let tag = &key[open + 1..];
+            tag.iter()
+                .position(|b| *b == b'}')
+                .filter(|close| *close > 0)
+                .map(|close| &tag[..close])
+        })
+        .unwrap_or(key);
+    crc16(hashed) % SLOT_COUNT
+}
+
+/// Which node serves each slot. Every slot is served here until CLUSTER SETSLOT hands it to
+/// another node.
+#[derive(Debug)]
+pub struct SlotState {
+    /// 40 hex characters, chosen at startup
+    node_id: String,
+    /// the slots that moved away, by the id of the node now serving them
+    moved: Mutex<HashMap<u16, String>>,
+}
+
+impl Default for SlotState {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        Self {
+            node_id: (0..40)
+                .map(|_| char::from_digit(rng.gen_range(0..16), 16).unwrap())
+                .collect(),
+            moved: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Backend {
+    pub fn node_id(&self) -> &str {
+        &self.slots.node_id
+    }
+
+    /// Hands `slot` to the node `node_id`, or takes it back when that is this node. The shard
+    /// channels of a slot that moves away are released, see [`Backend::release_slot`], and
+    /// returned.
+    pub fn set_slot_node(&self, slot: u16, node_id: &str) -> Vec<String> {
+        if node_id == self.slots.node_id {
+            self.slots.moved.lock().unwrap().remove(&slot);
+            return Vec::new();
+        }
+        self.slots
+            .moved
+            .lock()
+            .unwrap()
+            .insert(slot, node_id.to_string());
+        self.release_slot(slot)
+    }
+
+    /// The node serving `slot` when it isn't this one.
+    pub fn slot_moved_to(&self, slot: u16) -> Option<String> {
+        self.slots.moved.lock().unwrap().get(&slot).cloned()
+    }
+}
+
+/// CRC16-CCITT (XMODEM), polynomial 0x1021 with a zero initial value.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_hash_slot() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(key_hash_slot(b"foo"), 12182);
+        assert_eq!(key_hash_slot(b"{user1000}.following"), 3443);
+        assert_eq!(
+            key_hash_slot(b"{user1000}.followers"),
+            key_hash_slot(b"user1000")
+        );
+        // an empty tag hashes the whole key
+        assert_eq!(
+            key_hash_slot(b"foo{}{bar}"),
+            crc16(b"foo{}{bar}") % SLOT_COUNT
+        );
+        assert_eq!(key_hash_slot(b"foo{{bar}}zap"), key_hash_slot(b"{bar"));
+    }
+}
@@ -0,0 +1,776 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::Bound;
+use std::str::FromStr;
+
+use crate::backend::{now_millis, Backend};
+use crate::frame::RespFrame;
+
+/// A stream entry ID, `<milliseconds>-<sequence>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+/// The ID given to XADD.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamIdSpec {
+    /// `*`, fully generated
+    Auto,
+    /// `<ms>-*`, the sequence is generated
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+/// How XADD and XTRIM cut the oldest entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamTrim {
+    /// keep at most this many entries
+    MaxLen(usize),
+    /// drop the entries below this ID
+    MinId(StreamId),
+}
+
+/// An append-only log of field-value entries ordered by ID.
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    /// field-value pairs flattened the way XADD received them
+    entries: BTreeMap<StreamId, Vec<RespFrame>>,
+    last_id: StreamId,
+    max_deleted_id: StreamId,
+    entries_added: u64,
+    groups: BTreeMap<String, ConsumerGroup>,
+}
+
+/// A consumer group, which hands every entry to a single one of its consumers and remembers it
+/// until acknowledged.
+#[derive(Debug, Clone, Default)]
+pub struct ConsumerGroup {
+    last_delivered_id: StreamId,
+    /// how many entries the group read since the stream was created, `None` when unknown
+    entries_read: Option<u64>,
+    /// the pending entries list, delivered but not acknowledged yet
+    pending: BTreeMap<StreamId, PendingEntry>,
+    consumers: BTreeMap<String, Consumer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEntry {
+    pub consumer: String,
+    /// unix time in milliseconds of the last delivery
+    pub delivery_time: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Consumer {
+    /// last time the consumer tried to read or claim
+    seen_time: u64,
+    /// last time the consumer actually got entries
+    active_time: Option<u64>,
+    /// the IDs of the group's pending entries owned by this consumer
+    pending: BTreeSet<StreamId>,
+}
+
+/// The options of XCLAIM, also used by XAUTOCLAIM.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClaimOptions {
+    /// unix time in milliseconds recorded as the last delivery, now by default
+    pub delivery_time: Option<u64>,
+    /// the delivery count to record instead of incrementing it
+    pub retry_count: Option<u64>,
+    /// claim entries that aren't pending yet, as long as they exist in the stream
+    pub force: bool,
+    /// don't count the claim as a delivery
+    pub just_id: bool,
+    /// moves the group's last delivered ID forward
+    pub last_id: Option<StreamId>,
+}
+
+/// What XAUTOCLAIM did during one scan of the pending entries list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoClaim {
+    /// where the next scan starts, `0-0` once the whole list was scanned
+    pub cursor: StreamId,
+    pub claimed: Vec<(StreamId, Vec<RespFrame>)>,
+    /// pending entries that no longer existed in the stream and were dropped
+    pub deleted: Vec<StreamId>,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    /// The ID right after this one, if there is any.
+    pub fn next(&self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => self.ms.checked_add(1).map(|ms| StreamId::new(ms, 0)),
+        }
+    }
+
+    /// The ID right before this one, if there is any.
+    pub fn prev(&self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => self.ms.checked_sub(1).map(|ms| StreamId::new(ms, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl FromStr for StreamId {
+    type Err = ();
+
+    /// Parses `<ms>-<seq>`, or a bare `<ms>` meaning sequence 0.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ms, seq) = s.split_once('-').unwrap_or((s, "0"));
+        Ok(StreamId::new(
+            ms.parse().map_err(|_| ())?,
+            seq.parse().map_err(|_| ())?,
+        ))
+    }
+}
+
+impl Stream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn first_entry(&self) -> Option<(&StreamId, &Vec<RespFrame>)> {
+        self.entries.first_key_value()
+    }
+
+    pub fn last_entry(&self) -> Option<(&StreamId, &Vec<RespFrame>)> {
+        self.entries.last_key_value()
+    }
+
+    pub fn get(&self, id: &StreamId) -> Option<&Vec<RespFrame>> {
+        self.entries.get(id)
+    }
+
+    /// Appends an entry, the ID has to be greater than any ID the stream ever had.
+    pub fn add(&mut self, id: StreamIdSpec, fields: Vec<RespFrame>) -> Result<StreamId, String> {
+        let last = self.last_id;
+        let id = match id {
+            StreamIdSpec::Auto => {
+                let ms = now_millis();
+                if ms > last.ms {
+                    StreamId::new(ms, 0)
+                } else {
+                    last.next().ok_or_else(overflow_error)?
+                }
+            }
+            StreamIdSpec::AutoSeq(ms) if ms == last.ms && self.entries_added > 0 => last
+                .next()
+                .filter(|id| id.ms == ms)
+                .ok_or_else(too_small_error)?,
+            StreamIdSpec::AutoSeq(0) => StreamId::new(0, 1),
+            StreamIdSpec::AutoSeq(ms) => StreamId::new(ms, 0),
+            StreamIdSpec::Explicit(id) => id,
+        };
+        if id == StreamId::MIN {
+            return Err("The ID specified in XADD must be greater than 0-0".to_string());
+        }
+        if id <= last {
+            return Err(too_small_error());
+        }
+
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+        Ok(id)
+    }
+
+    /// Deletes the entries with the given IDs, returns how many existed.
+    pub fn delete(&mut self, ids: &[StreamId]) -> usize {
+        let mut deleted = 0;
+        for id in ids {
+            if self.entries.remove(id).is_some() {
+                self.max_deleted_id = self.max_deleted_id.max(*id);
+                deleted += 1;
+            }
+        }
+        deleted
+    }
+
+    /// Drops the oldest entries according to `trim`, at most `limit` of them. Returns how many
+    /// were removed.
+    pub fn trim(&mut self, trim: StreamTrim, limit: Option<usize>) -> usize {
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut removed = 0;
+        while removed < limit {
+            let len = self.entries.len();
+            let Some(entry) = self.entries.first_entry() else {
+                break;
+            };
+            let drop = match trim {
+                StreamTrim::MaxLen(max_len) => len > max_len,
+                StreamTrim::MinId(min_id) => *entry.key() < min_id,
+            };
+            if !drop {
+                break;
+            }
+            let (id, _) = entry.remove_entry();
+            self.max_deleted_id = self.max_deleted_id.max(id);
+            removed += 1;
+        }
+        removed
+    }
+
+    /// The entries with IDs between `start` and `end`, walked backwards with `rev`.
+    pub fn range(
+        &self,
+        start: Bound<StreamId>,
+        end: Bound<StreamId>,
+        rev: bool,
+        count: Option<usize>,
+    ) -> Vec<(StreamId, Vec<RespFrame>)> {
+        let invalid = match (start, end) {
+            (Bound::Included(s), Bound::Included(e)) => s > e,
+            (Bound::Included(s) | Bound::Excluded(s), Bound::Excluded(e))
+            | (Bound::Excluded(s), Bound::Included(e)) => s >= e,
+            _ => false,
+        };
+        if invalid {
+            return vec![];
+        }
+        let range = self.entries.range((start, end));
+        let count = count.unwrap_or(usize::MAX);
+        let clone = |(id, fields): (&StreamId, &Vec<RespFrame>)| (*id, fields.clone());
+        if rev {
+            range.rev().take(count).map(clone).collect()
+        } else {
+            range.take(count).map(clone).collect()
+        }
+    }
+}
+
+impl Stream {
+    pub fn groups(&self) -> impl Iterator<Item = (&String, &ConsumerGroup)> {
+        self.groups.iter()
+    }
+
+    pub fn group(&self, name: &str) -> Option<&ConsumerGroup> {
+        self.groups.get(name)
+    }
+
+    /// Creates a group that delivers the entries after `id`, returns false when it exists.
+    pub fn create_group(&mut self, name: &str, id: StreamId, entries_read: Option<u64>) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+        let group = ConsumerGroup {
+            last_delivered_id: id,
+            entries_read: entries_read.or_else(|| self.estimate_entries_read(id)),
+            ..Default::default()
+        };
+        self.groups.insert(name.to_string(), group);
+        true
+    }
+
+    /// Moves the last delivered ID of a group, returns false when there is no such group.
+    pub fn set_group_id(&mut self, name: &str, id: StreamId, entries_read: Option<u64>) -> bool {
+        let entries_read = entries_read.or_else(|| self.estimate_entries_read(id));
+        match self.groups.get_mut(name) {
+            Some(group) => {
+                group.last_delivered_id = id;
+                group.entries_read = entries_read;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn destroy_group(&mut self, name: &str) -> bool {
+        self.groups.remove(name).is_some()
+    }
+
+    /// Returns whether the consumer was created, or `None` when there is no such group.
+    pub fn create_consumer(&mut self, group: &str, consumer: &str) -> Option<bool> {
+        let group = self.groups.get_mut(group)?;
+        if group.consumers.contains_key(consumer) {
+            return Some(false);
+        }
+        group.consumer_mut(consumer, now_millis());
+        Some(true)
+    }
+
+    /// Deletes a consumer along with its pending entries, returns how many it had or `None`
+    /// when there is no such group.
+    pub fn delete_consumer(&mut self, group: &str, consumer: &str) -> Option<usize> {
+        let group = self.groups.get_mut(group)?;
+        let Some(consumer) = group.consumers.remove(consumer) else {
+            return Some(0);
+        };
+        for id in &consumer.pending {
+            group.pending.remove(id);
+        }
+        Some(consumer.pending.len())
+    }
+
+    /// Reads entries on behalf of `consumer`: the entries never delivered to the group when
+    /// `after` is `None`, like the `>` ID, or else the consumer's own pending entries after
+    /// `after`. Pending entries deleted from the stream come back without fields. Returns `None`
+    /// when there is no such group.
+    pub fn read_group(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        after: Option<StreamId>,
+        count: Option<usize>,
+        no_ack: bool,
+    ) -> Option<Vec<(StreamId, Option<Vec<RespFrame>>)>> {
+        let count = count.unwrap_or(usize::MAX);
+        let now = now_millis();
+        let Some(after) = after else {
+            let start = self.group(group)?.last_delivered_id;
+            let entries = self.range(Bound::Excluded(start), Bound::Unbounded, false, Some(count));
+            for (id, _) in &entries {
+                let entries_read = self.next_entries_read(group, *id);
+                let group = self.groups.get_mut(group)?;
+                group.last_delivered_id = *id;
+                group.entries_read = entries_read;
+                if !no_ack {
+                    group.deliver(*id, consumer, now);
+                }
+            }
+            let group = self.groups.get_mut(group)?;
+            let owner = group.consumer_mut(consumer, now);
+            if !entries.is_empty() {
+                owner.active_time = Some(now);
+            }
+            return Some(
+                entries
+                    .into_iter()
+                    .map(|(id, fields)| (id, Some(fields)))
+                    .collect(),
+            );
+        };
+
+        let group = self.groups.get_mut(group)?;
+        let ids: Vec<StreamId> = group
+            .consumer_mut(consumer, now)
+            .pending
+            .range((Bound::Excluded(after), Bound::Unbounded))
+            .take(count)
+            .copied()
+            .collect();
+        let entries = ids
+            .into_iter()
+            .map(|id| {
+                if let Some(pending) = group.pending.get_mut(&id) {
+                    pending.delivery_time = now;
+                    pending.delivery_count += 1;
+                }
+                (id, self.entries.get(&id).cloned())
+            })
+            .collect();
+        Some(entries)
+    }
+
+    /// Acknowledges the given IDs, returns how many were pending.
+    pub fn ack(&mut self, group: &str, ids: &[StreamId]) -> usize {
+        let Some(group) = self.groups.get_mut(group) else {
+            return 0;
+        };
+        ids.iter().filter(|id| group.remove_pending(id)).count()
+    }
+
+    /// Transfers the given pending entries idle for at least `min_idle` milliseconds to
+    /// `consumer`, returns the claimed entries or `None` when there is no such group. Pending
+    /// entries deleted from the stream are dropped.
+    pub fn claim(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        ids: &[StreamId],
+        options: ClaimOptions,
+    ) -> Option<Vec<(StreamId, Vec<RespFrame>)>> {
+        let now = now_millis();
+        let group = self.groups.get_mut(group)?;
+        group.consumer_mut(consumer, now);
+        if let Some(last_id) = options.last_id {
+            group.last_delivered_id = group.last_delivered_id.max(last_id);
+        }
+
+        let mut claimed = vec![];
+        for id in ids {
+            let Some(fields) = self.entries.get(id) else {
+                group.remove_pending(id);
+                continue;
+            };
+            if !group.pending.contains_key(id) {
+                if !options.force {
+                    continue;
+                }
+                group.deliver(*id, consumer, now);
+                if let Some(pending) = group.pending.get_mut(id) {
+                    pending.delivery_count = 0;
+                }
+            } else if group.idle(id, now) < min_idle {
+                continue;
+            }
+            group.claim(*id, consumer, now, options);
+            claimed.push((*id, fields.clone()));
+        }
+        if !claimed.is_empty() {
+            group.consumer_mut(consumer, now).active_time = Some(now);
+        }
+        Some(claimed)
+    }
+
+    /// Scans at most `count` pending entries from `start` on and claims those idle for at least
+    /// `min_idle` milliseconds, like XCLAIM does. Returns `None` when there is no such group.
+    pub fn auto_claim(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+    ) -> Option<AutoClaim> {
+        let now = now_millis();
+        let group = self.groups.get_mut(group)?;
+        group.consumer_mut(consumer, now);
+
+        let mut ids = group.pending.range(start..).map(|(id, _)| *id);
+        let scanned: Vec<StreamId> = ids.by_ref().take(count).collect();
+        let cursor = ids.next().unwrap_or_default();
+
+        let options = ClaimOptions {
+            just_id,
+            ..Default::default()
+        };
+        let mut claimed = vec![];
+        let mut deleted = vec![];
+        for id in scanned {
+            let Some(fields) = self.entries.get(&id) else {
+                group.remove_pending(&id);
+                deleted.push(id);
+                continue;
+            };
+            if group.idle(&id, now) < min_idle {
+                continue;
+            }
+            group.claim(id, consumer, now, options);
+            claimed.push((id, fields.clone()));
+        }
+        if !claimed.is_empty() {
+            group.consumer_mut(consumer, now).active_time = Some(now);
+        }
+        Some(AutoClaim {
+            cursor,
+            claimed,
+            deleted,
+        })
+    }
+
+    /// How many entries the group hasn't read yet, `None` when deleted entries make it unknown.
+    pub fn group_lag(&self, group: &ConsumerGroup) -> Option<u64> {
+        if group.last_delivered_id >= self.last_id {
+            return Some(0);
+        }
+        if self.has_tombstone_after(group.last_delivered_id) {
+            return None;
+        }
+        group
+            .entries_read
+            .map(|read| self.entries_added.saturating_sub(read))
+    }
+
+    /// The value of `entries_read` once the group delivered `id`.
+    fn next_entries_read(&self, group: &str, id: StreamId) -> Option<u64> {
+        let group = self.groups.get(group)?;
+        match group.entries_read {
+            Some(read) if !self.has_tombstone_after(group.last_delivered_id) => Some(read + 1),
+            _ => self.estimate_entries_read(id),
+        }
+    }
+
+    /// Counts the entries up to `id` that were ever added, which is only possible while the
+    /// entries after the oldest one left were never deleted.
+    fn estimate_entries_read(&self, id: StreamId) -> Option<u64> {
+        if id >= self.last_id {
+            return Some(self.entries_added);
+        }
+        if self.has_tombstone_after(StreamId::MIN) {
+            return None;
+        }
+        let after = self
+            .entries
+            .range((Bound::Excluded(id), Bound::Unbounded))
+            .count() as u64;
+        Some(self.entries_added - after)
+    }
+
+    /// Whether an entry after `id` was deleted while older entries are still around.
+    fn has_tombstone_after(&self, id: StreamId) -> bool {
+        let first = self.entries.first_key_value().map(|(id, _)| *id);
+        self.max_deleted_id > id && first.is_some_and(|first| first < self.max_deleted_id)
+    }
+}
+
+impl ConsumerGroup {
+    pub fn last_delivered_id(&self) -> StreamId {
+        self.last_delivered_id
+    }
+
+    pub fn entries_read(&self) -> Option<u64> {
+        self.entries_read
+    }
+
+    pub fn pending(&self) -> &BTreeMap<StreamId, PendingEntry> {
+        &self.pending
+    }
+
+    pub fn consumers(&self) -> impl Iterator<Item = (&String, &Consumer)> {
+        self.consumers.iter()
+    }
+
+    fn consumer_mut(&mut self, name: &str, now: u64) -> &mut Consumer {
+        let consumer = self.consumers.entry(name.to_string()).or_default();
+        consumer.seen_time = now;
+        consumer
+    }
+
+    /// Records a new delivery of `id` to `consumer`.
+    fn deliver(&mut self, id: StreamId, consumer: &str, now: u64) {
+        self.remove_pending(&id);
+        self.pending.insert(
+            id,
+            PendingEntry {
+                consumer: consumer.to_string(),
+                delivery_time: now,
+                delivery_count: 1,
+            },
+        );
+        self.consumer_mut(consumer, now).pending.insert(id);
+    }
+
+    /// Hands a pending entry over to `consumer`.
+    fn claim(&mut self, id: StreamId, consumer: &str, now: u64, options: ClaimOptions) {
+        let Some(pending) = self.pending.get_mut(&id) else {
+            return;
+        };
+        let previous = std::mem::replace(&mut pending.consumer, consumer.to_string());
+        pending.delivery_time = options.delivery_time.unwrap_or(now);
+        match options.retry_count {
+            Some(retry_count) => pending.delivery_count = retry_count,
+            None if !options.just_id => pending.delivery_count += 1,
+            None => {}
+        }
+        if let Some(previous) = self.consumers.get_mut(&previous) {
+            previous.pending.remove(&id);
+        }
+        self.consumer_mut(consumer, now).pending.insert(id);
+    }
+
+    fn remove_pending(&mut self, id: &StreamId) -> bool {
+        let Some(pending) = self.pending.remove(id) else {
+            return false;
+        };
+        if let Some(consumer) = self.consumers.get_mut(&pending.consumer) {
+            consumer.pending.remove(id);
+        }
+        true
+    }
+
+    fn idle(&self, id: &StreamId, now: u64) -> u64 {
+        self.pending
+            .get(id)
+            .map(|pending| now.saturating_sub(pending.delivery_time))
+            .unwrap_or(0)
+    }
+}
+
+impl Consumer {
+    pub fn seen_time(&self) -> u64 {
+        self.seen_time
+    }
+
+    pub fn active_time(&self) -> Option<u64> {
+        self.active_time
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+impl Backend {
+    pub fn stream_len(&self, key: &str) -> usize {
+        self.stream.get(key).map(|stream| stream.len()).unwrap_or(0)
+    }
+
+    /// The entries of `key` after `after`, which is how XREAD tails a stream.
+    pub fn stream_read_after(
+        &self,
+        key: &str,
+        after: StreamId,
+        count: Option<usize>,
+    ) -> Vec<(StreamId, Vec<RespFrame>)> {
+        match self.stream.get(key) {
+            Some(stream) => stream.range(Bound::Excluded(after), Bound::Unbounded, false, count),
+            None => vec![],
+        }
+    }
+}
+
+fn too_small_error() -> String {
+    "The ID specified in XADD is equal or smaller than the target stream top item".to_string()
+}
+
+fn overflow_error() -> String {
+    "The stream has exhausted the last possible ID, unable to add more items".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> Vec<RespFrame> {
+        vec![b"f".into(), b"v".into()]
+    }
+
+    #[test]
+    fn test_stream_add_and_trim() {
+        let mut stream = Stream::new();
+        let id = stream.add(StreamIdSpec::AutoSeq(5), fields()).unwrap();
+        assert_eq!(id, StreamId::new(5, 0));
+        let id = stream.add(StreamIdSpec::AutoSeq(5), fields()).unwrap();
+        assert_eq!(id, StreamId::new(5, 1));
+        assert!(stream
+            .add(StreamIdSpec::Explicit(StreamId::new(5, 1)), fields())
+            .is_err());
+        assert!(stream.add(StreamIdSpec::AutoSeq(4), fields()).is_err());
+        let id = stream.add(StreamIdSpec::Auto, fields()).unwrap();
+        assert!(id > StreamId::new(5, 1));
+
+        assert_eq!(stream.trim(StreamTrim::MaxLen(1), Some(1)), 1);
+        assert_eq!(stream.len(), 2);
+        assert_eq!(stream.trim(StreamTrim::MinId(id), None), 1);
+        assert_eq!(stream.first_entry().map(|(id, _)| *id), Some(id));
+        assert_eq!(stream.max_deleted_id(), StreamId::new(5, 1));
+        assert_eq!(stream.entries_added(), 3);
+
+        let ids: Vec<StreamId> = stream
+            .range(Bound::Unbounded, Bound::Excluded(id), false, None)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert!(ids.is_empty());
+    }
+
+    #[test]
+    fn test_consumer_group_pending_entries() {
+        let mut stream = Stream::new();
+        for ms in 1..=3 {
+            stream
+                .add(StreamIdSpec::Explicit(StreamId::new(ms, 0)), fields())
+                .unwrap();
+        }
+        assert!(stream.create_group("g", StreamId::MIN, None));
+        assert!(!stream.create_group("g", StreamId::MIN, None));
+        assert_eq!(stream.group("g").unwrap().entries_read(), Some(0));
+
+        let read = stream
+            .read_group("g", "alice", None, Some(2), false)
+            .unwrap();
+        assert_eq!(read.len(), 2);
+        let read = stream.read_group("g", "bob", None, None, false).unwrap();
+        assert_eq!(read, vec![(StreamId::new(3, 0), Some(fields()))]);
+        let group = stream.group("g").unwrap();
+        assert_eq!(group.last_delivered_id(), StreamId::new(3, 0));
+        assert_eq!(stream.group_lag(group), Some(0));
+        assert_eq!(group.pending().len(), 3);
+
+        // history reads only see the consumer's own entries, deleted ones without fields
+        stream.delete(&[StreamId::new(1, 0)]);
+        let history = stream
+            .read_group("g", "alice", Some(StreamId::MIN), None, false)
+            .unwrap();
+        assert_eq!(
+            history,
+            vec![
+                (StreamId::new(1, 0), None),
+                (StreamId::new(2, 0), Some(fields()))
+            ]
+        );
+        assert_eq!(
+            stream.group("g").unwrap().pending()[&StreamId::new(2, 0)].delivery_count,
+            2
+        );
+
+        let claimed = stream
+            .claim(
+                "g",
+                "bob",
+                0,
+                &[StreamId::new(1, 0), StreamId::new(2, 0)],
+                ClaimOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(claimed, vec![(StreamId::new(2, 0), fields())]);
+        assert_eq!(
+            stream.ack("g", &[StreamId::new(3, 0), StreamId::new(9, 0)]),
+            1
+        );
+        let consumers: Vec<(String, usize)> = stream
+            .group("g")
+            .unwrap()
+            .consumers()
+            .map(|(name, consumer)| (name.clone(), consumer.pending_count()))
+            .collect();
+        assert_eq!(
+            consumers,
+            vec![("alice".to_string(), 0), ("bob".to_string(), 1)]
+        );
+        assert_eq!(stream.delete_consumer("g", "bob"), Some(1));
+        assert!(stream.group("g").unwrap().pending().is_empty());
+    }
+
+    #[test]
+    fn test_stream_id_parse() {
+        assert_eq!("1-2".parse(), Ok(StreamId::new(1, 2)));
+        assert_eq!("7".parse(), Ok(StreamId::new(7, 0)));
+        assert!("1-x".parse::<StreamId>().is_err());
+        assert_eq!(StreamId::new(1, u64::MAX).next(), Some(StreamId::new(2, 0)));
+        assert_eq!(StreamId::new(2, 0).prev(), Some(StreamId::new(1, u64::MAX)));
+    }
+}
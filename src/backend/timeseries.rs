@@ -0,0 +1,587 @@
+use std::collections::BTreeMap;
+
+use crate::backend::{Backend, NOTIFY_MODULE};
+
+/// What happens when a sample is added at a timestamp that already has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// reject the new sample
+    #[default]
+    Block,
+    First,
+    Last,
+    Min,
+    Max,
+    Sum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Avg,
+    Sum,
+    Min,
+    Max,
+    Range,
+    Count,
+    First,
+    Last,
+    StdP,
+    StdS,
+    VarP,
+    VarS,
+}
+
+/// Downsamples every sample added to a series into `dest`, one aggregated sample per bucket.
+#[derive(Debug, Clone)]
+pub struct CompactionRule {
+    pub dest: String,
+    pub aggregation: Aggregation,
+    pub bucket: u64,
+    pub align: u64,
+    /// start of the bucket still being filled, written to `dest` once a later one starts
+    current: Option<u64>,
+}
+
+/// Which timestamp an aggregated bucket is reported at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BucketTimestamp {
+    #[default]
+    Start,
+    End,
+    Mid,
+}
+
+#[derive(Debug, Clone)]
+pub struct RangeAggregation {
+    pub aggregation: Aggregation,
+    pub bucket: u64,
+    pub align: u64,
+    pub bucket_timestamp: BucketTimestamp,
+    /// also report the buckets without samples
+    pub empty: bool,
+}
+
+/// The options shared by TS.RANGE, TS.REVRANGE and TS.MRANGE.
+#[derive(Debug, Clone, Default)]
+pub struct RangeQuery {
+    pub from: u64,
+    pub to: u64,
+    pub filter_ts: Option<Vec<u64>>,
+    pub filter_value: Option<(f64, f64)>,
+    pub count: Option<usize>,
+    pub aggregation: Option<RangeAggregation>,
+    pub reverse: bool,
+}
+
+/// A TS.MRANGE label matcher such as `area=eu`, `area!=(eu,us)` or `area=` (no such label).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelFilter {
+    pub label: String,
+    pub values: Vec<String>,
+    pub negate: bool,
+}
+
+/// Samples ordered by timestamp in milliseconds, along with the series' settings.
+#[derive(Debug, Clone, Default)]
+pub struct TimeSeries {
+    samples: BTreeMap<u64, f64>,
+    /// how far back from the last sample to keep samples, 0 to keep them forever
+    retention: u64,
+    duplicate_policy: DuplicatePolicy,
+    labels: Vec<(String, String)>,
+    rules: Vec<CompactionRule>,
+    /// the series this one is a compaction of
+    source: Option<String>,
+}
+
+impl TimeSeries {
+    pub fn new(
+        retention: u64,
+        duplicate_policy: DuplicatePolicy,
+        labels: Vec<(String, String)>,
+    ) -> Self {
+        Self {
+            retention,
+            duplicate_policy,
+            labels,
+            ..Default::default()
+        }
+    }
+
+    /// Adds a sample, `on_duplicate` overrides the series' duplicate policy.
+    pub fn add(
+        &mut self,
+        ts: u64,
+        value: f64,
+        on_duplicate: Option<DuplicatePolicy>,
+    ) -> Result<(), String> {
+        if let Some((last, _)) = self.last() {
+            if self.retention > 0 && ts < last.saturating_sub(self.retention) {
+                return Err("TSDB: Timestamp is older than retention".to_string());
+            }
+        }
+        match self.samples.get_mut(&ts) {
+            Some(current) => {
+                *current = match on_duplicate.unwrap_or(self.duplicate_policy) {
+                    DuplicatePolicy::Block => {
+                        return Err("TSDB: Error at upsert, update is not supported when DUPLICATE_POLICY is set to BLOCK mode".to_string())
+                    }
+                    DuplicatePolicy::First => *current,
+                    DuplicatePolicy::Last => value,
+                    DuplicatePolicy::Min => current.min(value),
+                    DuplicatePolicy::Max => current.max(value),
+                    DuplicatePolicy::Sum => *current + value,
+                }
+            }
+            None => {
+                self.samples.insert(ts, value);
+            }
+        }
+        if let Some((last, _)) = self.last().filter(|_| self.retention > 0) {
+            let oldest = last.saturating_sub(self.retention);
+            self.samples = self.samples.split_off(&oldest);
+        }
+        Ok(())
+    }
+
+    pub fn first(&self) -> Option<(u64, f64)> {
+        self.samples.first_key_value().map(|(ts, v)| (*ts, *v))
+    }
+
+    pub fn last(&self) -> Option<(u64, f64)> {
+        self.samples.last_key_value().map(|(ts, v)| (*ts, *v))
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn retention(&self) -> u64 {
+        self.retention
+    }
+
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicate_policy
+    }
+
+    pub fn labels(&self) -> &[(String, String)] {
+        &self.labels
+    }
+
+    pub fn label(&self, name: &str) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(label, _)| label == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn rules(&self) -> &[CompactionRule] {
+        &self.rules
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Whether the series' labels match every filter.
+    pub fn matches(&self, filters: &[LabelFilter]) -> bool {
+        filters.iter().all(|filter| {
+            let found = match self.label(&filter.label) {
+                Some(value) => filter.values.iter().any(|v| v == value),
+                None => filter.values.is_empty(),
+            };
+            found != filter.negate
+        })
+    }
+
+    /// The samples between `query.from` and `query.to`, filtered and aggregated.
+    pub fn query(&self, query: &RangeQuery) -> Vec<(u64, f64)> {
+        if query.from > query.to {
+            return vec![];
+        }
+        let samples = self.samples.range(query.from..=query.to).filter(|(ts, v)| {
+            query
+                .filter_ts
+                .as_ref()
+                .is_none_or(|filter| filter.contains(ts))
+                && query
+                    .filter_value
+                    .is_none_or(|(min, max)| **v >= min && **v <= max)
+        });
+        let mut result: Vec<(u64, f64)> = match &query.aggregation {
+            Some(aggregation) => aggregate(samples.map(|(ts, v)| (*ts, *v)), aggregation),
+            None => samples.map(|(ts, v)| (*ts, *v)).collect(),
+        };
+        if query.reverse {
+            result.reverse();
+        }
+        if let Some(count) = query.count {
+            result.truncate(count);
+        }
+        result
+    }
+
+    /// Feeds a sample just added at `ts` to the compaction rules, returns the samples to write
+    /// to their destinations.
+    fn compact(&mut self, ts: u64) -> Vec<(String, u64, f64)> {
+        let mut writes = vec![];
+        for rule in &mut self.rules {
+            let bucket = bucket_start(ts, rule.bucket, rule.align);
+            let closed = match rule.current {
+                None => None,
+                Some(current) if bucket > current => Some(current),
+                Some(current) if bucket == current => None,
+                // a late sample changes a bucket that was already written
+                Some(_) => Some(bucket),
+            };
+            if rule.current.is_none_or(|current| bucket > current) {
+                rule.current = Some(bucket);
+            }
+            let Some(closed) = closed else {
+                continue;
+            };
+            let values: Vec<f64> = self
+                .samples
+                .range(closed..closed.saturating_add(rule.bucket))
+                .map(|(_, v)| *v)
+                .collect();
+            if !values.is_empty() {
+                writes.push((rule.dest.clone(), closed, rule.aggregation.apply(&values)));
+            }
+        }
+        writes
+    }
+}
+
+impl DuplicatePolicy {
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "block" => DuplicatePolicy::Block,
+            "first" => DuplicatePolicy::First,
+            "last" => DuplicatePolicy::Last,
+            "min" => DuplicatePolicy::Min,
+            "max" => DuplicatePolicy::Max,
+            "sum" => DuplicatePolicy::Sum,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DuplicatePolicy::Block => "block",
+            DuplicatePolicy::First => "first",
+            DuplicatePolicy::Last => "last",
+            DuplicatePolicy::Min => "min",
+            DuplicatePolicy::Max => "max",
+            DuplicatePolicy::Sum => "sum",
+        }
+    }
+}
+
+impl Aggregation {
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "avg" => Aggregation::Avg,
+            "sum" => Aggregation::Sum,
+            "min" => Aggregation::Min,
+            "max" => Aggregation::Max,
+            "range" => Aggregation::Range,
+            "count" => Aggregation::Count,
+            "first" => Aggregation::First,
+            "last" => Aggregation::Last,
+            "std.p" => Aggregation::StdP,
+            "std.s" => Aggregation::StdS,
+            "var.p" => Aggregation::VarP,
+            "var.s" => Aggregation::VarS,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aggregation::Avg => "avg",
+            Aggregation::Sum => "sum",
+            Aggregation::Min => "min",
+            Aggregation::Max => "max",
+            Aggregation::Range => "range",
+            Aggregation::Count => "count",
+            Aggregation::First => "first",
+            Aggregation::Last => "last",
+            Aggregation::StdP => "std.p",
+            Aggregation::StdS => "std.s",
+            Aggregation::VarP => "var.p",
+            Aggregation::VarS => "var.s",
+        }
+    }
+
+    /// Aggregates the values of a bucket, in timestamp order. Empty buckets count and sum to
+    /// 0 and are NaN otherwise.
+    pub fn apply(&self, values: &[f64]) -> f64 {
+        let n = values.len() as f64;
+        let sum: f64 = values.iter().sum();
+        let variance = |ddof: f64| {
+            let mean = sum / n;
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - ddof)
+        };
+        match self {
+            Aggregation::Sum => sum,
+            Aggregation::Count => n,
+            _ if values.is_empty() => f64::NAN,
+            Aggregation::Avg => sum / n,
+            Aggregation::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Aggregation::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Aggregation::Range => Aggregation::Max.apply(values) - Aggregation::Min.apply(values),
+            Aggregation::First => values[0],
+            Aggregation::Last => values[values.len() - 1],
+            Aggregation::VarP => variance(0.0),
+            Aggregation::StdP => variance(0.0).sqrt(),
+            // a single sample has no sample variance
+            Aggregation::VarS | Aggregation::StdS if values.len() == 1 => 0.0,
+            Aggregation::VarS => variance(1.0),
+            Aggregation::StdS => variance(1.0).sqrt(),
+        }
+    }
+}
+
+/// The start of the bucket `ts` falls in, buckets being `bucket` wide and aligned to `align`.
+/// A bucket that would start before the epoch starts at 0.
+pub fn bucket_start(ts: u64, bucket: u64, align: u64) -> u64 {
+    let offset = (ts % bucket + bucket - align % bucket) % bucket;
+    ts.saturating_sub(offset)
+}
+
+fn aggregate(
+    samples: impl Iterator<Item = (u64, f64)>,
+    aggregation: &RangeAggregation,
+) -> Vec<(u64, f64)> {
+    let mut buckets: Vec<(u64, Vec<f64>)> = vec![];
+    for (ts, value) in samples {
+        let start = bucket_start(ts, aggregation.bucket, aggregation.align);
+        match buckets.last_mut() {
+            Some((last, values)) if *last == start => values.push(value),
+            Some((last, _)) if aggregation.empty => {
+                let mut next = last.saturating_add(aggregation.bucket);
+                while next < start {
+                    buckets.push((next, vec![]));
+                    next += aggregation.bucket;
+                }
+                buckets.push((start, vec![value]));
+            }
+            _ => buckets.push((start, vec![value])),
+        }
+    }
+    buckets
+        .into_iter()
+        .map(|(start, values)| {
+            let ts = match aggregation.bucket_timestamp {
+                BucketTimestamp::Start => start,
+                BucketTimestamp::End => start.saturating_add(aggregation.bucket),
+                BucketTimestamp::Mid => start.saturating_add(aggregation.bucket / 2),
+            };
+            (ts, aggregation.aggregation.apply(&values))
+        })
+        .collect()
+}
+
+impl Backend {
+    /// Adds a sample to the series at `key` with `add`, which returns the sample's timestamp,
+    /// then feeds the sample to the series' compaction rules. The series is created from
+    /// `create` when missing. `event` is notified for `key` once the sample is in.
+    pub fn ts_add(
+        &self,
+        key: &str,
+        create: Option<TimeSeries>,
+        event: &str,
+        add: impl FnOnce(&mut TimeSeries) -> Result<u64, String>,
+    ) -> Result<u64, String> {
+        let (ts, writes, created) = {
+            let (mut series, created) = match (self.timeseries.get_mut(key), create) {
+                (Some(series), _) => (series, false),
+                (None, Some(create)) => (
+                    self.timeseries.entry(key.to_string()).or_insert(create),
+                    true,
+                ),
+                (None, None) => return Err(missing_key()),
+            };
+            let ts = add(&mut series)?;
+            (ts, series.compact(ts), created)
+        };
+        self.notify_new(created, key);
+        self.notify(NOTIFY_MODULE, event, key);
+        // the destinations are written once the source is unlocked, as they may share a shard
+        self.ts_write_compactions(writes);
+        Ok(ts)
+    }
+
+    fn ts_write_compactions(&self, writes: Vec<(String, u64, f64)>) {
+        for (dest, ts, value) in writes {
+            let _ = self.ts_add(&dest, None, "ts.add", |series| {
+                series.add(ts, value, Some(DuplicatePolicy::Last))?;
+                Ok(ts)
+            });
+        }
+    }
+
+    pub fn ts_create_rule(
+        &self,
+        source: &str,
+        dest: &str,
+        aggregation: Aggregation,
+        bucket: u64,
+        align: u64,
+    ) -> Result<(), String> {
+        if source == dest {
+            return Err("TSDB: the source key and destination key should be different".to_string());
+        }
+        if !self.timeseries.contains_key(source) {
+            return Err(missing_key());
+        }
+        {
+            let mut dest_series = self.timeseries.get_mut(dest).ok_or_else(missing_key)?;
+            if dest_series.source.is_some() {
+                return Err("TSDB: the destination key already has a src rule".to_string());
+            }
+            if !dest_series.rules.is_empty() {
+                return Err("TSDB: the destination key already has a dst rule".to_string());
+            }
+            dest_series.source = Some(source.to_string());
+        }
+        let mut source_series = self.timeseries.get_mut(source).ok_or_else(missing_key)?;
+        if source_series.source.is_some() {
+            drop(source_series);
+            if let Some(mut dest_series) = self.timeseries.get_mut(dest) {
+                dest_series.source = None;
+            }
+            return Err("TSDB: the source key already has a src rule".to_string());
+        }
+        source_series.rules.push(CompactionRule {
+            dest: dest.to_string(),
+            aggregation,
+            bucket,
+            align,
+            current: None,
+        });
+        drop(source_series);
+        self.notify(NOTIFY_MODULE, "ts.createrule", source);
+        self.notify(NOTIFY_MODULE, "ts.createrule", dest);
+        Ok(())
+    }
+
+    pub fn ts_delete_rule(&self, source: &str, dest: &str) -> Result<(), String> {
+        {
+            let mut source_series = self.timeseries.get_mut(source).ok_or_else(missing_key)?;
+            let before = source_series.rules.len();
+            source_series.rules.retain(|rule| rule.dest != dest);
+            if source_series.rules.len() == before {
+                return Err("TSDB: compaction rule does not exist".to_string());
+            }
+        }
+        if let Some(mut dest_series) = self.timeseries.get_mut(dest) {
+            dest_series.source = None;
+        }
+        self.notify(NOTIFY_MODULE, "ts.deleterule", source);
+        self.notify(NOTIFY_MODULE, "ts.deleterule", dest);
+        Ok(())
+    }
+}
+
+fn missing_key() -> String {
+    "TSDB: the key does not exist".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(series: &TimeSeries, aggregation: Option<RangeAggregation>) -> Vec<(u64, f64)> {
+        series.query(&RangeQuery {
+            from: 0,
+            to: u64::MAX,
+            aggregation,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_time_series_add_and_aggregate() {
+        let mut series = TimeSeries::new(100, DuplicatePolicy::Block, vec![]);
+        for (ts, value) in [(10, 1.0), (15, 3.0), (22, 5.0), (41, 7.0)] {
+            series.add(ts, value, None).unwrap();
+        }
+        assert!(series.add(15, 4.0, None).is_err());
+        series.add(15, 4.0, Some(DuplicatePolicy::Sum)).unwrap();
+        assert_eq!(series.len(), 4);
+
+        let mut aggregation = RangeAggregation {
+            aggregation: Aggregation::Avg,
+            bucket: 10,
+            align: 0,
+            bucket_timestamp: BucketTimestamp::Start,
+            empty: false,
+        };
+        assert_eq!(
+            range(&series, Some(aggregation.clone())),
+            vec![(10, 4.0), (20, 5.0), (40, 7.0)]
+        );
+        aggregation.align = 5;
+        aggregation.aggregation = Aggregation::Count;
+        aggregation.empty = true;
+        aggregation.bucket_timestamp = BucketTimestamp::End;
+        assert_eq!(
+            range(&series, Some(aggregation)),
+            vec![(15, 1.0), (25, 2.0), (35, 0.0), (45, 1.0)]
+        );
+
+        // retention drops the samples more than 100ms older than the last one
+        series.add(125, 0.0, None).unwrap();
+        assert_eq!(series.first(), Some((41, 7.0)));
+        assert!(series.add(20, 0.0, None).is_err());
+    }
+
+    #[test]
+    fn test_aggregations() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(Aggregation::StdP.apply(&values), 2.0);
+        assert_eq!(Aggregation::Range.apply(&values), 7.0);
+        assert_eq!(Aggregation::First.apply(&values), 2.0);
+        assert_eq!(Aggregation::Sum.apply(&[]), 0.0);
+        assert!(Aggregation::Max.apply(&[]).is_nan());
+        assert_eq!(bucket_start(17, 10, 5), 15);
+        assert_eq!(bucket_start(3, 10, 5), 0);
+        assert_eq!(bucket_start(4, 10, 5), 0);
+        assert_eq!(bucket_start(5, 10, 5), 5);
+    }
+
+    #[test]
+    fn test_compaction_rule() {
+        let backend = Backend::new();
+        backend
+            .timeseries
+            .insert("raw".to_string(), TimeSeries::default());
+        backend
+            .timeseries
+            .insert("avg".to_string(), TimeSeries::default());
+        backend
+            .ts_create_rule("raw", "avg", Aggregation::Avg, 10, 0)
+            .unwrap();
+        assert!(backend
+            .ts_create_rule("avg", "raw", Aggregation::Avg, 10, 0)
+            .is_err());
+
+        for (ts, value) in [(1, 1.0), (5, 3.0), (12, 10.0), (25, 1.0)] {
+            backend
+                .ts_add("raw", None, "ts.add", |series| {
+                    series.add(ts, value, None)?;
+                    Ok(ts)
+                })
+                .unwrap();
+        }
+        let avg = backend.timeseries.get("avg").unwrap();
+        assert_eq!(range(&avg, None), vec![(0, 2.0), (10, 10.0)]);
+        assert_eq!(avg.source(), Some("raw"));
+    }
+}
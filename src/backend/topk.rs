@@ -0,0 +1,136 @@
+use rand::Rng;
+
+use crate::backend::hyperloglog::murmur_hash64a;
+
+pub const TOPK_DEFAULT_WIDTH: usize = 8;
+pub const TOPK_DEFAULT_DEPTH: usize = 7;
+pub const TOPK_DEFAULT_DECAY: f64 = 0.9;
+/// The most items TOPK.RESERVE may track.
+pub const TOPK_MAX_K: usize = 1 << 20;
+/// The most counters of the grid, 512MB worth.
+pub const TOPK_MAX_BUCKETS: usize = 1 << 25;
+
+/// Tracks the `k` most frequent items with the HeavyKeeper algorithm: a grid of counters owned
+/// by fingerprints, where colliding items decay the current owner until they take it over.
+#[derive(Debug, Clone)]
+pub struct TopK {
+    k: usize,
+    width: usize,
+    depth: usize,
+    decay: f64,
+    buckets: Vec<Bucket>,
+    /// the current heavy hitters with their estimated counts, unordered
+    heap: Vec<(String, u64)>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    fingerprint: u64,
+    count: u64,
+}
+
+impl TopK {
+    pub fn new(k: usize, width: usize, depth: usize, decay: f64) -> Self {
+        Self {
+            k,
+            width,
+            depth,
+            decay,
+            buckets: vec![Bucket::default(); width * depth],
+            heap: Vec::with_capacity(k),
+        }
+    }
+
+    /// Counts `incr` occurrences of an item, returns the item it pushed out of the top list.
+    pub fn incr_by(&mut self, item: &str, incr: u64) -> Option<String> {
+        let fingerprint = murmur_hash64a(item.as_bytes(), 0);
+        let mut rng = rand::thread_rng();
+        let mut max_count = 0;
+        for row in 0..self.depth {
+            let index = row * self.width
+                + (murmur_hash64a(item.as_bytes(), row as u64 + 1) % self.width as u64) as usize;
+            let bucket = &mut self.buckets[index];
+            if bucket.count == 0 || bucket.fingerprint == fingerprint {
+                bucket.fingerprint = fingerprint;
+                bucket.count += incr;
+            } else {
+                for left in (0..incr).rev() {
+                    // the larger the count, the less likely it decays
+                    if rng.gen::<f64>() < self.decay.powf(bucket.count as f64) {
+                        bucket.count -= 1;
+                        if bucket.count == 0 {
+                            bucket.fingerprint = fingerprint;
+                            bucket.count = left + 1;
+                            break;
+                        }
+                    }
+                }
+            }
+            if bucket.fingerprint == fingerprint {
+                max_count = max_count.max(bucket.count);
+            }
+        }
+
+        if let Some(entry) = self.heap.iter_mut().find(|(name, _)| name == item) {
+            entry.1 = entry.1.max(max_count);
+            return None;
+        }
+        if self.heap.len() < self.k {
+            self.heap.push((item.to_string(), max_count));
+            return None;
+        }
+        let (min_index, min) = self
+            .heap
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (_, count))| *count)?;
+        if max_count <= min.1 {
+            return None;
+        }
+        let expelled = std::mem::replace(&mut self.heap[min_index], (item.to_string(), max_count));
+        Some(expelled.0)
+    }
+
+    /// The top items, most frequent first.
+    pub fn list(&self) -> Vec<(String, u64)> {
+        let mut list = self.heap.clone();
+        list.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        list
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn decay(&self) -> f64 {
+        self.decay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topk_keeps_heavy_hitters() {
+        let mut topk = TopK::new(2, 50, 5, 0.9);
+        for round in 0..100 {
+            topk.incr_by("hot", 1);
+            if round % 2 == 0 {
+                topk.incr_by("warm", 1);
+            }
+            topk.incr_by(&format!("cold:{}", round), 1);
+        }
+        let list: Vec<String> = topk.list().into_iter().map(|(item, _)| item).collect();
+        assert_eq!(list, vec!["hot".to_string(), "warm".to_string()]);
+        assert_eq!(topk.incr_by("hot", 1), None);
+    }
+}
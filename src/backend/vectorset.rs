@@ -0,0 +1,863 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use rand::Rng;
+use serde_json::Value;
+
+/// The default number of links per node and layer, twice as many on layer 0.
+pub const VSET_DEFAULT_M: usize = 16;
+/// How many candidates insertions consider, unless set with EF.
+pub const VSET_DEFAULT_EF_CONSTRUCTION: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    Cosine,
+    L2,
+    InnerProduct,
+}
+
+#[derive(Debug)]
+struct Node {
+    name: String,
+    vector: Vec<f32>,
+    norm: f32,
+    attributes: Option<Value>,
+    /// the linked nodes on each layer the node is part of
+    links: Vec<Vec<usize>>,
+}
+
+/// Vectors of the same dimension indexed in an HNSW graph.
+#[derive(Debug)]
+pub struct VectorSet {
+    dim: usize,
+    metric: Metric,
+    m: usize,
+    ef_construction: usize,
+    /// removed nodes leave a hole so the links of other nodes never point to a different node
+    nodes: Vec<Option<Node>>,
+    ids: HashMap<String, usize>,
+    entry: Option<usize>,
+    max_level: usize,
+}
+
+/// A FILTER expression of VSIM, evaluated against the JSON attributes of an element.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VectorFilter {
+    Literal(Value),
+    /// `.a.b`, the path of an attribute
+    Attribute(Vec<String>),
+    Not(Box<VectorFilter>),
+    And(Box<VectorFilter>, Box<VectorFilter>),
+    Or(Box<VectorFilter>, Box<VectorFilter>),
+    Compare(Box<VectorFilter>, CompareOp, Box<VectorFilter>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+}
+
+/// A distance wrapper with a total order, for the heaps of the graph search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Distance(f32);
+
+impl Eq for Distance {}
+
+impl PartialOrd for Distance {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Distance {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Metric {
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "cosine" => Metric::Cosine,
+            "l2" => Metric::L2,
+            "ip" => Metric::InnerProduct,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Cosine => "cosine",
+            Metric::L2 => "l2",
+            Metric::InnerProduct => "ip",
+        }
+    }
+
+    /// Lower is closer for every metric.
+    fn distance(&self, a: &[f32], a_norm: f32, b: &[f32], b_norm: f32) -> f32 {
+        match self {
+            Metric::Cosine => {
+                if a_norm == 0.0 || b_norm == 0.0 {
+                    return 1.0;
+                }
+                1.0 - dot(a, b) / (a_norm * b_norm)
+            }
+            Metric::L2 => a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum(),
+            Metric::InnerProduct => -dot(a, b),
+        }
+    }
+
+    /// The score VSIM replies with: the similarity in [0, 1] for cosine, the euclidean distance
+    /// for l2 and the inner product for ip.
+    fn score(&self, distance: f32) -> f64 {
+        let distance = distance as f64;
+        match self {
+            Metric::Cosine => 1.0 - distance / 2.0,
+            Metric::L2 => distance.sqrt(),
+            Metric::InnerProduct => -distance,
+        }
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn norm(vector: &[f32]) -> f32 {
+    dot(vector, vector).sqrt()
+}
+
+impl VectorSet {
+    pub fn new(dim: usize, metric: Metric, m: usize, ef_construction: usize) -> Self {
+        Self {
+            dim,
+            metric,
+            m: m.max(2),
+            ef_construction: ef_construction.max(1),
+            nodes: vec![],
+            ids: HashMap::new(),
+            entry: None,
+            max_level: 0,
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    pub fn max_level(&self) -> usize {
+        self.max_level
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.ids.contains_key(name)
+    }
+
+    pub fn vector(&self, name: &str) -> Option<&[f32]> {
+        self.node(*self.ids.get(name)?)
+            .map(|node| node.vector.as_slice())
+    }
+
+    pub fn attributes(&self, name: &str) -> Option<&Value> {
+        self.node(*self.ids.get(name)?)?.attributes.as_ref()
+    }
+
+    /// Replaces the attributes of `name`, returns whether the element exists.
+    pub fn set_attributes(&mut self, name: &str, attributes: Option<Value>) -> bool {
+        let Some(node) = self
+            .ids
+            .get(name)
+            .and_then(|id| self.nodes.get_mut(*id)?.as_mut())
+        else {
+            return false;
+        };
+        node.attributes = attributes;
+        true
+    }
+
+    /// Adds or replaces the vector of `name`, returns whether the element is new. A replaced
+    /// element keeps its attributes unless new ones are given.
+    pub fn add(
+        &mut self,
+        name: &str,
+        vector: Vec<f32>,
+        attributes: Option<Value>,
+    ) -> Result<bool, String> {
+        if vector.len() != self.dim {
+            return Err(format!(
+                "Vector dimension mismatch - got {} but set has {}",
+                vector.len(),
+                self.dim
+            ));
+        }
+        let previous = self.take(name);
+        let is_new = previous.is_none();
+        let attributes = attributes.or_else(|| previous.and_then(|node| node.attributes));
+        let level = self.random_level();
+        let id = self.nodes.len();
+        self.nodes.push(Some(Node {
+            name: name.to_string(),
+            norm: norm(&vector),
+            vector,
+            attributes,
+            links: vec![vec![]; level + 1],
+        }));
+        self.ids.insert(name.to_string(), id);
+        self.link(id, level);
+        Ok(is_new)
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.take(name).is_some()
+    }
+
+    /// The `count` elements closest to `query` that pass `filter`, closest first, with their
+    /// score. The HNSW graph is searched with `ef` candidates, visiting at most `filter_ef`
+    /// nodes when filtering; `exact` or a graph search coming back short scans every element.
+    pub fn search(
+        &self,
+        query: &[f32],
+        count: usize,
+        ef: usize,
+        filter: Option<&VectorFilter>,
+        filter_ef: usize,
+        exact: bool,
+    ) -> Vec<(String, f64)> {
+        let query_norm = norm(query);
+        let passes = |id: usize| {
+            filter.is_none_or(|filter| {
+                self.node(id)
+                    .and_then(|node| node.attributes.as_ref())
+                    .is_some_and(|attributes| filter.matches(attributes))
+            })
+        };
+        let mut found = vec![];
+        if !exact {
+            if let Some(entry) = self.entry {
+                let entry = self.descend(query, query_norm, entry, 0);
+                let max_visits = if filter.is_some() {
+                    filter_ef
+                } else {
+                    usize::MAX
+                };
+                found = self.search_layer(
+                    query,
+                    query_norm,
+                    &[entry],
+                    ef.max(count),
+                    0,
+                    Some(&passes),
+                    max_visits,
+                );
+            }
+        }
+        if exact || (found.len() < count && found.len() < self.len()) {
+            found = self
+                .nodes
+                .iter()
+                .enumerate()
+                .filter_map(|(id, node)| Some((id, node.as_ref()?)))
+                .filter(|(id, _)| passes(*id))
+                .map(|(id, node)| {
+                    let distance = self
+                        .metric
+                        .distance(query, query_norm, &node.vector, node.norm);
+                    (Distance(distance), id)
+                })
+                .collect();
+            found.sort();
+        }
+        found
+            .into_iter()
+            .take(count)
+            .filter_map(|(distance, id)| {
+                let node = self.node(id)?;
+                Some((node.name.clone(), self.metric.score(distance.0)))
+            })
+            .collect()
+    }
+
+    fn node(&self, id: usize) -> Option<&Node> {
+        self.nodes.get(id)?.as_ref()
+    }
+
+    fn distance_to(&self, query: &[f32], query_norm: f32, id: usize) -> Distance {
+        let distance = self.node(id).map_or(f32::INFINITY, |node| {
+            self.metric
+                .distance(query, query_norm, &node.vector, node.norm)
+        });
+        Distance(distance)
+    }
+
+    fn links(&self, id: usize, level: usize) -> &[usize] {
+        self.node(id)
+            .and_then(|node| node.links.get(level))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn max_links(&self, level: usize) -> usize {
+        if level == 0 {
+            self.m * 2
+        } else {
+            self.m
+        }
+    }
+
+    fn random_level(&self) -> usize {
+        let ml = 1.0 / (self.m as f64).ln();
+        let uniform: f64 = rand::thread_rng().gen_range(f64::EPSILON..1.0);
+        ((-uniform.ln() * ml) as usize).min(16)
+    }
+
+    /// The `ef` nodes of a layer closest to the query, closest first, found by a best-first
+    /// walk from `entry`. Nodes failing `passes` are walked through but not returned.
+    #[allow(clippy::too_many_arguments)]
+    fn search_layer(
+        &self,
+        query: &[f32],
+        query_norm: f32,
+        entry: &[usize],
+        ef: usize,
+        level: usize,
+        passes: Option<&dyn Fn(usize) -> bool>,
+        max_visits: usize,
+    ) -> Vec<(Distance, usize)> {
+        let passes = |id: usize| passes.is_none_or(|passes| passes(id));
+        let mut visited: HashSet<usize> = entry.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut results = BinaryHeap::new();
+        for id in entry {
+            let distance = self.distance_to(query, query_norm, *id);
+            candidates.push(Reverse((distance, *id)));
+            if passes(*id) {
+                results.push((distance, *id));
+            }
+        }
+        while let Some(Reverse((distance, id))) = candidates.pop() {
+            if results.len() >= ef && results.peek().is_some_and(|(worst, _)| distance > *worst) {
+                break;
+            }
+            for &next in self.links(id, level) {
+                if visited.len() >= max_visits {
+                    break;
+                }
+                if !visited.insert(next) || self.node(next).is_none() {
+                    continue;
+                }
+                let distance = self.distance_to(query, query_norm, next);
+                if results.len() < ef || results.peek().is_some_and(|(worst, _)| distance < *worst)
+                {
+                    candidates.push(Reverse((distance, next)));
+                    if passes(next) {
+                        results.push((distance, next));
+                        if results.len() > ef {
+                            results.pop();
+                        }
+                    }
+                }
+            }
+        }
+        results.into_sorted_vec()
+    }
+
+    /// Walks greedily from `entry` on the top layer down to the layer above `level`, returning the
+    /// node closest to the query found there.
+    fn descend(&self, query: &[f32], query_norm: f32, mut entry: usize, level: usize) -> usize {
+        for layer in (level + 1..=self.max_level).rev() {
+            if let Some((_, closest)) = self
+                .search_layer(query, query_norm, &[entry], 1, layer, None, usize::MAX)
+                .first()
+            {
+                entry = *closest;
+            }
+        }
+        entry
+    }
+
+    /// Links a freshly pushed node into the graph from its top layer down.
+    fn link(&mut self, id: usize, level: usize) {
+        let Some(entry) = self.entry else {
+            self.entry = Some(id);
+            self.max_level = level;
+            return;
+        };
+        let (query, query_norm) = match self.node(id) {
+            Some(node) => (node.vector.clone(), node.norm),
+            None => return,
+        };
+        let mut entries = vec![self.descend(&query, query_norm, entry, level)];
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let found = self.search_layer(
+                &query,
+                query_norm,
+                &entries,
+                self.ef_construction,
+                layer,
+                None,
+                usize::MAX,
+            );
+            let neighbors: Vec<usize> = found
+                .iter()
+                .map(|(_, other)| *other)
+                .filter(|other| *other != id)
+                .take(self.max_links(layer))
+                .collect();
+            for &neighbor in &neighbors {
+                self.connect(neighbor, id, layer);
+            }
+            if let Some(node) = self.nodes[id].as_mut() {
+                node.links[layer] = neighbors;
+            }
+            entries = found.into_iter().map(|(_, other)| other).collect();
+        }
+        if level > self.max_level {
+            self.entry = Some(id);
+            self.max_level = level;
+        }
+    }
+
+    /// Adds a link from `from` to `to`, dropping the farthest link when `from` has too many.
+    fn connect(&mut self, from: usize, to: usize, layer: usize) {
+        let max_links = self.max_links(layer);
+        let mut links = self.links(from, layer).to_vec();
+        if links.contains(&to) {
+            return;
+        }
+        links.push(to);
+        if links.len() > max_links {
+            let Some(node) = self.node(from) else {
+                return;
+            };
+            let (vector, vector_norm) = (node.vector.clone(), node.norm);
+            links.sort_by_key(|other| self.distance_to(&vector, vector_norm, *other));
+            links.truncate(max_links);
+        }
+        if let Some(links_at_layer) = self.nodes[from]
+            .as_mut()
+            .and_then(|node| node.links.get_mut(layer))
+        {
+            *links_at_layer = links;
+        }
+    }
+
+    /// Unlinks and removes `name`. The nodes it linked with, both ways as links get pruned, are
+    /// offered links to each other so the graph stays connected.
+    fn take(&mut self, name: &str) -> Option<Node> {
+        let id = self.ids.remove(name)?;
+        let node = self.nodes[id].take()?;
+        for (layer, neighbors) in node.links.iter().enumerate() {
+            let mut affected = neighbors.clone();
+            for (other, links) in self.nodes.iter_mut().enumerate() {
+                let Some(links) = links.as_mut().and_then(|node| node.links.get_mut(layer)) else {
+                    continue;
+                };
+                if links.contains(&id) {
+                    links.retain(|link| *link != id);
+                    if !affected.contains(&other) {
+                        affected.push(other);
+                    }
+                }
+            }
+            for &from in &affected {
+                for &to in &affected {
+                    if from != to {
+                        self.connect(from, to, layer);
+                    }
+                }
+            }
+        }
+        if self.entry == Some(id) {
+            self.entry = None;
+            self.max_level = 0;
+            for (other, node) in self.nodes.iter().enumerate() {
+                if let Some(node) = node {
+                    if self.entry.is_none() || node.links.len() - 1 > self.max_level {
+                        self.entry = Some(other);
+                        self.max_level = node.links.len() - 1;
+                    }
+                }
+            }
+        }
+        Some(node)
+    }
+}
+
+impl VectorFilter {
+    /// Parses expressions like `.year >= 1980 and (.genre == "drama" or not .classic)`, with
+    /// `==`, `!=`, `<`, `<=`, `>`, `>=`, `in`, `and`/`&&`, `or`/`||`, `not`/`!`, numbers,
+    /// strings, `true`, `false`, `null` and `[..]` lists.
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let mut parser = FilterParser {
+            chars: expression.chars().collect(),
+            pos: 0,
+        };
+        let filter = parser.parse_or()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(format!("syntax error in FILTER at offset {}", parser.pos));
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, attributes: &Value) -> bool {
+        truthy(&self.eval(attributes))
+    }
+
+    fn eval(&self, attributes: &Value) -> Value {
+        match self {
+            VectorFilter::Literal(value) => value.clone(),
+            VectorFilter::Attribute(path) => path
+                .iter()
+                .try_fold(attributes, |value, key| value.get(key))
+                .cloned()
+                .unwrap_or(Value::Null),
+            VectorFilter::Not(filter) => Value::Bool(!filter.matches(attributes)),
+            VectorFilter::And(a, b) => Value::Bool(a.matches(attributes) && b.matches(attributes)),
+            VectorFilter::Or(a, b) => Value::Bool(a.matches(attributes) || b.matches(attributes)),
+            VectorFilter::Compare(a, op, b) => {
+                let (a, b) = (a.eval(attributes), b.eval(attributes));
+                let order = match (&a, &b) {
+                    (Value::Number(a), Value::Number(b)) => a
+                        .as_f64()
+                        .zip(b.as_f64())
+                        .and_then(|(a, b)| a.partial_cmp(&b)),
+                    (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                    (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+                    _ => None,
+                };
+                Value::Bool(match op {
+                    CompareOp::Eq => order.map_or(a == b, Ordering::is_eq),
+                    CompareOp::Ne => order.map_or(a != b, Ordering::is_ne),
+                    CompareOp::Lt => order.is_some_and(Ordering::is_lt),
+                    CompareOp::Le => order.is_some_and(Ordering::is_le),
+                    CompareOp::Gt => order.is_some_and(Ordering::is_gt),
+                    CompareOp::Ge => order.is_some_and(Ordering::is_ge),
+                    CompareOp::In => match (&a, &b) {
+                        (_, Value::Array(values)) => values.contains(&a),
+                        (Value::String(a), Value::String(b)) => b.contains(a.as_str()),
+                        _ => false,
+                    },
+                })
+            }
+        }
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(values) => !values.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+struct FilterParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl FilterParser {
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes `token` when it comes next, words only when they aren't part of a longer word.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let len = token.chars().count();
+        let matches = self
+            .chars
+            .get(self.pos..self.pos + len)
+            .is_some_and(|next| next.iter().copied().eq(token.chars()));
+        let is_word = token.chars().all(char::is_alphabetic);
+        if !matches
+            || (is_word
+                && self
+                    .chars
+                    .get(self.pos + len)
+                    .is_some_and(|c| c.is_alphanumeric() || *c == '_'))
+        {
+            return false;
+        }
+        self.pos += len;
+        true
+    }
+
+    fn parse_or(&mut self) -> Result<VectorFilter, String> {
+        let mut filter = self.parse_and()?;
+        while self.eat("||") || self.eat("or") {
+            filter = VectorFilter::Or(Box::new(filter), Box::new(self.parse_and()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_and(&mut self) -> Result<VectorFilter, String> {
+        let mut filter = self.parse_not()?;
+        while self.eat("&&") || self.eat("and") {
+            filter = VectorFilter::And(Box::new(filter), Box::new(self.parse_not()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_not(&mut self) -> Result<VectorFilter, String> {
+        if self.eat("not") || (!self.peek_is("!=") && self.eat("!")) {
+            return Ok(VectorFilter::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_compare()
+    }
+
+    fn peek_is(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let len = token.chars().count();
+        self.chars
+            .get(self.pos..self.pos + len)
+            .is_some_and(|next| next.iter().copied().eq(token.chars()))
+    }
+
+    fn parse_compare(&mut self) -> Result<VectorFilter, String> {
+        let left = self.parse_operand()?;
+        let op = [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+            ("in", CompareOp::In),
+        ]
+        .into_iter()
+        .find(|(token, _)| self.eat(token));
+        Ok(match op {
+            Some((_, op)) => {
+                VectorFilter::Compare(Box::new(left), op, Box::new(self.parse_operand()?))
+            }
+            None => left,
+        })
+    }
+
+    fn parse_operand(&mut self) -> Result<VectorFilter, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.chars.get(self.pos).copied() {
+            Some('(') => {
+                self.pos += 1;
+                let filter = self.parse_or()?;
+                if !self.eat(")") {
+                    return Err(format!(
+                        "unbalanced parenthesis in FILTER at offset {}",
+                        start
+                    ));
+                }
+                Ok(filter)
+            }
+            Some('.') => {
+                let mut path = vec![];
+                while self.chars.get(self.pos) == Some(&'.') {
+                    self.pos += 1;
+                    let name = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                    if name.is_empty() {
+                        return Err(format!(
+                            "missing attribute name in FILTER at offset {}",
+                            start
+                        ));
+                    }
+                    path.push(name);
+                }
+                Ok(VectorFilter::Attribute(path))
+            }
+            Some('[') => {
+                self.pos += 1;
+                let mut values = vec![];
+                while !self.eat("]") {
+                    if !values.is_empty() && !self.eat(",") {
+                        return Err(format!("malformed list in FILTER at offset {}", start));
+                    }
+                    match self.parse_operand()? {
+                        VectorFilter::Literal(value) => values.push(value),
+                        _ => return Err("lists in FILTER only hold literals".to_string()),
+                    }
+                }
+                Ok(VectorFilter::Literal(Value::Array(values)))
+            }
+            Some(quote @ ('"' | '\'')) => {
+                self.pos += 1;
+                let mut text = String::new();
+                loop {
+                    match self.chars.get(self.pos).copied() {
+                        None => {
+                            return Err(format!(
+                                "unterminated string in FILTER at offset {}",
+                                start
+                            ))
+                        }
+                        Some('\\') => {
+                            if let Some(c) = self.chars.get(self.pos + 1) {
+                                text.push(*c);
+                            }
+                            self.pos += 2;
+                        }
+                        Some(c) if c == quote => {
+                            self.pos += 1;
+                            break;
+                        }
+                        Some(c) => {
+                            text.push(c);
+                            self.pos += 1;
+                        }
+                    }
+                }
+                Ok(VectorFilter::Literal(Value::String(text)))
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => {
+                let number = self
+                    .take_while(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'));
+                let value: f64 = number
+                    .parse()
+                    .map_err(|_| format!("invalid number `{}` in FILTER", number))?;
+                Ok(VectorFilter::Literal(
+                    serde_json::Number::from_f64(value).map_or(Value::Null, Value::Number),
+                ))
+            }
+            Some(c) if c.is_alphabetic() => {
+                let word = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                Ok(VectorFilter::Literal(match word.as_str() {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    "null" => Value::Null,
+                    _ => return Err(format!("unexpected `{}` in FILTER", word)),
+                }))
+            }
+            _ => Err(format!("syntax error in FILTER at offset {}", start)),
+        }
+    }
+
+    fn take_while(&mut self, keep: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| keep(*c)) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(found: Vec<(String, f64)>) -> Vec<String> {
+        found.into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn test_vector_set_search() {
+        let mut set = VectorSet::new(2, Metric::L2, 4, 32);
+        let mut rng = rand::thread_rng();
+        for i in 0..500 {
+            let vector = vec![rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0)];
+            assert!(set.add(&format!("p{}", i), vector, None).unwrap());
+        }
+        assert!(set.add("x", vec![1.0], None).is_err());
+        assert!(set.add("origin", vec![0.0, 0.0], None).unwrap());
+        assert!(!set.add("origin", vec![0.5, 0.5], None).unwrap());
+        assert_eq!(set.len(), 501);
+
+        // the graph finds what a full scan finds
+        let exact = names(set.search(&[3.0, -7.0], 10, 10, None, 0, true));
+        let approximate = names(set.search(&[3.0, -7.0], 10, 64, None, 0, false));
+        let recall = approximate
+            .iter()
+            .filter(|name| exact.contains(name))
+            .count();
+        assert!(recall >= 9, "recall {} of 10", recall);
+
+        for i in 0..400 {
+            assert!(set.remove(&format!("p{}", i)));
+        }
+        assert!(!set.remove("p0"));
+        assert_eq!(set.len(), 101);
+        let found = set.search(&[0.0, 0.0], 1, 16, None, 0, false);
+        assert_eq!(found[0].0, "origin");
+        assert!((found[0].1 - 0.5f64.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_vector_set_metrics_and_filters() {
+        let mut set = VectorSet::new(2, Metric::Cosine, 16, 200);
+        let attributes = |value: &str| Some(serde_json::from_str(value).unwrap());
+        set.add(
+            "east",
+            vec![1.0, 0.0],
+            attributes(r#"{"year": 1990, "tags": ["a"]}"#),
+        )
+        .unwrap();
+        set.add(
+            "north",
+            vec![0.0, 2.0],
+            attributes(r#"{"year": 2005, "genre": "drama"}"#),
+        )
+        .unwrap();
+        set.add("west", vec![-1.0, 0.0], None).unwrap();
+        assert_eq!(
+            set.search(&[1.0, 0.1], 3, 10, None, 0, false)
+                .into_iter()
+                .map(|(name, score)| (name, (score * 100.0).round()))
+                .collect::<Vec<_>>(),
+            [
+                ("east".to_string(), 100.0),
+                ("north".to_string(), 55.0),
+                ("west".to_string(), 0.0)
+            ]
+        );
+
+        let filter =
+            VectorFilter::parse(".year > 2000 && .genre in [\"drama\", \"comedy\"]").unwrap();
+        assert_eq!(
+            names(set.search(&[1.0, 0.0], 3, 10, Some(&filter), 100, false)),
+            ["north"]
+        );
+        let filter = VectorFilter::parse("not (.year >= 2000) and \"a\" in .tags").unwrap();
+        assert_eq!(
+            names(set.search(&[0.0, 1.0], 3, 10, Some(&filter), 100, false)),
+            ["east"]
+        );
+        assert!(VectorFilter::parse(".year >").is_err());
+        assert!(VectorFilter::parse("(.year > 1").is_err());
+
+        set.set_attributes("east", None);
+        assert_eq!(set.attributes("east"), None);
+        let mut set = VectorSet::new(2, Metric::InnerProduct, 16, 200);
+        set.add("small", vec![1.0, 1.0], None).unwrap();
+        set.add("large", vec![3.0, 3.0], None).unwrap();
+        assert_eq!(
+            set.search(&[1.0, 0.0], 1, 10, None, 0, false),
+            [("large".to_string(), 3.0)]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::backend::Backend;
+
+/// The keys clients WATCH, like redis' watched keys. Writes don't keep versions of every key,
+/// they flag the clients watching the key they modified instead.
+#[derive(Default)]
+pub struct WatchState {
+    /// the dirty flags of the clients watching a key, by client id
+    keys: Mutex<HashMap<String, HashMap<u64, Arc<AtomicBool>>>>,
+    next_id: AtomicU64,
+}
+
+/// The keys one connection watches, which are unwatched when it's dropped.
+pub struct Watcher {
+    backend: Backend,
+    id: u64,
+    /// set once a watched key was modified, EXEC then fails
+    dirty: Arc<AtomicBool>,
+    keys: BTreeSet<String>,
+}
+
+impl Watcher {
+    pub fn new(backend: &Backend) -> Self {
+        Self {
+            backend: backend.clone(),
+            id: backend.watch.next_id.fetch_add(1, Ordering::Relaxed),
+            dirty: Arc::new(AtomicBool::new(false)),
+            keys: BTreeSet::new(),
+        }
+    }
+
+    pub fn watch(&mut self, key: &str) {
+        if self.keys.insert(key.to_string()) {
+            self.backend
+                .watch
+                .keys
+                .lock()
+                .unwrap()
+                .entry(key.to_string())
+                .or_default()
+                .insert(self.id, self.dirty.clone());
+        }
+    }
+
+    /// Forgets every watched key and whether one was modified.
+    pub fn unwatch(&mut self) {
+        let mut keys = self.backend.watch.keys.lock().unwrap();
+        for key in &self.keys {
+            if let Some(watchers) = keys.get_mut(key) {
+                watchers.remove(&self.id);
+                if watchers.is_empty() {
+                    keys.remove(key);
+                }
+            }
+        }
+        drop(keys);
+        self.keys.clear();
+        self.dirty.store(false, Ordering::Relaxed);
+    }
+
+    /// Whether a watched key was modified since it was watched.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.unwatch();
+    }
+}
+
+impl Backend {
+    /// Flags the clients watching `key`, called for every write.
+    pub fn touch_key(&self, key: &str) {
+        if let Some(watchers) = self.watch.keys.lock().unwrap().get(key) {
+            for dirty in watchers.values() {
+                dirty.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Flags every client watching any key, for FLUSHALL and FLUSHDB.
+    pub fn touch_all_keys(&self) {
+        for watchers in self.watch.keys.lock().unwrap().values() {
+            for dirty in watchers.values() {
+                dirty.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for WatchState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WatchState")
+            .field("keys", &self.keys.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl fmt::Debug for Watcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watcher")
+            .field("id", &self.id)
+            .field("dirty", &self.is_dirty())
+            .field("keys", &self.keys)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watcher() {
+        let backend = Backend::new();
+        let mut watcher = Watcher::new(&backend);
+        let mut other = Watcher::new(&backend);
+        watcher.watch("a");
+        other.watch("b");
+
+        backend.touch_key("c");
+        assert!(!watcher.is_dirty());
+        backend.touch_key("a");
+        assert!(watcher.is_dirty());
+        assert!(!other.is_dirty());
+
+        watcher.unwatch();
+        assert!(!watcher.is_dirty());
+        backend.touch_key("a");
+        assert!(!watcher.is_dirty());
+
+        drop(other);
+        assert!(backend.watch.keys.lock().unwrap().is_empty());
+
+        watcher.watch("a");
+        backend.touch_all_keys();
+        assert!(watcher.is_dirty());
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use crate::backend::skiplist::{Iter, SkipList};
+use crate::backend::{Backend, NOTIFY_GENERIC, NOTIFY_ZSET};
+
+/// A sorted set: members map to their score and a skiplist keeps them ordered.
+#[derive(Debug, Clone, Default)]
+pub struct SortedSet {
+    dict: HashMap<String, f64>,
+    list: SkipList,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreBound {
+    Inclusive(f64),
+    Exclusive(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexBound {
+    /// `-`, lower than any member
+    Min,
+    /// `+`, higher than any member
+    Max,
+    Inclusive(String),
+    Exclusive(String),
+}
+
+impl SortedSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.dict.get(member).copied()
+    }
+
+    /// Sets the score of `member`, returns true when it was newly added.
+    pub fn insert(&mut self, member: String, score: f64) -> bool {
+        // -0.0 and 0.0 must sort as the same score
+        let score = score + 0.0;
+        match self.dict.get_mut(&member) {
+            Some(old) if *old == score => false,
+            Some(old) => {
+                self.list.remove(*old, &member);
+                *old = score;
+                self.list.insert(score, member);
+                false
+            }
+            None => {
+                self.dict.insert(member.clone(), score);
+                self.list.insert(score, member);
+                true
+            }
+        }
+    }
+
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self.dict.remove(member) {
+            Some(score) => self.list.remove(score, member),
+            None => false,
+        }
+    }
+
+    /// 0-based rank counted from the lowest score.
+    pub fn rank(&self, member: &str) -> Option<usize> {
+        self.dict
+            .get(member)
+            .map(|score| self.list.rank(*score, member))
+    }
+
+    pub fn get_by_rank(&self, rank: usize) -> Option<(&str, f64)> {
+        self.list.get_by_rank(rank)
+    }
+
+    /// Elements with ranks in `start..end`, `rev` walks them from the highest score.
+    pub fn range(&self, start: usize, end: usize, rev: bool) -> Iter<'_> {
+        if rev {
+            self.list.rev_range(start, end)
+        } else {
+            self.list.range(start, end)
+        }
+    }
+
+    /// The ranks `start..end` of the elements whose score lies between `min` and `max`.
+    pub fn score_range(&self, min: ScoreBound, max: ScoreBound) -> (usize, usize) {
+        let start = self.list.count_while(|score, _| !min.lower_admits(score));
+        let end = self.list.count_while(|score, _| max.upper_admits(score));
+        (start, end.max(start))
+    }
+
+    /// The ranks `start..end` of the elements between `min` and `max` in lexicographical order,
+    /// which is only meaningful when all members share the same score.
+    pub fn lex_range(&self, min: &LexBound, max: &LexBound) -> (usize, usize) {
+        let start = self.list.count_while(|_, member| !min.lower_admits(member));
+        let end = self.list.count_while(|_, member| max.upper_admits(member));
+        (start, end.max(start))
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        self.list.range(0, self.len())
+    }
+}
+
+impl ScoreBound {
+    /// Whether `score` is within the range when this bound is its lower end.
+    fn lower_admits(&self, score: f64) -> bool {
+        match self {
+            ScoreBound::Inclusive(min) => score >= *min,
+            ScoreBound::Exclusive(min) => score > *min,
+        }
+    }
+
+    /// Whether `score` is within the range when this bound is its upper end.
+    fn upper_admits(&self, score: f64) -> bool {
+        match self {
+            ScoreBound::Inclusive(max) => score <= *max,
+            ScoreBound::Exclusive(max) => score < *max,
+        }
+    }
+}
+
+impl LexBound {
+    fn lower_admits(&self, member: &str) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(min) => member.as_bytes() >= min.as_bytes(),
+            LexBound::Exclusive(min) => member.as_bytes() > min.as_bytes(),
+        }
+    }
+
+    fn upper_admits(&self, member: &str) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(max) => member.as_bytes() <= max.as_bytes(),
+            LexBound::Exclusive(max) => member.as_bytes() < max.as_bytes(),
+        }
+    }
+}
+
+impl Backend {
+    /// Replaces `key` with `zset`, an empty set deletes the key. Returns the cardinality.
+    /// `event` is notified for the stored key, `del` when an existing key was deleted instead.
+    pub fn zset_store(&self, key: &str, zset: SortedSet, event: &str) -> usize {
+        let len = zset.len();
+        if zset.is_empty() {
+            if self.zset.remove(key).is_some() {
+                self.notify(NOTIFY_GENERIC, "del", key);
+            }
+        } else {
+            let created = self.zset.insert(key.to_string(), zset).is_none();
+            self.notify_new(created, key);
+            self.notify(NOTIFY_ZSET, event, key);
+            self.signal_key_ready(key);
+        }
+        len
+    }
+
+    pub fn zset_len(&self, key: &str) -> usize {
+        self.zset.get(key).map(|zset| zset.len()).unwrap_or(0)
+    }
+
+    /// Removes the sorted set when a write left it empty, notifying `del`.
+    pub(crate) fn zset_remove_if_empty(&self, key: &str) {
+        if self
+            .zset
+            .remove_if(key, |_, zset| zset.is_empty())
+            .is_some()
+        {
+            self.notify(NOTIFY_GENERIC, "del", key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_set_score_and_lex_range() {
+        let mut zset = SortedSet::new();
+        for (member, score) in [("a", 1.0), ("b", 2.0), ("c", 2.0), ("d", 3.0)] {
+            assert!(zset.insert(member.to_string(), score));
+        }
+        assert!(!zset.insert("a".to_string(), 4.0));
+        assert_eq!(zset.rank("a"), Some(3));
+
+        let (start, end) = zset.score_range(ScoreBound::Exclusive(1.0), ScoreBound::Inclusive(2.0));
+        let members: Vec<&str> = zset.range(start, end, false).map(|(m, _)| m).collect();
+        assert_eq!(members, vec!["b", "c"]);
+
+        let members: Vec<&str> = zset.range(2, 4, true).map(|(m, _)| m).collect();
+        assert_eq!(members, vec!["a", "d"]);
+
+        let mut zset = SortedSet::new();
+        for member in ["a", "b", "c", "d"] {
+            zset.insert(member.to_string(), 0.0);
+        }
+        let (start, end) = zset.lex_range(&LexBound::Exclusive("b".to_string()), &LexBound::Max);
+        let members: Vec<&str> = zset.range(start, end, false).map(|(m, _)| m).collect();
+        assert_eq!(members, vec!["c", "d"]);
+        let (start, end) = zset.lex_range(&LexBound::Min, &LexBound::Inclusive("b".to_string()));
+        assert_eq!((start, end), (0, 2));
+    }
+}
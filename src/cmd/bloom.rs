@@ -0,0 +1,338 @@
+use dashmap::mapref::entry::Entry;
+use dashmap::mapref::one::RefMut;
+
+use crate::array::RespArray;
+use crate::backend::{
+    bloom_bits, Backend, BloomFilter, BLOOM_DEFAULT_CAPACITY, BLOOM_DEFAULT_ERROR_RATE,
+    BLOOM_DEFAULT_EXPANSION, NOTIFY_MODULE,
+};
+use crate::cmd::{
+    extract_args, extract_bytes, extract_float, extract_int, extract_string, validate_command,
+    validate_command_at_least, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::map::RespMap;
+use crate::null::RespNull;
+
+#[derive(Debug)]
+pub struct BfReserve {
+    key: String,
+    error_rate: f64,
+    capacity: u64,
+    /// `None` for NONSCALING
+    expansion: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct BfAdd {
+    key: String,
+    item: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct BfMAdd {
+    key: String,
+    items: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct BfExists {
+    key: String,
+    item: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct BfInfo {
+    key: String,
+}
+
+impl CommandExecutor for BfReserve {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match backend.bloom.entry(self.key.clone()) {
+            Entry::Occupied(_) => Err(CommandError::InvalidArgument("item exists".to_string())),
+            Entry::Vacant(entry) => {
+                entry.insert(BloomFilter::new(
+                    self.error_rate,
+                    self.capacity,
+                    self.expansion,
+                ));
+                backend.notify_new(true, &self.key);
+                backend.notify(NOTIFY_MODULE, "bf.reserve", &self.key);
+                Ok(RESP_OK.clone())
+            }
+        }
+    }
+}
+
+impl CommandExecutor for BfAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let created = !backend.bloom.contains_key(&self.key);
+        let added = bloom_entry(backend, self.key.clone())
+            .add(&self.item)
+            .map_err(CommandError::InvalidArgument)?;
+        backend.notify_new(created, &self.key);
+        backend.notify(NOTIFY_MODULE, "bf.add", &self.key);
+        Ok(RespFrame::Integer(added as i64))
+    }
+}
+
+impl CommandExecutor for BfMAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let created = !backend.bloom.contains_key(&self.key);
+        let mut bloom = bloom_entry(backend, self.key.clone());
+        let added = self
+            .items
+            .iter()
+            .map(|item| match bloom.add(item) {
+                Ok(added) => RespFrame::Integer(added as i64),
+                Err(err) => RespFrame::Error(err.as_str().into()),
+            })
+            .collect::<Vec<_>>();
+        drop(bloom);
+        backend.notify_new(created, &self.key);
+        backend.notify(NOTIFY_MODULE, "bf.add", &self.key);
+        Ok(RespArray::new(added).into())
+    }
+}
+
+impl CommandExecutor for BfExists {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let exists = backend
+            .bloom
+            .get(&self.key)
+            .is_some_and(|bloom| bloom.contains(&self.item));
+        Ok(RespFrame::Integer(exists as i64))
+    }
+}
+
+impl CommandExecutor for BfInfo {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let bloom = backend
+            .bloom
+            .get(&self.key)
+            .ok_or_else(|| CommandError::InvalidArgument("not found".to_string()))?;
+        let mut info = RespMap::new();
+        info.insert(
+            "Capacity".to_string(),
+            RespFrame::Integer(bloom.capacity() as i64),
+        );
+        info.insert("Size".to_string(), RespFrame::Integer(bloom.size() as i64));
+        info.insert(
+            "Number of filters".to_string(),
+            RespFrame::Integer(bloom.num_filters() as i64),
+        );
+        info.insert(
+            "Number of items inserted".to_string(),
+            RespFrame::Integer(bloom.items() as i64),
+        );
+        info.insert(
+            "Expansion rate".to_string(),
+            match bloom.expansion() {
+                Some(expansion) => RespFrame::Integer(expansion as i64),
+                None => RespFrame::Null(RespNull),
+            },
+        );
+        Ok(info.into())
+    }
+}
+
+/// The filter at `key`, created with the default settings when missing.
+fn bloom_entry(backend: &Backend, key: String) -> RefMut<'_, String, BloomFilter> {
+    backend.bloom.entry(key).or_insert_with(|| {
+        BloomFilter::new(
+            BLOOM_DEFAULT_ERROR_RATE,
+            BLOOM_DEFAULT_CAPACITY,
+            Some(BLOOM_DEFAULT_EXPANSION),
+        )
+    })
+}
+
+impl TryFrom<RespArray> for BfReserve {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["bf.reserve"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let error_rate = extract_float(args.next())?;
+        if !(error_rate > 0.0 && error_rate < 1.0) {
+            return Err(CommandError::InvalidArgument(
+                "(0 < error rate range < 1)".to_string(),
+            ));
+        }
+        let capacity = u64::try_from(extract_int(args.next())?)
+            .ok()
+            .filter(|capacity| *capacity > 0)
+            .ok_or_else(|| {
+                CommandError::InvalidArgument("(capacity should be larger than 0)".to_string())
+            })?;
+        if bloom_bits(error_rate, capacity).is_none() {
+            return Err(CommandError::InvalidArgument(
+                "(capacity is too large for the error rate)".to_string(),
+            ));
+        }
+
+        let mut expansion = Some(BLOOM_DEFAULT_EXPANSION);
+        let (mut custom_expansion, mut non_scaling) = (false, false);
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                "EXPANSION" => {
+                    let value = u64::try_from(extract_int(args.next())?)
+                        .ok()
+                        .filter(|expansion| *expansion >= 1)
+                        .ok_or_else(|| {
+                            CommandError::InvalidArgument(
+                                "expansion should be greater or equal to 1".to_string(),
+                            )
+                        })?;
+                    expansion = Some(value);
+                    custom_expansion = true;
+                }
+                "NONSCALING" => non_scaling = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        if non_scaling {
+            if custom_expansion {
+                return Err(CommandError::InvalidArgument(
+                    "Nonscaling filters cannot expand".to_string(),
+                ));
+            }
+            expansion = None;
+        }
+        Ok(BfReserve {
+            key,
+            error_rate,
+            capacity,
+            expansion,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BfAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bf.add"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(BfAdd {
+            key: extract_string(args.next())?,
+            item: extract_bytes(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BfMAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["bf.madd"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let items = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(BfMAdd { key, items })
+    }
+}
+
+impl TryFrom<RespArray> for BfExists {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bf.exists"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(BfExists {
+            key: extract_string(args.next())?,
+            item: extract_bytes(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BfInfo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bf.info"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(BfInfo {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    #[test]
+    fn test_bf_reserve_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*5\r\n$10\r\nbf.reserve\r\n$1\r\nb\r\n$5\r\n0.001\r\n$4\r\n1000\r\n$10\r\nnonscaling\r\n");
+        let cmd: BfReserve = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.error_rate, 0.001);
+        assert_eq!(cmd.capacity, 1000);
+        assert_eq!(cmd.expansion, None);
+
+        let cmd: Result<BfReserve, _> = args(&[
+            "bf.reserve",
+            "b",
+            "0.01",
+            "10",
+            "expansion",
+            "2",
+            "nonscaling",
+        ])
+        .try_into();
+        assert!(cmd.is_err());
+
+        let err = BfReserve::try_from(args(&["bf.reserve", "b", "0.01", "9223372036854775807"]))
+            .unwrap_err();
+        assert!(err.to_string().contains("capacity is too large"));
+        let err =
+            BfReserve::try_from(args(&["bf.reserve", "b", "1e-300", "100000000"])).unwrap_err();
+        assert!(err.to_string().contains("capacity is too large"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bf_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: BfReserve = args(&["bf.reserve", "b", "0.01", "2", "nonscaling"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RESP_OK.clone());
+        let cmd: BfReserve = args(&["bf.reserve", "b", "0.01", "2"]).try_into()?;
+        assert!(cmd.execute(&backend).is_err());
+
+        let cmd: BfAdd = args(&["bf.add", "b", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+        let cmd: BfMAdd = args(&["bf.madd", "b", "x", "y", "z"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([
+                RespFrame::Integer(0),
+                RespFrame::Integer(1),
+                RespFrame::Error("non scaling filter is full".into()),
+            ])
+            .into()
+        );
+        let cmd: BfExists = args(&["bf.exists", "b", "y"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+
+        // adding to a missing key creates a scalable filter
+        let cmd: BfAdd = args(&["bf.add", "auto", "x"]).try_into()?;
+        cmd.execute(&backend)?;
+        let cmd: BfInfo = args(&["bf.info", "auto"]).try_into()?;
+        let RespFrame::Map(info) = cmd.execute(&backend)? else {
+            panic!("expected a map");
+        };
+        assert_eq!(info.get("Capacity"), Some(&RespFrame::Integer(100)));
+        assert_eq!(info.get("Expansion rate"), Some(&RespFrame::Integer(2)));
+        Ok(())
+    }
+}
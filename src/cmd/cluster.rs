@@ -0,0 +1,147 @@
+use crate::array::RespArray;
+use crate::backend::{key_hash_slot, Backend, SLOT_COUNT};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_bytes, extract_string, validate_command_at_least, CommandError,
+    CommandExecutor, RESP_OK,
+};
+use crate::frame::RespFrame;
+
+/// The slot routing part of CLUSTER. There is a single node, slots only move away from it
+/// when told so with SETSLOT.
+#[derive(Debug)]
+pub struct Cluster {
+    sub: ClusterSub,
+}
+
+#[derive(Debug)]
+enum ClusterSub {
+    MyId,
+    KeySlot(Vec<u8>),
+    /// `SETSLOT slot NODE node-id`
+    SetSlot {
+        slot: u16,
+        node_id: String,
+    },
+}
+
+impl CommandExecutor for Cluster {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match self.sub {
+            ClusterSub::MyId => Ok(BulkString::new(backend.node_id()).into()),
+            ClusterSub::KeySlot(key) => Ok(RespFrame::Integer(key_hash_slot(&key) as i64)),
+            ClusterSub::SetSlot { slot, node_id } => {
+                backend.set_slot_node(slot, &node_id);
+                Ok(RESP_OK.clone())
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Cluster {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["cluster"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_uppercase();
+        let sub = match (subcommand.as_str(), args.len()) {
+            ("MYID", 0) => ClusterSub::MyId,
+            ("KEYSLOT", 1) => ClusterSub::KeySlot(extract_bytes(args.next())?),
+            ("SETSLOT", 3) => {
+                let slot = extract_string(args.next())?
+                    .parse()
+                    .ok()
+                    .filter(|slot| *slot < SLOT_COUNT)
+                    .ok_or_else(|| {
+                        CommandError::InvalidArgument("Invalid or out of range slot".to_string())
+                    })?;
+                // IMPORTING, MIGRATING and STABLE need a second node to talk to
+                if !extract_string(args.next())?.eq_ignore_ascii_case("node") {
+                    return Err(CommandError::InvalidArgument(
+                        "Invalid CLUSTER SETSLOT action or number of arguments".to_string(),
+                    ));
+                }
+                let node_id = extract_string(args.next())?;
+                ClusterSub::SetSlot { slot, node_id }
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for CLUSTER {}",
+                    subcommand
+                )))
+            }
+        };
+        Ok(Cluster { sub })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Subscriber;
+    use crate::cmd::{args, SPublish, SSubscribe};
+    use crate::push::RespPush;
+    use anyhow::Result;
+
+    fn run(backend: &Backend, cmd: &[&str]) -> Result<RespFrame, CommandError> {
+        Cluster::try_from(args(cmd))?.execute(backend)
+    }
+
+    #[test]
+    fn test_cluster_setslot_moves_shard_channels() -> Result<()> {
+        let backend = Backend::new();
+        let (mut subscriber, mut rx) = Subscriber::new(&backend);
+        SSubscribe::try_from(args(&["ssubscribe", "{user1}.a"]))?.apply(&mut subscriber);
+        let slot = key_hash_slot(b"user1").to_string();
+        assert_eq!(
+            run(&backend, &["cluster", "keyslot", "{user1}.a"])?,
+            RespFrame::Integer(key_hash_slot(b"user1") as i64)
+        );
+
+        let other = "0".repeat(40);
+        assert_eq!(
+            run(&backend, &["cluster", "setslot", &slot, "node", &other])?,
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            subscriber.receive(rx.try_recv().unwrap()),
+            RespPush::new([
+                b"sunsubscribe".into(),
+                b"{user1}.a".into(),
+                RespFrame::Integer(0)
+            ])
+            .into()
+        );
+        let moved = format!("invalid argument: MOVED {} {}", slot, other);
+        let cmd = SSubscribe::try_from(args(&["ssubscribe", "{user1}.b"]))?;
+        assert_eq!(cmd.check_slot(&backend).unwrap_err().to_string(), moved);
+        let cmd = SPublish::try_from(args(&["spublish", "{user1}.a", "hi"]))?;
+        assert_eq!(cmd.execute(&backend).unwrap_err().to_string(), moved);
+
+        // handing the slot back to this node serves it again
+        let id = backend.node_id().to_string();
+        assert_eq!(
+            run(&backend, &["cluster", "myid"])?,
+            BulkString::new(id.as_str()).into()
+        );
+        run(&backend, &["cluster", "setslot", &slot, "node", &id])?;
+        let cmd = SSubscribe::try_from(args(&["ssubscribe", "{user1}.b"]))?;
+        assert!(cmd.check_slot(&backend).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cluster_setslot_invalid() {
+        let backend = Backend::new();
+        for cmd in [
+            &["cluster", "setslot", "16384", "node", "x"][..],
+            &["cluster", "setslot", "-1", "node", "x"],
+            &["cluster", "setslot", "1", "migrating", "x"],
+            &["cluster", "setslot", "1", "stable"],
+            &["cluster", "nodes"],
+        ] {
+            assert!(run(&backend, cmd).is_err(), "{:?}", cmd);
+        }
+    }
+}
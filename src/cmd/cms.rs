@@ -0,0 +1,309 @@
+use dashmap::mapref::entry::Entry;
+
+use crate::array::RespArray;
+use crate::backend::{Backend, CountMinSketch, CMS_MAX_COUNTERS, NOTIFY_MODULE};
+use crate::cmd::{
+    extract_args, extract_bytes, extract_float, extract_int, extract_string, validate_command,
+    validate_command_at_least, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::map::RespMap;
+
+#[derive(Debug)]
+pub struct CmsInitByDim {
+    key: String,
+    width: usize,
+    depth: usize,
+}
+
+#[derive(Debug)]
+pub struct CmsInitByProb {
+    key: String,
+    error: f64,
+    probability: f64,
+}
+
+#[derive(Debug)]
+pub struct CmsIncrBy {
+    key: String,
+    items: Vec<(Vec<u8>, u64)>,
+}
+
+#[derive(Debug)]
+pub struct CmsQuery {
+    key: String,
+    items: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct CmsInfo {
+    key: String,
+}
+
+impl CommandExecutor for CmsInitByDim {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        init_sketch(
+            backend,
+            self.key,
+            CountMinSketch::new(self.width, self.depth),
+            "cms.initbydim",
+        )
+    }
+}
+
+impl CommandExecutor for CmsInitByProb {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let sketch = CountMinSketch::with_error(self.error, self.probability);
+        init_sketch(backend, self.key, sketch, "cms.initbyprob")
+    }
+}
+
+fn init_sketch(
+    backend: &Backend,
+    key: String,
+    sketch: CountMinSketch,
+    event: &str,
+) -> Result<RespFrame, CommandError> {
+    match backend.cms.entry(key.clone()) {
+        Entry::Occupied(_) => Err(CommandError::InvalidArgument(
+            "CMS: key already exists".to_string(),
+        )),
+        Entry::Vacant(entry) => {
+            entry.insert(sketch);
+            backend.notify_new(true, &key);
+            backend.notify(NOTIFY_MODULE, event, &key);
+            Ok(RESP_OK.clone())
+        }
+    }
+}
+
+impl CommandExecutor for CmsIncrBy {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let mut sketch = backend.cms.get_mut(&self.key).ok_or_else(missing_key)?;
+        let counts = self
+            .items
+            .iter()
+            .map(|(item, incr)| RespFrame::Integer(sketch.incr_by(item, *incr) as i64))
+            .collect::<Vec<_>>();
+        drop(sketch);
+        backend.notify(NOTIFY_MODULE, "cms.incrby", &self.key);
+        Ok(RespArray::new(counts).into())
+    }
+}
+
+impl CommandExecutor for CmsQuery {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let sketch = backend.cms.get(&self.key).ok_or_else(missing_key)?;
+        let counts = self
+            .items
+            .iter()
+            .map(|item| RespFrame::Integer(sketch.query(item) as i64))
+            .collect::<Vec<_>>();
+        Ok(RespArray::new(counts).into())
+    }
+}
+
+impl CommandExecutor for CmsInfo {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let sketch = backend.cms.get(&self.key).ok_or_else(missing_key)?;
+        let mut info = RespMap::new();
+        info.insert(
+            "width".to_string(),
+            RespFrame::Integer(sketch.width() as i64),
+        );
+        info.insert(
+            "depth".to_string(),
+            RespFrame::Integer(sketch.depth() as i64),
+        );
+        info.insert(
+            "count".to_string(),
+            RespFrame::Integer(sketch.count() as i64),
+        );
+        Ok(info.into())
+    }
+}
+
+fn missing_key() -> CommandError {
+    CommandError::InvalidArgument("CMS: key does not exist".to_string())
+}
+
+impl TryFrom<RespArray> for CmsInitByDim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cms.initbydim"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let mut dimension = || {
+            usize::try_from(extract_int(args.next())?)
+                .ok()
+                .filter(|value| *value > 0)
+                .ok_or_else(|| {
+                    CommandError::InvalidArgument("CMS: invalid width/depth value".to_string())
+                })
+        };
+        let (width, depth) = (dimension()?, dimension()?);
+        validate_size(width, depth)?;
+        Ok(CmsInitByDim { key, width, depth })
+    }
+}
+
+impl TryFrom<RespArray> for CmsInitByProb {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cms.initbyprob"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let error = extract_float(args.next())?;
+        if !(error > 0.0 && error < 1.0) {
+            return Err(CommandError::InvalidArgument(
+                "CMS: invalid overestimation value".to_string(),
+            ));
+        }
+        let probability = extract_float(args.next())?;
+        if !(probability > 0.0 && probability < 1.0) {
+            return Err(CommandError::InvalidArgument(
+                "CMS: invalid prob value".to_string(),
+            ));
+        }
+        let (width, depth) = CountMinSketch::dimensions(error, probability);
+        validate_size(width, depth)?;
+        Ok(CmsInitByProb {
+            key,
+            error,
+            probability,
+        })
+    }
+}
+
+fn validate_size(width: usize, depth: usize) -> Result<(), CommandError> {
+    match width.checked_mul(depth) {
+        Some(counters) if counters <= CMS_MAX_COUNTERS => Ok(()),
+        _ => Err(CommandError::InvalidArgument(
+            "CMS: width*depth is too large".to_string(),
+        )),
+    }
+}
+
+impl TryFrom<RespArray> for CmsIncrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["cms.incrby"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        if !args.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'cms.incrby' command".to_string(),
+            ));
+        }
+        let mut items = Vec::with_capacity(args.len() / 2);
+        while let Some(item) = args.next() {
+            let item = extract_bytes(Some(item))?;
+            let incr = u64::try_from(extract_int(args.next())?).map_err(|_| {
+                CommandError::InvalidArgument("CMS: Cannot parse number".to_string())
+            })?;
+            items.push((item, incr));
+        }
+        Ok(CmsIncrBy { key, items })
+    }
+}
+
+impl TryFrom<RespArray> for CmsQuery {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["cms.query"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let items = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(CmsQuery { key, items })
+    }
+}
+
+impl TryFrom<RespArray> for CmsInfo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cms.info"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(CmsInfo {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    #[test]
+    fn test_cms_incrby_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$10\r\ncms.incrby\r\n$1\r\ns\r\n$1\r\na\r\n$1\r\n2\r\n$1\r\nb\r\n$1\r\n3\r\n",
+        );
+        let cmd: CmsIncrBy = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.items, vec![(b"a".to_vec(), 2), (b"b".to_vec(), 3)]);
+
+        let cmd: Result<CmsIncrBy, _> = args(&["cms.incrby", "s", "a", "1", "b"]).try_into();
+        assert!(cmd.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cms_init_too_large() {
+        let err = CmsInitByDim::try_from(args(&["cms.initbydim", "s", "4294967296", "4294967296"]))
+            .unwrap_err();
+        assert!(err.to_string().contains("CMS: width*depth is too large"));
+        let err =
+            CmsInitByDim::try_from(args(&["cms.initbydim", "s", "100000000", "10"])).unwrap_err();
+        assert!(err.to_string().contains("CMS: width*depth is too large"));
+        let err =
+            CmsInitByProb::try_from(args(&["cms.initbyprob", "s", "1e-300", "0.01"])).unwrap_err();
+        assert!(err.to_string().contains("CMS: width*depth is too large"));
+    }
+
+    #[test]
+    fn test_cms_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: CmsIncrBy = args(&["cms.incrby", "s", "a", "1"]).try_into()?;
+        assert!(cmd.execute(&backend).is_err());
+
+        let cmd: CmsInitByProb = args(&["cms.initbyprob", "s", "0.001", "0.01"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RESP_OK.clone());
+        let cmd: CmsInitByDim = args(&["cms.initbydim", "s", "10", "2"]).try_into()?;
+        assert!(cmd.execute(&backend).is_err());
+
+        let cmd: CmsIncrBy = args(&["cms.incrby", "s", "a", "2", "b", "3", "a", "1"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([
+                RespFrame::Integer(2),
+                RespFrame::Integer(3),
+                RespFrame::Integer(3)
+            ])
+            .into()
+        );
+        let cmd: CmsQuery = args(&["cms.query", "s", "a", "c"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([RespFrame::Integer(3), RespFrame::Integer(0)]).into()
+        );
+        let cmd: CmsInfo = args(&["cms.info", "s"]).try_into()?;
+        let RespFrame::Map(info) = cmd.execute(&backend)? else {
+            panic!("expected a map");
+        };
+        assert_eq!(info.get("count"), Some(&RespFrame::Integer(6)));
+        Ok(())
+    }
+}
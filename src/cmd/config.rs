@@ -0,0 +1,276 @@
+use crate::array::RespArray;
+use crate::backend::{format_notify_flags, parse_notify_flags, Backend};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_string, glob_match, validate_command_at_least, CommandError,
+    CommandExecutor, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::map::RespMap;
+
+/// The parameters CONFIG GET and CONFIG SET know about.
+const PARAMETERS: [&str; 4] = [
+    "busy-reply-threshold",
+    "notify-keyspace-events",
+    "plugin-max-fuel",
+    "plugin-max-memory",
+];
+
+#[derive(Debug)]
+pub struct Config {
+    sub: ConfigSub,
+}
+
+#[derive(Debug)]
+enum ConfigSub {
+    /// glob-style patterns of the parameter names
+    Get(Vec<String>),
+    Set(Vec<(String, String)>),
+}
+
+impl CommandExecutor for Config {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match self.sub {
+            ConfigSub::Get(patterns) => {
+                let mut map = RespMap::new();
+                for name in PARAMETERS {
+                    if patterns
+                        .iter()
+                        .any(|pattern| glob_match(pattern.as_bytes(), name.as_bytes()))
+                    {
+                        map.insert(name.to_string(), BulkString::new(get(backend, name)).into());
+                    }
+                }
+                Ok(map.into())
+            }
+            ConfigSub::Set(pairs) => {
+                // every value is checked before any is applied
+                let mut flags = None;
+                let mut threshold = None;
+                let mut max_fuel = None;
+                let mut max_memory = None;
+                for (name, value) in pairs {
+                    let invalid = || {
+                        CommandError::InvalidArgument(format!(
+                            "Invalid argument '{}' for CONFIG SET '{}'",
+                            value, name
+                        ))
+                    };
+                    match name.as_str() {
+                        "busy-reply-threshold" => {
+                            threshold = Some(value.parse().map_err(|_| invalid())?);
+                        }
+                        "notify-keyspace-events" => {
+                            flags = Some(parse_notify_flags(&value).ok_or_else(invalid)?);
+                        }
+                        "plugin-max-fuel" => {
+                            max_fuel = Some(value.parse().map_err(|_| invalid())?);
+                        }
+                        "plugin-max-memory" => {
+                            max_memory = Some(value.parse().map_err(|_| invalid())?);
+                        }
+                        _ => {
+                            return Err(CommandError::InvalidArgument(format!(
+                                "Unknown option or number of arguments for CONFIG SET - '{}'",
+                                name
+                            )))
+                        }
+                    }
+                }
+                if let Some(threshold) = threshold {
+                    backend.set_busy_reply_threshold(threshold);
+                }
+                if let Some(flags) = flags {
+                    backend.set_notify_flags(flags);
+                }
+                if let Some(fuel) = max_fuel {
+                    backend.set_plugin_max_fuel(fuel);
+                }
+                if let Some(bytes) = max_memory {
+                    backend.set_plugin_max_memory(bytes);
+                }
+                Ok(RESP_OK.clone())
+            }
+        }
+    }
+}
+
+fn get(backend: &Backend, name: &str) -> String {
+    match name {
+        "busy-reply-threshold" => backend.busy_reply_threshold().to_string(),
+        "notify-keyspace-events" => format_notify_flags(backend.notify_flags()),
+        "plugin-max-fuel" => backend.plugin_max_fuel().to_string(),
+        "plugin-max-memory" => backend.plugin_max_memory().to_string(),
+        _ => unreachable!("{} is not in PARAMETERS", name),
+    }
+}
+
+impl TryFrom<RespArray> for Config {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["config"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_uppercase();
+        let sub = match subcommand.as_str() {
+            "GET" => ConfigSub::Get(
+                args.map(|arg| Ok(extract_string(Some(arg))?.to_ascii_lowercase()))
+                    .collect::<Result<_, CommandError>>()?,
+            ),
+            "SET" if args.len() % 2 == 0 => {
+                let mut pairs = vec![];
+                while let Some(name) = args.next() {
+                    let name = extract_string(Some(name))?.to_ascii_lowercase();
+                    pairs.push((name, extract_string(args.next())?));
+                }
+                ConfigSub::Set(pairs)
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for CONFIG {}",
+                    subcommand
+                )))
+            }
+        };
+        Ok(Config { sub })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::backend::{PubSubEvent, Subscriber};
+    use crate::cmd::args;
+    use crate::cmd::{Del, LPop, RPush, Set};
+    use crate::decode::RespDecode;
+    use crate::push::RespPush;
+
+    use super::*;
+
+    fn run<T>(backend: &Backend, cmd: &[&str]) -> Result<RespFrame>
+    where
+        T: TryFrom<RespArray, Error = CommandError> + CommandExecutor,
+    {
+        Ok(T::try_from(args(cmd))?.execute(backend)?)
+    }
+
+    #[test]
+    fn test_config_try_from() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nconfig\r\n$3\r\nget\r\n$7\r\nNOTIFY*\r\n");
+        let config: Config = RespArray::decode(&mut buf)?.try_into()?;
+        assert!(matches!(config.sub, ConfigSub::Get(patterns) if patterns == ["notify*"]));
+
+        assert!(Config::try_from(args(&["config", "set", "notify-keyspace-events"])).is_err());
+        assert!(Config::try_from(args(&["config", "rewrite", "x"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_get_set() -> Result<()> {
+        let backend = Backend::new();
+        let get = |backend: &Backend| run::<Config>(backend, &["config", "get", "*"]);
+        let mut expected = RespMap::new();
+        expected.insert(
+            "busy-reply-threshold".to_string(),
+            BulkString::new("5000").into(),
+        );
+        expected.insert(
+            "notify-keyspace-events".to_string(),
+            BulkString::new("").into(),
+        );
+        expected.insert(
+            "plugin-max-fuel".to_string(),
+            BulkString::new("10000000").into(),
+        );
+        expected.insert(
+            "plugin-max-memory".to_string(),
+            BulkString::new("16777216").into(),
+        );
+        assert_eq!(get(&backend)?, expected.clone().into());
+
+        let ret = run::<Config>(
+            &backend,
+            &[
+                "config",
+                "set",
+                "notify-keyspace-events",
+                "KEA",
+                "busy-reply-threshold",
+                "100",
+            ],
+        )?;
+        assert_eq!(ret, RESP_OK.clone());
+        expected.insert(
+            "busy-reply-threshold".to_string(),
+            BulkString::new("100").into(),
+        );
+        expected.insert(
+            "notify-keyspace-events".to_string(),
+            BulkString::new("AKE").into(),
+        );
+        assert_eq!(get(&backend)?, expected.into());
+
+        let ret = run::<Config>(&backend, &["config", "set", "notify-keyspace-events", "Kq"]);
+        assert!(ret.is_err());
+        assert!(run::<Config>(&backend, &["config", "set", "maxmemory", "1"]).is_err());
+        let ret = run::<Config>(&backend, &["config", "set", "busy-reply-threshold", "-1"]);
+        assert!(ret.is_err());
+        assert_eq!(
+            run::<Config>(&backend, &["config", "get", "maxmemory"])?,
+            RespMap::new().into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_keyspace_notifications() -> Result<()> {
+        let backend = Backend::new();
+        let (mut subscriber, mut rx) = Subscriber::new(&backend);
+        subscriber.subscribe("__keyevent@0__:new");
+        subscriber.psubscribe("__keyspace@0__:*");
+        run::<Config>(
+            &backend,
+            &["config", "set", "notify-keyspace-events", "Kgl$En"],
+        )?;
+
+        run::<Set>(&backend, &["set", "s", "v"])?;
+        run::<RPush>(&backend, &["rpush", "l", "a"])?;
+        run::<LPop>(&backend, &["lpop", "l"])?;
+        run::<Del>(&backend, &["del", "s", "missing"])?;
+
+        let mut received = vec![];
+        while let Ok(event) = rx.try_recv() {
+            let PubSubEvent::Message(RespFrame::Push(RespPush(frames))) = event else {
+                panic!("unexpected event {:?}", event);
+            };
+            let [.., RespFrame::BulkString(channel), RespFrame::BulkString(payload)] =
+                frames.as_slice()
+            else {
+                panic!("unexpected message {:?}", frames);
+            };
+            received.push(format!(
+                "{} {}",
+                String::from_utf8_lossy(channel),
+                String::from_utf8_lossy(payload)
+            ));
+        }
+        assert_eq!(
+            received,
+            [
+                "__keyspace@0__:s new",
+                "__keyevent@0__:new s",
+                "__keyspace@0__:s set",
+                "__keyspace@0__:l new",
+                "__keyevent@0__:new l",
+                "__keyspace@0__:l rpush",
+                "__keyspace@0__:l lpop",
+                "__keyspace@0__:l del",
+                "__keyspace@0__:s del",
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use crate::array::RespArray;
+use crate::backend::Backend;
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    connection_only, extract_args, extract_bytes, extract_int, extract_string, validate_command,
+    CommandError, CommandExecutor,
+};
+use crate::frame::{RespFrame, RespProtocol};
+use crate::map::RespMap;
+use crate::simple_string::SimpleString;
+
+#[derive(Debug)]
+pub struct Hello {
+    protocol: Option<RespProtocol>,
+}
+
+#[derive(Debug)]
+pub struct Ping {
+    message: Option<Vec<u8>>,
+}
+
+/// Closes the connection once it replied.
+#[derive(Debug)]
+pub struct Quit;
+
+/// Brings the connection back to its initial state: no transaction, watched keys nor
+/// subscriptions, and RESP2.
+#[derive(Debug)]
+pub struct Reset;
+
+impl Hello {
+    /// Resolves the protocol the connection should speak from now on.
+    pub fn negotiate(&mut self, current: RespProtocol) -> RespProtocol {
+        *self.protocol.get_or_insert(current)
+    }
+}
+
+impl Ping {
+    /// The reply of a subscribed RESP2 connection, which may only receive arrays.
+    pub fn subscribed_reply(self) -> RespFrame {
+        RespArray::new([
+            BulkString::new("pong").into(),
+            BulkString::new(self.message.unwrap_or_default()).into(),
+        ])
+        .into()
+    }
+}
+
+impl CommandExecutor for Ping {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(match self.message {
+            Some(message) => BulkString::new(message).into(),
+            None => SimpleString::new("PONG").into(),
+        })
+    }
+}
+
+impl CommandExecutor for Quit {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("QUIT"))
+    }
+}
+
+impl CommandExecutor for Reset {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("RESET"))
+    }
+}
+
+impl CommandExecutor for Hello {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        let proto = match self.protocol.unwrap_or_default() {
+            RespProtocol::Resp2 => 2,
+            RespProtocol::Resp3 => 3,
+        };
+        let mut map = RespMap::new();
+        map.insert("server".to_string(), BulkString::new("simple-redis").into());
+        map.insert(
+            "version".to_string(),
+            BulkString::new(env!("CARGO_PKG_VERSION")).into(),
+        );
+        map.insert("proto".to_string(), RespFrame::Integer(proto));
+        map.insert("mode".to_string(), BulkString::new("standalone").into());
+        map.insert("role".to_string(), BulkString::new("master").into());
+        map.insert("modules".to_string(), RespArray::new([]).into());
+        Ok(map.into())
+    }
+}
+
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let protocol = match args.next() {
+            Some(arg) => match extract_int(Some(arg))? {
+                2 => Some(RespProtocol::Resp2),
+                3 => Some(RespProtocol::Resp3),
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "NOPROTO unsupported protocol version".to_string(),
+                    ))
+                }
+            },
+            None => None,
+        };
+        // there are no users nor client names, AUTH and SETNAME are accepted and ignored
+        while let Some(option) = args.next() {
+            let option = extract_string(Some(option))?;
+            let values = match option.to_ascii_uppercase().as_str() {
+                "AUTH" => 2,
+                "SETNAME" => 1,
+                _ => 0,
+            };
+            if values == 0 || args.by_ref().take(values).count() != values {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Syntax error in HELLO option '{}'",
+                    option
+                )));
+            }
+        }
+        Ok(Hello { protocol })
+    }
+}
+
+impl TryFrom<RespArray> for Ping {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() > 2 {
+            return Err(CommandError::InvalidArgument(
+                "ping command must have at most 1 argument".to_string(),
+            ));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let message = args
+            .next()
+            .map(|arg| extract_bytes(Some(arg)))
+            .transpose()?;
+        Ok(Ping { message })
+    }
+}
+
+impl TryFrom<RespArray> for Quit {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["quit"], 0)?;
+        Ok(Quit)
+    }
+}
+
+impl TryFrom<RespArray> for Reset {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["reset"], 0)?;
+        Ok(Reset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    #[test]
+    fn test_ping() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: Ping = args(&["ping"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, SimpleString::new("PONG").into());
+        let cmd: Ping = args(&["ping", "hi"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, BulkString::new("hi").into());
+        let cmd: Ping = args(&["ping"]).try_into()?;
+        assert_eq!(
+            cmd.subscribed_reply(),
+            RespArray::new([BulkString::new("pong").into(), BulkString::new("").into()]).into()
+        );
+        assert!(Ping::try_from(args(&["ping", "a", "b"])).is_err());
+        assert!(Quit::try_from(args(&["quit", "now"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hello_negotiate() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$5\r\nhello\r\n$1\r\n3\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let mut result: Hello = frame.try_into()?;
+        assert_eq!(result.negotiate(RespProtocol::Resp2), RespProtocol::Resp3);
+
+        buf.extend_from_slice(
+            b"*7\r\n$5\r\nhello\r\n$1\r\n2\r\n$4\r\nauth\r\n$7\r\ndefault\r\n$4\r\npass\r\n$7\r\nsetname\r\n$3\r\napp\r\n",
+        );
+        let mut result: Hello = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.negotiate(RespProtocol::Resp3), RespProtocol::Resp2);
+        buf.extend_from_slice(b"*4\r\n$5\r\nhello\r\n$1\r\n3\r\n$4\r\nauth\r\n$7\r\ndefault\r\n");
+        assert!(Hello::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        let mut result = Hello { protocol: None };
+        assert_eq!(result.negotiate(RespProtocol::Resp3), RespProtocol::Resp3);
+        let reply = result.execute(&Backend::new())?;
+        let RespFrame::Map(map) = reply else {
+            panic!("hello must reply with a map");
+        };
+        assert_eq!(map.get("proto"), Some(&RespFrame::Integer(3)));
+        Ok(())
+    }
+}
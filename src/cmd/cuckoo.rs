@@ -0,0 +1,297 @@
+use dashmap::mapref::entry::Entry;
+
+use crate::array::RespArray;
+use crate::backend::{
+    cuckoo_buckets, Backend, CuckooFilter, CUCKOO_DEFAULT_BUCKET_SIZE, CUCKOO_DEFAULT_CAPACITY,
+    CUCKOO_DEFAULT_EXPANSION, CUCKOO_DEFAULT_MAX_ITERATIONS, NOTIFY_MODULE,
+};
+use crate::cmd::{
+    extract_args, extract_bytes, extract_int, extract_string, validate_command,
+    validate_command_at_least, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::map::RespMap;
+
+#[derive(Debug)]
+pub struct CfReserve {
+    key: String,
+    capacity: u64,
+    bucket_size: usize,
+    max_iterations: usize,
+    expansion: u64,
+}
+
+#[derive(Debug)]
+pub struct CfAdd {
+    key: String,
+    item: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct CfDel {
+    key: String,
+    item: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct CfExists {
+    key: String,
+    item: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct CfInfo {
+    key: String,
+}
+
+impl CommandExecutor for CfReserve {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match backend.cuckoo.entry(self.key.clone()) {
+            Entry::Occupied(_) => Err(CommandError::InvalidArgument("item exists".to_string())),
+            Entry::Vacant(entry) => {
+                entry.insert(CuckooFilter::new(
+                    self.capacity,
+                    self.bucket_size,
+                    self.max_iterations,
+                    self.expansion,
+                ));
+                backend.notify_new(true, &self.key);
+                backend.notify(NOTIFY_MODULE, "cf.reserve", &self.key);
+                Ok(RESP_OK.clone())
+            }
+        }
+    }
+}
+
+impl CommandExecutor for CfAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let created = !backend.cuckoo.contains_key(&self.key);
+        backend
+            .cuckoo
+            .entry(self.key.clone())
+            .or_insert_with(|| {
+                CuckooFilter::new(
+                    CUCKOO_DEFAULT_CAPACITY,
+                    CUCKOO_DEFAULT_BUCKET_SIZE,
+                    CUCKOO_DEFAULT_MAX_ITERATIONS,
+                    CUCKOO_DEFAULT_EXPANSION,
+                )
+            })
+            .add(&self.item)
+            .map_err(CommandError::InvalidArgument)?;
+        backend.notify_new(created, &self.key);
+        backend.notify(NOTIFY_MODULE, "cf.add", &self.key);
+        Ok(RespFrame::Integer(1))
+    }
+}
+
+impl CommandExecutor for CfDel {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let deleted = backend
+            .cuckoo
+            .get_mut(&self.key)
+            .ok_or_else(not_found)?
+            .delete(&self.item);
+        if deleted {
+            backend.notify(NOTIFY_MODULE, "cf.del", &self.key);
+        }
+        Ok(RespFrame::Integer(deleted as i64))
+    }
+}
+
+impl CommandExecutor for CfExists {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let exists = backend
+            .cuckoo
+            .get(&self.key)
+            .is_some_and(|cuckoo| cuckoo.contains(&self.item));
+        Ok(RespFrame::Integer(exists as i64))
+    }
+}
+
+impl CommandExecutor for CfInfo {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let cuckoo = backend.cuckoo.get(&self.key).ok_or_else(not_found)?;
+        let mut info = RespMap::new();
+        for (name, value) in [
+            ("Size", cuckoo.size() as i64),
+            ("Number of buckets", cuckoo.num_buckets() as i64),
+            ("Number of filters", cuckoo.num_filters() as i64),
+            ("Number of items inserted", cuckoo.items() as i64),
+            ("Number of items deleted", cuckoo.deletes() as i64),
+            ("Bucket size", cuckoo.bucket_size() as i64),
+            ("Expansion rate", cuckoo.expansion() as i64),
+            ("Max iterations", cuckoo.max_iterations() as i64),
+        ] {
+            info.insert(name.to_string(), RespFrame::Integer(value));
+        }
+        Ok(info.into())
+    }
+}
+
+fn not_found() -> CommandError {
+    CommandError::InvalidArgument("not found".to_string())
+}
+
+impl TryFrom<RespArray> for CfReserve {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["cf.reserve"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let capacity = parse_positive(extract_int(args.next())?, "Capacity")?;
+
+        let mut cmd = CfReserve {
+            key,
+            capacity,
+            bucket_size: CUCKOO_DEFAULT_BUCKET_SIZE,
+            max_iterations: CUCKOO_DEFAULT_MAX_ITERATIONS,
+            expansion: CUCKOO_DEFAULT_EXPANSION,
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                "BUCKETSIZE" => {
+                    let bucket_size = parse_positive(extract_int(args.next())?, "Bucket size")?;
+                    if bucket_size > 255 {
+                        return Err(CommandError::InvalidArgument(
+                            "Bucket size must be between 1 and 255".to_string(),
+                        ));
+                    }
+                    cmd.bucket_size = bucket_size as usize;
+                }
+                "MAXITERATIONS" => {
+                    cmd.max_iterations =
+                        parse_positive(extract_int(args.next())?, "Max iterations")? as usize;
+                }
+                "EXPANSION" => {
+                    cmd.expansion = u64::try_from(extract_int(args.next())?).map_err(|_| {
+                        CommandError::InvalidArgument("Expansion must not be negative".to_string())
+                    })?;
+                }
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        if cuckoo_buckets(cmd.capacity, cmd.bucket_size).is_none() {
+            return Err(CommandError::InvalidArgument(
+                "Capacity is too large".to_string(),
+            ));
+        }
+        Ok(cmd)
+    }
+}
+
+fn parse_positive(value: i64, name: &str) -> Result<u64, CommandError> {
+    u64::try_from(value)
+        .ok()
+        .filter(|value| *value > 0)
+        .ok_or_else(|| CommandError::InvalidArgument(format!("{} must be positive", name)))
+}
+
+impl TryFrom<RespArray> for CfAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cf.add"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(CfAdd {
+            key: extract_string(args.next())?,
+            item: extract_bytes(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for CfDel {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cf.del"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(CfDel {
+            key: extract_string(args.next())?,
+            item: extract_bytes(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for CfExists {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cf.exists"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(CfExists {
+            key: extract_string(args.next())?,
+            item: extract_bytes(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for CfInfo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cf.info"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(CfInfo {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    #[test]
+    fn test_cf_reserve_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$10\r\ncf.reserve\r\n$1\r\nc\r\n$4\r\n1000\r\n$10\r\nbucketsize\r\n$1\r\n4\r\n",
+        );
+        let cmd: CfReserve = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.capacity, 1000);
+        assert_eq!(cmd.bucket_size, 4);
+        assert_eq!(cmd.expansion, CUCKOO_DEFAULT_EXPANSION);
+
+        for capacity in ["9223372036854775807", "1000000000000"] {
+            let err = CfReserve::try_from(args(&["cf.reserve", "c", capacity])).unwrap_err();
+            assert!(err.to_string().contains("Capacity is too large"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cf_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: CfDel = args(&["cf.del", "c", "x"]).try_into()?;
+        assert!(cmd.execute(&backend).is_err());
+
+        let cmd: CfAdd = args(&["cf.add", "c", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+        let cmd: CfExists = args(&["cf.exists", "c", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+        let cmd: CfDel = args(&["cf.del", "c", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+        let cmd: CfExists = args(&["cf.exists", "c", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(0));
+
+        let cmd: CfReserve = args(&["cf.reserve", "c", "10"]).try_into()?;
+        assert!(cmd.execute(&backend).is_err());
+        let cmd: CfInfo = args(&["cf.info", "c"]).try_into()?;
+        let RespFrame::Map(info) = cmd.execute(&backend)? else {
+            panic!("expected a map");
+        };
+        assert_eq!(
+            info.get("Number of items deleted"),
+            Some(&RespFrame::Integer(1))
+        );
+        Ok(())
+    }
+}
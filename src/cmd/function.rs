@@ -0,0 +1,680 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use bytes::BytesMut;
+use mlua::{HookTriggers, Lua, MultiValue, Table, Value, Variadic};
+
+use crate::array::RespArray;
+use crate::backend::{Backend, FunctionLibrary, RestorePolicy};
+use crate::bulk_string::BulkString;
+use crate::cmd::scripting::{
+    error_message, keys_and_args, lua_error, new_lua, parse_keys_and_args, run_function,
+};
+use crate::cmd::{
+    extract_args, extract_bytes, extract_string, glob_match, validate_command_at_least,
+    validate_flush_mode, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::decode::RespDecode;
+use crate::frame::RespFrame;
+use crate::map::RespMap;
+use crate::null::RespNull;
+use crate::set::RespSet;
+
+/// The flags `redis.register_function` accepts. Only `no-writes` changes anything here, the
+/// others are about memory limits, replicas and clusters.
+const FUNCTION_FLAGS: [&str; 5] = [
+    "no-writes",
+    "allow-oom",
+    "allow-stale",
+    "no-cluster",
+    "allow-cross-slot-keys",
+];
+
+/// How long library code may run to register its functions.
+const LOAD_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Where a library being loaded collects its functions, in the Lua registry.
+const REGISTERED_FUNCTIONS: &str = "registered_functions";
+
+#[derive(Debug)]
+pub struct Function {
+    sub: FunctionSub,
+}
+
+#[derive(Debug)]
+pub struct FCall {
+    call: FunctionCall,
+}
+
+#[derive(Debug)]
+pub struct FCallRo {
+    call: FunctionCall,
+}
+
+#[derive(Debug)]
+enum FunctionSub {
+    Load {
+        replace: bool,
+        code: String,
+    },
+    List {
+        /// glob-style pattern of the library names
+        pattern: Option<String>,
+        with_code: bool,
+    },
+    Delete(String),
+    Dump,
+    Restore {
+        payload: Vec<u8>,
+        policy: RestorePolicy,
+    },
+    Flush,
+}
+
+#[derive(Debug)]
+struct FunctionCall {
+    function: String,
+    keys: Vec<String>,
+    args: Vec<Vec<u8>>,
+}
+
+impl CommandExecutor for Function {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match self.sub {
+            FunctionSub::Load { replace, code } => {
+                let library = parse_library(backend, code)?;
+                let name = library.name.clone();
+                backend
+                    .function_load(library, replace)
+                    .map_err(CommandError::InvalidArgument)?;
+                Ok(BulkString::new(name).into())
+            }
+            FunctionSub::List { pattern, with_code } => {
+                let libraries = backend
+                    .function_libraries()
+                    .into_iter()
+                    .filter(|library| {
+                        pattern.as_ref().is_none_or(|pattern| {
+                            glob_match(pattern.as_bytes(), library.name.as_bytes())
+                        })
+                    })
+                    .map(|library| library_reply(library, with_code))
+                    .collect::<Vec<_>>();
+                Ok(RespArray::new(libraries).into())
+            }
+            FunctionSub::Delete(name) => {
+                match backend
+                    .function_delete(&name)
+                    .map_err(CommandError::InvalidArgument)?
+                {
+                    true => Ok(RESP_OK.clone()),
+                    false => Err(CommandError::InvalidArgument(
+                        "Library not found".to_string(),
+                    )),
+                }
+            }
+            FunctionSub::Dump => Ok(BulkString::new(backend.function_dump()).into()),
+            FunctionSub::Restore { payload, policy } => {
+                let libraries = parse_payload(backend, &payload)?;
+                backend
+                    .function_restore(libraries, policy)
+                    .map_err(CommandError::InvalidArgument)?;
+                Ok(RESP_OK.clone())
+            }
+            FunctionSub::Flush => {
+                backend
+                    .function_flush()
+                    .map_err(CommandError::InvalidArgument)?;
+                Ok(RESP_OK.clone())
+            }
+        }
+    }
+}
+
+impl CommandExecutor for FCall {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        self.call.execute(backend, false)
+    }
+}
+
+impl CommandExecutor for FCallRo {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        self.call.execute(backend, true)
+    }
+}
+
+impl FunctionCall {
+    /// Loads the library of the function in a fresh interpreter and calls the function with
+    /// the KEYS and ARGV tables, callers hold `exec_lock` exclusively.
+    fn execute(self, backend: &Backend, read_only: bool) -> Result<RespFrame, CommandError> {
+        let (code, flags) = backend
+            .function_find(&self.function)
+            .ok_or_else(|| CommandError::InvalidArgument("Function not found".to_string()))?;
+        let no_writes = flags.iter().any(|flag| flag == "no-writes");
+        if read_only && !no_writes {
+            return Err(CommandError::InvalidArgument(
+                "Can not execute a script with write flag using *_ro command.".to_string(),
+            ));
+        }
+        let lua = new_lua(backend, no_writes).map_err(lua_error)?;
+        let (_, functions) = load_library(&lua, &code)?;
+        let callback = functions
+            .get::<_, Table>(self.function.as_str())
+            .and_then(|function| function.get::<_, mlua::Function>("callback"))
+            .map_err(lua_error)?;
+        let (keys, args) = keys_and_args(&lua, self.keys, &self.args).map_err(lua_error)?;
+        let reply = run_function(backend, &lua, callback, (keys, args));
+        reply.map_err(|e| {
+            CommandError::InvalidArgument(format!(
+                "Error running function '{}': {}",
+                self.function,
+                error_message(&e)
+            ))
+        })
+    }
+}
+
+/// Loads the libraries saved in `path` and saves them there after every change, returns the
+/// number of libraries. A missing file is created with the first library.
+pub fn load_functions(backend: &Backend, path: impl AsRef<Path>) -> anyhow::Result<usize> {
+    let path = path.as_ref();
+    let libraries = match std::fs::read(path) {
+        Ok(payload) => parse_payload(backend, &payload)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e.into()),
+    };
+    let count = libraries.len();
+    backend
+        .function_restore(libraries, RestorePolicy::Flush)
+        .map_err(anyhow::Error::msg)?;
+    backend.function_save_to(path.to_path_buf());
+    Ok(count)
+}
+
+/// The libraries of a FUNCTION DUMP payload.
+fn parse_payload(backend: &Backend, payload: &[u8]) -> Result<Vec<FunctionLibrary>, CommandError> {
+    let invalid =
+        || CommandError::InvalidArgument("payload version or checksum are wrong".to_string());
+    let mut buf = BytesMut::from(payload);
+    let codes = RespArray::decode(&mut buf).map_err(|_| invalid())?;
+    if !buf.is_empty() {
+        return Err(invalid());
+    }
+    codes
+        .0
+        .into_iter()
+        .map(|code| parse_library(backend, extract_string(Some(code))?))
+        .collect()
+}
+
+/// Loads library code in a throwaway interpreter to learn the functions it registers.
+fn parse_library(backend: &Backend, code: String) -> Result<FunctionLibrary, CommandError> {
+    let lua = new_lua(backend, true).map_err(lua_error)?;
+    let (name, registered) = load_library(&lua, &code)?;
+    let mut functions = BTreeMap::new();
+    for pair in registered.pairs::<String, Table>() {
+        let (function, registration) = pair.map_err(lua_error)?;
+        let flags: Vec<String> = registration.get("flags").map_err(lua_error)?;
+        functions.insert(function, flags);
+    }
+    Ok(FunctionLibrary {
+        name,
+        code,
+        functions,
+    })
+}
+
+/// Runs library code, which registers its functions with `redis.register_function`. Returns
+/// the name of the library and the registered functions, by name.
+fn load_library<'lua>(lua: &'lua Lua, code: &str) -> Result<(String, Table<'lua>), CommandError> {
+    let (name, body) = parse_metadata(code)?;
+    let registered = lua.create_table().map_err(lua_error)?;
+    lua.set_named_registry_value(REGISTERED_FUNCTIONS, registered.clone())
+        .map_err(lua_error)?;
+    let redis: Table = lua.globals().get("redis").map_err(lua_error)?;
+    let call: Value = redis.get("call").map_err(lua_error)?;
+    let pcall: Value = redis.get("pcall").map_err(lua_error)?;
+    // the library only registers its functions, it can't reach the dataset yet
+    let not_allowed = lua
+        .create_function(|_, _: MultiValue| -> mlua::Result<()> {
+            Err(mlua::Error::RuntimeError(
+                "redis.call is not allowed while loading a library".to_string(),
+            ))
+        })
+        .map_err(lua_error)?;
+    let register = lua.create_function(register_function).map_err(lua_error)?;
+    redis
+        .set("call", not_allowed.clone())
+        .and_then(|_| redis.set("pcall", not_allowed))
+        .and_then(|_| redis.set("register_function", register))
+        .map_err(lua_error)?;
+
+    let started = Instant::now();
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(1000),
+        move |_, _| match started.elapsed() > LOAD_TIMEOUT {
+            true => Err(mlua::Error::RuntimeError(
+                "FUNCTION LOAD timeout".to_string(),
+            )),
+            false => Ok(()),
+        },
+    );
+    // the metadata line stays as an empty one, so that errors point at the right lines
+    let loaded = lua
+        .load(format!("\n{}", body))
+        .set_name("@user_function")
+        .exec();
+    lua.remove_hook();
+    loaded.map_err(|e| {
+        CommandError::InvalidArgument(format!(
+            "Error registering functions: {}",
+            error_message(&e)
+        ))
+    })?;
+
+    redis
+        .set("call", call)
+        .and_then(|_| redis.set("pcall", pcall))
+        .and_then(|_| redis.set("register_function", Value::Nil))
+        .map_err(lua_error)?;
+    if registered.clone().pairs::<Value, Value>().next().is_none() {
+        return Err(CommandError::InvalidArgument(
+            "No functions registered".to_string(),
+        ));
+    }
+    Ok((name, registered))
+}
+
+/// `redis.register_function(name, callback)` or `redis.register_function{function_name=name,
+/// callback=callback, flags={...}}`.
+fn register_function<'lua>(lua: &'lua Lua, args: Variadic<Value<'lua>>) -> mlua::Result<()> {
+    let runtime_error = |msg: &str| mlua::Error::RuntimeError(msg.to_string());
+    let (name, callback, flags) = match args.as_slice() {
+        [Value::String(name), Value::Function(callback)] => {
+            (name.to_str()?.to_string(), callback.clone(), vec![])
+        }
+        [Value::Table(registration)] => {
+            let name: Option<String> = registration.get("function_name")?;
+            let callback: Option<mlua::Function> = registration.get("callback")?;
+            let flags: Option<Vec<String>> = registration.get("flags")?;
+            (
+                name.ok_or_else(|| runtime_error("function_name is required"))?,
+                callback.ok_or_else(|| runtime_error("callback is required"))?,
+                flags.unwrap_or_default(),
+            )
+        }
+        _ => {
+            return Err(runtime_error(
+                "wrong arguments given to redis.register_function",
+            ))
+        }
+    };
+    if !valid_name(&name) {
+        return Err(runtime_error(
+            "Function names can only contain letters, numbers, or underscores(_) and must be at least one character long",
+        ));
+    }
+    if let Some(flag) = flags
+        .iter()
+        .find(|flag| !FUNCTION_FLAGS.contains(&flag.as_str()))
+    {
+        return Err(mlua::Error::RuntimeError(format!(
+            "unknown flag given: {}",
+            flag
+        )));
+    }
+    let registered: Table = lua.named_registry_value(REGISTERED_FUNCTIONS)?;
+    if registered.contains_key(name.as_str())? {
+        return Err(runtime_error("Function already exists in the library"));
+    }
+    let registration = lua.create_table()?;
+    registration.set("callback", callback)?;
+    registration.set("flags", flags)?;
+    registered.set(name, registration)
+}
+
+/// Parses the `#!lua name=<library>` line library code starts with, returns the library
+/// name and the rest of the code.
+fn parse_metadata(code: &str) -> Result<(String, &str), CommandError> {
+    let (metadata, body) = code.split_once('\n').unwrap_or((code, ""));
+    let Some(metadata) = metadata.strip_prefix("#!") else {
+        return Err(CommandError::InvalidArgument(
+            "Missing library metadata".to_string(),
+        ));
+    };
+    let mut parts = metadata.split_whitespace();
+    let engine = parts.next().unwrap_or_default();
+    if !engine.eq_ignore_ascii_case("lua") {
+        return Err(CommandError::InvalidArgument(format!(
+            "Engine '{}' not found",
+            engine
+        )));
+    }
+    let mut name = None;
+    for part in parts {
+        match part.strip_prefix("name=") {
+            Some(value) => name = Some(value),
+            None => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Invalid metadata value given: {}",
+                    part
+                )))
+            }
+        }
+    }
+    let name = name
+        .ok_or_else(|| CommandError::InvalidArgument("Library name was not given".to_string()))?;
+    if !valid_name(name) {
+        return Err(CommandError::InvalidArgument(
+            "Library names can only contain letters, numbers, or underscores(_) and must be at least one character long".to_string(),
+        ));
+    }
+    Ok((name.to_string(), body))
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_')
+}
+
+fn library_reply(library: FunctionLibrary, with_code: bool) -> RespFrame {
+    let functions = library
+        .functions
+        .into_iter()
+        .map(|(name, flags)| {
+            let mut function = RespMap::new();
+            function.insert("name".to_string(), BulkString::new(name).into());
+            function.insert("description".to_string(), RespNull.into());
+            let flags = flags
+                .into_iter()
+                .map(|flag| BulkString::new(flag).into())
+                .collect::<Vec<RespFrame>>();
+            function.insert("flags".to_string(), RespSet::new(flags).into());
+            function.into()
+        })
+        .collect::<Vec<RespFrame>>();
+    let mut reply = RespMap::new();
+    reply.insert(
+        "library_name".to_string(),
+        BulkString::new(library.name).into(),
+    );
+    reply.insert("engine".to_string(), BulkString::new("LUA").into());
+    reply.insert("functions".to_string(), RespArray::new(functions).into());
+    if with_code {
+        reply.insert(
+            "library_code".to_string(),
+            BulkString::new(library.code).into(),
+        );
+    }
+    reply.into()
+}
+
+impl TryFrom<RespArray> for Function {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["function"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_uppercase();
+        let sub = match (subcommand.as_str(), args.len()) {
+            ("LOAD", 1) => FunctionSub::Load {
+                replace: false,
+                code: extract_string(args.next())?,
+            },
+            ("LOAD", 2) => {
+                if !extract_string(args.next())?.eq_ignore_ascii_case("replace") {
+                    return Err(CommandError::InvalidArgument(
+                        "Unknown option given".to_string(),
+                    ));
+                }
+                FunctionSub::Load {
+                    replace: true,
+                    code: extract_string(args.next())?,
+                }
+            }
+            ("LIST", _) => {
+                let (mut pattern, mut with_code) = (None, false);
+                while let Some(arg) = args.next() {
+                    match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                        "WITHCODE" => with_code = true,
+                        "LIBRARYNAME" => pattern = Some(extract_string(args.next())?),
+                        option => {
+                            return Err(CommandError::InvalidArgument(format!(
+                                "Unknown argument {}",
+                                option
+                            )))
+                        }
+                    }
+                }
+                FunctionSub::List { pattern, with_code }
+            }
+            ("DELETE", 1) => FunctionSub::Delete(extract_string(args.next())?),
+            ("DUMP", 0) => FunctionSub::Dump,
+            ("RESTORE", 1 | 2) => {
+                let payload = extract_bytes(args.next())?;
+                let policy = match args.next().map(|arg| extract_string(Some(arg))).transpose()? {
+                    None => RestorePolicy::Append,
+                    Some(policy) => match policy.to_ascii_uppercase().as_str() {
+                        "APPEND" => RestorePolicy::Append,
+                        "REPLACE" => RestorePolicy::Replace,
+                        "FLUSH" => RestorePolicy::Flush,
+                        _ => {
+                            return Err(CommandError::InvalidArgument(
+                                "Wrong restore policy given, value should be either FLUSH, APPEND or REPLACE.".to_string(),
+                            ))
+                        }
+                    },
+                };
+                FunctionSub::Restore { payload, policy }
+            }
+            ("FLUSH", _) => {
+                validate_flush_mode(args)?;
+                FunctionSub::Flush
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for FUNCTION {}",
+                    subcommand
+                )))
+            }
+        };
+        Ok(Function { sub })
+    }
+}
+
+impl TryFrom<RespArray> for FCall {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(FCall {
+            call: parse_call(value, "fcall")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FCallRo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(FCallRo {
+            call: parse_call(value, "fcall_ro")?,
+        })
+    }
+}
+
+/// Parses `function numkeys [key ...] [arg ...]`.
+fn parse_call(value: RespArray, name: &'static str) -> Result<FunctionCall, CommandError> {
+    validate_command_at_least(&value, &[name], 2)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    let function = extract_string(args.next())?;
+    let (keys, args) = parse_keys_and_args(args)?;
+    Ok(FunctionCall {
+        function,
+        keys,
+        args,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+
+    use super::*;
+
+    const LIBRARY: &str = "#!lua name=mylib
+redis.register_function('set_get', function(keys, args)
+    redis.call('set', keys[1], args[1])
+    return redis.call('get', keys[1])
+end)
+redis.register_function{
+    function_name = 'get',
+    callback = function(keys) return redis.call('get', keys[1]) end,
+    flags = {'no-writes'},
+}";
+
+    fn run<T>(backend: &Backend, cmd: &[&str]) -> Result<RespFrame>
+    where
+        T: TryFrom<RespArray, Error = CommandError> + CommandExecutor,
+    {
+        Ok(T::try_from(args(cmd))?.execute(backend)?)
+    }
+
+    #[test]
+    fn test_function_try_from() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$8\r\nfunction\r\n$4\r\nlist\r\n$8\r\nwithcode\r\n$11\r\nlibraryname\r\n",
+        );
+        assert!(Function::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        let function = Function::try_from(args(&["function", "load", "replace", LIBRARY]))?;
+        assert!(matches!(
+            function.sub,
+            FunctionSub::Load { replace: true, .. }
+        ));
+        let function = Function::try_from(args(&["function", "restore", "x", "flush"]))?;
+        assert!(matches!(
+            function.sub,
+            FunctionSub::Restore {
+                policy: RestorePolicy::Flush,
+                ..
+            }
+        ));
+        assert!(Function::try_from(args(&["function", "restore", "x", "merge"])).is_err());
+        assert!(FCall::try_from(args(&["fcall", "f", "1"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_function_load_fcall() -> Result<()> {
+        let backend = Backend::new();
+        let ret = run::<Function>(&backend, &["function", "load", LIBRARY])?;
+        assert_eq!(ret, BulkString::new("mylib").into());
+        assert!(run::<Function>(&backend, &["function", "load", LIBRARY]).is_err());
+        run::<Function>(&backend, &["function", "load", "replace", LIBRARY])?;
+
+        let ret = run::<FCall>(&backend, &["fcall", "set_get", "1", "k", "v"])?;
+        assert_eq!(ret, BulkString::new("v").into());
+        let ret = run::<FCallRo>(&backend, &["fcall_ro", "get", "1", "k"])?;
+        assert_eq!(ret, BulkString::new("v").into());
+        let err = run::<FCallRo>(&backend, &["fcall_ro", "set_get", "1", "k", "w"]).unwrap_err();
+        assert!(err.to_string().contains("write flag"));
+        assert!(run::<FCall>(&backend, &["fcall", "missing", "0"]).is_err());
+
+        let RespFrame::Array(libraries) =
+            run::<Function>(&backend, &["function", "list", "withcode"])?
+        else {
+            panic!("FUNCTION LIST must reply with an array");
+        };
+        let RespFrame::Map(library) = &libraries[0] else {
+            panic!("libraries are maps");
+        };
+        assert_eq!(library.0["library_name"], BulkString::new("mylib").into());
+        assert_eq!(library.0["library_code"], BulkString::new(LIBRARY).into());
+        let RespFrame::Array(functions) = &library.0["functions"] else {
+            panic!("functions are an array");
+        };
+        assert_eq!(functions.len(), 2);
+        let ret = run::<Function>(&backend, &["function", "list", "libraryname", "other*"])?;
+        assert_eq!(ret, RespArray::new(vec![]).into());
+
+        run::<Function>(&backend, &["function", "delete", "mylib"])?;
+        assert!(run::<FCall>(&backend, &["fcall", "get", "1", "k"]).is_err());
+        assert!(run::<Function>(&backend, &["function", "delete", "mylib"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_function_load_errors() -> Result<()> {
+        let backend = Backend::new();
+        let load = |code: &str| run::<Function>(&backend, &["function", "load", code]);
+        for code in [
+            "redis.register_function('f', function() end)",
+            "#!python name=lib\n",
+            "#!lua\nredis.register_function('f', function() end)",
+            "#!lua name=my-lib\nredis.register_function('f', function() end)",
+            "#!lua name=lib\n",
+            "#!lua name=lib\nredis.call('set', 'k', 'v')",
+            "#!lua name=lib\nredis.register_function('f', function() end)\nredis.register_function('f', function() end)",
+            "#!lua name=lib\nredis.register_function{function_name='f', callback=function() end, flags={'bad'}}",
+            "#!lua name=lib\nwhile true do end",
+        ] {
+            assert!(load(code).is_err(), "{}", code);
+        }
+        assert!(backend.get("k").is_none());
+
+        load("#!lua name=a\nredis.register_function('f', function() return 1 end)")?;
+        let err = load("#!lua name=b\nredis.register_function('f', function() end)").unwrap_err();
+        assert!(err.to_string().contains("Function f already exists"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_function_dump_restore() -> Result<()> {
+        let backend = Backend::new();
+        run::<Function>(&backend, &["function", "load", LIBRARY])?;
+        let RespFrame::BulkString(payload) = run::<Function>(&backend, &["function", "dump"])?
+        else {
+            panic!("FUNCTION DUMP must reply with a bulk string");
+        };
+        let payload = String::from_utf8(payload.0)?;
+
+        let other = Backend::new();
+        run::<Function>(&other, &["function", "restore", &payload])?;
+        assert_eq!(other.function_libraries(), backend.function_libraries());
+        assert!(run::<Function>(&other, &["function", "restore", &payload]).is_err());
+        run::<Function>(&other, &["function", "restore", &payload, "replace"])?;
+        assert!(run::<Function>(&other, &["function", "restore", "garbage"]).is_err());
+
+        run::<Function>(&other, &["function", "flush"])?;
+        assert!(other.function_libraries().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_functions() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "simple-redis-load-functions-{}.resp",
+            std::process::id()
+        ));
+        let backend = Backend::new();
+        assert_eq!(load_functions(&backend, &path)?, 0);
+        run::<Function>(&backend, &["function", "load", LIBRARY])?;
+
+        // a restarted server gets the libraries back
+        let restarted = Backend::new();
+        assert_eq!(load_functions(&restarted, &path)?, 1);
+        assert_eq!(restarted.function_libraries(), backend.function_libraries());
+        let ret = run::<FCall>(&restarted, &["fcall", "set_get", "1", "k", "v"])?;
+        assert_eq!(ret, BulkString::new("v").into());
+
+        run::<Function>(&restarted, &["function", "delete", "mylib"])?;
+        assert_eq!(load_functions(&Backend::new(), &path)?, 0);
+        std::fs::write(&path, "garbage")?;
+        assert!(load_functions(&Backend::new(), &path).is_err());
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,623 @@
+use crate::array::{RespArray, RespNullArray};
+use crate::backend::{Backend, GeoMatch, GeoPoint, GeoShape, SortedSet, NOTIFY_ZSET};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_count, extract_float, extract_string, extract_strings,
+    validate_command_at_least, CommandError, CommandExecutor,
+};
+use crate::frame::RespFrame;
+use crate::null::RespNull;
+
+#[derive(Debug)]
+pub struct GeoAdd {
+    key: String,
+    nx: bool,
+    xx: bool,
+    ch: bool,
+    points: Vec<(GeoPoint, String)>,
+}
+
+#[derive(Debug)]
+pub struct GeoPos {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct GeoDist {
+    key: String,
+    member1: String,
+    member2: String,
+    /// meters per unit
+    unit: f64,
+}
+
+#[derive(Debug)]
+pub struct GeoHash {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct GeoSearch {
+    key: String,
+    query: SearchQuery,
+}
+
+#[derive(Debug)]
+pub struct GeoSearchStore {
+    dest: String,
+    key: String,
+    query: SearchQuery,
+    store_dist: bool,
+}
+
+/// The options shared by GEOSEARCH and GEOSEARCHSTORE.
+#[derive(Debug, Clone, PartialEq)]
+struct SearchQuery {
+    from: SearchFrom,
+    shape: GeoShape,
+    /// meters per unit of the shape, also used for the distances in the reply
+    unit: f64,
+    /// `None` keeps the geohash order, `Some(true)` sorts farthest first
+    desc: Option<bool>,
+    count: Option<usize>,
+    /// stop at the first `count` matches instead of the closest ones
+    any: bool,
+    with_coord: bool,
+    with_dist: bool,
+    with_hash: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SearchFrom {
+    Member(String),
+    LonLat(GeoPoint),
+}
+
+impl CommandExecutor for GeoAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let mut added = 0;
+        let mut changed = 0;
+        let created = {
+            let mut zset = backend.zset.entry(self.key.clone()).or_default();
+            let created = zset.is_empty();
+            for (point, member) in self.points {
+                let score = point.encode() as f64;
+                match zset.score(&member) {
+                    Some(_) if self.nx => {}
+                    Some(old) => {
+                        if old != score {
+                            zset.insert(member, score);
+                            changed += 1;
+                        }
+                    }
+                    None if self.xx => {}
+                    None => {
+                        zset.insert(member, score);
+                        added += 1;
+                    }
+                }
+            }
+            created
+        };
+        // only a key created above can be empty, so dropping it is not a deletion
+        backend.zset.remove_if(&self.key, |_, zset| zset.is_empty());
+        if added + changed > 0 {
+            backend.notify_new(created && added > 0, &self.key);
+            backend.notify(NOTIFY_ZSET, "zadd", &self.key);
+        }
+        if added > 0 {
+            backend.signal_key_ready(&self.key);
+        }
+        Ok(RespFrame::Integer(if self.ch {
+            added + changed
+        } else {
+            added
+        }))
+    }
+}
+
+impl CommandExecutor for GeoPos {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let zset = backend.zset.get(&self.key);
+        let positions = self
+            .members
+            .iter()
+            .map(
+                |member| match zset.as_ref().and_then(|zset| zset.score(member)) {
+                    Some(score) => coord_reply(GeoPoint::decode(score as u64)),
+                    None => RespNullArray.into(),
+                },
+            )
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(positions).into())
+    }
+}
+
+impl CommandExecutor for GeoDist {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let Some(zset) = backend.zset.get(&self.key) else {
+            return Ok(RespFrame::Null(RespNull));
+        };
+        match (zset.score(&self.member1), zset.score(&self.member2)) {
+            (Some(score1), Some(score2)) => {
+                let dist =
+                    GeoPoint::decode(score1 as u64).distance(&GeoPoint::decode(score2 as u64));
+                Ok(dist_reply(dist / self.unit))
+            }
+            _ => Ok(RespFrame::Null(RespNull)),
+        }
+    }
+}
+
+impl CommandExecutor for GeoHash {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let zset = backend.zset.get(&self.key);
+        let hashes = self
+            .members
+            .iter()
+            .map(
+                |member| match zset.as_ref().and_then(|zset| zset.score(member)) {
+                    Some(score) => {
+                        BulkString::from(GeoPoint::decode(score as u64).geohash()).into()
+                    }
+                    None => RespFrame::Null(RespNull),
+                },
+            )
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(hashes).into())
+    }
+}
+
+impl CommandExecutor for GeoSearch {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let found = self.query.run(backend, &self.key)?;
+        let query = &self.query;
+        let reply = found
+            .into_iter()
+            .map(|found| {
+                let member: RespFrame = BulkString::from(found.member).into();
+                if !(query.with_dist || query.with_hash || query.with_coord) {
+                    return member;
+                }
+                let mut item = vec![member];
+                if query.with_dist {
+                    item.push(dist_reply(found.dist / query.unit));
+                }
+                if query.with_hash {
+                    item.push(RespFrame::Integer(found.score as i64));
+                }
+                if query.with_coord {
+                    item.push(coord_reply(found.point));
+                }
+                RespArray::new(item).into()
+            })
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(reply).into())
+    }
+}
+
+impl CommandExecutor for GeoSearchStore {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let found = self.query.run(backend, &self.key)?;
+        let mut zset = SortedSet::new();
+        for found in found {
+            let score = if self.store_dist {
+                found.dist / self.query.unit
+            } else {
+                found.score
+            };
+            zset.insert(found.member, score);
+        }
+        Ok(RespFrame::Integer(
+            backend.zset_store(&self.dest, zset, "geosearchstore") as i64,
+        ))
+    }
+}
+
+impl SearchQuery {
+    /// The matches in the requested order, cut to COUNT.
+    fn run(&self, backend: &Backend, key: &str) -> Result<Vec<GeoMatch>, CommandError> {
+        let center = match &self.from {
+            SearchFrom::LonLat(point) => *point,
+            SearchFrom::Member(member) => {
+                let Some(zset) = backend.zset.get(key) else {
+                    return Ok(vec![]);
+                };
+                let score = zset.score(member).ok_or_else(|| {
+                    CommandError::InvalidArgument(
+                        "could not decode requested zset member".to_string(),
+                    )
+                })?;
+                GeoPoint::decode(score as u64)
+            }
+        };
+        let limit = self.count.filter(|_| self.any);
+        let mut found = backend.geo_search(key, center, self.shape, limit);
+        match self.desc {
+            Some(false) => found.sort_by(|a, b| a.dist.total_cmp(&b.dist)),
+            Some(true) => found.sort_by(|a, b| b.dist.total_cmp(&a.dist)),
+            None => {}
+        }
+        if let Some(count) = self.count {
+            found.truncate(count);
+        }
+        Ok(found)
+    }
+}
+
+fn coord_reply(point: GeoPoint) -> RespFrame {
+    RespArray::new([RespFrame::Double(point.lon), RespFrame::Double(point.lat)]).into()
+}
+
+fn dist_reply(dist: f64) -> RespFrame {
+    BulkString::from(format!("{:.4}", dist)).into()
+}
+
+fn parse_point(lon: Option<RespFrame>, lat: Option<RespFrame>) -> Result<GeoPoint, CommandError> {
+    let (lon, lat) = (extract_float(lon)?, extract_float(lat)?);
+    GeoPoint::new(lon, lat).ok_or_else(|| {
+        CommandError::InvalidArgument(format!(
+            "invalid longitude,latitude pair {:.6},{:.6}",
+            lon, lat
+        ))
+    })
+}
+
+/// Parses a distance and returns it in meters.
+fn parse_distance(arg: Option<RespFrame>, unit: f64) -> Result<f64, CommandError> {
+    let dist = extract_float(arg)?;
+    if dist < 0.0 {
+        return Err(CommandError::InvalidArgument(
+            "radius cannot be negative".to_string(),
+        ));
+    }
+    Ok(dist * unit)
+}
+
+/// The meters in a unit.
+fn parse_unit(arg: Option<RespFrame>) -> Result<f64, CommandError> {
+    match extract_string(arg)?.to_ascii_lowercase().as_str() {
+        "m" => Ok(1.0),
+        "km" => Ok(1000.0),
+        "ft" => Ok(0.3048),
+        "mi" => Ok(1609.34),
+        _ => Err(CommandError::InvalidArgument(
+            "unsupported unit provided. please use M, KM, FT, MI".to_string(),
+        )),
+    }
+}
+
+/// Parses the options of GEOSEARCH, plus STOREDIST when `store` is set.
+fn parse_search(
+    args: impl Iterator<Item = RespFrame>,
+    store: bool,
+) -> Result<(SearchQuery, bool), CommandError> {
+    let mut args = args.peekable();
+    let mut from = None;
+    let mut shape = None;
+    let mut query = SearchQuery {
+        from: SearchFrom::Member(String::new()),
+        shape: GeoShape::Radius(0.0),
+        unit: 1.0,
+        desc: None,
+        count: None,
+        any: false,
+        with_coord: false,
+        with_dist: false,
+        with_hash: false,
+    };
+    let mut store_dist = false;
+    let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+    while let Some(arg) = args.next() {
+        match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+            "FROMMEMBER" if from.is_none() => {
+                from = Some(SearchFrom::Member(extract_string(args.next())?))
+            }
+            "FROMLONLAT" if from.is_none() => {
+                from = Some(SearchFrom::LonLat(parse_point(args.next(), args.next())?))
+            }
+            "FROMMEMBER" | "FROMLONLAT" => {
+                return Err(CommandError::InvalidArgument(
+                    "exactly one of FROMMEMBER or FROMLONLAT can be specified".to_string(),
+                ))
+            }
+            "BYRADIUS" if shape.is_none() => {
+                let radius = args.next();
+                query.unit = parse_unit(args.next())?;
+                shape = Some(GeoShape::Radius(parse_distance(radius, query.unit)?));
+            }
+            "BYBOX" if shape.is_none() => {
+                let (width, height) = (args.next(), args.next());
+                query.unit = parse_unit(args.next())?;
+                shape = Some(GeoShape::Box {
+                    width: parse_distance(width, query.unit)?,
+                    height: parse_distance(height, query.unit)?,
+                });
+            }
+            "BYRADIUS" | "BYBOX" => {
+                return Err(CommandError::InvalidArgument(
+                    "exactly one of BYRADIUS and BYBOX can be specified".to_string(),
+                ))
+            }
+            "ASC" => query.desc = Some(false),
+            "DESC" => query.desc = Some(true),
+            "COUNT" => {
+                let count = extract_count(args.next())?;
+                if count == 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "COUNT must be > 0".to_string(),
+                    ));
+                }
+                query.count = Some(count);
+                if let Some(RespFrame::BulkString(arg)) = args.peek() {
+                    if arg.eq_ignore_ascii_case(b"ANY") {
+                        args.next();
+                        query.any = true;
+                    }
+                }
+            }
+            "WITHCOORD" if !store => query.with_coord = true,
+            "WITHDIST" if !store => query.with_dist = true,
+            "WITHHASH" if !store => query.with_hash = true,
+            "STOREDIST" if store => store_dist = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    query.from = from.ok_or_else(|| {
+        CommandError::InvalidArgument(
+            "exactly one of FROMMEMBER or FROMLONLAT can be specified".to_string(),
+        )
+    })?;
+    query.shape = shape.ok_or_else(|| {
+        CommandError::InvalidArgument(
+            "exactly one of BYRADIUS and BYBOX can be specified".to_string(),
+        )
+    })?;
+    // the closest matches are only known once they are sorted
+    if query.count.is_some() && query.desc.is_none() && !query.any {
+        query.desc = Some(false);
+    }
+    Ok((query, store_dist))
+}
+
+impl TryFrom<RespArray> for GeoAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["geoadd"], 4)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let (mut nx, mut xx, mut ch) = (false, false, false);
+        while let Some(RespFrame::BulkString(arg)) = args.peek() {
+            match String::from_utf8_lossy(arg).to_ascii_uppercase().as_str() {
+                "NX" => nx = true,
+                "XX" => xx = true,
+                "CH" => ch = true,
+                _ => break,
+            }
+            args.next();
+        }
+        if nx && xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+
+        let rest: Vec<RespFrame> = args.collect();
+        if rest.is_empty() || !rest.len().is_multiple_of(3) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let points = rest
+            .chunks(3)
+            .map(|chunk| {
+                let point = parse_point(Some(chunk[0].clone()), Some(chunk[1].clone()))?;
+                Ok((point, extract_string(Some(chunk[2].clone()))?))
+            })
+            .collect::<Result<_, CommandError>>()?;
+        Ok(GeoAdd {
+            key,
+            nx,
+            xx,
+            ch,
+            points,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoPos {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["geopos"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let members = extract_strings(args)?;
+        Ok(GeoPos { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for GeoDist {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["geodist"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let member1 = extract_string(args.next())?;
+        let member2 = extract_string(args.next())?;
+        let unit = match args.next() {
+            Some(arg) => parse_unit(Some(arg))?,
+            None => 1.0,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(GeoDist {
+            key,
+            member1,
+            member2,
+            unit,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoHash {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["geohash"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let members = extract_strings(args)?;
+        Ok(GeoHash { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["geosearch"], 5)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let (query, _) = parse_search(args, false)?;
+        Ok(GeoSearch { key, query })
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearchStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["geosearchstore"], 6)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let dest = extract_string(args.next())?;
+        let key = extract_string(args.next())?;
+        let (query, store_dist) = parse_search(args, true)?;
+        Ok(GeoSearchStore {
+            dest,
+            key,
+            query,
+            store_dist,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    fn sicily() -> Result<Backend> {
+        let backend = Backend::new();
+        let cmd: GeoAdd = args(&[
+            "geoadd",
+            "sicily",
+            "13.361389",
+            "38.115556",
+            "palermo",
+            "15.087269",
+            "37.502669",
+            "catania",
+        ])
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(2));
+        Ok(backend)
+    }
+
+    #[test]
+    fn test_geosearch_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*11\r\n$9\r\ngeosearch\r\n$1\r\ns\r\n$10\r\nfromlonlat\r\n$2\r\n15\r\n$2\r\n37\r\n$5\r\nbybox\r\n$3\r\n400\r\n$3\r\n400\r\n$2\r\nkm\r\n$5\r\ncount\r\n$1\r\n1\r\n");
+        let cmd: GeoSearch = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(
+            cmd.query.from,
+            SearchFrom::LonLat(GeoPoint {
+                lon: 15.0,
+                lat: 37.0
+            })
+        );
+        assert_eq!(
+            cmd.query.shape,
+            GeoShape::Box {
+                width: 400_000.0,
+                height: 400_000.0
+            }
+        );
+        assert_eq!(cmd.query.count, Some(1));
+        // COUNT without ANY returns the closest ones
+        assert_eq!(cmd.query.desc, Some(false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_geo_commands() -> Result<()> {
+        let backend = sicily()?;
+        let cmd: GeoDist = args(&["geodist", "sicily", "palermo", "catania", "km"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, BulkString::new("166.2742").into());
+        let cmd: GeoHash = args(&["geohash", "sicily", "palermo", "nowhere"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([
+                BulkString::new("sqc8b49rny0").into(),
+                RespFrame::Null(RespNull)
+            ])
+            .into()
+        );
+        let cmd: GeoAdd =
+            args(&["geoadd", "sicily", "xx", "ch", "13", "38", "palermo"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+
+        let cmd: GeoSearch = args(&[
+            "geosearch",
+            "sicily",
+            "fromlonlat",
+            "15",
+            "37",
+            "byradius",
+            "300",
+            "km",
+            "desc",
+            "withdist",
+        ])
+        .try_into()?;
+        let RespFrame::Array(found) = cmd.execute(&backend)? else {
+            panic!("expected an array");
+        };
+        assert_eq!(found.len(), 2);
+        let RespFrame::Array(first) = &found[0] else {
+            panic!("expected an array");
+        };
+        assert_eq!(first[0], BulkString::new("palermo").into());
+
+        let cmd: GeoSearchStore = args(&[
+            "geosearchstore",
+            "near",
+            "sicily",
+            "frommember",
+            "catania",
+            "byradius",
+            "100",
+            "km",
+            "storedist",
+        ])
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+        assert_eq!(
+            backend
+                .zset
+                .get("near")
+                .and_then(|zset| zset.score("catania")),
+            Some(0.0)
+        );
+        Ok(())
+    }
+}
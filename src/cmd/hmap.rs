@@ -1,29 +1,226 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use rand::Rng;
+
 use crate::array::RespArray;
+use crate::backend::{
+    now_millis, Backend, ExpireCondition, FieldTtl, EXPIRE_DELETED, EXPIRE_NO_FIELD, EXPIRE_SET,
+    NOTIFY_HASH,
+};
 use crate::bulk_string::BulkString;
 use crate::cmd::{
-    extract_args, validate_command, CommandError, CommandExecutor, HGet, HGetAll, HSet, RESP_OK,
+    extract_args, extract_count, extract_int, extract_random_count, extract_string,
+    extract_strings, glob_match, notify_miss, notify_write, validate_command,
+    validate_command_at_least, CommandError, CommandExecutor, HGet, HGetAll, HSet,
 };
-use crate::frame::RespFrame;
+use crate::frame::{format_double, RespFrame};
 use crate::null::RespNull;
 
+#[derive(Debug)]
+pub struct HDel {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HExists {
+    key: String,
+    field: String,
+}
+
+#[derive(Debug)]
+pub struct HLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HKeys {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HVals {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HMGet {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HSetNx {
+    key: String,
+    field: String,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct HIncrBy {
+    key: String,
+    field: String,
+    increment: i64,
+}
+
+#[derive(Debug)]
+pub struct HIncrByFloat {
+    key: String,
+    field: String,
+    increment: f64,
+}
+
+#[derive(Debug)]
+pub struct HStrLen {
+    key: String,
+    field: String,
+}
+
+#[derive(Debug)]
+pub struct HRandField {
+    key: String,
+    count: Option<i64>,
+    with_values: bool,
+}
+
+#[derive(Debug)]
+pub struct HScan {
+    key: String,
+    cursor: u64,
+    pattern: Option<String>,
+    count: usize,
+    no_values: bool,
+}
+
+#[derive(Debug)]
+pub struct HExpire {
+    key: String,
+    expiry: Expiry,
+    cond: ExpireCondition,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HPExpire {
+    key: String,
+    expiry: Expiry,
+    cond: ExpireCondition,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HExpireAt {
+    key: String,
+    expiry: Expiry,
+    cond: ExpireCondition,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HPExpireAt {
+    key: String,
+    expiry: Expiry,
+    cond: ExpireCondition,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HTtl {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HPTtl {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HExpireTime {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HPExpireTime {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HPersist {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HGetEx {
+    key: String,
+    /// `None` leaves the TTLs untouched
+    ttl: Option<ExpiryUpdate>,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HSetEx {
+    key: String,
+    /// `Some(true)` for FXX, only set when all fields exist, `Some(false)` for FNX
+    exist: Option<bool>,
+    ttl: Option<ExpiryUpdate>,
+    fields: Vec<(String, RespFrame)>,
+}
+
+#[derive(Debug)]
+pub struct HGetDel {
+    key: String,
+    fields: Vec<String>,
+}
+
+/// An expiry as given by the client, resolved to a unix time when the command runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expiry {
+    /// milliseconds from now
+    In(u64),
+    /// unix time in milliseconds
+    At(u64),
+}
+
+/// The `EX | PX | EXAT | PXAT | PERSIST | KEEPTTL` option of HGETEX and HSETEX.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExpiryUpdate {
+    Expire(Expiry),
+    Persist,
+    KeepTtl,
+}
+
 impl CommandExecutor for HGet {
     fn execute(self, backend: &crate::backend::Backend) -> Result<RespFrame, CommandError> {
-        Ok(backend
-            .hget(&self.key, &self.field)
-            .unwrap_or(RespFrame::Null(RespNull)))
+        let value = backend.hget(&self.key, &self.field);
+        notify_miss(
+            backend,
+            &self.key,
+            value.is_some() || backend.hmap.contains_key(&self.key),
+        );
+        Ok(value.unwrap_or(RespFrame::Null(RespNull)))
     }
 }
 
 impl CommandExecutor for HSet {
     fn execute(self, backend: &crate::backend::Backend) -> Result<RespFrame, CommandError> {
-        backend.hset(self.key, self.field, self.value);
-        Ok(RESP_OK.clone())
+        let created = !backend.hmap.contains_key(&self.key);
+        let added = backend.hset(&self.key, self.fields);
+        backend.notify_new(created, &self.key);
+        backend.notify(NOTIFY_HASH, "hset", &self.key);
+        Ok(RespFrame::Integer(added as i64))
     }
 }
 
 impl CommandExecutor for HGetAll {
     fn execute(self, backend: &crate::backend::Backend) -> Result<RespFrame, CommandError> {
-        let hmap = backend.hmap.get(&self.key);
+        let hmap = backend.hgetall(&self.key);
+        notify_miss(backend, &self.key, hmap.is_some());
 
         match hmap {
             Some(hmap) => {
@@ -47,6 +244,499 @@ impl CommandExecutor for HGetAll {
     }
 }
 
+impl CommandExecutor for HDel {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let deleted = backend.hdel(&self.key, &self.fields);
+        notify_hdel(backend, &self.key, deleted > 0);
+        Ok(RespFrame::Integer(deleted as i64))
+    }
+}
+
+impl CommandExecutor for HExists {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let exists = backend.hget(&self.key, &self.field).is_some();
+        Ok(RespFrame::Integer(exists as i64))
+    }
+}
+
+impl CommandExecutor for HLen {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(RespFrame::Integer(backend.hlen(&self.key) as i64))
+    }
+}
+
+impl CommandExecutor for HKeys {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let fields = hash_entries(backend, &self.key)
+            .into_iter()
+            .map(|(field, _)| BulkString::from(field).into())
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(fields).into())
+    }
+}
+
+impl CommandExecutor for HVals {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let values = hash_entries(backend, &self.key)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(values).into())
+    }
+}
+
+impl CommandExecutor for HMGet {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let hmap = backend.hgetall(&self.key);
+        notify_miss(backend, &self.key, hmap.is_some());
+        let values = self.fields.iter().map(|field| {
+            hmap.as_ref()
+                .and_then(|hmap| hmap.get(field).map(|v| v.value().clone()))
+        });
+        Ok(values_reply(values))
+    }
+}
+
+impl CommandExecutor for HSetNx {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let value = self.value;
+        let created = !backend.hmap.contains_key(&self.key);
+        let set = backend.hupdate(&self.key, &self.field, |current| match current {
+            Some(_) => Err(()),
+            None => Ok((value, ())),
+        });
+        if set.is_ok() {
+            backend.notify_new(created, &self.key);
+            backend.notify(NOTIFY_HASH, "hset", &self.key);
+        }
+        Ok(RespFrame::Integer(set.is_ok() as i64))
+    }
+}
+
+impl CommandExecutor for HIncrBy {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let increment = self.increment;
+        let created = !backend.hmap.contains_key(&self.key);
+        let value = backend.hupdate(&self.key, &self.field, |current| {
+            let current = match current {
+                Some(value) => hash_value_string(value)
+                    .and_then(|v| v.parse::<i64>().ok())
+                    .ok_or_else(|| {
+                        CommandError::InvalidArgument("hash value is not an integer".to_string())
+                    })?,
+                None => 0,
+            };
+            let value = current.checked_add(increment).ok_or_else(|| {
+                CommandError::InvalidArgument("increment or decrement would overflow".to_string())
+            })?;
+            Ok::<_, CommandError>((BulkString::from(value.to_string()).into(), value))
+        })?;
+        backend.notify_new(created, &self.key);
+        backend.notify(NOTIFY_HASH, "hincrby", &self.key);
+        Ok(RespFrame::Integer(value))
+    }
+}
+
+impl CommandExecutor for HIncrByFloat {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let increment = self.increment;
+        let created = !backend.hmap.contains_key(&self.key);
+        let value = backend.hupdate(&self.key, &self.field, |current| {
+            let current = match current {
+                Some(value) => hash_value_string(value)
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .filter(|v| v.is_finite())
+                    .ok_or_else(|| {
+                        CommandError::InvalidArgument("hash value is not a float".to_string())
+                    })?,
+                None => 0.0,
+            };
+            let value = current + increment;
+            if !value.is_finite() {
+                return Err(CommandError::InvalidArgument(
+                    "increment would produce NaN or Infinity".to_string(),
+                ));
+            }
+            let value = format_double(value);
+            Ok::<_, CommandError>((BulkString::from(value.clone()).into(), value))
+        })?;
+        backend.notify_new(created, &self.key);
+        backend.notify(NOTIFY_HASH, "hincrbyfloat", &self.key);
+        Ok(BulkString::from(value).into())
+    }
+}
+
+impl CommandExecutor for HStrLen {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let len = backend
+            .hget(&self.key, &self.field)
+            .and_then(|value| hash_value_string(&value))
+            .map_or(0, |value| value.len());
+        Ok(RespFrame::Integer(len as i64))
+    }
+}
+
+impl CommandExecutor for HRandField {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let entries = hash_entries(backend, &self.key);
+        let mut rng = rand::thread_rng();
+        let len = entries.len();
+        let picked: Vec<&(String, RespFrame)> = match self.count {
+            None if len == 0 => return Ok(RespFrame::Null(RespNull)),
+            None => {
+                let (field, _) = &entries[rng.gen_range(0..len)];
+                return Ok(BulkString::from(field.clone()).into());
+            }
+            Some(_) if len == 0 => vec![],
+            // a positive count returns distinct fields, a negative one allows repeats
+            Some(count) if count >= 0 => {
+                rand::seq::index::sample(&mut rng, len, (count as usize).min(len))
+                    .into_iter()
+                    .map(|i| &entries[i])
+                    .collect()
+            }
+            Some(count) => (0..count.unsigned_abs())
+                .map(|_| &entries[rng.gen_range(0..len)])
+                .collect(),
+        };
+
+        let mut ret = Vec::with_capacity(picked.len() * if self.with_values { 2 } else { 1 });
+        for (field, value) in picked {
+            ret.push(BulkString::from(field.clone()).into());
+            if self.with_values {
+                ret.push(value.clone());
+            }
+        }
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for HScan {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        // fields are walked in the order of their cursors, and the cursor of the last field
+        // returned resumes the scan, so adding or removing fields doesn't shift the others
+        let mut entries = hash_entries(backend, &self.key)
+            .into_iter()
+            .map(|(field, value)| (field_cursor(&field), field, value))
+            .filter(|(cursor, _, _)| *cursor > self.cursor)
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+        // fields sharing a cursor go out together, or the next scan would skip some of them
+        let mut end = self.count.min(entries.len());
+        while end < entries.len() && entries[end].0 == entries[end - 1].0 {
+            end += 1;
+        }
+        let next_cursor = match end < entries.len() {
+            true => entries[end - 1].0,
+            false => 0,
+        };
+
+        let mut ret = vec![];
+        for (_, field, value) in entries.into_iter().take(end) {
+            if let Some(pattern) = &self.pattern {
+                if !glob_match(pattern.as_bytes(), field.as_bytes()) {
+                    continue;
+                }
+            }
+            ret.push(BulkString::from(field).into());
+            if !self.no_values {
+                ret.push(value);
+            }
+        }
+        Ok(RespArray::new([
+            BulkString::from(next_cursor.to_string()).into(),
+            RespArray::new(ret).into(),
+        ])
+        .into())
+    }
+}
+
+/// The HSCAN cursor resuming after `field`, never 0 since 0 starts a scan.
+fn field_cursor(field: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    field.hash(&mut hasher);
+    hasher.finish().max(1)
+}
+
+impl CommandExecutor for HExpire {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(expire_fields(
+            backend,
+            &self.key,
+            self.expiry,
+            self.cond,
+            &self.fields,
+        ))
+    }
+}
+
+impl CommandExecutor for HPExpire {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(expire_fields(
+            backend,
+            &self.key,
+            self.expiry,
+            self.cond,
+            &self.fields,
+        ))
+    }
+}
+
+impl CommandExecutor for HExpireAt {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(expire_fields(
+            backend,
+            &self.key,
+            self.expiry,
+            self.cond,
+            &self.fields,
+        ))
+    }
+}
+
+impl CommandExecutor for HPExpireAt {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(expire_fields(
+            backend,
+            &self.key,
+            self.expiry,
+            self.cond,
+            &self.fields,
+        ))
+    }
+}
+
+impl CommandExecutor for HTtl {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let now = now_millis();
+        Ok(ttl_reply(backend, &self.key, &self.fields, |at| {
+            (at.saturating_sub(now) + 500) / 1000
+        }))
+    }
+}
+
+impl CommandExecutor for HPTtl {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let now = now_millis();
+        Ok(ttl_reply(backend, &self.key, &self.fields, |at| {
+            at.saturating_sub(now)
+        }))
+    }
+}
+
+impl CommandExecutor for HExpireTime {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(ttl_reply(backend, &self.key, &self.fields, |at| at / 1000))
+    }
+}
+
+impl CommandExecutor for HPExpireTime {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(ttl_reply(backend, &self.key, &self.fields, |at| at))
+    }
+}
+
+impl CommandExecutor for HPersist {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let codes = self
+            .fields
+            .iter()
+            .map(|field| match backend.hpersist(&self.key, field) {
+                Some(true) => 1,
+                Some(false) => -1,
+                None => EXPIRE_NO_FIELD,
+            })
+            .collect::<Vec<i64>>();
+        if codes.contains(&1) {
+            backend.notify(NOTIFY_HASH, "hpersist", &self.key);
+        }
+        Ok(RespArray::new(
+            codes
+                .into_iter()
+                .map(RespFrame::Integer)
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into())
+    }
+}
+
+impl CommandExecutor for HGetEx {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let values = self
+            .fields
+            .iter()
+            .map(|field| backend.hget(&self.key, field))
+            .collect::<Vec<_>>();
+        let mut codes = vec![];
+        let mut persisted = false;
+        for (field, value) in self.fields.iter().zip(&values) {
+            if value.is_none() {
+                continue;
+            }
+            match self.ttl {
+                Some(ExpiryUpdate::Expire(expiry)) => {
+                    let at = expiry.at_millis();
+                    codes.push(backend.hexpire_at(&self.key, field, at, ExpireCondition::Always));
+                }
+                Some(ExpiryUpdate::Persist) => {
+                    persisted |= backend.hpersist(&self.key, field) == Some(true);
+                }
+                Some(ExpiryUpdate::KeepTtl) | None => {}
+            }
+        }
+        notify_expire(backend, &self.key, &codes);
+        if persisted {
+            backend.notify(NOTIFY_HASH, "hpersist", &self.key);
+        }
+        Ok(values_reply(values.into_iter()))
+    }
+}
+
+impl CommandExecutor for HSetEx {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        if let Some(exist) = self.exist {
+            let all_match = self
+                .fields
+                .iter()
+                .all(|(field, _)| backend.hget(&self.key, field).is_some() == exist);
+            if !all_match {
+                return Ok(RespFrame::Integer(0));
+            }
+        }
+        let ttl = match self.ttl {
+            Some(ExpiryUpdate::Expire(expiry)) => FieldTtl::ExpireAt(expiry.at_millis()),
+            Some(ExpiryUpdate::KeepTtl) => FieldTtl::Keep,
+            Some(ExpiryUpdate::Persist) | None => FieldTtl::Clear,
+        };
+        let created = !backend.hmap.contains_key(&self.key);
+        backend.hsetex(&self.key, self.fields, ttl);
+        backend.notify_new(created, &self.key);
+        backend.notify(NOTIFY_HASH, "hset", &self.key);
+        if matches!(ttl, FieldTtl::ExpireAt(_)) {
+            backend.notify(NOTIFY_HASH, "hexpire", &self.key);
+        }
+        Ok(RespFrame::Integer(1))
+    }
+}
+
+impl CommandExecutor for HGetDel {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let values = backend.hgetdel(&self.key, &self.fields);
+        notify_hdel(backend, &self.key, values.iter().any(Option::is_some));
+        Ok(values_reply(values.into_iter()))
+    }
+}
+
+impl Expiry {
+    fn at_millis(self) -> u64 {
+        match self {
+            Expiry::In(ms) => now_millis().saturating_add(ms),
+            Expiry::At(at) => at,
+        }
+    }
+}
+
+fn expire_fields(
+    backend: &Backend,
+    key: &str,
+    expiry: Expiry,
+    cond: ExpireCondition,
+    fields: &[String],
+) -> RespFrame {
+    let at = expiry.at_millis();
+    let codes = fields
+        .iter()
+        .map(|field| backend.hexpire_at(key, field, at, cond))
+        .collect::<Vec<i64>>();
+    notify_expire(backend, key, &codes);
+    RespArray::new(
+        codes
+            .into_iter()
+            .map(RespFrame::Integer)
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+/// Notifies an `hexpire` when a TTL was set and an `hdel` when an expiry in the past deleted
+/// fields right away.
+fn notify_expire(backend: &Backend, key: &str, codes: &[i64]) {
+    if codes.contains(&EXPIRE_SET) {
+        backend.notify(NOTIFY_HASH, "hexpire", key);
+    }
+    notify_hdel(backend, key, codes.contains(&EXPIRE_DELETED));
+}
+
+fn notify_hdel(backend: &Backend, key: &str, deleted: bool) {
+    if deleted {
+        let emptied = !backend.hmap.contains_key(key);
+        notify_write(backend, NOTIFY_HASH, "hdel", key, emptied);
+    }
+}
+
+/// Replies per field with -2 when it doesn't exist, -1 when it has no TTL and `ttl(expire_at)`
+/// otherwise.
+fn ttl_reply(
+    backend: &Backend,
+    key: &str,
+    fields: &[String],
+    ttl: impl Fn(u64) -> u64,
+) -> RespFrame {
+    let codes = fields
+        .iter()
+        .map(|field| {
+            let code = match backend.hexpire_time(key, field) {
+                Some(Some(at)) => ttl(at) as i64,
+                Some(None) => -1,
+                None => EXPIRE_NO_FIELD,
+            };
+            RespFrame::Integer(code)
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(codes).into()
+}
+
+fn values_reply(values: impl Iterator<Item = Option<RespFrame>>) -> RespFrame {
+    RespArray::new(
+        values
+            .map(|value| value.unwrap_or(RespFrame::Null(RespNull)))
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+fn hash_entries(backend: &Backend, key: &str) -> Vec<(String, RespFrame)> {
+    backend
+        .hgetall(key)
+        .map(|hmap| {
+            hmap.iter()
+                .map(|v| (v.key().clone(), v.value().clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The textual form of a stored hash value, as HINCRBY and HSTRLEN see it.
+fn hash_value_string(value: &RespFrame) -> Option<String> {
+    match value {
+        RespFrame::BulkString(s) => String::from_utf8(s.0.clone()).ok(),
+        RespFrame::SimpleString(s) => Some(s.0.clone()),
+        RespFrame::Integer(i) => Some(i.to_string()),
+        _ => None,
+    }
+}
+
+fn parse_key_field(value: RespArray, name: &'static str) -> Result<(String, String), CommandError> {
+    validate_command(&value, &[name], 2)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    Ok((extract_string(args.next())?, extract_string(args.next())?))
+}
+
+fn parse_key(value: RespArray, name: &'static str) -> Result<String, CommandError> {
+    validate_command(&value, &[name], 1)?;
+    extract_string(extract_args(value, 1)?.into_iter().next())
+}
+
 impl TryFrom<RespArray> for HGet {
     type Error = CommandError;
 
@@ -70,40 +760,535 @@ impl TryFrom<RespArray> for HSet {
     type Error = CommandError;
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hset"], 3)?;
+        validate_command_at_least(&value, &["hset"], 3)?;
+        if !value.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'hset' command".to_string(),
+            ));
+        }
         let mut args = extract_args(value, 1)?.into_iter();
-        match (args.next(), args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
-                Ok(HSet {
-                    key: String::from_utf8(key.0)?,
-                    field: String::from_utf8(field.0)?,
-                    value,
-                })
+        let key = extract_string(args.next())?;
+        let mut fields = vec![];
+        while let Some(field) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| CommandError::InvalidArgument("Invalid key or value".to_string()))?;
+            fields.push((extract_string(Some(field))?, value));
+        }
+        Ok(HSet { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HGetAll {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["hgetall"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => String::from_utf8(key.0)?,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid key or filed or value".to_string(),
+                ))
             }
+        };
+        // SORT returns the fields in lexicographic order
+        let sort = match args.next() {
+            Some(arg) => match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                "SORT" => true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            None => false,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(HGetAll { key, sort })
+    }
+}
 
-            _ => Err(CommandError::InvalidArgument(
-                "Invalid key or value".to_string(),
-            )),
+impl TryFrom<RespArray> for HDel {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["hdel"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        Ok(HDel {
+            key,
+            fields: extract_strings(args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HExists {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, field) = parse_key_field(value, "hexists")?;
+        Ok(HExists { key, field })
+    }
+}
+
+impl TryFrom<RespArray> for HLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(HLen {
+            key: parse_key(value, "hlen")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HKeys {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(HKeys {
+            key: parse_key(value, "hkeys")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HVals {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(HVals {
+            key: parse_key(value, "hvals")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HMGet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["hmget"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        Ok(HMGet {
+            key,
+            fields: extract_strings(args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HSetNx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hsetnx"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HSetNx {
+            key: extract_string(args.next())?,
+            field: extract_string(args.next())?,
+            value: args
+                .next()
+                .ok_or_else(|| CommandError::InvalidArgument("Invalid key or value".to_string()))?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HIncrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hincrby"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HIncrBy {
+            key: extract_string(args.next())?,
+            field: extract_string(args.next())?,
+            increment: extract_int(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HIncrByFloat {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hincrbyfloat"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let field = extract_string(args.next())?;
+        let increment = extract_string(args.next())?
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| {
+                CommandError::InvalidArgument("value is not a valid float".to_string())
+            })?;
+        Ok(HIncrByFloat {
+            key,
+            field,
+            increment,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HStrLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, field) = parse_key_field(value, "hstrlen")?;
+        Ok(HStrLen { key, field })
+    }
+}
+
+impl TryFrom<RespArray> for HRandField {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["hrandfield"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let count = args
+            .next()
+            .map(|arg| extract_random_count(Some(arg)))
+            .transpose()?;
+        let with_values = match args.next() {
+            Some(arg) => match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                "WITHVALUES" => true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            None => false,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
         }
+        Ok(HRandField {
+            key,
+            count,
+            with_values,
+        })
     }
 }
 
-impl TryFrom<RespArray> for HGetAll {
+impl TryFrom<RespArray> for HScan {
     type Error = CommandError;
+
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hgetall"], 1)?;
+        validate_command_at_least(&value, &["hscan"], 2)?;
         let mut args = extract_args(value, 1)?.into_iter();
-        match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(HGetAll {
-                key: String::from_utf8(key.0)?,
-                sort: false,
-            }),
-            _ => Err(CommandError::InvalidArgument(
-                "Invalid key or filed or value".to_string(),
-            )),
+        let key = extract_string(args.next())?;
+        let cursor = extract_string(args.next())?
+            .parse()
+            .map_err(|_| CommandError::InvalidArgument("invalid cursor".to_string()))?;
+        let mut cmd = HScan {
+            key,
+            cursor,
+            pattern: None,
+            count: 10,
+            no_values: false,
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                "MATCH" => cmd.pattern = Some(extract_string(args.next())?),
+                "COUNT" => match extract_count(args.next())? {
+                    0 => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+                    count => cmd.count = count,
+                },
+                "NOVALUES" => cmd.no_values = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
         }
+        Ok(cmd)
+    }
+}
+
+/// Parses the `FIELDS numfields field [field ...]` tail, where each field is followed by a
+/// value when `with_values` is set.
+fn parse_fields(
+    args: &mut impl ExactSizeIterator<Item = RespFrame>,
+    with_values: bool,
+) -> Result<Vec<(String, Option<RespFrame>)>, CommandError> {
+    match args
+        .next()
+        .map(|arg| extract_string(Some(arg)))
+        .transpose()?
+    {
+        Some(arg) if arg.eq_ignore_ascii_case("FIELDS") => {}
+        _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+    let num_fields = extract_count(args.next())?;
+    if num_fields == 0 {
+        return Err(CommandError::InvalidArgument(
+            "Parameter `numFields` should be greater than 0".to_string(),
+        ));
+    }
+    // checked before allocating, the count comes from the client
+    let args_per_field = if with_values { 2 } else { 1 };
+    if num_fields.checked_mul(args_per_field) != Some(args.len()) {
+        return Err(CommandError::InvalidArgument(
+            "The `numfields` parameter must match the number of arguments".to_string(),
+        ));
+    }
+    let mut fields = Vec::with_capacity(num_fields);
+    for _ in 0..num_fields {
+        let field = extract_string(args.next())?;
+        let value = with_values.then(|| args.next()).flatten();
+        fields.push((field, value));
+    }
+    Ok(fields)
+}
+
+fn parse_field_names(
+    args: &mut impl ExactSizeIterator<Item = RespFrame>,
+) -> Result<Vec<String>, CommandError> {
+    Ok(parse_fields(args, false)?
+        .into_iter()
+        .map(|(field, _)| field)
+        .collect())
+}
+
+fn parse_expiry(
+    arg: Option<RespFrame>,
+    unit_ms: u64,
+    absolute: bool,
+) -> Result<Expiry, CommandError> {
+    let value = extract_int(arg)?;
+    let ms = u64::try_from(value)
+        .ok()
+        .and_then(|value| value.checked_mul(unit_ms))
+        .ok_or_else(|| {
+            CommandError::InvalidArgument("invalid expire time, must be >= 0".to_string())
+        })?;
+    Ok(if absolute {
+        Expiry::At(ms)
+    } else {
+        Expiry::In(ms)
+    })
+}
+
+/// Parses `key seconds|milliseconds|unix-time [NX | XX | GT | LT] FIELDS numfields field ...`.
+fn parse_hexpire(
+    value: RespArray,
+    name: &'static str,
+    unit_ms: u64,
+    absolute: bool,
+) -> Result<(String, Expiry, ExpireCondition, Vec<String>), CommandError> {
+    validate_command_at_least(&value, &[name], 5)?;
+    let mut args = extract_args(value, 1)?.into_iter().peekable();
+    let key = extract_string(args.next())?;
+    let expiry = parse_expiry(args.next(), unit_ms, absolute)?;
+    let cond = match args.peek() {
+        Some(RespFrame::BulkString(arg)) => match arg.to_ascii_uppercase().as_slice() {
+            b"NX" => Some(ExpireCondition::Nx),
+            b"XX" => Some(ExpireCondition::Xx),
+            b"GT" => Some(ExpireCondition::Gt),
+            b"LT" => Some(ExpireCondition::Lt),
+            _ => None,
+        },
+        _ => None,
+    };
+    if cond.is_some() {
+        args.next();
     }
+    let fields = parse_field_names(&mut args)?;
+    Ok((key, expiry, cond.unwrap_or(ExpireCondition::Always), fields))
+}
+
+fn parse_key_fields(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(String, Vec<String>), CommandError> {
+    validate_command_at_least(&value, &[name], 3)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    Ok((key, parse_field_names(&mut args)?))
 }
+
+/// Parses the `EX | PX | EXAT | PXAT | PERSIST | KEEPTTL` option at the front of `args`, if any.
+fn parse_expiry_update(
+    args: &mut std::iter::Peekable<impl Iterator<Item = RespFrame>>,
+    allow_persist: bool,
+) -> Result<Option<ExpiryUpdate>, CommandError> {
+    let option = match args.peek() {
+        Some(RespFrame::BulkString(arg)) => String::from_utf8_lossy(arg).to_ascii_uppercase(),
+        _ => return Ok(None),
+    };
+    let update = match option.as_str() {
+        "EX" | "PX" | "EXAT" | "PXAT" => {
+            args.next();
+            let unit_ms = if option.starts_with("EX") { 1000 } else { 1 };
+            ExpiryUpdate::Expire(parse_expiry(args.next(), unit_ms, option.ends_with("AT"))?)
+        }
+        "PERSIST" if allow_persist => {
+            args.next();
+            ExpiryUpdate::Persist
+        }
+        "KEEPTTL" if !allow_persist => {
+            args.next();
+            ExpiryUpdate::KeepTtl
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(update))
+}
+
+impl TryFrom<RespArray> for HExpire {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, expiry, cond, fields) = parse_hexpire(value, "hexpire", 1000, false)?;
+        Ok(HExpire {
+            key,
+            expiry,
+            cond,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HPExpire {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, expiry, cond, fields) = parse_hexpire(value, "hpexpire", 1, false)?;
+        Ok(HPExpire {
+            key,
+            expiry,
+            cond,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HExpireAt {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, expiry, cond, fields) = parse_hexpire(value, "hexpireat", 1000, true)?;
+        Ok(HExpireAt {
+            key,
+            expiry,
+            cond,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HPExpireAt {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, expiry, cond, fields) = parse_hexpire(value, "hpexpireat", 1, true)?;
+        Ok(HPExpireAt {
+            key,
+            expiry,
+            cond,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HTtl {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = parse_key_fields(value, "httl")?;
+        Ok(HTtl { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HPTtl {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = parse_key_fields(value, "hpttl")?;
+        Ok(HPTtl { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HExpireTime {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = parse_key_fields(value, "hexpiretime")?;
+        Ok(HExpireTime { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HPExpireTime {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = parse_key_fields(value, "hpexpiretime")?;
+        Ok(HPExpireTime { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HPersist {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = parse_key_fields(value, "hpersist")?;
+        Ok(HPersist { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HGetDel {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = parse_key_fields(value, "hgetdel")?;
+        Ok(HGetDel { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HGetEx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["hgetex"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let ttl = parse_expiry_update(&mut args, true)?;
+        let fields = parse_field_names(&mut args)?;
+        Ok(HGetEx { key, ttl, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HSetEx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["hsetex"], 4)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let mut exist = None;
+        let mut ttl = None;
+        loop {
+            if let Some(RespFrame::BulkString(arg)) = args.peek() {
+                let arg = arg.to_ascii_uppercase();
+                if exist.is_none() && (arg == b"FNX" || arg == b"FXX") {
+                    exist = Some(arg == b"FXX");
+                    args.next();
+                    continue;
+                }
+            }
+            if ttl.is_none() {
+                if let Some(update) = parse_expiry_update(&mut args, false)? {
+                    ttl = Some(update);
+                    continue;
+                }
+            }
+            break;
+        }
+        let fields = parse_fields(&mut args, true)?
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field, value)))
+            .collect();
+        Ok(HSetEx {
+            key,
+            exist,
+            ttl,
+            fields,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
@@ -128,13 +1313,18 @@ mod tests {
     fn test_hset_from_resp_array() -> Result<()> {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(
-            b"*4\r\n$4\r\nhset\r\n$4\r\nkey1\r\n$6\r\nfield1\r\n$6\r\nvalue1\r\n",
+            b"*6\r\n$4\r\nhset\r\n$4\r\nkey1\r\n$6\r\nfield1\r\n$6\r\nvalue1\r\n$6\r\nfield2\r\n:2\r\n",
         );
         let frame = RespArray::decode(&mut buf)?;
         let result: HSet = frame.try_into()?;
         assert_eq!(result.key, "key1");
-        assert_eq!(result.field, "field1");
-        assert_eq!(result.value, RespFrame::BulkString("value1".into()));
+        assert_eq!(
+            result.fields,
+            vec![
+                ("field1".to_string(), RespFrame::BulkString("value1".into())),
+                ("field2".to_string(), RespFrame::Integer(2)),
+            ]
+        );
 
         Ok(())
     }
@@ -147,12 +1337,12 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
         let cmd: HSet = frame.try_into()?;
         let ret = cmd.execute(&backend);
-        assert_eq!(ret?, RESP_OK.clone());
+        assert_eq!(ret?, RespFrame::Integer(1));
         buf.extend_from_slice(b"*4\r\n$4\r\nhset\r\n$3\r\nmap\r\n$6\r\nhello1\r\n$6\r\nworld1\r\n");
         let frame = RespArray::decode(&mut buf)?;
         let cmd: HSet = frame.try_into()?;
         let ret = cmd.execute(&backend);
-        assert_eq!(ret?, RESP_OK.clone());
+        assert_eq!(ret?, RespFrame::Integer(1));
 
         buf.extend_from_slice(b"*3\r\n$4\r\nhget\r\n$3\r\nmap\r\n$6\r\nhello1\r\n");
         let frame = RespArray::decode(&mut buf)?;
@@ -160,10 +1350,12 @@ mod tests {
         let ret = cmd.execute(&backend);
         assert_eq!(ret?, BulkString::new("world1").into());
 
-        let cmd = HGetAll {
-            key: "map".to_string(),
-            sort: true,
-        };
+        buf.extend_from_slice(b"*3\r\n$7\r\nhgetall\r\n$3\r\nmap\r\n$4\r\nsort\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: HGetAll = frame.try_into()?;
+        assert!(cmd.sort);
+        buf.extend_from_slice(b"*3\r\n$7\r\nhgetall\r\n$3\r\nmap\r\n$3\r\nfoo\r\n");
+        assert!(HGetAll::try_from(RespArray::decode(&mut buf)?).is_err());
         let ret = cmd.execute(&backend);
         assert_eq!(
             ret?,
@@ -178,4 +1370,265 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_hincrby_hdel_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = HIncrBy {
+            key: "counters".to_string(),
+            field: "hits".to_string(),
+            increment: 5,
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(5));
+        let cmd = HIncrByFloat {
+            key: "counters".to_string(),
+            field: "hits".to_string(),
+            increment: 0.5,
+        };
+        assert_eq!(cmd.execute(&backend)?, BulkString::new("5.5").into());
+        let cmd = HIncrBy {
+            key: "counters".to_string(),
+            field: "hits".to_string(),
+            increment: 1,
+        };
+        assert!(cmd.execute(&backend).is_err());
+
+        let cmd = HSetNx {
+            key: "counters".to_string(),
+            field: "hits".to_string(),
+            value: BulkString::new("0").into(),
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(0));
+        let cmd = HStrLen {
+            key: "counters".to_string(),
+            field: "hits".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(3));
+
+        let cmd = HDel {
+            key: "counters".to_string(),
+            fields: vec!["hits".to_string(), "misses".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+        assert!(!backend.hmap.contains_key("counters"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hrandfield_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.hset("h", vec![("a".to_string(), BulkString::new("1").into())]);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$10\r\nhrandfield\r\n$1\r\nh\r\n$2\r\n-2\r\n$10\r\nwithvalues\r\n",
+        );
+        let cmd: HRandField = RespArray::decode(&mut buf)?.try_into()?;
+        let field: Vec<RespFrame> = vec![BulkString::new("a").into(), BulkString::new("1").into()];
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([field.clone(), field].concat()).into()
+        );
+
+        buf.extend_from_slice(
+            b"*3\r\n$10\r\nhrandfield\r\n$1\r\nh\r\n$20\r\n-9223372036854775808\r\n",
+        );
+        let err = HRandField::try_from(RespArray::decode(&mut buf)?).unwrap_err();
+        assert!(err.to_string().contains("value is out of range"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hscan_command() -> Result<()> {
+        let backend = Backend::new();
+        let fields = ["a1", "a2", "b1", "b2"]
+            .iter()
+            .map(|f| (f.to_string(), BulkString::new("v").into()))
+            .collect();
+        backend.hset("h", fields);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*8\r\n$5\r\nhscan\r\n$1\r\nh\r\n$1\r\n0\r\n$5\r\nmatch\r\n$2\r\na*\r\n$5\r\ncount\r\n$2\r\n10\r\n$8\r\nnovalues\r\n",
+        );
+        let cmd: HScan = RespArray::decode(&mut buf)?.try_into()?;
+        let (cursor, mut fields) = scan_page(cmd.execute(&backend)?);
+        fields.sort();
+        assert_eq!(
+            (cursor, fields),
+            (0, vec!["a1".to_string(), "a2".to_string()])
+        );
+
+        // one field at a time, while fields come and go
+        let mut cursor = 0;
+        let mut seen = vec![];
+        loop {
+            let cmd = HScan {
+                key: "h".to_string(),
+                cursor,
+                pattern: None,
+                count: 1,
+                no_values: true,
+            };
+            let (next, fields) = scan_page(cmd.execute(&backend)?);
+            seen.extend(fields);
+            if seen.len() == 1 {
+                backend.hdel("h", &[seen[0].clone()]);
+                backend.hset("h", vec![("c1".to_string(), BulkString::new("v").into())]);
+            }
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        for field in ["a1", "a2", "b1", "b2"] {
+            assert!(seen.iter().any(|seen| seen == field));
+        }
+        Ok(())
+    }
+
+    fn scan_page(reply: RespFrame) -> (u64, Vec<String>) {
+        let RespFrame::Array(page) = reply else {
+            panic!("hscan must reply with an array");
+        };
+        let (RespFrame::BulkString(cursor), RespFrame::Array(fields)) = (&page[0], &page[1]) else {
+            panic!("hscan must reply with a cursor and fields");
+        };
+        let fields = fields
+            .iter()
+            .map(|field| match field {
+                RespFrame::BulkString(field) => String::from_utf8_lossy(field).into_owned(),
+                _ => panic!("fields must be bulk strings"),
+            })
+            .collect();
+        (String::from_utf8_lossy(cursor).parse().unwrap(), fields)
+    }
+
+    #[test]
+    fn test_hexpire_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$7\r\nhexpire\r\n$1\r\nh\r\n$2\r\n10\r\n$2\r\nnx\r\n$6\r\nfields\r\n$1\r\n1\r\n$1\r\na\r\n",
+        );
+        let cmd: HExpire = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.expiry, Expiry::In(10_000));
+        assert_eq!(cmd.cond, ExpireCondition::Nx);
+        assert_eq!(cmd.fields, vec!["a".to_string()]);
+
+        buf.extend_from_slice(
+            b"*6\r\n$7\r\nhexpire\r\n$1\r\nh\r\n$2\r\n10\r\n$6\r\nfields\r\n$1\r\n2\r\n$1\r\na\r\n",
+        );
+        let cmd: Result<HExpire, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(cmd.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hgetex_numfields_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nhgetex\r\n$1\r\nh\r\n$6\r\nfields\r\n$19\r\n9223372036854775807\r\n$1\r\na\r\n",
+        );
+        let err = HGetEx::try_from(RespArray::decode(&mut buf)?).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The `numfields` parameter must match the number of arguments"));
+
+        buf.extend_from_slice(
+            b"*6\r\n$6\r\nhgetex\r\n$1\r\nh\r\n$6\r\nfields\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n",
+        );
+        let cmd: HGetEx = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.fields, vec!["a".to_string(), "b".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_field_ttl_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = HSetEx {
+            key: "session".to_string(),
+            exist: Some(false),
+            ttl: Some(ExpiryUpdate::Expire(Expiry::In(60_000))),
+            fields: vec![
+                ("user".to_string(), BulkString::new("alice").into()),
+                ("role".to_string(), BulkString::new("admin").into()),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+
+        let cmd = HTtl {
+            key: "session".to_string(),
+            fields: vec!["user".to_string(), "missing".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([RespFrame::Integer(60), RespFrame::Integer(-2)]).into()
+        );
+
+        let cmd = HGetEx {
+            key: "session".to_string(),
+            ttl: Some(ExpiryUpdate::Persist),
+            fields: vec!["user".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([BulkString::new("alice").into()]).into()
+        );
+        let cmd = HPersist {
+            key: "session".to_string(),
+            fields: vec!["user".to_string(), "role".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([RespFrame::Integer(-1), RespFrame::Integer(1)]).into()
+        );
+
+        let cmd = HPExpire {
+            key: "session".to_string(),
+            expiry: Expiry::In(0),
+            cond: ExpireCondition::Always,
+            fields: vec!["role".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([RespFrame::Integer(2)]).into()
+        );
+
+        let cmd = HGetDel {
+            key: "session".to_string(),
+            fields: vec!["user".to_string(), "role".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([BulkString::new("alice").into(), RespFrame::Null(RespNull)]).into()
+        );
+        assert!(!backend.hmap.contains_key("session"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expired_fields_are_invisible() -> Result<()> {
+        let backend = Backend::new();
+        let fields = vec![
+            ("a".to_string(), BulkString::new("1").into()),
+            ("b".to_string(), BulkString::new("2").into()),
+        ];
+        backend.hset("h", fields);
+        let at = now_millis() + 20;
+        backend.hexpire_at("h", "a", at, ExpireCondition::Always);
+        assert_eq!(backend.hlen("h"), 2);
+
+        tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+        let cmd = HGetAll {
+            key: "h".to_string(),
+            sort: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([BulkString::new("b").into(), BulkString::new("2").into()]).into()
+        );
+        assert_eq!(backend.hlen("h"), 1);
+        Ok(())
+    }
 }
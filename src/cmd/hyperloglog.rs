@@ -0,0 +1,162 @@
+use crate::array::RespArray;
+use crate::backend::{Backend, NOTIFY_STRING};
+use crate::cmd::{
+    extract_args, extract_bytes, extract_string, extract_strings, validate_command_at_least,
+    CommandError, CommandExecutor, RESP_OK,
+};
+use crate::frame::RespFrame;
+
+#[derive(Debug)]
+pub struct PfAdd {
+    key: String,
+    elements: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PfCount {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct PfMerge {
+    dest: String,
+    sources: Vec<String>,
+}
+
+impl CommandExecutor for PfAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let created = !backend.map.contains_key(&self.key);
+        let changed = backend
+            .pfadd(&self.key, &self.elements)
+            .map_err(CommandError::InvalidArgument)?;
+        if changed {
+            backend.notify_new(created, &self.key);
+            backend.notify(NOTIFY_STRING, "pfadd", &self.key);
+        }
+        Ok(RespFrame::Integer(changed as i64))
+    }
+}
+
+impl CommandExecutor for PfCount {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        // a single key caches its cardinality, a union is computed on the fly
+        let card = match self.keys.as_slice() {
+            [key] => backend.pfcount(key),
+            keys => backend.pfcount_union(keys),
+        }
+        .map_err(CommandError::InvalidArgument)?;
+        Ok(RespFrame::Integer(card as i64))
+    }
+}
+
+impl CommandExecutor for PfMerge {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let created = !backend.map.contains_key(&self.dest);
+        backend
+            .pfmerge(&self.dest, &self.sources)
+            .map_err(CommandError::InvalidArgument)?;
+        // like redis, the merge is notified as an add to the destination
+        backend.notify_new(created, &self.dest);
+        backend.notify(NOTIFY_STRING, "pfadd", &self.dest);
+        Ok(RESP_OK.clone())
+    }
+}
+
+impl TryFrom<RespArray> for PfAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["pfadd"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let elements = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(PfAdd { key, elements })
+    }
+}
+
+impl TryFrom<RespArray> for PfCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["pfcount"], 1)?;
+        let keys = extract_strings(extract_args(value, 1)?.into_iter())?;
+        Ok(PfCount { keys })
+    }
+}
+
+impl TryFrom<RespArray> for PfMerge {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["pfmerge"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let dest = extract_string(args.next())?;
+        let sources = extract_strings(args)?;
+        Ok(PfMerge { dest, sources })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::bulk_string::BulkString;
+    use crate::cmd::args;
+    use crate::cmd::{Get, Set};
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    #[test]
+    fn test_pfadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\npfadd\r\n$3\r\nhll\r\n$1\r\na\r\n$1\r\nb\r\n");
+        let cmd: PfAdd = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.key, "hll");
+        assert_eq!(cmd.elements, vec![b"a".to_vec(), b"b".to_vec()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pfadd_pfcount_pfmerge_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: PfAdd = args(&["pfadd", "a", "x", "y", "z"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+        let cmd: PfAdd = args(&["pfadd", "a", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(0));
+        let cmd: PfAdd = args(&["pfadd", "b", "z", "w"]).try_into()?;
+        cmd.execute(&backend)?;
+
+        let cmd: PfCount = args(&["pfcount", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(3));
+        let cmd: PfCount = args(&["pfcount", "a", "b", "missing"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(4));
+        let cmd: PfMerge = args(&["pfmerge", "c", "a", "b"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RESP_OK.clone());
+        let cmd: PfCount = args(&["pfcount", "c"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(4));
+
+        // the raw string round-trips through GET and SET
+        let value = Get::try_from(args(&["get", "c"]))?.execute(&backend)?;
+        let RespFrame::BulkString(bytes) = value.clone() else {
+            panic!("expected a bulk string");
+        };
+        assert!(bytes.starts_with(b"HYLL"));
+        let cmd = Set::try_from(RespArray::new([
+            BulkString::new("set").into(),
+            BulkString::new("d").into(),
+            value,
+        ]))?;
+        cmd.execute(&backend)?;
+        let cmd: PfCount = args(&["pfcount", "d"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(4));
+
+        Set::try_from(args(&["set", "e", "plain"]))?.execute(&backend)?;
+        let cmd: PfAdd = args(&["pfadd", "e", "x"]).try_into()?;
+        assert!(cmd.execute(&backend).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,972 @@
+use dashmap::mapref::entry::Entry;
+use serde_json::{Map, Number, Value};
+
+use crate::array::RespArray;
+use crate::backend::{json_get_mut, json_remove, json_type, Backend, JsonPath, NOTIFY_MODULE};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_int, extract_string, notify_miss, validate_command,
+    validate_command_at_least, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::null::RespNull;
+use crate::simple_string::SimpleString;
+
+#[derive(Debug)]
+pub struct JsonSet {
+    key: String,
+    path: PathArg,
+    value: Value,
+    nx: bool,
+    xx: bool,
+}
+
+#[derive(Debug)]
+pub struct JsonGet {
+    key: String,
+    paths: Vec<PathArg>,
+}
+
+#[derive(Debug)]
+pub struct JsonMGet {
+    keys: Vec<String>,
+    path: PathArg,
+}
+
+#[derive(Debug)]
+pub struct JsonDel {
+    key: String,
+    path: PathArg,
+}
+
+#[derive(Debug)]
+pub struct JsonType {
+    key: String,
+    path: PathArg,
+}
+
+#[derive(Debug)]
+pub struct JsonNumIncrBy {
+    key: String,
+    path: PathArg,
+    incr: Number,
+}
+
+#[derive(Debug)]
+pub struct JsonStrAppend {
+    key: String,
+    path: PathArg,
+    value: String,
+}
+
+#[derive(Debug)]
+pub struct JsonArrAppend {
+    key: String,
+    path: PathArg,
+    values: Vec<Value>,
+}
+
+#[derive(Debug)]
+pub struct JsonArrInsert {
+    key: String,
+    path: PathArg,
+    index: i64,
+    values: Vec<Value>,
+}
+
+#[derive(Debug)]
+pub struct JsonArrPop {
+    key: String,
+    path: PathArg,
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct JsonArrLen {
+    key: String,
+    path: PathArg,
+}
+
+#[derive(Debug)]
+pub struct JsonObjKeys {
+    key: String,
+    path: PathArg,
+}
+
+#[derive(Debug)]
+pub struct JsonResp {
+    key: String,
+    path: PathArg,
+}
+
+/// A path argument along with how it was spelled, for replies and errors.
+#[derive(Debug)]
+struct PathArg {
+    text: String,
+    path: JsonPath,
+}
+
+impl CommandExecutor for JsonSet {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let key = self.key.clone();
+        let created = !backend.json.contains_key(&key);
+        if !self.write(backend)? {
+            return Ok(RespFrame::Null(RespNull));
+        }
+        backend.notify_new(created, &key);
+        backend.notify(NOTIFY_MODULE, "json.set", &key);
+        Ok(RESP_OK.clone())
+    }
+}
+
+impl JsonSet {
+    /// Sets the value, `false` when NX or XX left the document untouched.
+    fn write(self, backend: &Backend) -> Result<bool, CommandError> {
+        let path = &self.path.path;
+        let mut entry = match backend.json.entry(self.key) {
+            Entry::Vacant(_) if !path.is_root() => {
+                return Err(CommandError::InvalidArgument(
+                    "new objects must be created at the root".to_string(),
+                ))
+            }
+            Entry::Vacant(_) if self.xx => return Ok(false),
+            Entry::Vacant(entry) => {
+                entry.insert(self.value);
+                return Ok(true);
+            }
+            Entry::Occupied(entry) => entry,
+        };
+        let doc = entry.get_mut();
+
+        let locations = path.locate(doc);
+        if !locations.is_empty() {
+            if self.nx {
+                return Ok(false);
+            }
+            for location in locations {
+                if let Some(value) = json_get_mut(doc, &location) {
+                    *value = self.value.clone();
+                }
+            }
+            return Ok(true);
+        }
+
+        // a missing member is added to every object its parent path matches
+        let mut added = false;
+        if let Some((parent, key)) = path.split_last_key().filter(|_| !self.xx) {
+            for location in parent.locate(doc) {
+                if let Some(Value::Object(object)) = json_get_mut(doc, &location) {
+                    object.insert(key.to_string(), self.value.clone());
+                    added = true;
+                }
+            }
+        }
+        match added {
+            true => Ok(true),
+            false if path.is_legacy() && !self.xx => Err(no_such_path(&self.path)),
+            false => Ok(false),
+        }
+    }
+}
+
+impl CommandExecutor for JsonGet {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let Some(doc) = backend.json.get(&self.key) else {
+            notify_miss(backend, &self.key, false);
+            return Ok(RespFrame::Null(RespNull));
+        };
+        let reply = match self.paths.as_slice() {
+            [] => doc.value().clone(),
+            [path] => select_json(&doc, path)?,
+            paths => {
+                // with several paths the reply is keyed by path, all JSONPath style if any is
+                let legacy = paths.iter().all(|path| path.path.is_legacy());
+                let mut object = Map::new();
+                for path in paths {
+                    let values = match legacy {
+                        true => select_json(&doc, path)?,
+                        false => {
+                            Value::Array(path.path.select(&doc).into_iter().cloned().collect())
+                        }
+                    };
+                    object.insert(path.text.clone(), values);
+                }
+                Value::Object(object)
+            }
+        };
+        Ok(json_reply(&reply))
+    }
+}
+
+impl CommandExecutor for JsonMGet {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let values = self
+            .keys
+            .iter()
+            .map(|key| {
+                backend
+                    .json
+                    .get(key)
+                    .and_then(|doc| select_json(&doc, &self.path).ok())
+                    .map_or(RespFrame::Null(RespNull), |value| json_reply(&value))
+            })
+            .collect::<Vec<_>>();
+        Ok(RespArray::new(values).into())
+    }
+}
+
+impl CommandExecutor for JsonDel {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let deleted = if self.path.path.is_root() {
+            backend.json.remove(&self.key).is_some() as usize
+        } else {
+            let Some(mut doc) = backend.json.get_mut(&self.key) else {
+                return Ok(RespFrame::Integer(0));
+            };
+            // remove from the back so that earlier array indexes stay valid
+            let mut locations = self.path.path.locate(&doc);
+            locations.sort_unstable_by(|a, b| b.cmp(a));
+            locations
+                .iter()
+                .filter(|location| json_remove(&mut doc, location).is_some())
+                .count()
+        };
+        if deleted > 0 {
+            backend.notify(NOTIFY_MODULE, "json.del", &self.key);
+        }
+        Ok(RespFrame::Integer(deleted as i64))
+    }
+}
+
+impl CommandExecutor for JsonType {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let Some(doc) = backend.json.get(&self.key) else {
+            return Ok(RespFrame::Null(RespNull));
+        };
+        let types = self.path.path.select(&doc).into_iter().map(json_type);
+        Ok(match self.path.path.is_legacy() {
+            true => types.map_or_null(|t| SimpleString::new(t).into()),
+            false => RespArray::new(
+                types
+                    .map(|t| SimpleString::new(t).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+        })
+    }
+}
+
+impl CommandExecutor for JsonNumIncrBy {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let results = update(
+            backend,
+            &self.key,
+            &self.path,
+            "json.numincrby",
+            "a number",
+            |value| {
+                let Value::Number(n) = value else {
+                    return Ok(None);
+                };
+                let sum = match (n.as_i64(), self.incr.as_i64()) {
+                    (Some(a), Some(b)) if a.checked_add(b).is_some() => Number::from(a + b),
+                    _ => n
+                        .as_f64()
+                        .zip(self.incr.as_f64())
+                        .and_then(|(a, b)| Number::from_f64(a + b))
+                        .ok_or_else(|| {
+                            CommandError::InvalidArgument("result is not a number".to_string())
+                        })?,
+                };
+                *n = sum;
+                Ok(Some(value.clone()))
+            },
+        )?;
+        let reply = match self.path.path.is_legacy() {
+            true => results.into_iter().flatten().next().unwrap_or_default(),
+            false => Value::Array(results.into_iter().map(Option::unwrap_or_default).collect()),
+        };
+        Ok(json_reply(&reply))
+    }
+}
+
+impl CommandExecutor for JsonStrAppend {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let results = update(
+            backend,
+            &self.key,
+            &self.path,
+            "json.strappend",
+            "a string",
+            |value| {
+                Ok(match value {
+                    Value::String(s) => {
+                        s.push_str(&self.value);
+                        Some(s.len())
+                    }
+                    _ => None,
+                })
+            },
+        )?;
+        Ok(integers_reply(&self.path, results))
+    }
+}
+
+impl CommandExecutor for JsonArrAppend {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let results = update(
+            backend,
+            &self.key,
+            &self.path,
+            "json.arrappend",
+            "an array",
+            |value| {
+                Ok(match value {
+                    Value::Array(array) => {
+                        array.extend(self.values.iter().cloned());
+                        Some(array.len())
+                    }
+                    _ => None,
+                })
+            },
+        )?;
+        Ok(integers_reply(&self.path, results))
+    }
+}
+
+impl CommandExecutor for JsonArrInsert {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let results = update(
+            backend,
+            &self.key,
+            &self.path,
+            "json.arrinsert",
+            "an array",
+            |value| {
+                let Value::Array(array) = value else {
+                    return Ok(None);
+                };
+                let len = array.len() as i64;
+                let index = if self.index < 0 {
+                    len + self.index
+                } else {
+                    self.index
+                };
+                if !(0..=len).contains(&index) {
+                    return Err(CommandError::InvalidArgument(
+                        "index out of bounds".to_string(),
+                    ));
+                }
+                let index = index as usize;
+                array.splice(index..index, self.values.iter().cloned());
+                Ok(Some(array.len()))
+            },
+        )?;
+        Ok(integers_reply(&self.path, results))
+    }
+}
+
+impl CommandExecutor for JsonArrPop {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let results = update(
+            backend,
+            &self.key,
+            &self.path,
+            "json.arrpop",
+            "an array",
+            |value| {
+                let Value::Array(array) = value else {
+                    return Ok(None);
+                };
+                if array.is_empty() {
+                    return Ok(Some(None));
+                }
+                // out of range indexes pop the first or last element
+                let len = array.len() as i64;
+                let index = if self.index < 0 {
+                    len + self.index
+                } else {
+                    self.index
+                };
+                Ok(Some(Some(array.remove(index.clamp(0, len - 1) as usize))))
+            },
+        )?;
+        let popped =
+            |value: Option<Value>| value.map_or(RespFrame::Null(RespNull), |v| json_reply(&v));
+        Ok(match self.path.path.is_legacy() {
+            true => results.into_iter().flatten().map_or_null(popped),
+            false => RespArray::new(
+                results
+                    .into_iter()
+                    .map(|value| popped(value.flatten()))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+        })
+    }
+}
+
+impl CommandExecutor for JsonArrLen {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let Some(results) = read(backend, &self.key, &self.path, "an array", |value| {
+            value.as_array().map(Vec::len)
+        })?
+        else {
+            return Ok(RespFrame::Null(RespNull));
+        };
+        Ok(integers_reply(&self.path, results))
+    }
+}
+
+impl CommandExecutor for JsonObjKeys {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let Some(results) = read(backend, &self.key, &self.path, "an object", |value| {
+            value.as_object().map(|object| {
+                RespFrame::from(RespArray::new(
+                    object
+                        .keys()
+                        .map(|key| BulkString::new(key.as_str()).into())
+                        .collect::<Vec<RespFrame>>(),
+                ))
+            })
+        })?
+        else {
+            return Ok(RespFrame::Null(RespNull));
+        };
+        Ok(frames_reply(&self.path, results))
+    }
+}
+
+impl CommandExecutor for JsonResp {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let Some(results) = read(backend, &self.key, &self.path, "a value", |value| {
+            Some(RespFrame::from(value))
+        })?
+        else {
+            return Ok(RespFrame::Null(RespNull));
+        };
+        Ok(frames_reply(&self.path, results))
+    }
+}
+
+/// The document text GET style replies carry.
+fn json_reply(value: &Value) -> RespFrame {
+    BulkString::new(value.to_string()).into()
+}
+
+/// What `path` selects from `doc` as one JSON value: the first match for a legacy path, which
+/// must exist, or the array of all matches.
+fn select_json(doc: &Value, path: &PathArg) -> Result<Value, CommandError> {
+    let values = path.path.select(doc);
+    match path.path.is_legacy() {
+        true => values
+            .first()
+            .map(|value| (*value).clone())
+            .ok_or_else(|| no_such_path(path)),
+        false => Ok(Value::Array(values.into_iter().cloned().collect())),
+    }
+}
+
+/// Runs `op` on every value the path matches in the document at `key`, `None` when the key is
+/// missing. `op` returns `None` for values of the wrong type.
+fn read<T>(
+    backend: &Backend,
+    key: &str,
+    path: &PathArg,
+    expected: &str,
+    mut op: impl FnMut(&Value) -> Option<T>,
+) -> Result<Option<Vec<Option<T>>>, CommandError> {
+    let Some(doc) = backend.json.get(key) else {
+        return Ok(None);
+    };
+    let results = path
+        .path
+        .select(&doc)
+        .into_iter()
+        .map(|value| (json_type(value), op(value)))
+        .collect();
+    check_legacy(path, expected, results).map(Some)
+}
+
+/// Like [`read`] for commands that modify the values, which need the key to exist. `event` is
+/// notified once the update succeeded.
+fn update<T>(
+    backend: &Backend,
+    key: &str,
+    path: &PathArg,
+    event: &str,
+    expected: &str,
+    mut op: impl FnMut(&mut Value) -> Result<Option<T>, CommandError>,
+) -> Result<Vec<Option<T>>, CommandError> {
+    let results = {
+        let mut doc = backend.json.get_mut(key).ok_or_else(|| {
+            CommandError::InvalidArgument(
+                "could not perform this operation on a key that doesn't exist".to_string(),
+            )
+        })?;
+        let mut results = vec![];
+        for location in path.path.locate(&doc) {
+            if let Some(value) = json_get_mut(&mut doc, &location) {
+                results.push((json_type(value), op(value)?));
+            }
+        }
+        check_legacy(path, expected, results)?
+    };
+    backend.notify(NOTIFY_MODULE, event, key);
+    Ok(results)
+}
+
+/// A legacy path names a single value, so it must match one of the expected type.
+fn check_legacy<T>(
+    path: &PathArg,
+    expected: &str,
+    results: Vec<(&'static str, Option<T>)>,
+) -> Result<Vec<Option<T>>, CommandError> {
+    if path.path.is_legacy() {
+        match results.first() {
+            None => return Err(no_such_path(path)),
+            Some((found, None)) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "WRONGTYPE wrong type of path value - expected {} but found {}",
+                    expected, found
+                )))
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+fn integers_reply(path: &PathArg, results: Vec<Option<usize>>) -> RespFrame {
+    frames_reply(
+        path,
+        results
+            .into_iter()
+            .map(|n| n.map(|n| RespFrame::Integer(n as i64)))
+            .collect(),
+    )
+}
+
+/// The first result for a legacy path, otherwise all of them with nulls for mismatched types.
+fn frames_reply(path: &PathArg, results: Vec<Option<RespFrame>>) -> RespFrame {
+    match path.path.is_legacy() {
+        true => results.into_iter().flatten().map_or_null(|frame| frame),
+        false => RespArray::new(
+            results
+                .into_iter()
+                .map(|frame| frame.unwrap_or(RespFrame::Null(RespNull)))
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+    }
+}
+
+trait FirstOrNull: Iterator + Sized {
+    /// The first item mapped to a frame, or null.
+    fn map_or_null(mut self, f: impl FnOnce(Self::Item) -> RespFrame) -> RespFrame {
+        self.next().map_or(RespFrame::Null(RespNull), f)
+    }
+}
+
+impl<I: Iterator> FirstOrNull for I {}
+
+fn no_such_path(path: &PathArg) -> CommandError {
+    CommandError::InvalidArgument(format!("Path '{}' does not exist", path.text))
+}
+
+fn parse_path(arg: Option<RespFrame>) -> Result<PathArg, CommandError> {
+    let text = extract_string(arg)?;
+    let path = JsonPath::parse(&text).map_err(CommandError::InvalidArgument)?;
+    Ok(PathArg { text, path })
+}
+
+/// The path argument of commands where it is optional, the root by default.
+fn parse_optional_path(arg: Option<RespFrame>) -> Result<PathArg, CommandError> {
+    match arg {
+        Some(arg) => parse_path(Some(arg)),
+        None => parse_path(Some(BulkString::new("$").into())),
+    }
+}
+
+fn parse_json(arg: Option<RespFrame>) -> Result<Value, CommandError> {
+    let text = extract_string(arg)?;
+    serde_json::from_str(&text).map_err(|e| CommandError::InvalidArgument(e.to_string()))
+}
+
+fn parse_json_values(args: impl Iterator<Item = RespFrame>) -> Result<Vec<Value>, CommandError> {
+    args.map(|arg| parse_json(Some(arg))).collect()
+}
+
+impl TryFrom<RespArray> for JsonSet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["json.set"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let path = parse_path(args.next())?;
+        let value = parse_json(args.next())?;
+        let (mut nx, mut xx) = (false, false);
+        for arg in args {
+            match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                "NX" => nx = true,
+                "XX" => xx = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        if nx && xx {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(JsonSet {
+            key,
+            path,
+            value,
+            nx,
+            xx,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for JsonGet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["json.get"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let paths = args
+            .map(|arg| parse_path(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(JsonGet { key, paths })
+    }
+}
+
+impl TryFrom<RespArray> for JsonMGet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["json.mget"], 2)?;
+        let mut args = extract_args(value, 1)?;
+        let path = parse_path(args.pop())?;
+        let keys = args
+            .into_iter()
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(JsonMGet { keys, path })
+    }
+}
+
+/// Parses `key [path]`, the shape of most read commands.
+fn parse_key_path(value: RespArray, name: &'static str) -> Result<(String, PathArg), CommandError> {
+    validate_command_at_least(&value, &[name], 1)?;
+    if value.len() > 3 {
+        return Err(CommandError::InvalidArgument(format!(
+            "wrong number of arguments for '{}' command",
+            name
+        )));
+    }
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    Ok((key, parse_optional_path(args.next())?))
+}
+
+impl TryFrom<RespArray> for JsonDel {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, path) = parse_key_path(value, "json.del")?;
+        Ok(JsonDel { key, path })
+    }
+}
+
+impl TryFrom<RespArray> for JsonType {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, path) = parse_key_path(value, "json.type")?;
+        Ok(JsonType { key, path })
+    }
+}
+
+impl TryFrom<RespArray> for JsonNumIncrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["json.numincrby"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let path = parse_path(args.next())?;
+        let Ok(Value::Number(incr)) = parse_json(args.next()) else {
+            return Err(CommandError::InvalidArgument(
+                "value is not a number".to_string(),
+            ));
+        };
+        Ok(JsonNumIncrBy { key, path, incr })
+    }
+}
+
+impl TryFrom<RespArray> for JsonStrAppend {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["json.strappend"], 2)?;
+        if value.len() > 4 {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'json.strappend' command".to_string(),
+            ));
+        }
+        let mut args = extract_args(value, 1)?;
+        let Ok(Value::String(value)) = parse_json(args.pop()) else {
+            return Err(CommandError::InvalidArgument(
+                "value must be a JSON string".to_string(),
+            ));
+        };
+        let mut args = args.into_iter();
+        let key = extract_string(args.next())?;
+        let path = parse_optional_path(args.next())?;
+        Ok(JsonStrAppend { key, path, value })
+    }
+}
+
+impl TryFrom<RespArray> for JsonArrAppend {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["json.arrappend"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let path = parse_path(args.next())?;
+        let values = parse_json_values(args)?;
+        Ok(JsonArrAppend { key, path, values })
+    }
+}
+
+impl TryFrom<RespArray> for JsonArrInsert {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["json.arrinsert"], 4)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let path = parse_path(args.next())?;
+        let index = extract_int(args.next())?;
+        let values = parse_json_values(args)?;
+        Ok(JsonArrInsert {
+            key,
+            path,
+            index,
+            values,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for JsonArrPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["json.arrpop"], 1)?;
+        if value.len() > 4 {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'json.arrpop' command".to_string(),
+            ));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let path = parse_optional_path(args.next())?;
+        let index = match args.next() {
+            Some(arg) => extract_int(Some(arg))?,
+            None => -1,
+        };
+        Ok(JsonArrPop { key, path, index })
+    }
+}
+
+impl TryFrom<RespArray> for JsonArrLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, path) = parse_key_path(value, "json.arrlen")?;
+        Ok(JsonArrLen { key, path })
+    }
+}
+
+impl TryFrom<RespArray> for JsonObjKeys {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, path) = parse_key_path(value, "json.objkeys")?;
+        Ok(JsonObjKeys { key, path })
+    }
+}
+
+impl TryFrom<RespArray> for JsonResp {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, path) = parse_key_path(value, "json.resp")?;
+        Ok(JsonResp { key, path })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::{args, bulk};
+    use crate::decode::RespDecode;
+    use crate::map::RespMap;
+
+    use super::*;
+
+    fn run<T>(backend: &Backend, cmd: &[&str]) -> Result<RespFrame>
+    where
+        T: TryFrom<RespArray, Error = CommandError> + CommandExecutor,
+    {
+        Ok(T::try_from(args(cmd))?.execute(backend)?)
+    }
+
+    #[test]
+    fn test_json_set_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$8\r\njson.set\r\n$3\r\ndoc\r\n$3\r\n$.a\r\n$7\r\n[1,\"x\"]\r\n$2\r\nnx\r\n",
+        );
+        let cmd: JsonSet = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.key, "doc");
+        assert_eq!(cmd.path.text, "$.a");
+        assert_eq!(cmd.value, serde_json::json!([1, "x"]));
+        assert!(cmd.nx && !cmd.xx);
+
+        let cmd: Result<JsonSet, _> = args(&["json.set", "doc", "$", "{oops"]).try_into();
+        assert!(cmd.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_set_get_commands() -> Result<()> {
+        let backend = Backend::new();
+        assert!(run::<JsonSet>(&backend, &["json.set", "doc", "$.a", "1"]).is_err());
+        let doc = r#"{"a":1,"nested":{"a":"x","b":[1,2]}}"#;
+        run::<JsonSet>(&backend, &["json.set", "doc", "$", doc])?;
+        assert_eq!(run::<JsonGet>(&backend, &["json.get", "doc"])?, bulk(doc));
+
+        assert_eq!(
+            run::<JsonSet>(&backend, &["json.set", "doc", "$.c", "true", "xx"])?,
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            run::<JsonSet>(&backend, &["json.set", "doc", "$..a", "2", "xx"])?,
+            RESP_OK.clone()
+        );
+        run::<JsonSet>(&backend, &["json.set", "doc", "$.nested.c", "null"])?;
+        assert_eq!(
+            run::<JsonGet>(&backend, &["json.get", "doc", "$..a", "$.nested.c"])?,
+            bulk(r#"{"$..a":[2,2],"$.nested.c":[null]}"#)
+        );
+        assert_eq!(
+            run::<JsonGet>(&backend, &["json.get", "doc", ".nested.b[1]"])?,
+            bulk("2")
+        );
+        assert!(run::<JsonGet>(&backend, &["json.get", "doc", ".missing"]).is_err());
+        assert_eq!(
+            run::<JsonGet>(&backend, &["json.get", "doc", "$.missing"])?,
+            bulk("[]")
+        );
+
+        run::<JsonSet>(&backend, &["json.set", "other", ".", r#"{"a":"y"}"#])?;
+        assert_eq!(
+            run::<JsonMGet>(&backend, &["json.mget", "doc", "other", "none", "$.a"])?,
+            RespArray::new([bulk("[2]"), bulk(r#"["y"]"#), RespFrame::Null(RespNull)]).into()
+        );
+        assert_eq!(
+            run::<JsonType>(&backend, &["json.type", "doc", "$..a"])?,
+            RespArray::new([
+                SimpleString::new("integer").into(),
+                SimpleString::new("integer").into()
+            ])
+            .into()
+        );
+        assert_eq!(
+            run::<JsonDel>(&backend, &["json.del", "doc", "$..a"])?,
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            run::<JsonObjKeys>(&backend, &["json.objkeys", "doc", "."])?,
+            RespArray::new([bulk("nested")]).into()
+        );
+        assert_eq!(
+            run::<JsonDel>(&backend, &["json.del", "doc"])?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run::<JsonGet>(&backend, &["json.get", "doc"])?,
+            RespFrame::Null(RespNull)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_update_commands() -> Result<()> {
+        let backend = Backend::new();
+        let doc = r#"{"n":1,"f":1.5,"s":"ab","arr":[1,2],"obj":{"n":"x","arr":[]}}"#;
+        run::<JsonSet>(&backend, &["json.set", "doc", "$", doc])?;
+
+        assert_eq!(
+            run::<JsonNumIncrBy>(&backend, &["json.numincrby", "doc", "$..n", "2"])?,
+            bulk("[3,null]")
+        );
+        assert_eq!(
+            run::<JsonNumIncrBy>(&backend, &["json.numincrby", "doc", ".f", "1"])?,
+            bulk("2.5")
+        );
+        assert!(run::<JsonNumIncrBy>(&backend, &["json.numincrby", "doc", ".s", "1"]).is_err());
+
+        assert_eq!(
+            run::<JsonStrAppend>(&backend, &["json.strappend", "doc", ".s", r#""cd""#])?,
+            RespFrame::Integer(4)
+        );
+        assert_eq!(
+            run::<JsonArrAppend>(
+                &backend,
+                &["json.arrappend", "doc", "$..arr", "3", r#""x""#]
+            )?,
+            RespArray::new([RespFrame::Integer(4), RespFrame::Integer(2)]).into()
+        );
+        assert_eq!(
+            run::<JsonArrInsert>(&backend, &["json.arrinsert", "doc", ".arr", "-1", "0"])?,
+            RespFrame::Integer(5)
+        );
+        assert!(
+            run::<JsonArrInsert>(&backend, &["json.arrinsert", "doc", ".arr", "9", "0"]).is_err()
+        );
+        assert_eq!(
+            run::<JsonArrPop>(&backend, &["json.arrpop", "doc", ".arr"])?,
+            bulk(r#""x""#)
+        );
+        assert_eq!(
+            run::<JsonArrPop>(&backend, &["json.arrpop", "doc", "$.arr", "0"])?,
+            RespArray::new([bulk("1")]).into()
+        );
+        assert_eq!(
+            run::<JsonArrLen>(&backend, &["json.arrlen", "doc", "$..arr"])?,
+            RespArray::new([RespFrame::Integer(3), RespFrame::Integer(2)]).into()
+        );
+        assert_eq!(
+            run::<JsonGet>(&backend, &["json.get", "doc", "$.arr"])?,
+            bulk("[[2,3,0]]")
+        );
+
+        let mut obj = RespMap::new();
+        obj.insert(
+            "arr".to_string(),
+            RespArray::new([RespFrame::Integer(3), bulk("x")]).into(),
+        );
+        obj.insert("n".to_string(), bulk("x"));
+        assert_eq!(
+            run::<JsonResp>(&backend, &["json.resp", "doc", ".obj"])?,
+            obj.into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,1139 @@
+use std::time::Duration;
+
+use crate::array::{RespArray, RespNullArray};
+use crate::backend::{Backend, ListSide, RestoreFn, ServeFn, NOTIFY_LIST};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_count, extract_int, extract_string, extract_timeout, normalize_index,
+    normalize_range, notify_miss, validate_command, validate_command_at_least, CommandError,
+    CommandExecutor, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::null::RespNull;
+
+#[derive(Debug)]
+pub struct LPush {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct RPush {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct LPushX {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct RPushX {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct LPop {
+    key: String,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct RPop {
+    key: String,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct LRange {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct LIndex {
+    key: String,
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct LSet {
+    key: String,
+    index: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LInsert {
+    key: String,
+    before: bool,
+    pivot: RespFrame,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LRem {
+    key: String,
+    count: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LTrim {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LPos {
+    key: String,
+    value: RespFrame,
+    rank: i64,
+    count: Option<usize>,
+    max_len: usize,
+}
+
+#[derive(Debug)]
+pub struct LMove {
+    src: String,
+    dst: String,
+    from: ListSide,
+    to: ListSide,
+}
+
+#[derive(Debug)]
+pub struct LMPop {
+    keys: Vec<String>,
+    side: ListSide,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct BLPop {
+    keys: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BRPop {
+    keys: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BLMove {
+    src: String,
+    dst: String,
+    from: ListSide,
+    to: ListSide,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BLMPop {
+    keys: Vec<String>,
+    side: ListSide,
+    count: usize,
+    timeout: Option<Duration>,
+}
+
+impl CommandExecutor for LPush {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let len = backend.list_push(&self.key, ListSide::Left, self.values);
+        Ok(RespFrame::Integer(len as i64))
+    }
+}
+
+impl CommandExecutor for RPush {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let len = backend.list_push(&self.key, ListSide::Right, self.values);
+        Ok(RespFrame::Integer(len as i64))
+    }
+}
+
+impl CommandExecutor for LPushX {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let len = backend.list_push_existing(&self.key, ListSide::Left, self.values);
+        Ok(RespFrame::Integer(len as i64))
+    }
+}
+
+impl CommandExecutor for RPushX {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let len = backend.list_push_existing(&self.key, ListSide::Right, self.values);
+        Ok(RespFrame::Integer(len as i64))
+    }
+}
+
+impl CommandExecutor for LPop {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(pop_reply(backend, &self.key, ListSide::Left, self.count))
+    }
+}
+
+impl CommandExecutor for RPop {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(pop_reply(backend, &self.key, ListSide::Right, self.count))
+    }
+}
+
+impl CommandExecutor for LRange {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let ret = match backend.list.get(&self.key) {
+            Some(list) => match normalize_range(self.start, self.stop, list.len()) {
+                Some((start, stop)) => list.range(start..=stop).cloned().collect(),
+                None => vec![],
+            },
+            None => {
+                notify_miss(backend, &self.key, false);
+                vec![]
+            }
+        };
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for LLen {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(RespFrame::Integer(backend.list_len(&self.key) as i64))
+    }
+}
+
+impl CommandExecutor for LIndex {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let value = match backend.list.get(&self.key) {
+            Some(list) => normalize_index(self.index, list.len()).map(|index| list[index].clone()),
+            None => {
+                notify_miss(backend, &self.key, false);
+                None
+            }
+        };
+        Ok(value.unwrap_or(RespFrame::Null(RespNull)))
+    }
+}
+
+impl CommandExecutor for LSet {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        {
+            let mut list = backend
+                .list
+                .get_mut(&self.key)
+                .ok_or_else(|| CommandError::InvalidArgument("no such key".to_string()))?;
+            let index = normalize_index(self.index, list.len())
+                .ok_or_else(|| CommandError::InvalidArgument("index out of range".to_string()))?;
+            list[index] = self.value;
+        }
+        backend.notify(NOTIFY_LIST, "lset", &self.key);
+        Ok(RESP_OK.clone())
+    }
+}
+
+impl CommandExecutor for LInsert {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let len = {
+            let Some(mut list) = backend.list.get_mut(&self.key) else {
+                return Ok(RespFrame::Integer(0));
+            };
+            let Some(pos) = list.iter().position(|v| v == &self.pivot) else {
+                return Ok(RespFrame::Integer(-1));
+            };
+            let pos = if self.before { pos } else { pos + 1 };
+            list.insert(pos, self.value);
+            list.len()
+        };
+        backend.notify(NOTIFY_LIST, "linsert", &self.key);
+        Ok(RespFrame::Integer(len as i64))
+    }
+}
+
+impl CommandExecutor for LRem {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let removed = {
+            let Some(mut list) = backend.list.get_mut(&self.key) else {
+                return Ok(RespFrame::Integer(0));
+            };
+            let limit = match self.count {
+                0 => usize::MAX,
+                n => n.unsigned_abs() as usize,
+            };
+            let mut positions: Vec<usize> = list
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| *v == &self.value)
+                .map(|(i, _)| i)
+                .collect();
+            if self.count < 0 {
+                positions.reverse();
+            }
+            positions.truncate(limit);
+            positions.sort_unstable();
+            for pos in positions.iter().rev() {
+                list.remove(*pos);
+            }
+            positions.len()
+        };
+        if removed > 0 {
+            backend.notify(NOTIFY_LIST, "lrem", &self.key);
+        }
+        backend.list_remove_if_empty(&self.key);
+        Ok(RespFrame::Integer(removed as i64))
+    }
+}
+
+impl CommandExecutor for LTrim {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let trimmed = match backend.list.get_mut(&self.key) {
+            Some(mut list) => {
+                match normalize_range(self.start, self.stop, list.len()) {
+                    Some((start, stop)) => {
+                        list.truncate(stop + 1);
+                        list.drain(..start);
+                    }
+                    None => list.clear(),
+                }
+                true
+            }
+            None => false,
+        };
+        if trimmed {
+            backend.notify(NOTIFY_LIST, "ltrim", &self.key);
+        }
+        backend.list_remove_if_empty(&self.key);
+        Ok(RESP_OK.clone())
+    }
+}
+
+impl CommandExecutor for LPos {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let matches = match backend.list.get(&self.key) {
+            Some(list) => {
+                let len = list.len();
+                let scan = if self.max_len == 0 {
+                    len
+                } else {
+                    self.max_len.min(len)
+                };
+                let skip = self.rank.unsigned_abs() as usize - 1;
+                let limit = match self.count {
+                    Some(0) => usize::MAX,
+                    Some(n) => n,
+                    None => 1,
+                };
+                let indexes: Box<dyn Iterator<Item = usize>> = if self.rank > 0 {
+                    Box::new(0..scan)
+                } else {
+                    Box::new((len - scan..len).rev())
+                };
+                indexes
+                    .filter(|i| list[*i] == self.value)
+                    .skip(skip)
+                    .take(limit)
+                    .map(|i| RespFrame::Integer(i as i64))
+                    .collect()
+            }
+            None => vec![],
+        };
+
+        match self.count {
+            Some(_) => Ok(RespArray::new(matches).into()),
+            None => Ok(matches
+                .into_iter()
+                .next()
+                .unwrap_or(RespFrame::Null(RespNull))),
+        }
+    }
+}
+
+impl CommandExecutor for LMove {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(backend
+            .list_move(&self.src, &self.dst, self.from, self.to)
+            .unwrap_or(RespFrame::Null(RespNull)))
+    }
+}
+
+impl CommandExecutor for LMPop {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(
+            serve_first(backend, &self.keys, mpop_serve(self.side, self.count))
+                .unwrap_or_else(|| RespNullArray.into()),
+        )
+    }
+}
+
+// Outside of a connection that can park (e.g. inside a transaction) the blocking commands
+// behave like their non-blocking counterparts and time out right away.
+impl CommandExecutor for BLPop {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(serve_first(backend, &self.keys, pop_serve(ListSide::Left))
+            .unwrap_or_else(|| RespNullArray.into()))
+    }
+}
+
+impl CommandExecutor for BRPop {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(serve_first(backend, &self.keys, pop_serve(ListSide::Right))
+            .unwrap_or_else(|| RespNullArray.into()))
+    }
+}
+
+impl CommandExecutor for BLMove {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(backend
+            .list_move(&self.src, &self.dst, self.from, self.to)
+            .unwrap_or(RespFrame::Null(RespNull)))
+    }
+}
+
+impl CommandExecutor for BLMPop {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(
+            serve_first(backend, &self.keys, mpop_serve(self.side, self.count))
+                .unwrap_or_else(|| RespNullArray.into()),
+        )
+    }
+}
+
+impl BLPop {
+    pub async fn block(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let reply = backend
+            .block_on(
+                self.keys,
+                self.timeout,
+                pop_serve(ListSide::Left),
+                Some(pop_restore(ListSide::Left)),
+            )
+            .await;
+        Ok(reply.unwrap_or_else(|| RespNullArray.into()))
+    }
+}
+
+impl BRPop {
+    pub async fn block(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let reply = backend
+            .block_on(
+                self.keys,
+                self.timeout,
+                pop_serve(ListSide::Right),
+                Some(pop_restore(ListSide::Right)),
+            )
+            .await;
+        Ok(reply.unwrap_or_else(|| RespNullArray.into()))
+    }
+}
+
+impl BLMove {
+    pub async fn block(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let (dst, from, to) = (self.dst, self.from, self.to);
+        let serve: ServeFn = Box::new(move |backend, key| backend.list_move(key, &dst, from, to));
+        // the element already sits in dst when the reply is lost, nothing to restore
+        let reply = backend
+            .block_on(vec![self.src], self.timeout, serve, None)
+            .await;
+        Ok(reply.unwrap_or(RespFrame::Null(RespNull)))
+    }
+}
+
+impl BLMPop {
+    pub async fn block(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let serve = mpop_serve(self.side, self.count);
+        let restore = mpop_restore(self.side);
+        let reply = backend
+            .block_on(self.keys, self.timeout, serve, Some(restore))
+            .await;
+        Ok(reply.unwrap_or_else(|| RespNullArray.into()))
+    }
+}
+
+fn serve_first(backend: &Backend, keys: &[String], serve: ServeFn) -> Option<RespFrame> {
+    keys.iter().find_map(|key| serve(backend, key))
+}
+
+fn pop_serve(side: ListSide) -> ServeFn {
+    Box::new(move |backend, key| {
+        let value = backend.list_pop(key, side, 1)?.pop()?;
+        Some(RespArray::new([BulkString::from(key).into(), value]).into())
+    })
+}
+
+fn mpop_serve(side: ListSide, count: usize) -> ServeFn {
+    Box::new(move |backend, key| {
+        let values = backend.list_pop(key, side, count)?;
+        Some(RespArray::new([BulkString::from(key).into(), RespArray::new(values).into()]).into())
+    })
+}
+
+/// Pushes the element of a [`pop_serve`] reply back where it was popped from.
+fn pop_restore(side: ListSide) -> RestoreFn {
+    Box::new(move |backend, key, reply| {
+        if let RespFrame::Array(RespArray(mut reply)) = reply {
+            if let Some(value) = reply.pop() {
+                backend.list_push(key, side, vec![value]);
+            }
+        }
+    })
+}
+
+fn mpop_restore(side: ListSide) -> RestoreFn {
+    Box::new(move |backend, key, reply| {
+        if let RespFrame::Array(RespArray(mut reply)) = reply {
+            if let Some(RespFrame::Array(RespArray(mut values))) = reply.pop() {
+                // pushed one by one, the first popped has to go last
+                values.reverse();
+                backend.list_push(key, side, values);
+            }
+        }
+    })
+}
+
+fn pop_reply(backend: &Backend, key: &str, side: ListSide, count: Option<usize>) -> RespFrame {
+    match (backend.list_pop(key, side, count.unwrap_or(1)), count) {
+        (Some(values), Some(_)) => RespArray::new(values).into(),
+        (Some(mut values), None) => values.pop().unwrap_or(RespFrame::Null(RespNull)),
+        (None, Some(_)) => RespNullArray.into(),
+        (None, None) => RespFrame::Null(RespNull),
+    }
+}
+
+pub(crate) fn parse_side(arg: Option<RespFrame>) -> Result<ListSide, CommandError> {
+    match extract_string(arg)?.to_ascii_uppercase().as_str() {
+        "LEFT" => Ok(ListSide::Left),
+        "RIGHT" => Ok(ListSide::Right),
+        other => Err(CommandError::InvalidArgument(format!(
+            "expected LEFT or RIGHT but got {}",
+            other
+        ))),
+    }
+}
+
+fn parse_push(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(String, Vec<RespFrame>), CommandError> {
+    validate_command_at_least(&value, &[name], 2)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    Ok((key, args.collect()))
+}
+
+fn parse_pop(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(String, Option<usize>), CommandError> {
+    validate_command_at_least(&value, &[name], 1)?;
+    if value.len() > 3 {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have at most 2 argument",
+            name
+        )));
+    }
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    let count = args
+        .next()
+        .map(|arg| extract_count(Some(arg)))
+        .transpose()?;
+    Ok((key, count))
+}
+
+impl TryFrom<RespArray> for LPush {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value, "lpush")?;
+        Ok(LPush { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for RPush {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value, "rpush")?;
+        Ok(RPush { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for LPushX {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value, "lpushx")?;
+        Ok(LPushX { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for RPushX {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value, "rpushx")?;
+        Ok(RPushX { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for LPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_pop(value, "lpop")?;
+        Ok(LPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for RPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_pop(value, "rpop")?;
+        Ok(RPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for LRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lrange"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LRange {
+            key: extract_string(args.next())?,
+            start: extract_int(args.next())?,
+            stop: extract_int(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["llen"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LLen {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LIndex {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lindex"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LIndex {
+            key: extract_string(args.next())?,
+            index: extract_int(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LSet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lset"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (
+            extract_string(args.next())?,
+            extract_int(args.next())?,
+            args.next(),
+        ) {
+            (key, index, Some(value)) => Ok(LSet { key, index, value }),
+            _ => Err(CommandError::InvalidArgument("Invalid value".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LInsert {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["linsert"], 4)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let before = match extract_string(args.next())?.to_ascii_uppercase().as_str() {
+            "BEFORE" => true,
+            "AFTER" => false,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        match (args.next(), args.next()) {
+            (Some(pivot), Some(value)) => Ok(LInsert {
+                key,
+                before,
+                pivot,
+                value,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid pivot or value".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lrem"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (
+            extract_string(args.next())?,
+            extract_int(args.next())?,
+            args.next(),
+        ) {
+            (key, count, Some(value)) => Ok(LRem { key, count, value }),
+            _ => Err(CommandError::InvalidArgument("Invalid value".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LTrim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ltrim"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LTrim {
+            key: extract_string(args.next())?,
+            start: extract_int(args.next())?,
+            stop: extract_int(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LPos {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["lpos"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let value = args
+            .next()
+            .ok_or_else(|| CommandError::InvalidArgument("Invalid value".to_string()))?;
+        let mut cmd = LPos {
+            key,
+            value,
+            rank: 1,
+            count: None,
+            max_len: 0,
+        };
+        while let Some(option) = args.next() {
+            match extract_string(Some(option))?.to_ascii_uppercase().as_str() {
+                "RANK" => {
+                    cmd.rank = extract_int(args.next())?;
+                    if cmd.rank == 0 {
+                        return Err(CommandError::InvalidArgument(
+                            "RANK can't be zero".to_string(),
+                        ));
+                    }
+                }
+                "COUNT" => cmd.count = Some(extract_count(args.next())?),
+                "MAXLEN" => cmd.max_len = extract_count(args.next())?,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+impl TryFrom<RespArray> for LMove {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lmove"], 4)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LMove {
+            src: extract_string(args.next())?,
+            dst: extract_string(args.next())?,
+            from: parse_side(args.next())?,
+            to: parse_side(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["lmpop"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (keys, side, count) = parse_mpop_args(&mut args)?;
+        Ok(LMPop { keys, side, count })
+    }
+}
+
+fn parse_blocking_pop(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<String>, Option<Duration>), CommandError> {
+    validate_command_at_least(&value, &[name], 2)?;
+    let mut args = extract_args(value, 1)?;
+    let timeout = extract_timeout(args.pop())?;
+    let keys = args
+        .into_iter()
+        .map(|arg| extract_string(Some(arg)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((keys, timeout))
+}
+
+impl TryFrom<RespArray> for BLPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = parse_blocking_pop(value, "blpop")?;
+        Ok(BLPop { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BRPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = parse_blocking_pop(value, "brpop")?;
+        Ok(BRPop { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BLMove {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["blmove"], 5)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(BLMove {
+            src: extract_string(args.next())?,
+            dst: extract_string(args.next())?,
+            from: parse_side(args.next())?,
+            to: parse_side(args.next())?,
+            timeout: extract_timeout(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BLMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["blmpop"], 4)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let timeout = extract_timeout(args.next())?;
+        let (keys, side, count) = parse_mpop_args(&mut args)?;
+        Ok(BLMPop {
+            keys,
+            side,
+            count,
+            timeout,
+        })
+    }
+}
+
+/// Parses the `numkeys key [key ...] LEFT|RIGHT [COUNT count]` tail shared by LMPOP and BLMPOP.
+pub(crate) fn parse_mpop_args(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<(Vec<String>, ListSide, usize), CommandError> {
+    let num_keys = extract_count(args.next())?;
+    if num_keys == 0 {
+        return Err(CommandError::InvalidArgument(
+            "numkeys should be greater than 0".to_string(),
+        ));
+    }
+    let keys = (0..num_keys)
+        .map(|_| extract_string(args.next()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let side = parse_side(args.next())?;
+    let count = match args.next() {
+        Some(option) => match extract_string(Some(option))?.to_ascii_uppercase().as_str() {
+            "COUNT" => match extract_count(args.next())? {
+                0 => {
+                    return Err(CommandError::InvalidArgument(
+                        "count should be greater than 0".to_string(),
+                    ))
+                }
+                count => count,
+            },
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        },
+        None => 1,
+    };
+    if args.next().is_some() {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+    Ok((keys, side, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::bulk_array;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    #[test]
+    fn test_lpush_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nlpush\r\n$4\r\nlist\r\n$1\r\na\r\n$1\r\nb\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: LPush = frame.try_into()?;
+        assert_eq!(result.key, "list");
+        assert_eq!(result.values, vec![b"a".into(), b"b".into()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmpop_count_zero() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$5\r\nlmpop\r\n$1\r\n1\r\n$4\r\nlist\r\n$4\r\nleft\r\n$5\r\ncount\r\n$1\r\n0\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let err = LMPop::try_from(frame).unwrap_err();
+        assert!(err.to_string().contains("count should be greater than 0"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lpos_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$4\r\nlpos\r\n$4\r\nlist\r\n$1\r\na\r\n$4\r\nrank\r\n$2\r\n-1\r\n$5\r\nCOUNT\r\n$1\r\n0\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: LPos = frame.try_into()?;
+        assert_eq!(result.rank, -1);
+        assert_eq!(result.count, Some(0));
+        assert_eq!(result.max_len, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_push_pop_range_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = RPush {
+            key: "list".to_string(),
+            values: vec![b"b".into(), b"c".into()],
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(2));
+        let cmd = LPush {
+            key: "list".to_string(),
+            values: vec![b"a".into(), b"z".into()],
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(4));
+
+        let cmd = LRange {
+            key: "list".to_string(),
+            start: 0,
+            stop: -1,
+        };
+        assert_eq!(cmd.execute(&backend)?, bulk_array(["z", "a", "b", "c"]));
+
+        let cmd = RPop {
+            key: "list".to_string(),
+            count: Some(2),
+        };
+        assert_eq!(cmd.execute(&backend)?, bulk_array(["c", "b"]));
+
+        let cmd = LPop {
+            key: "list".to_string(),
+            count: Some(5),
+        };
+        assert_eq!(cmd.execute(&backend)?, bulk_array(["z", "a"]));
+        assert!(!backend.list.contains_key("list"));
+
+        let cmd = LPop {
+            key: "list".to_string(),
+            count: None,
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Null(RespNull));
+
+        let cmd = RPushX {
+            key: "list".to_string(),
+            values: vec![b"a".into()],
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lrem_ltrim_lpos_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.list_push(
+            "list",
+            ListSide::Right,
+            ["a", "b", "a", "c", "a"]
+                .iter()
+                .map(|v| BulkString::new(*v).into())
+                .collect(),
+        );
+
+        let cmd = LPos {
+            key: "list".to_string(),
+            value: b"a".into(),
+            rank: -1,
+            count: Some(2),
+            max_len: 0,
+        };
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([RespFrame::Integer(4), RespFrame::Integer(2)]).into()
+        );
+
+        let cmd = LRem {
+            key: "list".to_string(),
+            count: -2,
+            value: b"a".into(),
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(2));
+        let cmd = LRange {
+            key: "list".to_string(),
+            start: 0,
+            stop: -1,
+        };
+        assert_eq!(cmd.execute(&backend)?, bulk_array(["a", "b", "c"]));
+
+        let cmd = LTrim {
+            key: "list".to_string(),
+            start: 1,
+            stop: -1,
+        };
+        assert_eq!(cmd.execute(&backend)?, RESP_OK.clone());
+        let cmd = LIndex {
+            key: "list".to_string(),
+            index: -2,
+        };
+        assert_eq!(cmd.execute(&backend)?, b"b".into());
+
+        let cmd = LTrim {
+            key: "list".to_string(),
+            start: 5,
+            stop: 10,
+        };
+        cmd.execute(&backend)?;
+        assert!(!backend.list.contains_key("list"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_blpop_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nblpop\r\n$1\r\na\r\n$1\r\nb\r\n$3\r\n0.5\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: BLPop = frame.try_into()?;
+        assert_eq!(result.keys, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(result.timeout, Some(Duration::from_millis(500)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blmove_wakes_on_push() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = BLMove {
+            src: "src".to_string(),
+            dst: "dst".to_string(),
+            from: ListSide::Left,
+            to: ListSide::Right,
+            timeout: Some(Duration::from_secs(5)),
+        };
+        let handle = tokio::spawn({
+            let backend = backend.clone();
+            async move { cmd.block(&backend).await }
+        });
+        while backend.blocked_clients() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let cmd = LPush {
+            key: "src".to_string(),
+            values: vec![b"job".into()],
+        };
+        cmd.execute(&backend)?;
+        assert_eq!(handle.await??, b"job".into());
+        assert!(!backend.list.contains_key("src"));
+        assert_eq!(backend.list_len("dst"), 1);
+
+        let cmd = BRPop {
+            keys: vec!["src".to_string()],
+            timeout: Some(Duration::from_millis(10)),
+        };
+        assert_eq!(cmd.block(&backend).await?, RespNullArray.into());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blmpop_restores_on_disconnect() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = BLMPop {
+            keys: vec!["list".to_string()],
+            side: ListSide::Left,
+            count: 2,
+            timeout: None,
+        };
+        let handle = tokio::spawn({
+            let backend = backend.clone();
+            async move { cmd.block(&backend).await }
+        });
+        while backend.blocked_clients() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // the client is served but goes away before reading its reply
+        let cmd = RPush {
+            key: "list".to_string(),
+            values: vec![b"a".into(), b"b".into(), b"c".into()],
+        };
+        cmd.execute(&backend)?;
+        handle.abort();
+        assert!(handle.await.unwrap_err().is_cancelled());
+        let list = backend.list.get("list").unwrap().clone();
+        assert_eq!(list, [b"a".into(), b"b".into(), b"c".into()]);
+        assert_eq!(backend.blocked_clients(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmove_lmpop_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.list_push("src", ListSide::Right, vec![b"a".into(), b"b".into()]);
+
+        let cmd = LMove {
+            src: "src".to_string(),
+            dst: "dst".to_string(),
+            from: ListSide::Right,
+            to: ListSide::Left,
+        };
+        assert_eq!(cmd.execute(&backend)?, b"b".into());
+
+        let cmd = LMPop {
+            keys: vec!["missing".to_string(), "dst".to_string()],
+            side: ListSide::Left,
+            count: 10,
+        };
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([BulkString::new("dst").into(), bulk_array(["b"])]).into()
+        );
+
+        let cmd = LMPop {
+            keys: vec!["dst".to_string()],
+            side: ListSide::Left,
+            count: 1,
+        };
+        assert_eq!(cmd.execute(&backend)?, RespNullArray.into());
+        Ok(())
+    }
+}
@@ -1,19 +1,58 @@
 use crate::array::RespArray;
+use crate::backend::{NOTIFY_GENERIC, NOTIFY_STRING};
 use crate::cmd::{
-    extract_args, validate_command, CommandError, CommandExecutor, Get, Set, RESP_OK,
+    extract_args, extract_strings, notify_miss, validate_command, validate_command_at_least,
+    validate_flush_mode, CommandError, CommandExecutor, Del, FlushAll, FlushDb, Get, Set, RESP_OK,
 };
 use crate::frame::RespFrame;
 use crate::null::RespNull;
 
 impl CommandExecutor for Get {
     fn execute(self, backend: &crate::backend::Backend) -> Result<RespFrame, CommandError> {
-        Ok(backend.get(&self.key).unwrap_or(RespFrame::Null(RespNull)))
+        let value = backend.get(&self.key);
+        notify_miss(backend, &self.key, value.is_some());
+        Ok(value.unwrap_or(RespFrame::Null(RespNull)))
     }
 }
 
 impl CommandExecutor for Set {
     fn execute(self, backend: &crate::backend::Backend) -> Result<RespFrame, CommandError> {
+        let created = !backend.map.contains_key(&self.key);
         backend.set(&self.key, self.value);
+        backend.notify_new(created, &self.key);
+        backend.notify(NOTIFY_STRING, "set", &self.key);
+        Ok(RESP_OK.clone())
+    }
+}
+
+impl CommandExecutor for Del {
+    fn execute(self, backend: &crate::backend::Backend) -> Result<RespFrame, CommandError> {
+        let deleted = self
+            .keys
+            .iter()
+            .filter(|key| {
+                let deleted = backend.del(key);
+                if deleted {
+                    backend.notify(NOTIFY_GENERIC, "del", key);
+                }
+                deleted
+            })
+            .count();
+        Ok(RespFrame::Integer(deleted as i64))
+    }
+}
+
+impl CommandExecutor for FlushAll {
+    fn execute(self, backend: &crate::backend::Backend) -> Result<RespFrame, CommandError> {
+        backend.flush();
+        Ok(RESP_OK.clone())
+    }
+}
+
+// there is a single database, so FLUSHDB is FLUSHALL
+impl CommandExecutor for FlushDb {
+    fn execute(self, backend: &crate::backend::Backend) -> Result<RespFrame, CommandError> {
+        backend.flush();
         Ok(RESP_OK.clone())
     }
 }
@@ -54,13 +93,45 @@ impl TryFrom<RespArray> for Set {
     }
 }
 
+impl TryFrom<RespArray> for Del {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["del"], 1)?;
+        let args = extract_args(value, 1)?.into_iter();
+        Ok(Del {
+            keys: extract_strings(args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FlushAll {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["flushall"], 0)?;
+        validate_flush_mode(extract_args(value, 1)?.into_iter())?;
+        Ok(FlushAll)
+    }
+}
+
+impl TryFrom<RespArray> for FlushDb {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["flushdb"], 0)?;
+        validate_flush_mode(extract_args(value, 1)?.into_iter())?;
+        Ok(FlushDb)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
     use bytes::BytesMut;
 
     use crate::array::RespArray;
-    use crate::cmd::{CommandExecutor, Get, Set, RESP_OK};
+    use crate::cmd::{CommandError, CommandExecutor, Del, FlushAll, FlushDb, Get, Set, RESP_OK};
     use crate::decode::RespDecode;
     use crate::frame::RespFrame;
 
@@ -103,4 +174,41 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_del_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$3\r\ndel\r\n$5\r\nhello\r\n$4\r\nlist\r\n");
+        let cmd: Del = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.keys, ["hello", "list"]);
+
+        let backend = crate::backend::Backend::new();
+        backend.set("hello", RespFrame::BulkString(b"world".into()));
+        backend.list.insert("list".to_string(), Default::default());
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(2));
+        assert_eq!(backend.get("hello"), None);
+        assert!(!backend.list.contains_key("list"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_flushall_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$8\r\nflushall\r\n$5\r\nasync\r\n");
+        let cmd: FlushAll = RespArray::decode(&mut buf)?.try_into()?;
+
+        let backend = crate::backend::Backend::new();
+        backend.set("hello", RespFrame::BulkString(b"world".into()));
+        backend.list.insert("list".to_string(), Default::default());
+        backend.json.insert("doc".to_string(), Default::default());
+        assert_eq!(cmd.execute(&backend)?, RESP_OK.clone());
+        assert_eq!(backend.get("hello"), None);
+        assert!(backend.list.is_empty());
+        assert!(backend.json.is_empty());
+
+        buf.extend_from_slice(b"*2\r\n$7\r\nflushdb\r\n$4\r\nlazy\r\n");
+        let ret: Result<FlushDb, CommandError> = RespArray::decode(&mut buf)?.try_into();
+        assert!(ret.is_err());
+        Ok(())
+    }
 }
@@ -1,15 +1,98 @@
+use std::time::Duration;
+
 use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
 use thiserror::Error;
 
 use crate::array::RespArray;
-use crate::backend::Backend;
+use crate::backend::{Backend, NOTIFY_GENERIC, NOTIFY_KEY_MISS};
+use crate::bulk_string::BulkString;
 use crate::frame::RespFrame;
 use crate::simple_string::SimpleString;
 use crate::RespError;
 
+use registry::registered_command;
+
+mod bloom;
+mod cluster;
+mod cms;
+mod config;
+mod connection;
+mod cuckoo;
+mod function;
+mod geo;
 mod hmap;
+mod hyperloglog;
+mod json;
+mod list;
 mod map;
+mod plugin;
+mod pubsub;
+mod registry;
+mod scripting;
+mod search;
+mod set;
+mod stream;
+mod timeseries;
+mod topk;
+mod transaction;
+mod vectorset;
+mod zset;
+
+pub use bloom::{BfAdd, BfExists, BfInfo, BfMAdd, BfReserve};
+pub use cluster::Cluster;
+pub use cms::{CmsIncrBy, CmsInfo, CmsInitByDim, CmsInitByProb, CmsQuery};
+pub use config::Config;
+pub use connection::{Hello, Ping, Quit, Reset};
+pub use cuckoo::{CfAdd, CfDel, CfExists, CfInfo, CfReserve};
+pub use function::{load_functions, FCall, FCallRo, Function};
+pub use geo::{GeoAdd, GeoDist, GeoHash, GeoPos, GeoSearch, GeoSearchStore};
+pub use hmap::{
+    HDel, HExists, HExpire, HExpireAt, HExpireTime, HGetDel, HGetEx, HIncrBy, HIncrByFloat, HKeys,
+    HLen, HMGet, HPExpire, HPExpireAt, HPExpireTime, HPTtl, HPersist, HRandField, HScan, HSetEx,
+    HSetNx, HStrLen, HTtl, HVals,
+};
+pub use hyperloglog::{PfAdd, PfCount, PfMerge};
+pub use json::{
+    JsonArrAppend, JsonArrInsert, JsonArrLen, JsonArrPop, JsonDel, JsonGet, JsonMGet,
+    JsonNumIncrBy, JsonObjKeys, JsonResp, JsonSet, JsonStrAppend, JsonType,
+};
+pub use list::{
+    BLMPop, BLMove, BLPop, BRPop, LIndex, LInsert, LLen, LMPop, LMove, LPop, LPos, LPush, LPushX,
+    LRange, LRem, LSet, LTrim, RPop, RPush, RPushX,
+};
+pub use plugin::{load_plugins, Plugin, PluginCall};
+pub use pubsub::{
+    PSubscribe, PUnsubscribe, PubSub, Publish, SPublish, SSubscribe, SUnsubscribe, Subscribe,
+    Unsubscribe, SUBSCRIBED_COMMANDS,
+};
+pub use registry::{
+    register_command, registered_commands, unregister_command, CommandFlag, CommandSpec,
+    ExtensionCommand,
+};
+pub use scripting::{Eval, EvalRo, EvalSha, EvalShaRo, Script};
+pub use search::{FtAggregate, FtCreate, FtDropIndex, FtInfo, FtSearch};
+pub use set::{
+    SAdd, SCard, SDiff, SDiffStore, SInter, SInterCard, SInterStore, SIsMember, SMIsMember,
+    SMembers, SMove, SPop, SRandMember, SRem, SUnion, SUnionStore,
+};
+pub use stream::{
+    XAck, XAdd, XAutoClaim, XClaim, XDel, XGroup, XInfo, XLen, XPending, XRange, XRead, XReadGroup,
+    XRevRange, XTrim,
+};
+pub use timeseries::{
+    TsAdd, TsCreate, TsCreateRule, TsDecrBy, TsDeleteRule, TsGet, TsIncrBy, TsInfo, TsMAdd,
+    TsMRange, TsMRevRange, TsRange, TsRevRange,
+};
+pub use topk::{TopKAdd, TopKInfo, TopKList, TopKReserve};
+pub use transaction::{Discard, Exec, Multi, Transaction, Unwatch, Watch};
+pub use vectorset::{VAdd, VCard, VDim, VEmb, VGetAttr, VInfo, VRem, VSetAttr, VSim};
+pub use zset::{
+    BZMPop, BZPopMax, BZPopMin, ZAdd, ZCard, ZCount, ZDiff, ZDiffStore, ZIncrBy, ZInter,
+    ZInterStore, ZLexCount, ZMPop, ZMScore, ZPopMax, ZPopMin, ZRandMember, ZRange, ZRangeStore,
+    ZRank, ZRem, ZRemRangeByLex, ZRemRangeByRank, ZRemRangeByScore, ZRevRank, ZScore, ZUnion,
+    ZUnionStore,
+};
 
 lazy_static! {
     static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
@@ -37,12 +120,498 @@ pub trait CommandExecutor {
 pub enum Command {
     Get(Get),
     Set(Set),
+    Del(Del),
+    FlushAll(FlushAll),
+    FlushDb(FlushDb),
     HGet(HGet),
     HSet(HSet),
     HGetAll(HGetAll),
+    HDel(HDel),
+    HExists(HExists),
+    HLen(HLen),
+    HKeys(HKeys),
+    HVals(HVals),
+    HMGet(HMGet),
+    HSetNx(HSetNx),
+    HIncrBy(HIncrBy),
+    HIncrByFloat(HIncrByFloat),
+    HStrLen(HStrLen),
+    HRandField(HRandField),
+    HScan(HScan),
+    HExpire(HExpire),
+    HPExpire(HPExpire),
+    HExpireAt(HExpireAt),
+    HPExpireAt(HPExpireAt),
+    HTtl(HTtl),
+    HPTtl(HPTtl),
+    HExpireTime(HExpireTime),
+    HPExpireTime(HPExpireTime),
+    HPersist(HPersist),
+    HGetEx(HGetEx),
+    HSetEx(HSetEx),
+    HGetDel(HGetDel),
+    LPush(LPush),
+    RPush(RPush),
+    LPushX(LPushX),
+    RPushX(RPushX),
+    LPop(LPop),
+    RPop(RPop),
+    LRange(LRange),
+    LLen(LLen),
+    LIndex(LIndex),
+    LSet(LSet),
+    LInsert(LInsert),
+    LRem(LRem),
+    LTrim(LTrim),
+    LPos(LPos),
+    LMove(LMove),
+    LMPop(LMPop),
+    BLPop(BLPop),
+    BRPop(BRPop),
+    BLMove(BLMove),
+    BLMPop(BLMPop),
+    SAdd(SAdd),
+    SRem(SRem),
+    SMembers(SMembers),
+    SIsMember(SIsMember),
+    SMIsMember(SMIsMember),
+    SCard(SCard),
+    SPop(SPop),
+    SRandMember(SRandMember),
+    SMove(SMove),
+    SInter(SInter),
+    SUnion(SUnion),
+    SDiff(SDiff),
+    SInterStore(SInterStore),
+    SUnionStore(SUnionStore),
+    SDiffStore(SDiffStore),
+    SInterCard(SInterCard),
+    ZAdd(ZAdd),
+    ZIncrBy(ZIncrBy),
+    ZRem(ZRem),
+    ZScore(ZScore),
+    ZMScore(ZMScore),
+    ZRank(ZRank),
+    ZRevRank(ZRevRank),
+    ZCard(ZCard),
+    ZCount(ZCount),
+    ZLexCount(ZLexCount),
+    ZRange(ZRange),
+    ZRangeStore(ZRangeStore),
+    ZRemRangeByRank(ZRemRangeByRank),
+    ZRemRangeByScore(ZRemRangeByScore),
+    ZRemRangeByLex(ZRemRangeByLex),
+    ZPopMin(ZPopMin),
+    ZPopMax(ZPopMax),
+    ZRandMember(ZRandMember),
+    ZUnion(ZUnion),
+    ZInter(ZInter),
+    ZDiff(ZDiff),
+    ZUnionStore(ZUnionStore),
+    ZInterStore(ZInterStore),
+    ZDiffStore(ZDiffStore),
+    ZMPop(ZMPop),
+    BZPopMin(BZPopMin),
+    BZPopMax(BZPopMax),
+    BZMPop(BZMPop),
+    XAdd(XAdd),
+    XRange(XRange),
+    XRevRange(XRevRange),
+    XLen(XLen),
+    XDel(XDel),
+    XTrim(XTrim),
+    XInfo(XInfo),
+    XRead(XRead),
+    XGroup(XGroup),
+    XReadGroup(XReadGroup),
+    XAck(XAck),
+    XPending(XPending),
+    XClaim(XClaim),
+    XAutoClaim(XAutoClaim),
+    PfAdd(PfAdd),
+    PfCount(PfCount),
+    PfMerge(PfMerge),
+    GeoAdd(GeoAdd),
+    GeoPos(GeoPos),
+    GeoDist(GeoDist),
+    GeoHash(GeoHash),
+    GeoSearch(GeoSearch),
+    GeoSearchStore(GeoSearchStore),
+    BfReserve(BfReserve),
+    BfAdd(BfAdd),
+    BfMAdd(BfMAdd),
+    BfExists(BfExists),
+    BfInfo(BfInfo),
+    CfReserve(CfReserve),
+    CfAdd(CfAdd),
+    CfDel(CfDel),
+    CfExists(CfExists),
+    CfInfo(CfInfo),
+    CmsInitByDim(CmsInitByDim),
+    CmsInitByProb(CmsInitByProb),
+    CmsIncrBy(CmsIncrBy),
+    CmsQuery(CmsQuery),
+    CmsInfo(CmsInfo),
+    TopKReserve(TopKReserve),
+    TopKAdd(TopKAdd),
+    TopKList(TopKList),
+    TopKInfo(TopKInfo),
+    JsonSet(JsonSet),
+    JsonGet(JsonGet),
+    JsonMGet(JsonMGet),
+    JsonDel(JsonDel),
+    JsonType(JsonType),
+    JsonNumIncrBy(JsonNumIncrBy),
+    JsonStrAppend(JsonStrAppend),
+    JsonArrAppend(JsonArrAppend),
+    JsonArrInsert(JsonArrInsert),
+    JsonArrPop(JsonArrPop),
+    JsonArrLen(JsonArrLen),
+    JsonObjKeys(JsonObjKeys),
+    JsonResp(JsonResp),
+    TsCreate(TsCreate),
+    TsAdd(TsAdd),
+    TsMAdd(TsMAdd),
+    TsIncrBy(TsIncrBy),
+    TsDecrBy(TsDecrBy),
+    TsGet(TsGet),
+    TsRange(TsRange),
+    TsRevRange(TsRevRange),
+    TsMRange(TsMRange),
+    TsMRevRange(TsMRevRange),
+    TsCreateRule(TsCreateRule),
+    TsDeleteRule(TsDeleteRule),
+    TsInfo(TsInfo),
+    FtCreate(FtCreate),
+    FtSearch(FtSearch),
+    FtAggregate(FtAggregate),
+    FtInfo(FtInfo),
+    FtDropIndex(FtDropIndex),
+    VAdd(VAdd),
+    VRem(VRem),
+    VSim(VSim),
+    VCard(VCard),
+    VDim(VDim),
+    VEmb(VEmb),
+    VInfo(VInfo),
+    VSetAttr(VSetAttr),
+    VGetAttr(VGetAttr),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
+    Publish(Publish),
+    SSubscribe(SSubscribe),
+    SUnsubscribe(SUnsubscribe),
+    SPublish(SPublish),
+    PubSub(PubSub),
+    Cluster(Cluster),
+    Config(Config),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
+    Watch(Watch),
+    Unwatch(Unwatch),
+    Eval(Eval),
+    EvalRo(EvalRo),
+    EvalSha(EvalSha),
+    EvalShaRo(EvalShaRo),
+    Script(Script),
+    Function(Function),
+    FCall(FCall),
+    FCallRo(FCallRo),
+    Plugin(Plugin),
+    Extension(ExtensionCommand),
+    Hello(Hello),
+    Ping(Ping),
+    Quit(Quit),
+    Reset(Reset),
     UnRecognized(UnRecognized),
 }
 
+impl Command {
+    /// What the command may do and where it may run, like a registered command's flags.
+    /// Every command is listed, so that a new one can't be left out by mistake.
+    pub fn flags(&self) -> &[CommandFlag] {
+        match self {
+            Command::Set(_)
+            | Command::Del(_)
+            | Command::FlushAll(_)
+            | Command::FlushDb(_)
+            | Command::HSet(_)
+            | Command::HDel(_)
+            | Command::HSetNx(_)
+            | Command::HIncrBy(_)
+            | Command::HIncrByFloat(_)
+            | Command::HExpire(_)
+            | Command::HPExpire(_)
+            | Command::HExpireAt(_)
+            | Command::HPExpireAt(_)
+            | Command::HPersist(_)
+            | Command::HGetEx(_)
+            | Command::HSetEx(_)
+            | Command::HGetDel(_)
+            | Command::LPush(_)
+            | Command::RPush(_)
+            | Command::LPushX(_)
+            | Command::RPushX(_)
+            | Command::LPop(_)
+            | Command::RPop(_)
+            | Command::LSet(_)
+            | Command::LInsert(_)
+            | Command::LRem(_)
+            | Command::LTrim(_)
+            | Command::LMove(_)
+            | Command::LMPop(_)
+            | Command::BLPop(_)
+            | Command::BRPop(_)
+            | Command::BLMove(_)
+            | Command::BLMPop(_)
+            | Command::SAdd(_)
+            | Command::SRem(_)
+            | Command::SPop(_)
+            | Command::SMove(_)
+            | Command::SInterStore(_)
+            | Command::SUnionStore(_)
+            | Command::SDiffStore(_)
+            | Command::ZAdd(_)
+            | Command::ZIncrBy(_)
+            | Command::ZRem(_)
+            | Command::ZRangeStore(_)
+            | Command::ZRemRangeByRank(_)
+            | Command::ZRemRangeByScore(_)
+            | Command::ZRemRangeByLex(_)
+            | Command::ZPopMin(_)
+            | Command::ZPopMax(_)
+            | Command::ZUnionStore(_)
+            | Command::ZInterStore(_)
+            | Command::ZDiffStore(_)
+            | Command::ZMPop(_)
+            | Command::BZPopMin(_)
+            | Command::BZPopMax(_)
+            | Command::BZMPop(_)
+            | Command::XAdd(_)
+            | Command::XDel(_)
+            | Command::XTrim(_)
+            | Command::XGroup(_)
+            | Command::XReadGroup(_)
+            | Command::XAck(_)
+            | Command::XClaim(_)
+            | Command::XAutoClaim(_)
+            | Command::PfAdd(_)
+            | Command::PfMerge(_)
+            | Command::GeoAdd(_)
+            | Command::GeoSearchStore(_)
+            | Command::BfReserve(_)
+            | Command::BfAdd(_)
+            | Command::BfMAdd(_)
+            | Command::CfReserve(_)
+            | Command::CfAdd(_)
+            | Command::CfDel(_)
+            | Command::CmsInitByDim(_)
+            | Command::CmsInitByProb(_)
+            | Command::CmsIncrBy(_)
+            | Command::TopKReserve(_)
+            | Command::TopKAdd(_)
+            | Command::JsonSet(_)
+            | Command::JsonDel(_)
+            | Command::JsonNumIncrBy(_)
+            | Command::JsonStrAppend(_)
+            | Command::JsonArrAppend(_)
+            | Command::JsonArrInsert(_)
+            | Command::JsonArrPop(_)
+            | Command::TsCreate(_)
+            | Command::TsAdd(_)
+            | Command::TsMAdd(_)
+            | Command::TsIncrBy(_)
+            | Command::TsDecrBy(_)
+            | Command::TsCreateRule(_)
+            | Command::TsDeleteRule(_)
+            | Command::FtCreate(_)
+            | Command::FtDropIndex(_)
+            | Command::VAdd(_)
+            | Command::VRem(_)
+            | Command::VSetAttr(_) => &[CommandFlag::Write],
+            // connection state, transactions, scripts and server configuration
+            Command::Cluster(_)
+            | Command::Config(_)
+            | Command::Multi(_)
+            | Command::Exec(_)
+            | Command::Discard(_)
+            | Command::Watch(_)
+            | Command::Unwatch(_)
+            | Command::Eval(_)
+            | Command::EvalRo(_)
+            | Command::EvalSha(_)
+            | Command::EvalShaRo(_)
+            | Command::Script(_)
+            | Command::Function(_)
+            | Command::FCall(_)
+            | Command::FCallRo(_)
+            | Command::Plugin(_)
+            | Command::Quit(_)
+            | Command::Reset(_) => &[CommandFlag::NoScript],
+            // the subscribe commands and HELLO change what the connection receives
+            Command::Subscribe(_)
+            | Command::Unsubscribe(_)
+            | Command::PSubscribe(_)
+            | Command::PUnsubscribe(_)
+            | Command::SSubscribe(_)
+            | Command::SUnsubscribe(_)
+            | Command::Hello(_) => &[CommandFlag::NoScript, CommandFlag::NoMulti],
+            Command::Get(_)
+            | Command::HGet(_)
+            | Command::HGetAll(_)
+            | Command::HExists(_)
+            | Command::HLen(_)
+            | Command::HKeys(_)
+            | Command::HVals(_)
+            | Command::HMGet(_)
+            | Command::HStrLen(_)
+            | Command::HRandField(_)
+            | Command::HScan(_)
+            | Command::HTtl(_)
+            | Command::HPTtl(_)
+            | Command::HExpireTime(_)
+            | Command::HPExpireTime(_)
+            | Command::LRange(_)
+            | Command::LLen(_)
+            | Command::LIndex(_)
+            | Command::LPos(_)
+            | Command::SMembers(_)
+            | Command::SIsMember(_)
+            | Command::SMIsMember(_)
+            | Command::SCard(_)
+            | Command::SRandMember(_)
+            | Command::SInter(_)
+            | Command::SUnion(_)
+            | Command::SDiff(_)
+            | Command::SInterCard(_)
+            | Command::ZScore(_)
+            | Command::ZMScore(_)
+            | Command::ZRank(_)
+            | Command::ZRevRank(_)
+            | Command::ZCard(_)
+            | Command::ZCount(_)
+            | Command::ZLexCount(_)
+            | Command::ZRange(_)
+            | Command::ZRandMember(_)
+            | Command::ZUnion(_)
+            | Command::ZInter(_)
+            | Command::ZDiff(_)
+            | Command::XRange(_)
+            | Command::XRevRange(_)
+            | Command::XLen(_)
+            | Command::XInfo(_)
+            | Command::XRead(_)
+            | Command::XPending(_)
+            | Command::PfCount(_)
+            | Command::GeoPos(_)
+            | Command::GeoDist(_)
+            | Command::GeoHash(_)
+            | Command::GeoSearch(_)
+            | Command::BfExists(_)
+            | Command::BfInfo(_)
+            | Command::CfExists(_)
+            | Command::CfInfo(_)
+            | Command::CmsQuery(_)
+            | Command::CmsInfo(_)
+            | Command::TopKList(_)
+            | Command::TopKInfo(_)
+            | Command::JsonGet(_)
+            | Command::JsonMGet(_)
+            | Command::JsonType(_)
+            | Command::JsonArrLen(_)
+            | Command::JsonObjKeys(_)
+            | Command::JsonResp(_)
+            | Command::TsGet(_)
+            | Command::TsRange(_)
+            | Command::TsRevRange(_)
+            | Command::TsMRange(_)
+            | Command::TsMRevRange(_)
+            | Command::TsInfo(_)
+            | Command::FtSearch(_)
+            | Command::FtAggregate(_)
+            | Command::FtInfo(_)
+            | Command::VSim(_)
+            | Command::VCard(_)
+            | Command::VDim(_)
+            | Command::VEmb(_)
+            | Command::VInfo(_)
+            | Command::VGetAttr(_)
+            | Command::Publish(_)
+            | Command::SPublish(_)
+            | Command::PubSub(_)
+            | Command::Ping(_) => &[],
+            Command::Extension(cmd) => cmd.flags(),
+            // a plugin command's flags are known once it's resolved against the backend
+            Command::UnRecognized(_) => &[],
+        }
+    }
+
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags().contains(&flag)
+    }
+
+    /// Executes the command, letting blocking commands park until they can be served.
+    pub async fn execute_blocking(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match self {
+            Command::BLPop(cmd) => cmd.block(backend).await,
+            Command::BRPop(cmd) => cmd.block(backend).await,
+            Command::BLMove(cmd) => cmd.block(backend).await,
+            Command::BLMPop(cmd) => cmd.block(backend).await,
+            Command::BZPopMin(cmd) => cmd.block(backend).await,
+            Command::BZPopMax(cmd) => cmd.block(backend).await,
+            Command::BZMPop(cmd) => cmd.block(backend).await,
+            Command::XRead(cmd) => cmd.block(backend).await,
+            Command::XReadGroup(cmd) => cmd.block(backend).await,
+            cmd @ (Command::Eval(_)
+            | Command::EvalRo(_)
+            | Command::EvalSha(_)
+            | Command::EvalShaRo(_)
+            | Command::FCall(_)
+            | Command::FCallRo(_)) => scripting::run_script(cmd, backend).await,
+            // SCRIPT KILL has to get through while a script holds the lock
+            Command::Script(cmd) => cmd.execute(backend),
+            // like scripts, plugin commands run alone
+            Command::UnRecognized(cmd) => match PluginCall::resolve(cmd, backend) {
+                Ok(call) => {
+                    let _exclusive = backend
+                        .exec_exclusive()
+                        .await
+                        .map_err(CommandError::InvalidArgument)?;
+                    call.execute(backend)
+                }
+                Err(_) => Ok(RESP_OK.clone()),
+            },
+            cmd => {
+                let _shared = backend
+                    .exec_shared()
+                    .await
+                    .map_err(CommandError::InvalidArgument)?;
+                cmd.execute(backend)
+            }
+        }
+    }
+}
+
+/// Whether `frame` is a command that may park until it's served, like BLPOP or XREAD BLOCK.
+pub fn is_blocking(frame: &RespFrame) -> bool {
+    let RespFrame::Array(array) = frame else {
+        return false;
+    };
+    let Some(RespFrame::BulkString(name)) = array.first() else {
+        return false;
+    };
+    match name.to_ascii_lowercase().as_slice() {
+        b"blpop" | b"brpop" | b"blmove" | b"blmpop" | b"bzpopmin" | b"bzpopmax" | b"bzmpop" => true,
+        b"xread" | b"xreadgroup" => array.iter().skip(1).any(
+            |arg| matches!(arg, RespFrame::BulkString(arg) if arg.eq_ignore_ascii_case(b"block")),
+        ),
+        _ => false,
+    }
+}
+
 impl TryFrom<RespFrame> for Command {
     type Error = CommandError;
 
@@ -63,10 +632,215 @@ impl TryFrom<RespArray> for Command {
             Some(RespFrame::BulkString(ref cmd)) => match cmd.as_ref() {
                 b"get" => Ok(Get::try_from(value)?.into()),
                 b"set" => Ok(Set::try_from(value)?.into()),
+                b"del" => Ok(Del::try_from(value)?.into()),
+                b"flushall" => Ok(FlushAll::try_from(value)?.into()),
+                b"flushdb" => Ok(FlushDb::try_from(value)?.into()),
                 b"hget" => Ok(HGet::try_from(value)?.into()),
                 b"hset" => Ok(HSet::try_from(value)?.into()),
                 b"hgetall" => Ok(HGetAll::try_from(value)?.into()),
-                _ => Ok(UnRecognized.into()),
+                b"hdel" => Ok(HDel::try_from(value)?.into()),
+                b"hexists" => Ok(HExists::try_from(value)?.into()),
+                b"hlen" => Ok(HLen::try_from(value)?.into()),
+                b"hkeys" => Ok(HKeys::try_from(value)?.into()),
+                b"hvals" => Ok(HVals::try_from(value)?.into()),
+                b"hmget" => Ok(HMGet::try_from(value)?.into()),
+                b"hsetnx" => Ok(HSetNx::try_from(value)?.into()),
+                b"hincrby" => Ok(HIncrBy::try_from(value)?.into()),
+                b"hincrbyfloat" => Ok(HIncrByFloat::try_from(value)?.into()),
+                b"hstrlen" => Ok(HStrLen::try_from(value)?.into()),
+                b"hrandfield" => Ok(HRandField::try_from(value)?.into()),
+                b"hscan" => Ok(HScan::try_from(value)?.into()),
+                b"hexpire" => Ok(HExpire::try_from(value)?.into()),
+                b"hpexpire" => Ok(HPExpire::try_from(value)?.into()),
+                b"hexpireat" => Ok(HExpireAt::try_from(value)?.into()),
+                b"hpexpireat" => Ok(HPExpireAt::try_from(value)?.into()),
+                b"httl" => Ok(HTtl::try_from(value)?.into()),
+                b"hpttl" => Ok(HPTtl::try_from(value)?.into()),
+                b"hexpiretime" => Ok(HExpireTime::try_from(value)?.into()),
+                b"hpexpiretime" => Ok(HPExpireTime::try_from(value)?.into()),
+                b"hpersist" => Ok(HPersist::try_from(value)?.into()),
+                b"hgetex" => Ok(HGetEx::try_from(value)?.into()),
+                b"hsetex" => Ok(HSetEx::try_from(value)?.into()),
+                b"hgetdel" => Ok(HGetDel::try_from(value)?.into()),
+                b"lpush" => Ok(LPush::try_from(value)?.into()),
+                b"rpush" => Ok(RPush::try_from(value)?.into()),
+                b"lpushx" => Ok(LPushX::try_from(value)?.into()),
+                b"rpushx" => Ok(RPushX::try_from(value)?.into()),
+                b"lpop" => Ok(LPop::try_from(value)?.into()),
+                b"rpop" => Ok(RPop::try_from(value)?.into()),
+                b"lrange" => Ok(LRange::try_from(value)?.into()),
+                b"llen" => Ok(LLen::try_from(value)?.into()),
+                b"lindex" => Ok(LIndex::try_from(value)?.into()),
+                b"lset" => Ok(LSet::try_from(value)?.into()),
+                b"linsert" => Ok(LInsert::try_from(value)?.into()),
+                b"lrem" => Ok(LRem::try_from(value)?.into()),
+                b"ltrim" => Ok(LTrim::try_from(value)?.into()),
+                b"lpos" => Ok(LPos::try_from(value)?.into()),
+                b"lmove" => Ok(LMove::try_from(value)?.into()),
+                b"lmpop" => Ok(LMPop::try_from(value)?.into()),
+                b"blpop" => Ok(BLPop::try_from(value)?.into()),
+                b"brpop" => Ok(BRPop::try_from(value)?.into()),
+                b"blmove" => Ok(BLMove::try_from(value)?.into()),
+                b"blmpop" => Ok(BLMPop::try_from(value)?.into()),
+                b"sadd" => Ok(SAdd::try_from(value)?.into()),
+                b"srem" => Ok(SRem::try_from(value)?.into()),
+                b"smembers" => Ok(SMembers::try_from(value)?.into()),
+                b"sismember" => Ok(SIsMember::try_from(value)?.into()),
+                b"smismember" => Ok(SMIsMember::try_from(value)?.into()),
+                b"scard" => Ok(SCard::try_from(value)?.into()),
+                b"spop" => Ok(SPop::try_from(value)?.into()),
+                b"srandmember" => Ok(SRandMember::try_from(value)?.into()),
+                b"smove" => Ok(SMove::try_from(value)?.into()),
+                b"sinter" => Ok(SInter::try_from(value)?.into()),
+                b"sunion" => Ok(SUnion::try_from(value)?.into()),
+                b"sdiff" => Ok(SDiff::try_from(value)?.into()),
+                b"sinterstore" => Ok(SInterStore::try_from(value)?.into()),
+                b"sunionstore" => Ok(SUnionStore::try_from(value)?.into()),
+                b"sdiffstore" => Ok(SDiffStore::try_from(value)?.into()),
+                b"sintercard" => Ok(SInterCard::try_from(value)?.into()),
+                b"zadd" => Ok(ZAdd::try_from(value)?.into()),
+                b"zincrby" => Ok(ZIncrBy::try_from(value)?.into()),
+                b"zrem" => Ok(ZRem::try_from(value)?.into()),
+                b"zscore" => Ok(ZScore::try_from(value)?.into()),
+                b"zmscore" => Ok(ZMScore::try_from(value)?.into()),
+                b"zrank" => Ok(ZRank::try_from(value)?.into()),
+                b"zrevrank" => Ok(ZRevRank::try_from(value)?.into()),
+                b"zcard" => Ok(ZCard::try_from(value)?.into()),
+                b"zcount" => Ok(ZCount::try_from(value)?.into()),
+                b"zlexcount" => Ok(ZLexCount::try_from(value)?.into()),
+                b"zrange" => Ok(ZRange::try_from(value)?.into()),
+                b"zrangestore" => Ok(ZRangeStore::try_from(value)?.into()),
+                b"zremrangebyrank" => Ok(ZRemRangeByRank::try_from(value)?.into()),
+                b"zremrangebyscore" => Ok(ZRemRangeByScore::try_from(value)?.into()),
+                b"zremrangebylex" => Ok(ZRemRangeByLex::try_from(value)?.into()),
+                b"zpopmin" => Ok(ZPopMin::try_from(value)?.into()),
+                b"zpopmax" => Ok(ZPopMax::try_from(value)?.into()),
+                b"zrandmember" => Ok(ZRandMember::try_from(value)?.into()),
+                b"zunion" => Ok(ZUnion::try_from(value)?.into()),
+                b"zinter" => Ok(ZInter::try_from(value)?.into()),
+                b"zdiff" => Ok(ZDiff::try_from(value)?.into()),
+                b"zunionstore" => Ok(ZUnionStore::try_from(value)?.into()),
+                b"zinterstore" => Ok(ZInterStore::try_from(value)?.into()),
+                b"zdiffstore" => Ok(ZDiffStore::try_from(value)?.into()),
+                b"zmpop" => Ok(ZMPop::try_from(value)?.into()),
+                b"bzpopmin" => Ok(BZPopMin::try_from(value)?.into()),
+                b"bzpopmax" => Ok(BZPopMax::try_from(value)?.into()),
+                b"bzmpop" => Ok(BZMPop::try_from(value)?.into()),
+                b"xadd" => Ok(XAdd::try_from(value)?.into()),
+                b"xrange" => Ok(XRange::try_from(value)?.into()),
+                b"xrevrange" => Ok(XRevRange::try_from(value)?.into()),
+                b"xlen" => Ok(XLen::try_from(value)?.into()),
+                b"xdel" => Ok(XDel::try_from(value)?.into()),
+                b"xtrim" => Ok(XTrim::try_from(value)?.into()),
+                b"xinfo" => Ok(XInfo::try_from(value)?.into()),
+                b"xread" => Ok(XRead::try_from(value)?.into()),
+                b"xgroup" => Ok(XGroup::try_from(value)?.into()),
+                b"xreadgroup" => Ok(XReadGroup::try_from(value)?.into()),
+                b"xack" => Ok(XAck::try_from(value)?.into()),
+                b"xpending" => Ok(XPending::try_from(value)?.into()),
+                b"xclaim" => Ok(XClaim::try_from(value)?.into()),
+                b"xautoclaim" => Ok(XAutoClaim::try_from(value)?.into()),
+                b"pfadd" => Ok(PfAdd::try_from(value)?.into()),
+                b"pfcount" => Ok(PfCount::try_from(value)?.into()),
+                b"pfmerge" => Ok(PfMerge::try_from(value)?.into()),
+                b"geoadd" => Ok(GeoAdd::try_from(value)?.into()),
+                b"geopos" => Ok(GeoPos::try_from(value)?.into()),
+                b"geodist" => Ok(GeoDist::try_from(value)?.into()),
+                b"geohash" => Ok(GeoHash::try_from(value)?.into()),
+                b"geosearch" => Ok(GeoSearch::try_from(value)?.into()),
+                b"geosearchstore" => Ok(GeoSearchStore::try_from(value)?.into()),
+                b"bf.reserve" => Ok(BfReserve::try_from(value)?.into()),
+                b"bf.add" => Ok(BfAdd::try_from(value)?.into()),
+                b"bf.madd" => Ok(BfMAdd::try_from(value)?.into()),
+                b"bf.exists" => Ok(BfExists::try_from(value)?.into()),
+                b"bf.info" => Ok(BfInfo::try_from(value)?.into()),
+                b"cf.reserve" => Ok(CfReserve::try_from(value)?.into()),
+                b"cf.add" => Ok(CfAdd::try_from(value)?.into()),
+                b"cf.del" => Ok(CfDel::try_from(value)?.into()),
+                b"cf.exists" => Ok(CfExists::try_from(value)?.into()),
+                b"cf.info" => Ok(CfInfo::try_from(value)?.into()),
+                b"cms.initbydim" => Ok(CmsInitByDim::try_from(value)?.into()),
+                b"cms.initbyprob" => Ok(CmsInitByProb::try_from(value)?.into()),
+                b"cms.incrby" => Ok(CmsIncrBy::try_from(value)?.into()),
+                b"cms.query" => Ok(CmsQuery::try_from(value)?.into()),
+                b"cms.info" => Ok(CmsInfo::try_from(value)?.into()),
+                b"topk.reserve" => Ok(TopKReserve::try_from(value)?.into()),
+                b"topk.add" => Ok(TopKAdd::try_from(value)?.into()),
+                b"topk.list" => Ok(TopKList::try_from(value)?.into()),
+                b"topk.info" => Ok(TopKInfo::try_from(value)?.into()),
+                b"json.set" => Ok(JsonSet::try_from(value)?.into()),
+                b"json.get" => Ok(JsonGet::try_from(value)?.into()),
+                b"json.mget" => Ok(JsonMGet::try_from(value)?.into()),
+                b"json.del" => Ok(JsonDel::try_from(value)?.into()),
+                b"json.type" => Ok(JsonType::try_from(value)?.into()),
+                b"json.numincrby" => Ok(JsonNumIncrBy::try_from(value)?.into()),
+                b"json.strappend" => Ok(JsonStrAppend::try_from(value)?.into()),
+                b"json.arrappend" => Ok(JsonArrAppend::try_from(value)?.into()),
+                b"json.arrinsert" => Ok(JsonArrInsert::try_from(value)?.into()),
+                b"json.arrpop" => Ok(JsonArrPop::try_from(value)?.into()),
+                b"json.arrlen" => Ok(JsonArrLen::try_from(value)?.into()),
+                b"json.objkeys" => Ok(JsonObjKeys::try_from(value)?.into()),
+                b"json.resp" => Ok(JsonResp::try_from(value)?.into()),
+                b"ts.create" => Ok(TsCreate::try_from(value)?.into()),
+                b"ts.add" => Ok(TsAdd::try_from(value)?.into()),
+                b"ts.madd" => Ok(TsMAdd::try_from(value)?.into()),
+                b"ts.incrby" => Ok(TsIncrBy::try_from(value)?.into()),
+                b"ts.decrby" => Ok(TsDecrBy::try_from(value)?.into()),
+                b"ts.get" => Ok(TsGet::try_from(value)?.into()),
+                b"ts.range" => Ok(TsRange::try_from(value)?.into()),
+                b"ts.revrange" => Ok(TsRevRange::try_from(value)?.into()),
+                b"ts.mrange" => Ok(TsMRange::try_from(value)?.into()),
+                b"ts.mrevrange" => Ok(TsMRevRange::try_from(value)?.into()),
+                b"ts.createrule" => Ok(TsCreateRule::try_from(value)?.into()),
+                b"ts.deleterule" => Ok(TsDeleteRule::try_from(value)?.into()),
+                b"ts.info" => Ok(TsInfo::try_from(value)?.into()),
+                b"ft.create" => Ok(FtCreate::try_from(value)?.into()),
+                b"ft.search" => Ok(FtSearch::try_from(value)?.into()),
+                b"ft.aggregate" => Ok(FtAggregate::try_from(value)?.into()),
+                b"ft.info" => Ok(FtInfo::try_from(value)?.into()),
+                b"ft.dropindex" => Ok(FtDropIndex::try_from(value)?.into()),
+                b"vadd" => Ok(VAdd::try_from(value)?.into()),
+                b"vrem" => Ok(VRem::try_from(value)?.into()),
+                b"vsim" => Ok(VSim::try_from(value)?.into()),
+                b"vcard" => Ok(VCard::try_from(value)?.into()),
+                b"vdim" => Ok(VDim::try_from(value)?.into()),
+                b"vemb" => Ok(VEmb::try_from(value)?.into()),
+                b"vinfo" => Ok(VInfo::try_from(value)?.into()),
+                b"vsetattr" => Ok(VSetAttr::try_from(value)?.into()),
+                b"vgetattr" => Ok(VGetAttr::try_from(value)?.into()),
+                b"subscribe" => Ok(Subscribe::try_from(value)?.into()),
+                b"unsubscribe" => Ok(Unsubscribe::try_from(value)?.into()),
+                b"psubscribe" => Ok(PSubscribe::try_from(value)?.into()),
+                b"punsubscribe" => Ok(PUnsubscribe::try_from(value)?.into()),
+                b"publish" => Ok(Publish::try_from(value)?.into()),
+                b"ssubscribe" => Ok(SSubscribe::try_from(value)?.into()),
+                b"sunsubscribe" => Ok(SUnsubscribe::try_from(value)?.into()),
+                b"spublish" => Ok(SPublish::try_from(value)?.into()),
+                b"pubsub" => Ok(PubSub::try_from(value)?.into()),
+                b"cluster" => Ok(Cluster::try_from(value)?.into()),
+                b"config" => Ok(Config::try_from(value)?.into()),
+                b"multi" => Ok(Multi::try_from(value)?.into()),
+                b"exec" => Ok(Exec::try_from(value)?.into()),
+                b"discard" => Ok(Discard::try_from(value)?.into()),
+                b"watch" => Ok(Watch::try_from(value)?.into()),
+                b"unwatch" => Ok(Unwatch::try_from(value)?.into()),
+                b"eval" => Ok(Eval::try_from(value)?.into()),
+                b"eval_ro" => Ok(EvalRo::try_from(value)?.into()),
+                b"evalsha" => Ok(EvalSha::try_from(value)?.into()),
+                b"evalsha_ro" => Ok(EvalShaRo::try_from(value)?.into()),
+                b"script" => Ok(Script::try_from(value)?.into()),
+                b"function" => Ok(Function::try_from(value)?.into()),
+                b"fcall" => Ok(FCall::try_from(value)?.into()),
+                b"fcall_ro" => Ok(FCallRo::try_from(value)?.into()),
+                b"plugin" => Ok(Plugin::try_from(value)?.into()),
+                b"hello" => Ok(Hello::try_from(value)?.into()),
+                b"ping" => Ok(Ping::try_from(value)?.into()),
+                b"quit" => Ok(Quit::try_from(value)?.into()),
+                b"reset" => Ok(Reset::try_from(value)?.into()),
+                name => match registered_command(name) {
+                    Some(spec) => Ok(spec.parse(value)?.into()),
+                    None => Ok(UnRecognized::try_from(value)?.into()),
+                },
             },
             _ => Err(CommandError::InvalidCommand(
                 "Command must have a BulkString as the first argument".to_string(),
@@ -86,6 +860,17 @@ pub struct Set {
     value: RespFrame,
 }
 
+#[derive(Debug)]
+pub struct Del {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct FlushAll;
+
+#[derive(Debug)]
+pub struct FlushDb;
+
 #[derive(Debug)]
 pub struct HGet {
     key: String,
@@ -95,8 +880,7 @@ pub struct HGet {
 #[derive(Debug)]
 pub struct HSet {
     key: String,
-    field: String,
-    value: RespFrame,
+    fields: Vec<(String, RespFrame)>,
 }
 
 #[derive(Debug)]
@@ -104,12 +888,62 @@ pub struct HGetAll {
     key: String,
     sort: bool,
 }
+/// A command no builtin or registered command handles. It may still be a plugin command, which
+/// only the backend knows, see [`PluginCall::resolve`].
 #[derive(Debug)]
-pub struct UnRecognized;
+pub struct UnRecognized {
+    /// lowercase
+    name: String,
+    args: Vec<Vec<u8>>,
+}
 
 impl CommandExecutor for UnRecognized {
-    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
-        Ok(RESP_OK.clone())
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match PluginCall::resolve(self, backend) {
+            Ok(call) => call.execute(backend),
+            Err(_) => Ok(RESP_OK.clone()),
+        }
+    }
+}
+
+impl UnRecognized {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl TryFrom<RespArray> for UnRecognized {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 0)?.into_iter();
+        let name = extract_string(args.next())?.to_ascii_lowercase();
+        let args = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(UnRecognized { name, args })
+    }
+}
+
+/// Subscriptions and transactions belong to a connection, so the commands managing them are
+/// applied by the connection handler and can't run anywhere else.
+fn connection_only(name: &str) -> CommandError {
+    CommandError::InvalidArgument(format!("{} is not allowed in this context", name))
+}
+
+/// Notifies a `keymiss` when a read found nothing at `key`.
+fn notify_miss(backend: &Backend, key: &str, found: bool) {
+    if !found {
+        backend.notify(NOTIFY_KEY_MISS, "keymiss", key);
+    }
+}
+
+/// Notifies `event` on `key` and a `del` when the write removed its last element, the key
+/// is then gone.
+fn notify_write(backend: &Backend, class: u32, event: &str, key: &str, emptied: bool) {
+    backend.notify(class, event, key);
+    if emptied {
+        backend.notify(NOTIFY_GENERIC, "del", key);
     }
 }
 
@@ -127,6 +961,26 @@ fn validate_command(
         )));
     }
 
+    validate_names(value, names)
+}
+
+fn validate_command_at_least(
+    value: &RespArray,
+    names: &[&'static str],
+    min_args: usize,
+) -> Result<(), CommandError> {
+    if value.len() < min_args + names.len() {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have at least {} argument",
+            names.join(" "),
+            min_args
+        )));
+    }
+
+    validate_names(value, names)
+}
+
+fn validate_names(value: &RespArray, names: &[&'static str]) -> Result<(), CommandError> {
     for (i, name) in names.iter().enumerate() {
         match value[i] {
             RespFrame::BulkString(ref cmd) => {
@@ -149,6 +1003,279 @@ fn validate_command(
     Ok(())
 }
 
+/// Accepts an optional ASYNC or SYNC, flushing always happens right away.
+fn validate_flush_mode(mut args: impl Iterator<Item = RespFrame>) -> Result<(), CommandError> {
+    let mode = args
+        .next()
+        .map(|arg| extract_string(Some(arg)))
+        .transpose()?;
+    match mode.as_deref().map(str::to_ascii_uppercase).as_deref() {
+        None | Some("ASYNC") | Some("SYNC") if args.next().is_none() => Ok(()),
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
 fn extract_args(value: RespArray, start: usize) -> Result<Vec<RespFrame>, CommandError> {
     Ok(value.0.into_iter().skip(start).collect())
 }
+
+fn extract_bytes(arg: Option<RespFrame>) -> Result<Vec<u8>, CommandError> {
+    match arg {
+        Some(RespFrame::BulkString(s)) => Ok(s.0),
+        Some(RespFrame::SimpleString(s)) => Ok(s.0.into_bytes()),
+        Some(_) => Err(CommandError::InvalidArgument(
+            "argument must be a BulkString".to_string(),
+        )),
+        None => Err(CommandError::InvalidArgument(
+            "missing argument".to_string(),
+        )),
+    }
+}
+
+fn extract_string(arg: Option<RespFrame>) -> Result<String, CommandError> {
+    Ok(String::from_utf8(extract_bytes(arg)?)?)
+}
+
+fn extract_strings(args: impl Iterator<Item = RespFrame>) -> Result<Vec<String>, CommandError> {
+    args.map(|arg| extract_string(Some(arg))).collect()
+}
+
+fn extract_int(arg: Option<RespFrame>) -> Result<i64, CommandError> {
+    match arg {
+        Some(RespFrame::Integer(i)) => Ok(i),
+        arg => extract_string(arg)?.parse().map_err(|_| {
+            CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+        }),
+    }
+}
+
+fn extract_float(arg: Option<RespFrame>) -> Result<f64, CommandError> {
+    match arg {
+        Some(RespFrame::Double(f)) => Ok(f),
+        arg => extract_string(arg)?
+            .parse()
+            .map_err(|_| CommandError::InvalidArgument("value is not a valid float".to_string())),
+    }
+}
+
+/// Parses a blocking timeout in (fractional) seconds, where 0 means wait forever.
+fn extract_timeout(arg: Option<RespFrame>) -> Result<Option<Duration>, CommandError> {
+    let timeout: f64 = extract_string(arg)?.parse().map_err(|_| {
+        CommandError::InvalidArgument("timeout is not a float or out of range".to_string())
+    })?;
+    if !timeout.is_finite() || timeout < 0.0 {
+        return Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        ));
+    }
+    if timeout == 0.0 {
+        return Ok(None);
+    }
+    Duration::try_from_secs_f64(timeout)
+        .map(Some)
+        .map_err(|_| CommandError::InvalidArgument("timeout is out of range".to_string()))
+}
+
+/// The most members SRANDMEMBER, HRANDFIELD and ZRANDMEMBER return for a negative count, which
+/// allows repeats and so isn't bounded by the size of the collection.
+const MAX_RANDOM_REPEATS: u64 = 1 << 24;
+
+/// Parses the count of SRANDMEMBER, HRANDFIELD and ZRANDMEMBER. A negative count asks for
+/// repeats, up to [`MAX_RANDOM_REPEATS`].
+fn extract_random_count(arg: Option<RespFrame>) -> Result<i64, CommandError> {
+    let count = extract_int(arg)?;
+    if count < 0 && count.unsigned_abs() > MAX_RANDOM_REPEATS {
+        return Err(CommandError::InvalidArgument(
+            "value is out of range".to_string(),
+        ));
+    }
+    Ok(count)
+}
+
+fn extract_count(arg: Option<RespFrame>) -> Result<usize, CommandError> {
+    let count = extract_int(arg)?;
+    usize::try_from(count).map_err(|_| {
+        CommandError::InvalidArgument("value is out of range, must be positive".to_string())
+    })
+}
+
+/// Turns redis style inclusive `start`/`stop` indexes (negative counts from the tail) into a
+/// `start..=stop` range within `len`, or `None` when the range is empty.
+fn normalize_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        return None;
+    }
+    Some((start as usize, stop as usize))
+}
+
+/// Resolves a redis style index (negative counts from the tail) within `len`.
+fn normalize_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+/// Matches `s` against a glob-style `pattern` the way redis does for KEYS, SCAN MATCH and
+/// PSUBSCRIBE: `*`, `?`, `[abc]`, `[^abc]`, `[a-z]` and `\` escapes.
+///
+/// A mismatch only backtracks to the last `*`, so matching takes at most
+/// `pattern.len() * s.len()` steps whatever the number of stars.
+pub(crate) fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // where the last `*` resumes in the pattern, and the byte of `s` it swallows next
+    let mut backtrack = None;
+    while i < s.len() {
+        if pattern.get(p) == Some(&b'*') {
+            p += 1;
+            backtrack = Some((p, i));
+            continue;
+        }
+        if let Some(len) = match_one(&pattern[p..], s[i]) {
+            p += len;
+            i += 1;
+            continue;
+        }
+        match backtrack {
+            Some((star_p, star_i)) => {
+                p = star_p;
+                i = star_i + 1;
+                backtrack = Some((star_p, i));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Matches `c` against the token `pattern` starts with, other than `*`. Returns the length of
+/// the token when it matches.
+fn match_one(pattern: &[u8], c: u8) -> Option<usize> {
+    match pattern {
+        [] => None,
+        [b'?', ..] => Some(1),
+        [b'[', class @ ..] => {
+            let (negate, mut rest) = match class.split_first() {
+                Some((b'^', rest)) => (true, rest),
+                _ => (false, class),
+            };
+            let mut matched = false;
+            loop {
+                match rest {
+                    [] => break,
+                    [b']', tail @ ..] => {
+                        rest = tail;
+                        break;
+                    }
+                    [b'\\', x, tail @ ..] => {
+                        matched |= *x == c;
+                        rest = tail;
+                    }
+                    [lo, b'-', hi, tail @ ..] if *hi != b']' => {
+                        let (lo, hi) = if lo <= hi { (*lo, *hi) } else { (*hi, *lo) };
+                        matched |= (lo..=hi).contains(&c);
+                        rest = tail;
+                    }
+                    [x, tail @ ..] => {
+                        matched |= *x == c;
+                        rest = tail;
+                    }
+                }
+            }
+            (matched != negate).then_some(pattern.len() - rest.len())
+        }
+        [b'\\', x, ..] => (*x == c).then_some(2),
+        [x, ..] => (*x == c).then_some(1),
+    }
+}
+
+/// An array of bulk strings, like the members or elements many commands reply with.
+fn bulk_array<T: Into<BulkString>>(items: impl IntoIterator<Item = T>) -> RespFrame {
+    RespArray::new(
+        items
+            .into_iter()
+            .map(|item| item.into().into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+/// A command from its name and arguments, for the tests of the commands.
+#[cfg(test)]
+pub(crate) fn args(args: &[&str]) -> RespArray {
+    RespArray::new(
+        args.iter()
+            .map(|arg| BulkString::new(*arg).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+}
+
+#[cfg(test)]
+pub(crate) fn bulk(s: &str) -> RespFrame {
+    BulkString::new(s).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bulk_string::BulkString;
+
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(glob_match(b"h*llo", b"heeeello"));
+        assert!(glob_match(b"h*", b"h"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"h[a-b]llo", b"hbllo"));
+        assert!(glob_match(b"h\\*llo", b"h*llo"));
+        assert!(!glob_match(b"h\\*llo", b"hello"));
+        assert!(!glob_match(b"hello", b"hello!"));
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"a*b*c", b"axxbyyc"));
+        assert!(!glob_match(b"a*b*c", b"axxbyy"));
+        assert!(glob_match(b"*[0-9]", b"key7"));
+    }
+
+    #[test]
+    fn test_glob_match_pathological() {
+        // exponential for a matcher that backtracks into every star
+        let pattern = format!("{}b", "a*".repeat(30));
+        let s = "a".repeat(100);
+        let start = std::time::Instant::now();
+        assert!(!glob_match(pattern.as_bytes(), s.as_bytes()));
+        assert!(glob_match(pattern.as_bytes(), format!("{}b", s).as_bytes()));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_extract_timeout() {
+        let timeout = |arg: &str| extract_timeout(Some(BulkString::new(arg).into()));
+        assert_eq!(timeout("0").unwrap(), None);
+        assert_eq!(timeout("1.5").unwrap(), Some(Duration::from_millis(1500)));
+        assert!(timeout("-1").is_err());
+        let err = timeout("1e300").unwrap_err();
+        assert!(err.to_string().contains("timeout is out of range"));
+    }
+
+    #[test]
+    fn test_is_blocking() {
+        let frame = |cmd: &[&str]| RespFrame::Array(args(cmd));
+        assert!(is_blocking(&frame(&["BLPOP", "list", "0"])));
+        assert!(is_blocking(&frame(&[
+            "xread", "block", "0", "streams", "s", "$"
+        ])));
+        assert!(!is_blocking(&frame(&["xread", "streams", "s", "0"])));
+        assert!(!is_blocking(&frame(&["lpop", "list"])));
+    }
+}
@@ -0,0 +1,754 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use wasmi::core::TrapCode;
+use wasmi::{
+    Caller, Extern, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+use crate::array::RespArray;
+use crate::backend::{Backend, PluginModule};
+use crate::bulk_string::BulkString;
+use crate::cmd::registry::builtin;
+use crate::cmd::{
+    extract_args, extract_string, validate_command_at_least, Command, CommandError,
+    CommandExecutor, UnRecognized, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::map::RespMap;
+use crate::null::RespNull;
+use crate::simple_error::SimpleError;
+use crate::simple_string::SimpleString;
+
+/// The module plugins import the host API from.
+const HOST_MODULE: &str = "redis";
+
+/// The export plugins register their commands from. Commands are exports too, taking and
+/// returning nothing, named after the command.
+const INIT_EXPORT: &str = "init";
+
+/// `register_command` flag for commands that write.
+const FLAG_WRITE: i32 = 1;
+
+#[derive(Debug)]
+pub struct Plugin {
+    sub: PluginSub,
+}
+
+/// A command registered by a plugin, executed by that plugin.
+#[derive(Debug)]
+pub struct PluginCall {
+    name: String,
+    args: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+enum PluginSub {
+    Load(String),
+    List,
+    Unload(String),
+}
+
+/// What host functions see of the command or the loading in progress.
+struct HostState {
+    /// unset while loading, plugins may only register commands then
+    backend: Option<Backend>,
+    args: Vec<Vec<u8>>,
+    /// the commands registered while loading, and whether each one writes
+    registered: BTreeMap<String, bool>,
+    reply: ReplyBuilder,
+    limits: StoreLimits,
+}
+
+/// Assembles the reply of a plugin command from the `reply_*` calls. `reply_array` opens an
+/// array that the next calls fill in.
+#[derive(Default)]
+struct ReplyBuilder {
+    reply: Option<RespFrame>,
+    /// the arrays being filled in, with their lengths
+    open: Vec<(Vec<RespFrame>, usize)>,
+}
+
+impl CommandExecutor for Plugin {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match self.sub {
+            PluginSub::Load(path) => {
+                let plugin = load_plugin(backend, &path)?;
+                let name = plugin.name.clone();
+                backend
+                    .plugin_add(plugin)
+                    .map_err(CommandError::InvalidArgument)?;
+                Ok(BulkString::new(name).into())
+            }
+            PluginSub::List => {
+                let plugins = backend
+                    .plugin_list()
+                    .into_iter()
+                    .map(|plugin| {
+                        let mut map = RespMap::new();
+                        map.insert("name".to_string(), BulkString::new(plugin.name).into());
+                        map.insert("path".to_string(), BulkString::new(plugin.path).into());
+                        let commands = plugin
+                            .commands
+                            .into_keys()
+                            .map(|command| BulkString::new(command).into())
+                            .collect::<Vec<RespFrame>>();
+                        map.insert("commands".to_string(), RespArray::new(commands).into());
+                        map.into()
+                    })
+                    .collect::<Vec<RespFrame>>();
+                Ok(RespArray::new(plugins).into())
+            }
+            PluginSub::Unload(name) => match backend.plugin_remove(&name) {
+                true => Ok(RESP_OK.clone()),
+                false => Err(CommandError::InvalidArgument(format!(
+                    "Plugin '{}' is not loaded",
+                    name
+                ))),
+            },
+        }
+    }
+}
+
+impl CommandExecutor for PluginCall {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        // the plugin may have been unloaded since the command was resolved
+        let Some((module, _)) = backend.plugin_command(&self.name) else {
+            return Ok(RESP_OK.clone());
+        };
+        let mut store = new_store(backend, HostState::running(backend, self.args));
+        let reply = instantiate(backend, &mut store, &module)
+            .and_then(|instance| {
+                let command = instance.get_typed_func::<(), ()>(&store, &self.name)?;
+                command.call(&mut store, ())?;
+                store.into_data().reply.finish()
+            })
+            .map_err(|e| {
+                CommandError::InvalidArgument(format!(
+                    "Error running plugin command '{}': {}",
+                    self.name,
+                    error_message(&e)
+                ))
+            })?;
+        Ok(reply)
+    }
+}
+
+impl PluginCall {
+    /// The plugin command `cmd` names, or `cmd` back when no plugin registered it.
+    pub fn resolve(cmd: UnRecognized, backend: &Backend) -> Result<Self, UnRecognized> {
+        match backend.plugin_command(&cmd.name) {
+            Some(_) => Ok(PluginCall {
+                name: cmd.name,
+                args: cmd.args,
+            }),
+            None => Err(cmd),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Loads the `.wasm` files of `dir`, in the order of their names, returns the names of the
+/// plugins.
+pub fn load_plugins(backend: &Backend, dir: impl AsRef<Path>) -> anyhow::Result<Vec<String>> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "wasm"));
+    paths.sort();
+    let mut names = vec![];
+    for path in paths {
+        let plugin = load_plugin(backend, &path.to_string_lossy())?;
+        names.push(plugin.name.clone());
+        backend.plugin_add(plugin).map_err(anyhow::Error::msg)?;
+    }
+    Ok(names)
+}
+
+/// Compiles the module at `path` and runs its `init` export to learn its commands. The plugin
+/// is named after the file.
+fn load_plugin(backend: &Backend, path: &str) -> Result<PluginModule, CommandError> {
+    let failed = |e: &dyn std::fmt::Display| {
+        CommandError::InvalidArgument(format!("Error loading plugin: {}", e))
+    };
+    let name = Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .ok_or_else(|| failed(&"invalid path"))?;
+    let wasm = std::fs::read(path).map_err(|e| failed(&e))?;
+    let module = Module::new(backend.plugin_engine(), &wasm).map_err(|e| failed(&e))?;
+
+    let mut store = new_store(backend, HostState::loading(backend));
+    let commands = instantiate(backend, &mut store, &module)
+        .and_then(|instance| {
+            let init = instance
+                .get_typed_func::<(), ()>(&store, INIT_EXPORT)
+                .map_err(|_| {
+                    wasmi::Error::new(format!(
+                        "the plugin has no '{}' export taking and returning nothing",
+                        INIT_EXPORT
+                    ))
+                })?;
+            init.call(&mut store, ())?;
+            let commands = std::mem::take(&mut store.data_mut().registered);
+            for command in commands.keys() {
+                instance
+                    .get_typed_func::<(), ()>(&store, command)
+                    .map_err(|_| {
+                        wasmi::Error::new(format!(
+                            "command '{}' has no export taking and returning nothing",
+                            command
+                        ))
+                    })?;
+            }
+            Ok(commands)
+        })
+        .map_err(|e| failed(&error_message(&e)))?;
+    if commands.is_empty() {
+        return Err(failed(&"no commands registered"));
+    }
+    Ok(PluginModule {
+        name,
+        path: path.to_string(),
+        module: Arc::new(module),
+        commands,
+    })
+}
+
+impl HostState {
+    /// The state of a plugin being loaded.
+    fn loading(backend: &Backend) -> Self {
+        Self {
+            backend: None,
+            args: vec![],
+            registered: BTreeMap::new(),
+            reply: ReplyBuilder::default(),
+            limits: limits(backend),
+        }
+    }
+
+    /// The state of a plugin command called with `args`.
+    fn running(backend: &Backend, args: Vec<Vec<u8>>) -> Self {
+        Self {
+            backend: Some(backend.clone()),
+            args,
+            ..Self::loading(backend)
+        }
+    }
+
+    /// Executes a builtin on behalf of the plugin.
+    fn call(&self, args: Vec<Vec<u8>>) -> Result<RespFrame, wasmi::Error> {
+        let Some(backend) = &self.backend else {
+            return Err(wasmi::Error::new(
+                "the dataset can't be accessed while the plugin loads",
+            ));
+        };
+        let frames = args
+            .into_iter()
+            .map(|arg| BulkString::new(arg).into())
+            .collect::<Vec<RespFrame>>();
+        Command::try_from(RespArray::new(frames))
+            .and_then(|cmd| cmd.execute(backend))
+            .map_err(|e| wasmi::Error::new(e.to_string()))
+    }
+}
+
+impl ReplyBuilder {
+    fn push(&mut self, mut frame: RespFrame) -> Result<(), wasmi::Error> {
+        loop {
+            let Some((items, len)) = self.open.last_mut() else {
+                if self.reply.is_some() {
+                    return Err(wasmi::Error::new("the command already replied"));
+                }
+                self.reply = Some(frame);
+                return Ok(());
+            };
+            items.push(frame);
+            if items.len() < *len {
+                return Ok(());
+            }
+            let (items, _) = self.open.pop().expect("an array is open");
+            frame = RespArray::new(items).into();
+        }
+    }
+
+    fn array(&mut self, len: i32) -> Result<(), wasmi::Error> {
+        let len = usize::try_from(len)
+            .map_err(|_| wasmi::Error::new("array length must not be negative"))?;
+        if len == 0 {
+            return self.push(RespArray::new(vec![]).into());
+        }
+        if self.open.is_empty() && self.reply.is_some() {
+            return Err(wasmi::Error::new("the command already replied"));
+        }
+        self.open.push((vec![], len));
+        Ok(())
+    }
+
+    /// The reply, null when the command didn't reply.
+    fn finish(self) -> Result<RespFrame, wasmi::Error> {
+        if !self.open.is_empty() {
+            return Err(wasmi::Error::new("the reply has an unfinished array"));
+        }
+        Ok(self.reply.unwrap_or(RespFrame::Null(RespNull)))
+    }
+}
+
+fn limits(backend: &Backend) -> StoreLimits {
+    let max_memory = usize::try_from(backend.plugin_max_memory()).unwrap_or(usize::MAX);
+    StoreLimitsBuilder::new()
+        .memory_size(max_memory)
+        .instances(1)
+        .build()
+}
+
+fn new_store(backend: &Backend, state: HostState) -> Store<HostState> {
+    let mut store = Store::new(backend.plugin_engine(), state);
+    store.limiter(|state| &mut state.limits);
+    store
+        .set_fuel(backend.plugin_max_fuel())
+        .expect("the engine meters fuel");
+    store
+}
+
+fn instantiate(
+    backend: &Backend,
+    store: &mut Store<HostState>,
+    module: &Module,
+) -> Result<Instance, wasmi::Error> {
+    let linker = host_api(backend)?;
+    linker.instantiate(&mut *store, module)?.start(&mut *store)
+}
+
+/// The functions plugins import from [`HOST_MODULE`]. Strings are passed as a pointer and a
+/// length into the plugin's `memory`. Functions returning a string copy it to `out` when it
+/// fits in `cap` bytes and return its length either way, or -1 when there's none.
+fn host_api(backend: &Backend) -> Result<Linker<HostState>, wasmi::Error> {
+    let mut linker = Linker::new(backend.plugin_engine());
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "register_command",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32, flags: i32| {
+                if caller.data().backend.is_some() {
+                    return Err(wasmi::Error::new(
+                        "commands can only be registered while the plugin loads",
+                    ));
+                }
+                let name = String::from_utf8(read(&caller, ptr, len)?)
+                    .map_err(|_| wasmi::Error::new("command names must be UTF-8"))?
+                    .to_ascii_lowercase();
+                if name.is_empty() || builtin(&name) {
+                    return Err(wasmi::Error::new(format!(
+                        "command '{}' can't be registered",
+                        name
+                    )));
+                }
+                let write = flags & FLAG_WRITE != 0;
+                caller.data_mut().registered.insert(name, write);
+                Ok(())
+            },
+        )?
+        .func_wrap(HOST_MODULE, "arg_count", |caller: Caller<'_, HostState>| {
+            caller.data().args.len() as i32
+        })?
+        .func_wrap(
+            HOST_MODULE,
+            "arg",
+            |mut caller: Caller<'_, HostState>, index: i32, out: i32, cap: i32| {
+                let arg = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| caller.data().args.get(index).cloned());
+                write_out(&mut caller, arg, out, cap)
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "get",
+            |mut caller: Caller<'_, HostState>, key: i32, key_len: i32, out: i32, cap: i32| {
+                let key = read(&caller, key, key_len)?;
+                let value = bulk_reply(caller.data().call(vec![b"get".to_vec(), key])?)?;
+                write_out(&mut caller, value, out, cap)
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "set",
+            |caller: Caller<'_, HostState>, key: i32, key_len: i32, value: i32, value_len: i32| {
+                let key = read(&caller, key, key_len)?;
+                let value = read(&caller, value, value_len)?;
+                caller.data().call(vec![b"set".to_vec(), key, value])?;
+                Ok(())
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "del",
+            |caller: Caller<'_, HostState>, key: i32, key_len: i32| {
+                let key = read(&caller, key, key_len)?;
+                integer_reply(caller.data().call(vec![b"del".to_vec(), key])?)
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "hget",
+            |mut caller: Caller<'_, HostState>,
+             key: i32,
+             key_len: i32,
+             field: i32,
+             field_len: i32,
+             out: i32,
+             cap: i32| {
+                let key = read(&caller, key, key_len)?;
+                let field = read(&caller, field, field_len)?;
+                let value = bulk_reply(caller.data().call(vec![b"hget".to_vec(), key, field])?)?;
+                write_out(&mut caller, value, out, cap)
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "hset",
+            |caller: Caller<'_, HostState>,
+             key: i32,
+             key_len: i32,
+             field: i32,
+             field_len: i32,
+             value: i32,
+             value_len: i32| {
+                let key = read(&caller, key, key_len)?;
+                let field = read(&caller, field, field_len)?;
+                let value = read(&caller, value, value_len)?;
+                integer_reply(
+                    caller
+                        .data()
+                        .call(vec![b"hset".to_vec(), key, field, value])?,
+                )
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "hdel",
+            |caller: Caller<'_, HostState>, key: i32, key_len: i32, field: i32, field_len: i32| {
+                let key = read(&caller, key, key_len)?;
+                let field = read(&caller, field, field_len)?;
+                integer_reply(caller.data().call(vec![b"hdel".to_vec(), key, field])?)
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "reply_null",
+            |mut caller: Caller<'_, HostState>| {
+                caller.data_mut().reply.push(RespFrame::Null(RespNull))
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "reply_int",
+            |mut caller: Caller<'_, HostState>, value: i64| {
+                caller.data_mut().reply.push(RespFrame::Integer(value))
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "reply_bulk",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let value = read(&caller, ptr, len)?;
+                caller.data_mut().reply.push(BulkString::new(value).into())
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "reply_simple",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let value = String::from_utf8_lossy(&read(&caller, ptr, len)?).into_owned();
+                caller
+                    .data_mut()
+                    .reply
+                    .push(SimpleString::new(value).into())
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "reply_error",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let value = String::from_utf8_lossy(&read(&caller, ptr, len)?).into_owned();
+                caller.data_mut().reply.push(SimpleError::new(value).into())
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "reply_array",
+            |mut caller: Caller<'_, HostState>, len: i32| caller.data_mut().reply.array(len),
+        )?;
+    Ok(linker)
+}
+
+fn memory(caller: &Caller<'_, HostState>) -> Result<Memory, wasmi::Error> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("the plugin doesn't export its memory"))
+}
+
+fn read(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Result<Vec<u8>, wasmi::Error> {
+    let start = ptr as u32 as usize;
+    let end = start.saturating_add(len as u32 as usize);
+    memory(caller)?
+        .data(caller)
+        .get(start..end)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| wasmi::Error::new("out of bounds memory access"))
+}
+
+fn write_out(
+    caller: &mut Caller<'_, HostState>,
+    value: Option<Vec<u8>>,
+    out: i32,
+    cap: i32,
+) -> Result<i32, wasmi::Error> {
+    let Some(value) = value else {
+        return Ok(-1);
+    };
+    let len = i32::try_from(value.len()).map_err(|_| wasmi::Error::new("value is too large"))?;
+    if len <= cap {
+        let start = out as u32 as usize;
+        memory(caller)?
+            .data_mut(&mut *caller)
+            .get_mut(start..start + value.len())
+            .ok_or_else(|| wasmi::Error::new("out of bounds memory access"))?
+            .copy_from_slice(&value);
+    }
+    Ok(len)
+}
+
+fn bulk_reply(frame: RespFrame) -> Result<Option<Vec<u8>>, wasmi::Error> {
+    match frame {
+        RespFrame::BulkString(s) => Ok(Some(s.0)),
+        RespFrame::Null(_) => Ok(None),
+        frame => Err(wasmi::Error::new(format!("unexpected reply {:?}", frame))),
+    }
+}
+
+fn integer_reply(frame: RespFrame) -> Result<i32, wasmi::Error> {
+    match frame {
+        RespFrame::Integer(i) => Ok(i as i32),
+        frame => Err(wasmi::Error::new(format!("unexpected reply {:?}", frame))),
+    }
+}
+
+/// The message of an error, naming the limit a command ran into.
+fn error_message(err: &wasmi::Error) -> String {
+    match err.as_trap_code() {
+        Some(TrapCode::OutOfFuel) => "the command ran out of fuel".to_string(),
+        _ => err.to_string(),
+    }
+}
+
+impl TryFrom<RespArray> for Plugin {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["plugin"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_uppercase();
+        let sub = match (subcommand.as_str(), args.len()) {
+            ("LOAD", 1) => PluginSub::Load(extract_string(args.next())?),
+            ("LIST", 0) => PluginSub::List,
+            ("UNLOAD", 1) => PluginSub::Unload(extract_string(args.next())?),
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for PLUGIN {}",
+                    subcommand
+                )))
+            }
+        };
+        Ok(Plugin { sub })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    /// `echo` replies its arguments, `copy` copies the string at a key to another, `spin` never
+    /// returns and `grow` replies what growing its memory by 64MB returned.
+    const PLUGIN: &str = r#"(module
+        (import "redis" "register_command" (func $register (param i32 i32 i32)))
+        (import "redis" "arg_count" (func $arg_count (result i32)))
+        (import "redis" "arg" (func $arg (param i32 i32 i32) (result i32)))
+        (import "redis" "get" (func $get (param i32 i32 i32 i32) (result i32)))
+        (import "redis" "set" (func $set (param i32 i32 i32 i32)))
+        (import "redis" "reply_null" (func $reply_null))
+        (import "redis" "reply_int" (func $reply_int (param i64)))
+        (import "redis" "reply_bulk" (func $reply_bulk (param i32 i32)))
+        (import "redis" "reply_array" (func $reply_array (param i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "echocopyspingrow")
+        (func (export "init")
+            (call $register (i32.const 0) (i32.const 4) (i32.const 0))
+            (call $register (i32.const 4) (i32.const 4) (i32.const 1))
+            (call $register (i32.const 8) (i32.const 4) (i32.const 0))
+            (call $register (i32.const 12) (i32.const 4) (i32.const 0)))
+        (func (export "echo") (local $i i32) (local $len i32)
+            (call $reply_array (call $arg_count))
+            (block $done
+                (loop $next
+                    (br_if $done (i32.ge_s (local.get $i) (call $arg_count)))
+                    (local.set $len (call $arg (local.get $i) (i32.const 1024) (i32.const 1024)))
+                    (call $reply_bulk (i32.const 1024) (local.get $len))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br $next))))
+        (func (export "copy") (local $from i32) (local $to i32) (local $len i32)
+            (local.set $from (call $arg (i32.const 0) (i32.const 100) (i32.const 100)))
+            (local.set $to (call $arg (i32.const 1) (i32.const 200) (i32.const 100)))
+            (local.set $len (call $get (i32.const 100) (local.get $from) (i32.const 300) (i32.const 700)))
+            (if (i32.lt_s (local.get $len) (i32.const 0))
+                (then (call $reply_null))
+                (else
+                    (call $set (i32.const 200) (local.get $to) (i32.const 300) (local.get $len))
+                    (call $reply_int (i64.extend_i32_s (local.get $len))))))
+        (func (export "spin") (loop $forever (br $forever)))
+        (func (export "grow")
+            (call $reply_int (i64.extend_i32_s (memory.grow (i32.const 1024))))))"#;
+
+    fn run(backend: &Backend, cmd: &[&str]) -> Result<RespFrame> {
+        Ok(Command::try_from(args(cmd))?.execute(backend)?)
+    }
+
+    /// Writes `wat` compiled to `<name>.wasm` in a directory of its own, returns the directory.
+    fn plugin_dir(name: &str, wat: &str) -> Result<std::path::PathBuf> {
+        let dir = std::env::temp_dir().join(format!(
+            "simple-redis-plugin-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(format!("{}.wasm", name)), wat::parse_str(wat)?)?;
+        Ok(dir)
+    }
+
+    #[test]
+    fn test_plugin_try_from() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nplugin\r\n$4\r\nload\r\n$8\r\nx/a.wasm\r\n");
+        let plugin = Plugin::try_from(RespArray::decode(&mut buf)?)?;
+        assert!(matches!(plugin.sub, PluginSub::Load(path) if path == "x/a.wasm"));
+
+        assert!(Plugin::try_from(args(&["plugin", "list", "x"])).is_err());
+        assert!(Plugin::try_from(args(&["plugin", "unload"])).is_err());
+        let cmd = UnRecognized::try_from(args(&["ECHO", "a"]))?;
+        assert_eq!(cmd.name(), "echo");
+        assert!(PluginCall::resolve(cmd, &Backend::new()).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_command_runs_alongside() -> Result<()> {
+        let backend = Backend::new();
+        // another client's command holds the lock shared
+        let _shared = backend.exec_shared().await;
+        let cmd = Command::try_from(args(&["nosuchcommand", "a"]))?;
+        assert!(matches!(cmd, Command::UnRecognized(_)));
+        let reply = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            cmd.execute_blocking(&backend),
+        )
+        .await??;
+        assert_eq!(reply, RESP_OK.clone());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plugin_commands() -> Result<()> {
+        let backend = Backend::new();
+        let dir = plugin_dir("basic", PLUGIN)?;
+        assert_eq!(load_plugins(&backend, &dir)?, vec!["basic".to_string()]);
+
+        assert_eq!(
+            run(&backend, &["echo", "a", "bc"])?,
+            RespArray::new(vec![
+                BulkString::new("a").into(),
+                BulkString::new("bc").into()
+            ])
+            .into()
+        );
+        assert_eq!(run(&backend, &["copy", "k", "k2"])?, RespNull.into());
+        run(&backend, &["set", "k", "value"])?;
+        assert_eq!(run(&backend, &["copy", "k", "k2"])?, RespFrame::Integer(5));
+        assert_eq!(backend.get("k2"), Some(BulkString::new("value").into()));
+
+        // fuel and memory are limited
+        let err = run(&backend, &["spin"]).unwrap_err();
+        assert!(err.to_string().contains("ran out of fuel"));
+        assert_eq!(run(&backend, &["grow"])?, RespFrame::Integer(-1));
+        backend.set_plugin_max_memory(128 * 1024 * 1024);
+        assert_eq!(run(&backend, &["grow"])?, RespFrame::Integer(1));
+
+        let path = dir.join("basic.wasm").to_string_lossy().into_owned();
+        assert!(run(&backend, &["plugin", "load", &path]).is_err());
+        let RespFrame::Array(plugins) = run(&backend, &["plugin", "list"])? else {
+            panic!("PLUGIN LIST must reply with an array");
+        };
+        assert_eq!(plugins.len(), 1);
+        let cmd = UnRecognized::try_from(args(&["ECHO", "a"]))?;
+        assert_eq!(PluginCall::resolve(cmd, &backend).unwrap().name(), "echo");
+        assert_eq!(
+            run(&backend, &["plugin", "unload", "basic"])?,
+            RESP_OK.clone()
+        );
+        // without the plugin, the command is unknown again
+        assert_eq!(run(&backend, &["echo", "a"])?, RESP_OK.clone());
+        assert_eq!(
+            run(&backend, &["plugin", "load", &path])?,
+            BulkString::new("basic").into()
+        );
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_plugin_load_errors() -> Result<()> {
+        let backend = Backend::new();
+        let load = |name: &str, wat: &str| -> Result<String> {
+            let dir = plugin_dir(name, wat)?;
+            let path = dir.join(format!("{}.wasm", name));
+            let err = run(&backend, &["plugin", "load", &path.to_string_lossy()]).unwrap_err();
+            std::fs::remove_dir_all(dir)?;
+            Ok(err.to_string())
+        };
+        let register = |command: &str, export: &str| {
+            format!(
+                r#"(module
+                    (import "redis" "register_command" (func $register (param i32 i32 i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "{}")
+                    (func (export "init") (call $register (i32.const 0) (i32.const {}) (i32.const 0)))
+                    (func (export "{}")))"#,
+                command,
+                command.len(),
+                export
+            )
+        };
+
+        assert!(load("empty", "(module)")?.contains("no 'init' export"));
+        assert!(load("builtin", &register("get", "get"))?.contains("can't be registered"));
+        assert!(load("missing", &register("cmd", "other"))?.contains("has no export"));
+        let touch = r#"(module
+            (import "redis" "del" (func $del (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "init") (drop (call $del (i32.const 0) (i32.const 1)))))"#;
+        assert!(load("touch", touch)?.contains("while the plugin loads"));
+        assert!(backend.plugin_list().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,634 @@
+use crate::array::RespArray;
+use crate::backend::{key_hash_slot, Backend, Subscriber};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    connection_only, extract_args, extract_bytes, extract_string, extract_strings,
+    validate_command, validate_command_at_least, CommandError, CommandExecutor,
+};
+use crate::frame::RespFrame;
+use crate::null::RespNull;
+use crate::push::RespPush;
+
+#[derive(Debug)]
+pub struct Subscribe {
+    channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Unsubscribe {
+    /// no channels unsubscribes from all of them
+    channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct PSubscribe {
+    patterns: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct PUnsubscribe {
+    /// no patterns unsubscribes from all of them
+    patterns: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SSubscribe {
+    channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SUnsubscribe {
+    /// no channels unsubscribes from all of them
+    channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Publish {
+    channel: String,
+    message: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SPublish {
+    channel: String,
+    message: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct PubSub {
+    query: PubSubQuery,
+}
+
+#[derive(Debug)]
+enum PubSubQuery {
+    Channels(Option<String>),
+    NumSub(Vec<String>),
+    NumPat,
+    ShardChannels(Option<String>),
+    ShardNumSub(Vec<String>),
+}
+
+/// The commands a RESP2 client may still send once it subscribed to something.
+pub const SUBSCRIBED_COMMANDS: [&str; 9] = [
+    "subscribe",
+    "unsubscribe",
+    "psubscribe",
+    "punsubscribe",
+    "ssubscribe",
+    "sunsubscribe",
+    "ping",
+    "quit",
+    "reset",
+];
+
+impl Subscribe {
+    /// Subscribes the connection, replying with one confirmation per channel.
+    pub fn apply(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        self.channels
+            .into_iter()
+            .map(|channel| {
+                let count = subscriber.subscribe(&channel);
+                confirmation("subscribe", Some(channel), count)
+            })
+            .collect()
+    }
+}
+
+impl Unsubscribe {
+    pub fn apply(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        let channels = match self.channels.is_empty() {
+            true => subscriber.channels(),
+            false => self.channels,
+        };
+        if channels.is_empty() {
+            return vec![confirmation("unsubscribe", None, subscriber.count())];
+        }
+        channels
+            .into_iter()
+            .map(|channel| {
+                let count = subscriber.unsubscribe(&channel);
+                confirmation("unsubscribe", Some(channel), count)
+            })
+            .collect()
+    }
+}
+
+impl PSubscribe {
+    pub fn apply(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        self.patterns
+            .into_iter()
+            .map(|pattern| {
+                let count = subscriber.psubscribe(&pattern);
+                confirmation("psubscribe", Some(pattern), count)
+            })
+            .collect()
+    }
+}
+
+impl PUnsubscribe {
+    pub fn apply(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        let patterns = match self.patterns.is_empty() {
+            true => subscriber.patterns(),
+            false => self.patterns,
+        };
+        if patterns.is_empty() {
+            return vec![confirmation("punsubscribe", None, subscriber.count())];
+        }
+        patterns
+            .into_iter()
+            .map(|pattern| {
+                let count = subscriber.punsubscribe(&pattern);
+                confirmation("punsubscribe", Some(pattern), count)
+            })
+            .collect()
+    }
+}
+
+impl SSubscribe {
+    /// Fails with `MOVED` when the slot of the channels is served by another node.
+    pub fn check_slot(&self, backend: &Backend) -> Result<(), CommandError> {
+        match self.channels.first() {
+            Some(channel) => check_slot(backend, channel),
+            None => Ok(()),
+        }
+    }
+
+    /// Subscribes the connection to shard channels, counted apart from the other ones.
+    pub fn apply(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        self.channels
+            .into_iter()
+            .map(|channel| {
+                let count = subscriber.ssubscribe(&channel);
+                confirmation("ssubscribe", Some(channel), count)
+            })
+            .collect()
+    }
+}
+
+impl SUnsubscribe {
+    pub fn apply(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        let channels = match self.channels.is_empty() {
+            true => subscriber.shard_channels(),
+            false => self.channels,
+        };
+        if channels.is_empty() {
+            return vec![confirmation("sunsubscribe", None, subscriber.shard_count())];
+        }
+        channels
+            .into_iter()
+            .map(|channel| {
+                let count = subscriber.sunsubscribe(&channel);
+                confirmation("sunsubscribe", Some(channel), count)
+            })
+            .collect()
+    }
+}
+
+impl CommandExecutor for Subscribe {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("SUBSCRIBE"))
+    }
+}
+
+impl CommandExecutor for Unsubscribe {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("UNSUBSCRIBE"))
+    }
+}
+
+impl CommandExecutor for PSubscribe {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("PSUBSCRIBE"))
+    }
+}
+
+impl CommandExecutor for PUnsubscribe {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("PUNSUBSCRIBE"))
+    }
+}
+
+impl CommandExecutor for SSubscribe {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("SSUBSCRIBE"))
+    }
+}
+
+impl CommandExecutor for SUnsubscribe {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("SUNSUBSCRIBE"))
+    }
+}
+
+impl CommandExecutor for Publish {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let received = backend.publish(&self.channel, &self.message);
+        Ok(RespFrame::Integer(received as i64))
+    }
+}
+
+impl CommandExecutor for SPublish {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        check_slot(backend, &self.channel)?;
+        let received = backend.spublish(&self.channel, &self.message);
+        Ok(RespFrame::Integer(received as i64))
+    }
+}
+
+impl CommandExecutor for PubSub {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(match self.query {
+            PubSubQuery::Channels(pattern) => {
+                channels_reply(backend.pubsub_channels(pattern.as_deref()))
+            }
+            PubSubQuery::NumSub(channels) => {
+                numsub_reply(channels, |channel| backend.pubsub_numsub(channel))
+            }
+            PubSubQuery::NumPat => RespFrame::Integer(backend.pubsub_numpat() as i64),
+            PubSubQuery::ShardChannels(pattern) => {
+                channels_reply(backend.pubsub_shard_channels(pattern.as_deref()))
+            }
+            PubSubQuery::ShardNumSub(channels) => {
+                numsub_reply(channels, |channel| backend.pubsub_shard_numsub(channel))
+            }
+        })
+    }
+}
+
+fn channels_reply(channels: Vec<String>) -> RespFrame {
+    RespArray::new(
+        channels
+            .into_iter()
+            .map(|channel| BulkString::new(channel).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+/// A flat array of each channel followed by its subscriber count.
+fn numsub_reply(channels: Vec<String>, numsub: impl Fn(&str) -> usize) -> RespFrame {
+    RespArray::new(
+        channels
+            .into_iter()
+            .flat_map(|channel| {
+                let count = numsub(&channel);
+                [
+                    BulkString::new(channel).into(),
+                    RespFrame::Integer(count as i64),
+                ]
+            })
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+/// Shard channels are routed like keys, so a single command may only name channels of one
+/// slot.
+fn validate_same_slot(channels: &[String]) -> Result<(), CommandError> {
+    let mut slots = channels
+        .iter()
+        .map(|channel| key_hash_slot(channel.as_bytes()));
+    match slots.next() {
+        Some(first) if slots.any(|slot| slot != first) => Err(CommandError::InvalidArgument(
+            "CROSSSLOT Keys in request don't hash to the same slot".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_slot(backend: &Backend, channel: &str) -> Result<(), CommandError> {
+    let slot = key_hash_slot(channel.as_bytes());
+    match backend.slot_moved_to(slot) {
+        Some(node_id) => Err(CommandError::InvalidArgument(format!(
+            "MOVED {} {}",
+            slot, node_id
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// `[kind, channel, count]`, pushed to RESP3 clients like messages.
+fn confirmation(kind: &str, channel: Option<String>, count: usize) -> RespFrame {
+    let channel = channel.map_or(RespFrame::Null(RespNull), |channel| {
+        BulkString::new(channel).into()
+    });
+    RespPush::new([
+        BulkString::new(kind).into(),
+        channel,
+        RespFrame::Integer(count as i64),
+    ])
+    .into()
+}
+
+impl TryFrom<RespArray> for Subscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["subscribe"], 1)?;
+        let args = extract_args(value, 1)?;
+        Ok(Subscribe {
+            channels: extract_strings(args.into_iter())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Unsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["unsubscribe"], 0)?;
+        let args = extract_args(value, 1)?;
+        Ok(Unsubscribe {
+            channels: extract_strings(args.into_iter())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PSubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["psubscribe"], 1)?;
+        let args = extract_args(value, 1)?;
+        Ok(PSubscribe {
+            patterns: extract_strings(args.into_iter())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PUnsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["punsubscribe"], 0)?;
+        let args = extract_args(value, 1)?;
+        Ok(PUnsubscribe {
+            patterns: extract_strings(args.into_iter())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SSubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["ssubscribe"], 1)?;
+        let channels = extract_strings(extract_args(value, 1)?.into_iter())?;
+        validate_same_slot(&channels)?;
+        Ok(SSubscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for SUnsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["sunsubscribe"], 0)?;
+        let channels = extract_strings(extract_args(value, 1)?.into_iter())?;
+        validate_same_slot(&channels)?;
+        Ok(SUnsubscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for Publish {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["publish"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Publish {
+            channel: extract_string(args.next())?,
+            message: extract_bytes(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SPublish {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["spublish"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SPublish {
+            channel: extract_string(args.next())?,
+            message: extract_bytes(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PubSub {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["pubsub"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_uppercase();
+        let query = match subcommand.as_str() {
+            "CHANNELS" | "SHARDCHANNELS" => {
+                let pattern = args
+                    .next()
+                    .map(|arg| extract_string(Some(arg)))
+                    .transpose()?;
+                if args.next().is_some() {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "PUBSUB {} takes at most one pattern",
+                        subcommand
+                    )));
+                }
+                match subcommand.as_str() {
+                    "CHANNELS" => PubSubQuery::Channels(pattern),
+                    _ => PubSubQuery::ShardChannels(pattern),
+                }
+            }
+            "NUMSUB" => PubSubQuery::NumSub(extract_strings(args)?),
+            "SHARDNUMSUB" => PubSubQuery::ShardNumSub(extract_strings(args)?),
+            "NUMPAT" if args.len() == 0 => PubSubQuery::NumPat,
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for PUBSUB {}",
+                    subcommand
+                )))
+            }
+        };
+        Ok(PubSub { query })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::{args, bulk};
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    fn run<T>(backend: &Backend, cmd: &[&str]) -> Result<RespFrame, CommandError>
+    where
+        T: TryFrom<RespArray, Error = CommandError> + CommandExecutor,
+    {
+        T::try_from(args(cmd))?.execute(backend)
+    }
+
+    #[test]
+    fn test_publish_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$7\r\npublish\r\n$4\r\nnews\r\n$2\r\nhi\r\n");
+        let cmd: Publish = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.channel, "news");
+        assert_eq!(cmd.message, b"hi");
+
+        let cmd: Result<PubSub, _> = args(&["pubsub", "numpat", "x"]).try_into();
+        assert!(cmd.is_err());
+        let cmd: Result<Subscribe, _> = args(&["subscribe"]).try_into();
+        assert!(cmd.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_psubscribe_pathological_pattern() -> Result<()> {
+        let backend = Backend::new();
+        let (mut subscriber, mut rx) = Subscriber::new(&backend);
+        let pattern = format!("{}b", "a*".repeat(30));
+        PSubscribe::try_from(args(&["psubscribe", &pattern]))?.apply(&mut subscriber);
+
+        // delivery matches every published channel against the pattern
+        let channel = "a".repeat(100);
+        assert_eq!(
+            run::<Publish>(&backend, &["publish", &channel, "hi"])?,
+            RespFrame::Integer(0)
+        );
+        let channel = format!("{}b", channel);
+        assert_eq!(
+            run::<Publish>(&backend, &["publish", &channel, "hi"])?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            subscriber.receive(rx.try_recv()?),
+            RespPush::new([bulk("pmessage"), bulk(&pattern), bulk(&channel), bulk("hi")]).into()
+        );
+
+        subscriber.unsubscribe_all();
+        assert!(!subscriber.is_subscribed());
+        assert_eq!(
+            run::<PubSub>(&backend, &["pubsub", "numpat"])?,
+            RespFrame::Integer(0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscribe_and_publish() -> Result<()> {
+        let backend = Backend::new();
+        let (mut subscriber, mut rx) = Subscriber::new(&backend);
+        let replies = Subscribe::try_from(args(&["subscribe", "a", "b"]))?.apply(&mut subscriber);
+        assert_eq!(
+            replies,
+            [
+                RespPush::new([bulk("subscribe"), bulk("a"), RespFrame::Integer(1)]).into(),
+                RespPush::new([bulk("subscribe"), bulk("b"), RespFrame::Integer(2)]).into(),
+            ]
+        );
+        PSubscribe::try_from(args(&["psubscribe", "a*"]))?.apply(&mut subscriber);
+        assert!(run::<Subscribe>(&backend, &["subscribe", "c"]).is_err());
+
+        assert_eq!(
+            run::<Publish>(&backend, &["publish", "a", "hi"])?,
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            subscriber.receive(rx.try_recv()?),
+            RespPush::new([bulk("message"), bulk("a"), bulk("hi")]).into()
+        );
+        assert_eq!(
+            run::<PubSub>(&backend, &["pubsub", "channels", "?"])?,
+            RespArray::new([bulk("a"), bulk("b")]).into()
+        );
+        assert_eq!(
+            run::<PubSub>(&backend, &["pubsub", "numsub", "a", "z"])?,
+            RespArray::new([
+                bulk("a"),
+                RespFrame::Integer(1),
+                bulk("z"),
+                RespFrame::Integer(0)
+            ])
+            .into()
+        );
+        assert_eq!(
+            run::<PubSub>(&backend, &["pubsub", "numpat"])?,
+            RespFrame::Integer(1)
+        );
+
+        let replies = Unsubscribe::try_from(args(&["unsubscribe"]))?.apply(&mut subscriber);
+        assert_eq!(replies.len(), 2);
+        assert_eq!(
+            replies[1],
+            RespPush::new([bulk("unsubscribe"), bulk("b"), RespFrame::Integer(1)]).into()
+        );
+        let replies = PUnsubscribe::try_from(args(&["punsubscribe"]))?.apply(&mut subscriber);
+        let replies_again = PUnsubscribe::try_from(args(&["punsubscribe"]))?.apply(&mut subscriber);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(
+            replies_again,
+            [RespPush::new([
+                bulk("punsubscribe"),
+                RespFrame::Null(RespNull),
+                RespFrame::Integer(0)
+            ])
+            .into()]
+        );
+        assert!(!subscriber.is_subscribed());
+        Ok(())
+    }
+
+    #[test]
+    fn test_shard_channels() -> Result<()> {
+        let cmd: Result<SSubscribe, _> = args(&["ssubscribe", "a", "b"]).try_into();
+        assert!(cmd.is_err());
+
+        let backend = Backend::new();
+        let (mut subscriber, mut rx) = Subscriber::new(&backend);
+        subscriber.subscribe("news");
+        let replies =
+            SSubscribe::try_from(args(&["ssubscribe", "{n}.a", "{n}.b"]))?.apply(&mut subscriber);
+        assert_eq!(
+            replies[1],
+            RespPush::new([bulk("ssubscribe"), bulk("{n}.b"), RespFrame::Integer(2)]).into()
+        );
+        assert_eq!(
+            run::<SPublish>(&backend, &["spublish", "{n}.a", "hi"])?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run::<Publish>(&backend, &["publish", "{n}.a", "hi"])?,
+            RespFrame::Integer(0)
+        );
+        assert_eq!(
+            subscriber.receive(rx.try_recv()?),
+            RespPush::new([bulk("smessage"), bulk("{n}.a"), bulk("hi")]).into()
+        );
+        assert_eq!(
+            run::<PubSub>(&backend, &["pubsub", "shardchannels"])?,
+            RespArray::new([bulk("{n}.a"), bulk("{n}.b")]).into()
+        );
+        assert_eq!(
+            run::<PubSub>(&backend, &["pubsub", "shardnumsub", "{n}.a", "news"])?,
+            RespArray::new([
+                bulk("{n}.a"),
+                RespFrame::Integer(1),
+                bulk("news"),
+                RespFrame::Integer(0)
+            ])
+            .into()
+        );
+
+        let replies = SUnsubscribe::try_from(args(&["sunsubscribe"]))?.apply(&mut subscriber);
+        assert_eq!(
+            replies[1],
+            RespPush::new([bulk("sunsubscribe"), bulk("{n}.b"), RespFrame::Integer(0)]).into()
+        );
+        assert!(subscriber.is_subscribed());
+        Ok(())
+    }
+}
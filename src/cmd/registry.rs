@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use lazy_static::lazy_static;
+
+use crate::array::RespArray;
+use crate::backend::Backend;
+use crate::bulk_string::BulkString;
+use crate::cmd::{Command, CommandError, CommandExecutor};
+use crate::frame::RespFrame;
+
+lazy_static! {
+    /// The commands registered by embedders, by lowercase name.
+    static ref REGISTRY: RwLock<HashMap<String, CommandSpec>> = RwLock::new(HashMap::new());
+}
+
+/// What a registered command may do, and where it may run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandFlag {
+    /// modifies the dataset, so read-only scripts can't call it
+    Write,
+    /// can't be called from scripts
+    NoScript,
+    /// can't be queued in a transaction
+    NoMulti,
+}
+
+type Parser = dyn Fn(RespArray) -> Result<Box<dyn BoxedExecutor>, CommandError> + Send + Sync;
+
+/// A command added to the ones [`Command`] parses, see [`register_command`].
+#[derive(Clone)]
+pub struct CommandSpec {
+    name: String,
+    /// the number of arguments, the name included. Negative for a minimum, like redis's
+    /// COMMAND INFO.
+    arity: i64,
+    flags: Vec<CommandFlag>,
+    parse: Arc<Parser>,
+}
+
+/// A registered command, parsed and ready to execute.
+pub struct ExtensionCommand {
+    name: String,
+    flags: Vec<CommandFlag>,
+    cmd: Box<dyn BoxedExecutor>,
+}
+
+/// [`CommandExecutor`] takes `self` by value, which a trait object can't do without a box.
+trait BoxedExecutor: Send {
+    fn execute_boxed(self: Box<Self>, backend: &Backend) -> Result<RespFrame, CommandError>;
+}
+
+impl<T: CommandExecutor + Send> BoxedExecutor for T {
+    fn execute_boxed(self: Box<Self>, backend: &Backend) -> Result<RespFrame, CommandError> {
+        (*self).execute(backend)
+    }
+}
+
+impl CommandSpec {
+    /// A command parsed by `T`'s `TryFrom<RespArray>`.
+    pub fn new<T>(name: &str, arity: i64) -> Self
+    where
+        T: TryFrom<RespArray, Error = CommandError> + CommandExecutor + Send + 'static,
+    {
+        Self::with_parser(name, arity, T::try_from)
+    }
+
+    /// A command parsed by `parse`, which is given the whole command, name included, once
+    /// its arity was checked.
+    pub fn with_parser<T, F>(name: &str, arity: i64, parse: F) -> Self
+    where
+        T: CommandExecutor + Send + 'static,
+        F: Fn(RespArray) -> Result<T, CommandError> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.to_ascii_lowercase(),
+            arity,
+            flags: vec![],
+            parse: Arc::new(move |value| {
+                parse(value).map(|cmd| Box::new(cmd) as Box<dyn BoxedExecutor>)
+            }),
+        }
+    }
+
+    pub fn flag(mut self, flag: CommandFlag) -> Self {
+        if !self.flags.contains(&flag) {
+            self.flags.push(flag);
+        }
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> i64 {
+        self.arity
+    }
+
+    pub fn flags(&self) -> &[CommandFlag] {
+        &self.flags
+    }
+
+    pub(crate) fn parse(&self, value: RespArray) -> Result<ExtensionCommand, CommandError> {
+        let arity = self.arity.unsigned_abs() as usize;
+        let valid = match self.arity {
+            0.. => value.len() == arity,
+            _ => value.len() >= arity,
+        };
+        if !valid {
+            let bound = if self.arity < 0 {
+                "at least"
+            } else {
+                "exactly"
+            };
+            return Err(CommandError::InvalidArgument(format!(
+                "{} command must have {} {} argument",
+                self.name,
+                bound,
+                arity.saturating_sub(1)
+            )));
+        }
+        Ok(ExtensionCommand {
+            name: self.name.clone(),
+            flags: self.flags.clone(),
+            cmd: (self.parse)(value)?,
+        })
+    }
+}
+
+impl fmt::Debug for CommandSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandSpec")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .field("flags", &self.flags)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExtensionCommand {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn flags(&self) -> &[CommandFlag] {
+        &self.flags
+    }
+
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+}
+
+impl CommandExecutor for ExtensionCommand {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        self.cmd.execute_boxed(backend)
+    }
+}
+
+impl fmt::Debug for ExtensionCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtensionCommand")
+            .field("name", &self.name)
+            .field("flags", &self.flags)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Adds a command to the ones every [`Backend`] of the process serves. Builtin and registered
+/// names can't be taken again, an arity of 0 isn't valid.
+pub fn register_command(spec: CommandSpec) -> Result<(), CommandError> {
+    if spec.arity == 0 {
+        return Err(CommandError::InvalidArgument(format!(
+            "invalid arity for command '{}'",
+            spec.name
+        )));
+    }
+    // parsing takes the registry, so the builtins are checked before it's locked
+    if spec.name.is_empty() || builtin(&spec.name) {
+        return Err(CommandError::InvalidArgument(format!(
+            "command '{}' already exists",
+            spec.name
+        )));
+    }
+    let mut registry = REGISTRY.write().unwrap();
+    if registry.contains_key(&spec.name) {
+        return Err(CommandError::InvalidArgument(format!(
+            "command '{}' already exists",
+            spec.name
+        )));
+    }
+    registry.insert(spec.name.clone(), spec);
+    Ok(())
+}
+
+/// Removes a registered command, returns whether it was registered.
+pub fn unregister_command(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    REGISTRY.write().unwrap().remove(&name).is_some()
+}
+
+/// Every registered command, sorted by name.
+pub fn registered_commands() -> Vec<CommandSpec> {
+    let mut specs = REGISTRY
+        .read()
+        .unwrap()
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    specs.sort_by(|a, b| a.name.cmp(&b.name));
+    specs
+}
+
+/// The command registered under `name`, if any.
+pub(crate) fn registered_command(name: &[u8]) -> Option<CommandSpec> {
+    let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+    REGISTRY.read().unwrap().get(&name).cloned()
+}
+
+/// Whether `name` is taken by a builtin or a registered command.
+pub(crate) fn builtin(name: &str) -> bool {
+    let name = RespArray::new(vec![BulkString::new(name).into()]);
+    !matches!(Command::try_from(name), Ok(Command::UnRecognized(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::cmd::{extract_args, extract_int, extract_string, Transaction};
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    /// Adds to the integer at a key, the registry is shared by the tests so each one registers
+    /// it under its own name.
+    #[derive(Debug)]
+    struct IncrBy {
+        key: String,
+        by: i64,
+    }
+
+    impl CommandExecutor for IncrBy {
+        fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+            let current = match backend.get(&self.key) {
+                Some(RespFrame::BulkString(s)) => extract_int(Some(s.into()))?,
+                _ => 0,
+            };
+            backend.set(
+                &self.key,
+                BulkString::new((current + self.by).to_string()).into(),
+            );
+            Ok(RespFrame::Integer(current + self.by))
+        }
+    }
+
+    impl TryFrom<RespArray> for IncrBy {
+        type Error = CommandError;
+
+        fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+            let mut args = extract_args(value, 1)?.into_iter();
+            Ok(IncrBy {
+                key: extract_string(args.next())?,
+                by: args
+                    .next()
+                    .map(|by| extract_int(Some(by)))
+                    .unwrap_or(Ok(1))?,
+            })
+        }
+    }
+
+    fn run(backend: &Backend, cmd: &[&str]) -> Result<RespFrame> {
+        Ok(Command::try_from(args(cmd))?.execute(backend)?)
+    }
+
+    #[test]
+    fn test_register_command() -> Result<()> {
+        register_command(CommandSpec::new::<IncrBy>("Test.IncrBy", -2).flag(CommandFlag::Write))?;
+        assert!(register_command(CommandSpec::new::<IncrBy>("test.incrby", 2)).is_err());
+        assert!(register_command(CommandSpec::new::<IncrBy>("get", 2)).is_err());
+        assert!(register_command(CommandSpec::new::<IncrBy>("test.noarity", 0)).is_err());
+        let spec = registered_commands()
+            .into_iter()
+            .find(|spec| spec.name() == "test.incrby")
+            .unwrap();
+        assert_eq!(
+            (spec.arity(), spec.flags()),
+            (-2, &[CommandFlag::Write][..])
+        );
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$11\r\ntest.incrby\r\n$1\r\nk\r\n");
+        let cmd = Command::try_from(RespArray::decode(&mut buf)?)?;
+        assert!(matches!(&cmd, Command::Extension(cmd) if cmd.name() == "test.incrby"));
+
+        let backend = Backend::new();
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+        assert_eq!(
+            run(&backend, &["test.incrby", "k", "41"])?,
+            RespFrame::Integer(42)
+        );
+        assert!(run(&backend, &["test.incrby"]).is_err());
+        assert!(run(&backend, &["test.incrby", "k", "x"]).is_err());
+
+        assert!(unregister_command("test.incrby"));
+        assert!(!unregister_command("test.incrby"));
+        // unknown again
+        assert!(matches!(
+            Command::try_from(args(&["test.incrby", "k"]))?,
+            Command::UnRecognized(_)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_registered_command_flags() -> Result<()> {
+        register_command(CommandSpec::new::<IncrBy>("test.incr", 2).flag(CommandFlag::Write))?;
+        register_command(
+            CommandSpec::with_parser("test.incr.noscript", 2, IncrBy::try_from)
+                .flag(CommandFlag::NoScript)
+                .flag(CommandFlag::NoMulti),
+        )?;
+        let backend = Backend::new();
+
+        let script = |name: &str, cmd: &str| {
+            let body = format!("return redis.call('{}', 'k')", cmd);
+            run(&backend, &[name, &body, "0"])
+        };
+        assert_eq!(script("eval", "test.incr")?, RespFrame::Integer(1));
+        let err = script("eval_ro", "test.incr").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("not allowed from read-only scripts"));
+        let err = script("eval", "test.incr.noscript").unwrap_err();
+        assert!(err.to_string().contains("not allowed from script"));
+
+        let mut transaction = Transaction::default();
+        transaction.queue(Command::try_from(args(&["test.incr", "k"])))?;
+        assert!(transaction
+            .queue(Command::try_from(args(&["test.incr.noscript", "k"])))
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,788 @@
+use mlua::{Function, HookTriggers, IntoLuaMulti, Lua, LuaOptions, StdLib, Table, Value, Variadic};
+use tracing::info;
+
+use crate::array::RespArray;
+use crate::backend::Backend;
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_bytes, extract_int, extract_string, extract_strings,
+    validate_command_at_least, validate_flush_mode, Command, CommandError, CommandExecutor,
+    CommandFlag, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::null::RespNull;
+use crate::simple_error::SimpleError;
+use crate::simple_string::SimpleString;
+
+/// How many Lua instructions a script runs between checks for SCRIPT KILL.
+const KILL_CHECK_INSTRUCTIONS: u32 = 1000;
+
+#[derive(Debug)]
+pub struct Eval {
+    call: ScriptCall,
+}
+
+#[derive(Debug)]
+pub struct EvalRo {
+    call: ScriptCall,
+}
+
+#[derive(Debug)]
+pub struct EvalSha {
+    call: ScriptCall,
+}
+
+#[derive(Debug)]
+pub struct EvalShaRo {
+    call: ScriptCall,
+}
+
+#[derive(Debug)]
+pub struct Script {
+    sub: ScriptSub,
+}
+
+#[derive(Debug)]
+struct ScriptCall {
+    script: ScriptSource,
+    keys: Vec<String>,
+    args: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+enum ScriptSource {
+    Body(String),
+    Sha(String),
+}
+
+#[derive(Debug)]
+enum ScriptSub {
+    Load(String),
+    Exists(Vec<String>),
+    Flush,
+    Kill,
+}
+
+impl CommandExecutor for Eval {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        self.call.execute(backend, false)
+    }
+}
+
+impl CommandExecutor for EvalRo {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        self.call.execute(backend, true)
+    }
+}
+
+impl CommandExecutor for EvalSha {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        self.call.execute(backend, false)
+    }
+}
+
+impl CommandExecutor for EvalShaRo {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        self.call.execute(backend, true)
+    }
+}
+
+impl CommandExecutor for Script {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match self.sub {
+            ScriptSub::Load(body) => {
+                let lua = Lua::new_with(StdLib::NONE, LuaOptions::default()).map_err(lua_error)?;
+                compile(&lua, &body)?;
+                Ok(BulkString::new(backend.script_load(body)).into())
+            }
+            ScriptSub::Exists(shas) => Ok(RespArray::new(
+                shas.iter()
+                    .map(|sha| RespFrame::Integer(backend.script_exists(sha) as i64))
+                    .collect::<Vec<_>>(),
+            )
+            .into()),
+            ScriptSub::Flush => {
+                backend.script_flush();
+                Ok(RESP_OK.clone())
+            }
+            ScriptSub::Kill => {
+                backend
+                    .script_kill()
+                    .map_err(CommandError::InvalidArgument)?;
+                Ok(RESP_OK.clone())
+            }
+        }
+    }
+}
+
+/// Runs a script sent by a client while no other client can execute commands. It runs on the
+/// blocking pool, so that a long script doesn't hold up a runtime worker and SCRIPT KILL still
+/// gets through.
+pub(crate) async fn run_script(cmd: Command, backend: &Backend) -> Result<RespFrame, CommandError> {
+    let exclusive = backend
+        .exec_exclusive()
+        .await
+        .map_err(CommandError::InvalidArgument)?;
+    let backend = backend.clone();
+    tokio::task::spawn_blocking(move || {
+        let _exclusive = exclusive;
+        cmd.execute(&backend)
+    })
+    .await
+    .map_err(|e| CommandError::InvalidArgument(e.to_string()))?
+}
+
+impl ScriptCall {
+    /// Runs the script in a fresh interpreter, callers hold `exec_lock` exclusively.
+    fn execute(self, backend: &Backend, read_only: bool) -> Result<RespFrame, CommandError> {
+        let body = match self.script {
+            ScriptSource::Body(body) => body,
+            ScriptSource::Sha(sha) => backend.script_get(&sha).ok_or_else(|| {
+                CommandError::InvalidArgument(
+                    "NOSCRIPT No matching script. Please use EVAL.".to_string(),
+                )
+            })?,
+        };
+        let lua = new_lua(backend, read_only).map_err(lua_error)?;
+        let (keys, args) = keys_and_args(&lua, self.keys, &self.args).map_err(lua_error)?;
+        let globals = lua.globals();
+        globals.set("KEYS", keys).map_err(lua_error)?;
+        globals.set("ARGV", args).map_err(lua_error)?;
+        let function = compile(&lua, &body)?;
+        // like in redis, EVAL caches the script for EVALSHA
+        let sha = backend.script_load(body);
+
+        run_function(backend, &lua, function, ()).map_err(|e| {
+            CommandError::InvalidArgument(format!(
+                "Error running script (call to f_{}): {}",
+                sha,
+                error_message(&e)
+            ))
+        })
+    }
+}
+
+/// The KEYS and ARGV tables scripts get.
+pub(crate) fn keys_and_args<'lua>(
+    lua: &'lua Lua,
+    keys: Vec<String>,
+    args: &[Vec<u8>],
+) -> mlua::Result<(Table<'lua>, Table<'lua>)> {
+    let args = args
+        .iter()
+        .map(|arg| lua.create_string(arg))
+        .collect::<mlua::Result<Vec<_>>>()?;
+    Ok((
+        lua.create_sequence_from(keys)?,
+        lua.create_sequence_from(args)?,
+    ))
+}
+
+/// Calls a script function while the script is marked running, so that other clients can
+/// tell it's busy and SCRIPT KILL can stop it.
+pub(crate) fn run_function<'lua>(
+    backend: &Backend,
+    lua: &'lua Lua,
+    function: Function<'lua>,
+    args: impl IntoLuaMulti<'lua>,
+) -> mlua::Result<RespFrame> {
+    let _running = backend.script_start();
+    let killer = backend.clone();
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(KILL_CHECK_INSTRUCTIONS),
+        move |_, _| match killer.script_killed() {
+            true => Err(mlua::Error::RuntimeError(
+                "Script killed by user with SCRIPT KILL...".to_string(),
+            )),
+            false => Ok(()),
+        },
+    );
+    let reply = function.call::<_, Value>(args).map(to_resp);
+    lua.remove_hook();
+    reply
+}
+
+/// An interpreter with the `redis` library and none of the standard ones reaching outside the
+/// dataset, like `io` or `os`.
+pub(crate) fn new_lua(backend: &Backend, read_only: bool) -> mlua::Result<Lua> {
+    let lua = Lua::new_with(
+        StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8,
+        LuaOptions::default(),
+    )?;
+    {
+        let globals = lua.globals();
+        for name in ["dofile", "loadfile", "print"] {
+            globals.set(name, Value::Nil)?;
+        }
+
+        let redis = lua.create_table()?;
+        let call_backend = backend.clone();
+        let call = lua.create_function(move |lua, args: Variadic<Value>| {
+            let frame =
+                call_command(&call_backend, args, read_only).map_err(mlua::Error::RuntimeError)?;
+            to_lua(lua, frame)
+        })?;
+        redis.set("call", call)?;
+        let pcall_backend = backend.clone();
+        let pcall = lua.create_function(move |lua, args: Variadic<Value>| {
+            match call_command(&pcall_backend, args, read_only) {
+                Ok(frame) => to_lua(lua, frame),
+                Err(e) => reply_table(lua, "err", e),
+            }
+        })?;
+        redis.set("pcall", pcall)?;
+        let error_reply = lua.create_function(|lua, msg: String| reply_table(lua, "err", msg))?;
+        redis.set("error_reply", error_reply)?;
+        let status_reply = lua.create_function(|lua, msg: String| reply_table(lua, "ok", msg))?;
+        redis.set("status_reply", status_reply)?;
+        let sha1hex = lua.create_function(|_, body: mlua::String| {
+            Ok(sha1_smol::Sha1::from(body.as_bytes()).digest().to_string())
+        })?;
+        redis.set("sha1hex", sha1hex)?;
+        let log = lua.create_function(|_, (level, msg): (i64, String)| {
+            info!("script log, level {}: {}", level, msg);
+            Ok(())
+        })?;
+        redis.set("log", log)?;
+        for (name, level) in [
+            ("LOG_DEBUG", 0),
+            ("LOG_VERBOSE", 1),
+            ("LOG_NOTICE", 2),
+            ("LOG_WARNING", 3),
+        ] {
+            redis.set(name, level)?;
+        }
+        globals.set("redis", redis)?;
+    }
+    Ok(lua)
+}
+
+fn compile<'lua>(lua: &'lua Lua, body: &str) -> Result<Function<'lua>, CommandError> {
+    lua.load(body)
+        .set_name("@user_script")
+        .into_function()
+        .map_err(|e| {
+            CommandError::InvalidArgument(format!(
+                "Error compiling script (new function): {}",
+                error_message(&e)
+            ))
+        })
+}
+
+/// Executes a command for `redis.call` and `redis.pcall`, an error is the message of the reply.
+fn call_command(
+    backend: &Backend,
+    args: Variadic<Value>,
+    read_only: bool,
+) -> Result<RespFrame, String> {
+    let mut frames = Vec::with_capacity(args.len());
+    for arg in args.iter() {
+        let arg = match arg {
+            Value::String(s) => s.as_bytes().to_vec(),
+            Value::Integer(i) => i.to_string().into_bytes(),
+            Value::Number(n) => n.to_string().into_bytes(),
+            _ => {
+                return Err(
+                    "Lua redis lib command arguments must be strings or integers".to_string(),
+                )
+            }
+        };
+        frames.push(BulkString::new(arg).into());
+    }
+    let Some(RespFrame::BulkString(name)) = frames.first_mut() else {
+        return Err("Please specify at least one argument for this redis lib call".to_string());
+    };
+    // commands are only dispatched by their lowercase names, scripts often spell them in caps
+    name.0.make_ascii_lowercase();
+    let cmd = Command::try_from(RespArray::new(frames)).map_err(|e| e.to_string())?;
+    if cmd.has_flag(CommandFlag::NoScript) {
+        return Err("This Redis command is not allowed from script".to_string());
+    }
+    let write = match &cmd {
+        Command::UnRecognized(cmd) => match backend.plugin_command(cmd.name()) {
+            Some((_, write)) => write,
+            None => return Err("Unknown Redis command called from script".to_string()),
+        },
+        cmd => cmd.has_flag(CommandFlag::Write),
+    };
+    if write && read_only {
+        return Err("Write commands are not allowed from read-only scripts.".to_string());
+    }
+    if write {
+        backend.script_wrote();
+    }
+    cmd.execute(backend).map_err(|e| e.to_string())
+}
+
+/// Converts a reply to Lua the way redis does for RESP2: status and error replies become
+/// tables with an `ok` or `err` field, nulls become `false`.
+fn to_lua(lua: &Lua, frame: RespFrame) -> mlua::Result<Value<'_>> {
+    let sequence = |frames: Vec<RespFrame>| -> mlua::Result<Value<'_>> {
+        let values = frames
+            .into_iter()
+            .map(|frame| to_lua(lua, frame))
+            .collect::<mlua::Result<Vec<_>>>()?;
+        Ok(Value::Table(lua.create_sequence_from(values)?))
+    };
+    match frame {
+        RespFrame::SimpleString(s) => reply_table(lua, "ok", s.0),
+        RespFrame::Error(e) => reply_table(lua, "err", e.0),
+        RespFrame::Integer(i) => Ok(Value::Integer(i)),
+        RespFrame::Boolean(b) => Ok(Value::Integer(b as i64)),
+        RespFrame::BulkString(s) => Ok(Value::String(lua.create_string(s.0)?)),
+        RespFrame::Double(d) => Ok(Value::String(lua.create_string(d.to_string())?)),
+        RespFrame::Array(array) => sequence(array.0),
+        RespFrame::Set(set) => sequence(set.0),
+        RespFrame::Push(push) => sequence(push.0),
+        RespFrame::Map(map) => sequence(
+            map.0
+                .into_iter()
+                .flat_map(|(k, v)| [BulkString::new(k).into(), v])
+                .collect(),
+        ),
+        RespFrame::NullBulkString(_) | RespFrame::Null(_) | RespFrame::NullArray(_) => {
+            Ok(Value::Boolean(false))
+        }
+    }
+}
+
+/// Converts what a script returned to a reply: numbers are truncated to integers, tables
+/// with an `ok` or `err` field become status or error replies and other tables arrays, up to
+/// their first nil.
+fn to_resp(value: Value) -> RespFrame {
+    match value {
+        Value::Boolean(true) => RespFrame::Integer(1),
+        Value::Integer(i) => RespFrame::Integer(i),
+        Value::Number(n) => RespFrame::Integer(n as i64),
+        Value::String(s) => BulkString::new(s.as_bytes()).into(),
+        Value::Table(table) => {
+            if let Ok(Value::String(err)) = table.raw_get::<_, Value>("err") {
+                return SimpleError::new(err.to_string_lossy()).into();
+            }
+            if let Ok(Value::String(ok)) = table.raw_get::<_, Value>("ok") {
+                return SimpleString::new(ok.to_string_lossy()).into();
+            }
+            RespArray::new(
+                table
+                    .sequence_values::<Value>()
+                    .map_while(Result::ok)
+                    .map(to_resp)
+                    .collect::<Vec<_>>(),
+            )
+            .into()
+        }
+        _ => RespFrame::Null(RespNull),
+    }
+}
+
+fn reply_table<'lua>(lua: &'lua Lua, field: &str, msg: String) -> mlua::Result<Value<'lua>> {
+    let table = lua.create_table()?;
+    table.set(field, msg)?;
+    Ok(Value::Table(table))
+}
+
+/// The message of a Lua error, without the tracebacks of the callbacks it went through.
+pub(crate) fn error_message(err: &mlua::Error) -> String {
+    match err {
+        mlua::Error::CallbackError { cause, .. } => error_message(cause),
+        mlua::Error::RuntimeError(msg) | mlua::Error::SyntaxError { message: msg, .. } => {
+            msg.clone()
+        }
+        err => err.to_string(),
+    }
+}
+
+pub(crate) fn lua_error(err: mlua::Error) -> CommandError {
+    CommandError::InvalidArgument(error_message(&err))
+}
+
+impl TryFrom<RespArray> for Eval {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Eval {
+            call: parse_call(value, "eval")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for EvalRo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(EvalRo {
+            call: parse_call(value, "eval_ro")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for EvalSha {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(EvalSha {
+            call: parse_call(value, "evalsha")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for EvalShaRo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(EvalShaRo {
+            call: parse_call(value, "evalsha_ro")?,
+        })
+    }
+}
+
+/// Parses `<script or sha> numkeys [key ...] [arg ...]`.
+fn parse_call(value: RespArray, name: &'static str) -> Result<ScriptCall, CommandError> {
+    validate_command_at_least(&value, &[name], 2)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    let script = extract_string(args.next())?;
+    let script = match name.starts_with("evalsha") {
+        true => ScriptSource::Sha(script),
+        false => ScriptSource::Body(script),
+    };
+    let (keys, args) = parse_keys_and_args(args)?;
+    Ok(ScriptCall { script, keys, args })
+}
+
+/// Parses the `numkeys [key ...] [arg ...]` of EVAL and FCALL.
+pub(crate) fn parse_keys_and_args(
+    mut args: impl ExactSizeIterator<Item = RespFrame>,
+) -> Result<(Vec<String>, Vec<Vec<u8>>), CommandError> {
+    let numkeys = extract_int(args.next())?;
+    if numkeys < 0 {
+        return Err(CommandError::InvalidArgument(
+            "Number of keys can't be negative".to_string(),
+        ));
+    }
+    if numkeys as usize > args.len() {
+        return Err(CommandError::InvalidArgument(
+            "Number of keys can't be greater than number of args".to_string(),
+        ));
+    }
+    let keys = extract_strings(args.by_ref().take(numkeys as usize))?;
+    let args = args
+        .map(|arg| extract_bytes(Some(arg)))
+        .collect::<Result<_, _>>()?;
+    Ok((keys, args))
+}
+
+impl TryFrom<RespArray> for Script {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["script"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_uppercase();
+        let sub = match subcommand.as_str() {
+            "LOAD" if args.len() == 1 => ScriptSub::Load(extract_string(args.next())?),
+            "EXISTS" if args.len() > 0 => ScriptSub::Exists(extract_strings(args)?),
+            "FLUSH" => {
+                validate_flush_mode(args)?;
+                ScriptSub::Flush
+            }
+            "KILL" if args.len() == 0 => ScriptSub::Kill,
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for SCRIPT {}",
+                    subcommand
+                )))
+            }
+        };
+        Ok(Script { sub })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    fn run<T>(backend: &Backend, cmd: &[&str]) -> Result<RespFrame>
+    where
+        T: TryFrom<RespArray, Error = CommandError> + CommandExecutor,
+    {
+        Ok(T::try_from(args(cmd))?.execute(backend)?)
+    }
+
+    fn eval(backend: &Backend, script: &str, keys_and_args: &[&str]) -> Result<RespFrame> {
+        let mut cmd = vec!["eval", script];
+        cmd.extend_from_slice(keys_and_args);
+        run::<Eval>(backend, &cmd)
+    }
+
+    #[test]
+    fn test_eval_try_from() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\neval\r\n$8\r\nreturn 1\r\n$1\r\n1\r\n$1\r\nk\r\n$1\r\na\r\n",
+        );
+        let eval: Eval = RespArray::decode(&mut buf)?.try_into()?;
+        assert!(matches!(eval.call.script, ScriptSource::Body(ref body) if body == "return 1"));
+        assert_eq!(eval.call.keys, ["k"]);
+        assert_eq!(eval.call.args, [b"a".to_vec()]);
+
+        assert!(Eval::try_from(args(&["eval", "return 1"])).is_err());
+        assert!(Eval::try_from(args(&["eval", "return 1", "-1"])).is_err());
+        assert!(Eval::try_from(args(&["eval", "return 1", "2", "k"])).is_err());
+        assert!(Script::try_from(args(&["script", "load"])).is_err());
+        assert!(Script::try_from(args(&["script", "flush", "now"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_eval() -> Result<()> {
+        let backend = Backend::new();
+        let ret = eval(
+            &backend,
+            "redis.call('SET', KEYS[1], ARGV[1]) return redis.call('get', KEYS[1])",
+            &["1", "k", "v"],
+        )?;
+        assert_eq!(ret, BulkString::new("v").into());
+        assert_eq!(backend.get("k"), Some(BulkString::new("v").into()));
+
+        let ret = eval(
+            &backend,
+            "return {1, 2.9, 'a', true, redis.call('get', 'missing'), nil, 'unreached'}",
+            &["0"],
+        )?;
+        let expected = RespArray::new([
+            RespFrame::Integer(1),
+            RespFrame::Integer(2),
+            BulkString::new("a").into(),
+            RespFrame::Integer(1),
+            RespNull.into(),
+        ]);
+        assert_eq!(ret, expected.into());
+        assert_eq!(
+            eval(&backend, "return redis.call('set', 'k', 'w')", &["0"])?,
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            eval(&backend, "return redis.error_reply('my error')", &["0"])?,
+            SimpleError::new("my error").into()
+        );
+        assert_eq!(eval(&backend, "return nil", &["0"])?, RespNull.into());
+        assert_eq!(
+            eval(&backend, "return redis.sha1hex('')", &["0"])?,
+            BulkString::new("da39a3ee5e6b4b0d3255bfef95601890afd80709").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_eval_errors() -> Result<()> {
+        let backend = Backend::new();
+        let err = eval(&backend, "return redis.call('lset', 'l', 0, 'a')", &["0"]);
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("Error running script"));
+        let ret = eval(&backend, "return redis.pcall('lset', 'l', 0, 'a')", &["0"])?;
+        assert!(matches!(ret, RespFrame::Error(_)));
+
+        for script in [
+            "return redis.call('nosuchcommand')",
+            "return redis.call('multi')",
+            "return redis.call('eval', 'return 1', 0)",
+            "return redis.call({})",
+            "return os.time()",
+        ] {
+            assert!(eval(&backend, script, &["0"]).is_err(), "{}", script);
+        }
+        let err = eval(&backend, "return (", &["0"]).unwrap_err();
+        assert!(err.to_string().contains("Error compiling script"));
+
+        let ret = run::<EvalRo>(&backend, &["eval_ro", "return redis.call('get', 'k')", "0"])?;
+        assert_eq!(ret, RespNull.into());
+        let ret = run::<EvalRo>(
+            &backend,
+            &["eval_ro", "return redis.call('set', 'k', 'v')", "0"],
+        );
+        assert!(ret.unwrap_err().to_string().contains("read-only"));
+        assert!(backend.get("k").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_eval_ro_rejects_every_write_command() -> Result<()> {
+        let backend = Backend::new();
+        for cmd in [
+            &["set", "k", "v"][..],
+            &["del", "k"][..],
+            &["flushall"][..],
+            &["flushdb"][..],
+            &["hset", "h", "f", "v"][..],
+            &["hdel", "h", "f"][..],
+            &["hsetnx", "h", "f", "v"][..],
+            &["hincrby", "h", "f", "1"][..],
+            &["hincrbyfloat", "h", "f", "1.5"][..],
+            &["hexpire", "h", "10", "FIELDS", "1", "f"][..],
+            &["hpexpire", "h", "10", "FIELDS", "1", "f"][..],
+            &["hexpireat", "h", "10", "FIELDS", "1", "f"][..],
+            &["hpexpireat", "h", "10", "FIELDS", "1", "f"][..],
+            &["hpersist", "h", "FIELDS", "1", "f"][..],
+            &["hgetex", "h", "PERSIST", "FIELDS", "1", "f"][..],
+            &["hsetex", "h", "FIELDS", "1", "f", "v"][..],
+            &["hgetdel", "h", "FIELDS", "1", "f"][..],
+            &["lpush", "l", "a"][..],
+            &["rpush", "l", "a"][..],
+            &["lpushx", "l", "a"][..],
+            &["rpushx", "l", "a"][..],
+            &["lpop", "l"][..],
+            &["rpop", "l"][..],
+            &["lset", "l", "0", "a"][..],
+            &["linsert", "l", "BEFORE", "a", "b"][..],
+            &["lrem", "l", "0", "a"][..],
+            &["ltrim", "l", "0", "1"][..],
+            &["lmove", "l", "m", "LEFT", "RIGHT"][..],
+            &["lmpop", "1", "l", "LEFT"][..],
+            &["blpop", "l", "1"][..],
+            &["brpop", "l", "1"][..],
+            &["blmove", "l", "m", "LEFT", "RIGHT", "1"][..],
+            &["blmpop", "1", "1", "l", "LEFT"][..],
+            &["sadd", "s", "a"][..],
+            &["srem", "s", "a"][..],
+            &["spop", "s"][..],
+            &["smove", "s", "t", "a"][..],
+            &["sinterstore", "d", "s"][..],
+            &["sunionstore", "d", "s"][..],
+            &["sdiffstore", "d", "s"][..],
+            &["zadd", "z", "1", "a"][..],
+            &["zincrby", "z", "1", "a"][..],
+            &["zrem", "z", "a"][..],
+            &["zrangestore", "d", "z", "0", "-1"][..],
+            &["zremrangebyrank", "z", "0", "1"][..],
+            &["zremrangebyscore", "z", "0", "1"][..],
+            &["zremrangebylex", "z", "-", "+"][..],
+            &["zpopmin", "z"][..],
+            &["zpopmax", "z"][..],
+            &["zunionstore", "d", "1", "z"][..],
+            &["zinterstore", "d", "1", "z"][..],
+            &["zdiffstore", "d", "1", "z"][..],
+            &["zmpop", "1", "z", "MIN"][..],
+            &["bzpopmin", "z", "1"][..],
+            &["bzpopmax", "z", "1"][..],
+            &["bzmpop", "1", "1", "z", "MIN"][..],
+            &["xadd", "x", "*", "f", "v"][..],
+            &["xdel", "x", "1-0"][..],
+            &["xtrim", "x", "MAXLEN", "10"][..],
+            &["xgroup", "CREATE", "x", "g", "$", "MKSTREAM"][..],
+            &["xreadgroup", "GROUP", "g", "c", "STREAMS", "x", ">"][..],
+            &["xack", "x", "g", "1-0"][..],
+            &["xclaim", "x", "g", "c", "0", "1-0"][..],
+            &["xautoclaim", "x", "g", "c", "0", "0"][..],
+            &["pfadd", "p", "a"][..],
+            &["pfmerge", "d", "p"][..],
+            &["geoadd", "g", "13.361389", "38.115556", "a"][..],
+            &[
+                "geosearchstore",
+                "d",
+                "g",
+                "FROMLONLAT",
+                "15",
+                "37",
+                "BYRADIUS",
+                "200",
+                "km",
+            ][..],
+            &["bf.reserve", "b", "0.01", "100"][..],
+            &["bf.add", "b", "a"][..],
+            &["bf.madd", "b", "a"][..],
+            &["cf.reserve", "c", "100"][..],
+            &["cf.add", "c", "a"][..],
+            &["cf.del", "c", "a"][..],
+            &["cms.initbydim", "m", "10", "5"][..],
+            &["cms.initbyprob", "m", "0.01", "0.01"][..],
+            &["cms.incrby", "m", "a", "1"][..],
+            &["topk.reserve", "t", "3"][..],
+            &["topk.add", "t", "a"][..],
+            &["json.set", "j", "$", "1"][..],
+            &["json.del", "j"][..],
+            &["json.numincrby", "j", "$", "1"][..],
+            &["json.strappend", "j", "$", "\"a\""][..],
+            &["json.arrappend", "j", "$", "1"][..],
+            &["json.arrinsert", "j", "$", "0", "1"][..],
+            &["json.arrpop", "j"][..],
+            &["ts.create", "ts"][..],
+            &["ts.add", "ts", "1", "1"][..],
+            &["ts.madd", "ts", "1", "1"][..],
+            &["ts.incrby", "ts", "1"][..],
+            &["ts.decrby", "ts", "1"][..],
+            &["ts.createrule", "ts", "ts2", "AGGREGATION", "avg", "1000"][..],
+            &["ts.deleterule", "ts", "ts2"][..],
+            &["ft.create", "idx", "SCHEMA", "f", "TEXT"][..],
+            &["ft.dropindex", "idx"][..],
+            &["vadd", "v", "VALUES", "2", "1", "2", "a"][..],
+            &["vrem", "v", "a"][..],
+            &["vsetattr", "v", "a", "{}"][..],
+        ] {
+            let parsed = Command::try_from(args(cmd))?;
+            assert!(parsed.has_flag(CommandFlag::Write), "{:?}", cmd);
+            let mut eval_ro = vec!["eval_ro", "return redis.call(table.unpack(ARGV))", "0"];
+            eval_ro.extend_from_slice(cmd);
+            let err = run::<EvalRo>(&backend, &eval_ro).unwrap_err();
+            assert!(err.to_string().contains("read-only"), "{:?}: {}", cmd, err);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_script_cache() -> Result<()> {
+        let backend = Backend::new();
+        let script = "return ARGV[1]";
+        let sha = run::<Script>(&backend, &["script", "load", script])?;
+        let RespFrame::BulkString(sha) = sha else {
+            panic!("SCRIPT LOAD must reply with the SHA1");
+        };
+        let sha = String::from_utf8(sha.0)?;
+        let ret = run::<EvalSha>(&backend, &["evalsha", &sha, "0", "a"])?;
+        assert_eq!(ret, BulkString::new("a").into());
+
+        let ret = run::<Script>(&backend, &["script", "exists", &sha, "nosuchsha"])?;
+        let expected = RespArray::new([RespFrame::Integer(1), RespFrame::Integer(0)]);
+        assert_eq!(ret, expected.into());
+
+        run::<Script>(&backend, &["script", "flush"])?;
+        let err = run::<EvalShaRo>(&backend, &["evalsha_ro", &sha, "0"]).unwrap_err();
+        assert!(err.to_string().contains("NOSCRIPT"));
+        // EVAL caches what it runs too
+        eval(&backend, script, &["0"])?;
+        assert!(backend.script_exists(&sha));
+        assert!(run::<Script>(&backend, &["script", "load", "return ("]).is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_script_busy_kill() -> Result<()> {
+        let backend = Backend::new();
+        backend.set_busy_reply_threshold(10);
+        let cmd = |cmd: &[&str]| Command::try_from(args(cmd));
+        let script = cmd(&["eval", "while true do end", "0"])?;
+        let runner = backend.clone();
+        let running = tokio::spawn(async move { script.execute_blocking(&runner).await });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let err = cmd(&["get", "k"])?.execute_blocking(&backend).await;
+        assert!(err.unwrap_err().to_string().contains("BUSY"));
+        cmd(&["script", "kill"])?.execute_blocking(&backend).await?;
+        let err = running.await?.unwrap_err();
+        assert!(err.to_string().contains("Script killed"));
+
+        assert!(cmd(&["get", "k"])?.execute_blocking(&backend).await.is_ok());
+        let err = cmd(&["script", "kill"])?.execute_blocking(&backend).await;
+        assert!(err.unwrap_err().to_string().contains("NOTBUSY"));
+        Ok(())
+    }
+}
@@ -0,0 +1,1116 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use dashmap::mapref::one::Ref;
+
+use crate::array::RespArray;
+use crate::backend::{Backend, FieldKind, FieldSpec, SearchIndex, NOTIFY_GENERIC};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_count, extract_float, extract_string, validate_command,
+    validate_command_at_least, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::frame::{format_double, RespFrame};
+use crate::map::RespMap;
+use crate::null::RespNull;
+
+#[derive(Debug)]
+pub struct FtCreate {
+    index: String,
+    prefixes: Vec<String>,
+    stopwords: Option<Vec<String>>,
+    schema: Vec<FieldSpec>,
+}
+
+#[derive(Debug)]
+pub struct FtSearch {
+    index: String,
+    query: String,
+    nocontent: bool,
+    withscores: bool,
+    /// the aliases or hash fields to reply with, every field of the hash when `None`
+    return_fields: Option<Vec<String>>,
+    /// the field to order by and whether the order is ascending
+    sortby: Option<(String, bool)>,
+    offset: usize,
+    num: usize,
+}
+
+#[derive(Debug)]
+pub struct FtAggregate {
+    index: String,
+    query: String,
+    /// the fields rows start with, the schema fields when `None`
+    load: Option<Vec<String>>,
+    steps: Vec<AggregateStep>,
+}
+
+#[derive(Debug)]
+pub struct FtInfo {
+    index: String,
+}
+
+#[derive(Debug)]
+pub struct FtDropIndex {
+    index: String,
+    /// DD, also deletes the indexed hashes
+    delete_docs: bool,
+}
+
+#[derive(Debug)]
+enum AggregateStep {
+    GroupBy {
+        fields: Vec<String>,
+        reducers: Vec<ReducerSpec>,
+    },
+    SortBy {
+        /// fields with whether they are ascending
+        keys: Vec<(String, bool)>,
+        max: Option<usize>,
+    },
+    Limit {
+        offset: usize,
+        num: usize,
+    },
+}
+
+#[derive(Debug)]
+struct ReducerSpec {
+    reducer: Reducer,
+    field: Option<String>,
+    alias: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Reducer {
+    Count,
+    CountDistinct,
+    Sum,
+    Min,
+    Max,
+    Avg,
+    ToList,
+}
+
+/// A row of FT.AGGREGATE: fields and their values, in reply order.
+type Row = Vec<(String, RespFrame)>;
+
+impl CommandExecutor for FtCreate {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let index = SearchIndex::new(self.prefixes, self.stopwords, self.schema);
+        backend
+            .ft_create(self.index, index)
+            .map_err(CommandError::InvalidArgument)?;
+        Ok(RESP_OK.clone())
+    }
+}
+
+impl CommandExecutor for FtSearch {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        // the index is released before the hashes are read, reading them may re-index
+        let (total, hits, returns) = {
+            let index = get_index(backend, &self.index)?;
+            let query = index
+                .parse_query(&self.query)
+                .map_err(CommandError::InvalidArgument)?;
+            let mut hits = index.search(&query);
+            if let Some((alias, ascending)) = &self.sortby {
+                let field = index
+                    .sortable(alias)
+                    .map_err(CommandError::InvalidArgument)?;
+                hits.sort_by(|a, b| {
+                    let order = index.compare(&a.0, &b.0, field);
+                    if *ascending {
+                        order
+                    } else {
+                        order.reverse()
+                    }
+                });
+            }
+            let total = hits.len();
+            let hits: Vec<(String, f64)> =
+                hits.into_iter().skip(self.offset).take(self.num).collect();
+            let returns = self.return_fields.map(|fields| {
+                fields
+                    .into_iter()
+                    .map(|field| {
+                        let name = index
+                            .field(&field)
+                            .map_or_else(|| field.clone(), |i| index.schema()[i].name.clone());
+                        (field, name)
+                    })
+                    .collect::<Vec<_>>()
+            });
+            (total, hits, returns)
+        };
+
+        let mut reply = vec![RespFrame::Integer(total as i64)];
+        for (key, score) in hits {
+            reply.push(BulkString::new(key.as_str()).into());
+            if self.withscores {
+                reply.push(RespFrame::Double(score));
+            }
+            if self.nocontent {
+                continue;
+            }
+            let fields = hash_fields(backend, &key);
+            let content: Vec<RespFrame> = match &returns {
+                Some(returns) => returns
+                    .iter()
+                    .filter_map(|(field, name)| {
+                        let value = fields.get(name)?;
+                        Some([BulkString::new(field.as_str()).into(), value.clone()])
+                    })
+                    .flatten()
+                    .collect(),
+                None => {
+                    let mut fields: Vec<(String, RespFrame)> = fields.into_iter().collect();
+                    fields.sort_by(|a, b| a.0.cmp(&b.0));
+                    fields
+                        .into_iter()
+                        .flat_map(|(field, value)| [BulkString::new(field).into(), value])
+                        .collect()
+                }
+            };
+            reply.push(RespArray::new(content).into());
+        }
+        Ok(RespArray::new(reply).into())
+    }
+}
+
+impl CommandExecutor for FtAggregate {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let (keys, schema) = {
+            let index = get_index(backend, &self.index)?;
+            let query = index
+                .parse_query(&self.query)
+                .map_err(CommandError::InvalidArgument)?;
+            let keys: Vec<String> = index
+                .search(&query)
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect();
+            let schema: Vec<(String, String)> = index
+                .schema()
+                .iter()
+                .map(|spec| (spec.alias.clone(), spec.name.clone()))
+                .collect();
+            (keys, schema)
+        };
+
+        let mut rows: Vec<Row> = keys
+            .iter()
+            .map(|key| {
+                let fields = hash_fields(backend, key);
+                match &self.load {
+                    Some(load) => load
+                        .iter()
+                        .filter_map(|field| {
+                            let name = schema
+                                .iter()
+                                .find(|(alias, _)| alias == field)
+                                .map_or(field, |(_, name)| name);
+                            Some((field.clone(), fields.get(name)?.clone()))
+                        })
+                        .collect(),
+                    None => schema
+                        .iter()
+                        .filter_map(|(alias, name)| {
+                            Some((alias.clone(), fields.get(name)?.clone()))
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        for step in &self.steps {
+            rows = step.apply(rows);
+        }
+
+        let mut reply = vec![RespFrame::Integer(rows.len() as i64)];
+        reply.extend(rows.into_iter().map(|row| {
+            RespArray::new(
+                row.into_iter()
+                    .flat_map(|(field, value)| [BulkString::new(field).into(), value])
+                    .collect::<Vec<_>>(),
+            )
+            .into()
+        }));
+        Ok(RespArray::new(reply).into())
+    }
+}
+
+impl CommandExecutor for FtInfo {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let index = get_index(backend, &self.index)?;
+        let mut info = RespMap::new();
+        info.insert(
+            "index_name".to_string(),
+            BulkString::new(self.index.as_str()).into(),
+        );
+        let prefixes: Vec<RespFrame> = index
+            .prefixes()
+            .iter()
+            .map(|prefix| BulkString::new(prefix.as_str()).into())
+            .collect();
+        info.insert(
+            "index_definition".to_string(),
+            RespArray::new([
+                BulkString::new("key_type").into(),
+                BulkString::new("HASH").into(),
+                BulkString::new("prefixes").into(),
+                RespArray::new(prefixes).into(),
+            ])
+            .into(),
+        );
+        let attributes: Vec<RespFrame> = index
+            .schema()
+            .iter()
+            .map(|spec| {
+                let mut attribute: Vec<RespFrame> = vec![
+                    BulkString::new("identifier").into(),
+                    BulkString::new(spec.name.as_str()).into(),
+                    BulkString::new("attribute").into(),
+                    BulkString::new(spec.alias.as_str()).into(),
+                    BulkString::new("type").into(),
+                    BulkString::new(spec.type_name()).into(),
+                ];
+                match spec.kind {
+                    FieldKind::Text { weight, nostem } => {
+                        attribute.push(BulkString::new("WEIGHT").into());
+                        attribute.push(BulkString::new(format_double(weight)).into());
+                        if nostem {
+                            attribute.push(BulkString::new("NOSTEM").into());
+                        }
+                    }
+                    FieldKind::Tag {
+                        separator,
+                        case_sensitive,
+                    } => {
+                        attribute.push(BulkString::new("SEPARATOR").into());
+                        attribute.push(BulkString::new(separator.to_string()).into());
+                        if case_sensitive {
+                            attribute.push(BulkString::new("CASESENSITIVE").into());
+                        }
+                    }
+                    FieldKind::Numeric => {}
+                }
+                if spec.sortable {
+                    attribute.push(BulkString::new("SORTABLE").into());
+                }
+                RespArray::new(attribute).into()
+            })
+            .collect();
+        info.insert("attributes".to_string(), RespArray::new(attributes).into());
+        info.insert(
+            "num_docs".to_string(),
+            RespFrame::Integer(index.num_docs() as i64),
+        );
+        info.insert(
+            "num_terms".to_string(),
+            RespFrame::Integer(index.num_terms() as i64),
+        );
+        info.insert(
+            "num_records".to_string(),
+            RespFrame::Integer(index.num_records() as i64),
+        );
+        Ok(info.into())
+    }
+}
+
+impl CommandExecutor for FtDropIndex {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let (_, index) = backend
+            .search
+            .remove(&self.index)
+            .ok_or_else(unknown_index)?;
+        if self.delete_docs {
+            for key in index.keys() {
+                if backend.del(key) {
+                    backend.notify(NOTIFY_GENERIC, "del", key);
+                }
+            }
+        }
+        Ok(RESP_OK.clone())
+    }
+}
+
+impl AggregateStep {
+    fn apply(&self, rows: Vec<Row>) -> Vec<Row> {
+        match self {
+            AggregateStep::GroupBy { fields, reducers } => {
+                let mut groups: Vec<(Vec<Option<RespFrame>>, Vec<Row>)> = vec![];
+                let mut positions: HashMap<Vec<Option<String>>, usize> = HashMap::new();
+                for row in rows {
+                    let values: Vec<Option<RespFrame>> = fields
+                        .iter()
+                        .map(|field| row_value(&row, field).cloned())
+                        .collect();
+                    let group = values
+                        .iter()
+                        .map(|value| value.as_ref().and_then(frame_text))
+                        .collect();
+                    let position = *positions.entry(group).or_insert_with(|| {
+                        groups.push((values, vec![]));
+                        groups.len() - 1
+                    });
+                    groups[position].1.push(row);
+                }
+                groups
+                    .into_iter()
+                    .map(|(values, rows)| {
+                        let mut group: Row = fields
+                            .iter()
+                            .zip(values)
+                            .map(|(field, value)| {
+                                (field.clone(), value.unwrap_or(RespFrame::Null(RespNull)))
+                            })
+                            .collect();
+                        group.extend(reducers.iter().map(|spec| {
+                            (
+                                spec.alias.clone(),
+                                spec.reducer.apply(&rows, spec.field.as_deref()),
+                            )
+                        }));
+                        group
+                    })
+                    .collect()
+            }
+            AggregateStep::SortBy { keys, max } => {
+                let mut rows = rows;
+                rows.sort_by(|a, b| {
+                    keys.iter()
+                        .map(|(field, ascending)| {
+                            let order = compare_values(row_value(a, field), row_value(b, field));
+                            if *ascending {
+                                order
+                            } else {
+                                order.reverse()
+                            }
+                        })
+                        .find(|order| order.is_ne())
+                        .unwrap_or(Ordering::Equal)
+                });
+                if let Some(max) = max {
+                    rows.truncate(*max);
+                }
+                rows
+            }
+            AggregateStep::Limit { offset, num } => {
+                rows.into_iter().skip(*offset).take(*num).collect()
+            }
+        }
+    }
+}
+
+impl Reducer {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_uppercase().as_str() {
+            "COUNT" => Reducer::Count,
+            "COUNT_DISTINCT" => Reducer::CountDistinct,
+            "SUM" => Reducer::Sum,
+            "MIN" => Reducer::Min,
+            "MAX" => Reducer::Max,
+            "AVG" => Reducer::Avg,
+            "TOLIST" => Reducer::ToList,
+            _ => return None,
+        })
+    }
+
+    fn apply(&self, rows: &[Row], field: Option<&str>) -> RespFrame {
+        let texts: Vec<String> = field
+            .map(|field| {
+                rows.iter()
+                    .filter_map(|row| row_value(row, field).and_then(frame_text))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let numbers: Vec<f64> = texts.iter().filter_map(|text| text.parse().ok()).collect();
+        let number = |value: Option<f64>| {
+            value.map_or(RespFrame::Null(RespNull), |value| {
+                BulkString::new(format_double(value)).into()
+            })
+        };
+        match self {
+            Reducer::Count => BulkString::new(rows.len().to_string()).into(),
+            Reducer::CountDistinct => {
+                let distinct: HashSet<&String> = texts.iter().collect();
+                BulkString::new(distinct.len().to_string()).into()
+            }
+            Reducer::Sum => number(Some(numbers.iter().sum())),
+            Reducer::Min => number(numbers.iter().copied().reduce(f64::min)),
+            Reducer::Max => number(numbers.iter().copied().reduce(f64::max)),
+            Reducer::Avg => number(
+                (!numbers.is_empty()).then(|| numbers.iter().sum::<f64>() / numbers.len() as f64),
+            ),
+            Reducer::ToList => {
+                let mut seen = HashSet::new();
+                RespArray::new(
+                    texts
+                        .into_iter()
+                        .filter(|text| seen.insert(text.clone()))
+                        .map(|text| BulkString::new(text).into())
+                        .collect::<Vec<_>>(),
+                )
+                .into()
+            }
+        }
+    }
+}
+
+fn row_value<'a>(row: &'a Row, field: &str) -> Option<&'a RespFrame> {
+    row.iter()
+        .find(|(name, _)| name == field)
+        .map(|(_, value)| value)
+}
+
+fn frame_text(frame: &RespFrame) -> Option<String> {
+    match frame {
+        RespFrame::BulkString(s) => String::from_utf8(s.0.clone()).ok(),
+        RespFrame::SimpleString(s) => Some(s.0.clone()),
+        RespFrame::Integer(i) => Some(i.to_string()),
+        RespFrame::Double(f) => Some(format_double(*f)),
+        _ => None,
+    }
+}
+
+/// Orders numerically when both values are numbers, as text otherwise. Missing values come last.
+fn compare_values(a: Option<&RespFrame>, b: Option<&RespFrame>) -> Ordering {
+    match (a.and_then(frame_text), b.and_then(frame_text)) {
+        (Some(a), Some(b)) => match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(a), Ok(b)) => a.total_cmp(&b),
+            _ => a.cmp(&b),
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn hash_fields(backend: &Backend, key: &str) -> HashMap<String, RespFrame> {
+    backend
+        .hgetall(key)
+        .map(|hmap| hmap.into_iter().collect())
+        .unwrap_or_default()
+}
+
+fn get_index<'a>(
+    backend: &'a Backend,
+    name: &str,
+) -> Result<Ref<'a, String, SearchIndex>, CommandError> {
+    backend.search.get(name).ok_or_else(unknown_index)
+}
+
+fn unknown_index() -> CommandError {
+    CommandError::InvalidArgument("Unknown index name".to_string())
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+/// A `@field` reference of FT.AGGREGATE, without the `@`.
+fn parse_property(arg: Option<RespFrame>) -> Result<String, CommandError> {
+    let property = extract_string(arg)?;
+    match property.strip_prefix('@') {
+        Some(field) => Ok(field.to_string()),
+        None => Err(CommandError::InvalidArgument(format!(
+            "Bad arguments for property `{}`: missing @",
+            property
+        ))),
+    }
+}
+
+fn is_keyword(arg: &RespFrame, keywords: &[&str]) -> bool {
+    match arg {
+        RespFrame::BulkString(s) => keywords
+            .iter()
+            .any(|keyword| s.0.eq_ignore_ascii_case(keyword.as_bytes())),
+        _ => false,
+    }
+}
+
+fn parse_field_spec(args: &mut Peekable<IntoIter<RespFrame>>) -> Result<FieldSpec, CommandError> {
+    let name = extract_string(args.next())?;
+    let mut alias = name.clone();
+    let mut kind = extract_string(args.next())?.to_ascii_uppercase();
+    if kind == "AS" {
+        alias = extract_string(args.next())?;
+        kind = extract_string(args.next())?.to_ascii_uppercase();
+    }
+    let mut kind = match kind.as_str() {
+        "TEXT" => FieldKind::Text {
+            weight: 1.0,
+            nostem: false,
+        },
+        "TAG" => FieldKind::Tag {
+            separator: ',',
+            case_sensitive: false,
+        },
+        "NUMERIC" => FieldKind::Numeric,
+        _ => {
+            return Err(CommandError::InvalidArgument(format!(
+                "Invalid field type for field `{}`",
+                name
+            )))
+        }
+    };
+    let mut sortable = false;
+    const OPTIONS: &[&str] = &["WEIGHT", "NOSTEM", "SEPARATOR", "CASESENSITIVE", "SORTABLE"];
+    while let Some(option) = args.next_if(|arg| is_keyword(arg, OPTIONS)) {
+        let option = extract_string(Some(option))?.to_ascii_uppercase();
+        match (&mut kind, option.as_str()) {
+            (FieldKind::Text { weight, .. }, "WEIGHT") => {
+                *weight = extract_float(args.next())?;
+                if !(weight.is_finite() && *weight >= 0.0) {
+                    return Err(CommandError::InvalidArgument(
+                        "WEIGHT must be a non-negative number".to_string(),
+                    ));
+                }
+            }
+            (FieldKind::Text { nostem, .. }, "NOSTEM") => *nostem = true,
+            (FieldKind::Tag { separator, .. }, "SEPARATOR") => {
+                let value = extract_string(args.next())?;
+                let mut chars = value.chars();
+                *separator = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "SEPARATOR must be a single character".to_string(),
+                        ))
+                    }
+                };
+            }
+            (FieldKind::Tag { case_sensitive, .. }, "CASESENSITIVE") => *case_sensitive = true,
+            (_, "SORTABLE") => sortable = true,
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "{} is not valid for field `{}`",
+                    option, name
+                )))
+            }
+        }
+    }
+    Ok(FieldSpec {
+        name,
+        alias,
+        kind,
+        sortable,
+    })
+}
+
+fn parse_reducer(args: &mut Peekable<IntoIter<RespFrame>>) -> Result<ReducerSpec, CommandError> {
+    let name = extract_string(args.next())?;
+    let reducer = Reducer::parse(&name)
+        .ok_or_else(|| CommandError::InvalidArgument(format!("Unknown reducer `{}`", name)))?;
+    let nargs = extract_count(args.next())?;
+    let expected = if reducer == Reducer::Count { 0 } else { 1 };
+    if nargs != expected {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} takes {} arguments",
+            name.to_ascii_uppercase(),
+            expected
+        )));
+    }
+    let field = match expected {
+        0 => None,
+        _ => Some(parse_property(args.next())?),
+    };
+    let alias = match args.next_if(|arg| is_keyword(arg, &["AS"])) {
+        Some(_) => extract_string(args.next())?,
+        None => format!(
+            "__generated_alias{}{}",
+            name.to_ascii_lowercase(),
+            field.as_deref().unwrap_or_default()
+        ),
+    };
+    Ok(ReducerSpec {
+        reducer,
+        field,
+        alias,
+    })
+}
+
+fn parse_limit(args: &mut Peekable<IntoIter<RespFrame>>) -> Result<(usize, usize), CommandError> {
+    Ok((extract_count(args.next())?, extract_count(args.next())?))
+}
+
+impl TryFrom<RespArray> for FtCreate {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["ft.create"], 4)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let index = extract_string(args.next())?;
+        let mut prefixes = vec![];
+        let mut stopwords = None;
+        loop {
+            match extract_string(args.next())?.to_ascii_uppercase().as_str() {
+                "ON" => {
+                    if !extract_string(args.next())?.eq_ignore_ascii_case("hash") {
+                        return Err(CommandError::InvalidArgument(
+                            "only HASH indexes are supported".to_string(),
+                        ));
+                    }
+                }
+                "PREFIX" => {
+                    let count = extract_count(args.next())?;
+                    prefixes = (0..count)
+                        .map(|_| extract_string(args.next()))
+                        .collect::<Result<_, _>>()?;
+                }
+                "STOPWORDS" => {
+                    let count = extract_count(args.next())?;
+                    stopwords = Some(
+                        (0..count)
+                            .map(|_| extract_string(args.next()))
+                            .collect::<Result<_, _>>()?,
+                    );
+                }
+                "SCHEMA" => break,
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        let mut schema: Vec<FieldSpec> = vec![];
+        while args.peek().is_some() {
+            let spec = parse_field_spec(&mut args)?;
+            if schema.iter().any(|other| other.alias == spec.alias) {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Duplicate field in schema - {}",
+                    spec.alias
+                )));
+            }
+            schema.push(spec);
+        }
+        if schema.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "Fields arguments are missing".to_string(),
+            ));
+        }
+        Ok(FtCreate {
+            index,
+            prefixes,
+            stopwords,
+            schema,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FtSearch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["ft.search"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let mut search = FtSearch {
+            index: extract_string(args.next())?,
+            query: extract_string(args.next())?,
+            nocontent: false,
+            withscores: false,
+            return_fields: None,
+            sortby: None,
+            offset: 0,
+            num: 10,
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                "NOCONTENT" => search.nocontent = true,
+                "WITHSCORES" => search.withscores = true,
+                "VERBATIM" => {}
+                "RETURN" => {
+                    let count = extract_count(args.next())?;
+                    search.return_fields = Some(
+                        (0..count)
+                            .map(|_| extract_string(args.next()))
+                            .collect::<Result<_, _>>()?,
+                    );
+                }
+                "SORTBY" => {
+                    let field = extract_string(args.next())?;
+                    let ascending = match args.next_if(|arg| is_keyword(arg, &["ASC", "DESC"])) {
+                        Some(order) => extract_string(Some(order))?.eq_ignore_ascii_case("asc"),
+                        None => true,
+                    };
+                    search.sortby = Some((field, ascending));
+                }
+                "LIMIT" => (search.offset, search.num) = parse_limit(&mut args)?,
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(search)
+    }
+}
+
+impl TryFrom<RespArray> for FtAggregate {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["ft.aggregate"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let index = extract_string(args.next())?;
+        let query = extract_string(args.next())?;
+        let mut load = None;
+        let mut steps = vec![];
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                "LOAD" => {
+                    let count = extract_count(args.next())?;
+                    load = Some(
+                        (0..count)
+                            .map(|_| parse_property(args.next()))
+                            .collect::<Result<_, _>>()?,
+                    );
+                }
+                "GROUPBY" => {
+                    let count = extract_count(args.next())?;
+                    let fields = (0..count)
+                        .map(|_| parse_property(args.next()))
+                        .collect::<Result<_, _>>()?;
+                    let mut reducers = vec![];
+                    while args.next_if(|arg| is_keyword(arg, &["REDUCE"])).is_some() {
+                        reducers.push(parse_reducer(&mut args)?);
+                    }
+                    steps.push(AggregateStep::GroupBy { fields, reducers });
+                }
+                "SORTBY" => {
+                    let count = extract_count(args.next())?;
+                    let mut keys: Vec<(String, bool)> = vec![];
+                    for _ in 0..count {
+                        let arg = args.next();
+                        match (&arg, keys.last_mut()) {
+                            (Some(order), Some((_, ascending)))
+                                if is_keyword(order, &["ASC", "DESC"]) =>
+                            {
+                                *ascending = is_keyword(order, &["ASC"]);
+                            }
+                            _ => keys.push((parse_property(arg)?, true)),
+                        }
+                    }
+                    let max = match args.next_if(|arg| is_keyword(arg, &["MAX"])) {
+                        Some(_) => Some(extract_count(args.next())?),
+                        None => None,
+                    };
+                    steps.push(AggregateStep::SortBy { keys, max });
+                }
+                "LIMIT" => {
+                    let (offset, num) = parse_limit(&mut args)?;
+                    steps.push(AggregateStep::Limit { offset, num });
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(FtAggregate {
+            index,
+            query,
+            load,
+            steps,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FtInfo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ft.info"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(FtInfo {
+            index: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FtDropIndex {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["ft.dropindex"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let index = extract_string(args.next())?;
+        let delete_docs = match args.next() {
+            Some(arg) if is_keyword(&arg, &["DD"]) => true,
+            Some(_) => return Err(syntax_error()),
+            None => false,
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(FtDropIndex { index, delete_docs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::backend::{now_millis, ExpireCondition};
+    use crate::cmd::{args, bulk};
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    fn run<T>(backend: &Backend, cmd: &[&str]) -> Result<RespFrame, CommandError>
+    where
+        T: TryFrom<RespArray, Error = CommandError> + CommandExecutor,
+    {
+        T::try_from(args(cmd))?.execute(backend)
+    }
+
+    fn hset(backend: &Backend, key: &str, fields: &[(&str, &str)]) {
+        backend.hset(
+            key,
+            fields
+                .iter()
+                .map(|(field, value)| (field.to_string(), BulkString::new(*value).into()))
+                .collect(),
+        );
+    }
+
+    fn found_keys(reply: RespFrame) -> Vec<RespFrame> {
+        let RespFrame::Array(reply) = reply else {
+            panic!("expected an array");
+        };
+        reply.0.into_iter().skip(1).collect()
+    }
+
+    #[test]
+    fn test_ft_create_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*11\r\n$9\r\nft.create\r\n$3\r\nidx\r\n$6\r\nprefix\r\n$1\r\n1\r\n$5\r\nitem:\r\n$6\r\nschema\r\n$5\r\ntitle\r\n$4\r\ntext\r\n$6\r\nweight\r\n$1\r\n2\r\n$8\r\nsortable\r\n",
+        );
+        let cmd: FtCreate = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.prefixes, ["item:"]);
+        assert_eq!(
+            cmd.schema,
+            [FieldSpec {
+                name: "title".to_string(),
+                alias: "title".to_string(),
+                kind: FieldKind::Text {
+                    weight: 2.0,
+                    nostem: false,
+                },
+                sortable: true,
+            }]
+        );
+
+        let cmd: Result<FtCreate, _> =
+            args(&["ft.create", "idx", "schema", "n", "numeric", "nostem"]).try_into();
+        assert!(cmd.is_err());
+        let cmd: Result<FtCreate, _> =
+            args(&["ft.create", "idx", "on", "json", "schema", "n", "numeric"]).try_into();
+        assert!(cmd.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ft_search_follows_hash_writes() -> Result<()> {
+        let backend = Backend::new();
+        hset(
+            &backend,
+            "item:1",
+            &[("title", "red apple"), ("price", "3")],
+        );
+        run::<FtCreate>(
+            &backend,
+            &[
+                "ft.create",
+                "idx",
+                "prefix",
+                "1",
+                "item:",
+                "schema",
+                "title",
+                "text",
+                "price",
+                "numeric",
+                "sortable",
+                "kind",
+                "tag",
+            ],
+        )?;
+        hset(
+            &backend,
+            "item:2",
+            &[("title", "green apples"), ("price", "1")],
+        );
+        hset(&backend, "item:3", &[("title", "banana"), ("price", "2")]);
+        hset(&backend, "other:1", &[("title", "apple")]);
+
+        assert_eq!(
+            run::<FtSearch>(
+                &backend,
+                &[
+                    "ft.search",
+                    "idx",
+                    "apple",
+                    "sortby",
+                    "price",
+                    "return",
+                    "1",
+                    "price"
+                ]
+            )?,
+            RespArray::new([
+                RespFrame::Integer(2),
+                bulk("item:2"),
+                RespArray::new([bulk("price"), bulk("1")]).into(),
+                bulk("item:1"),
+                RespArray::new([bulk("price"), bulk("3")]).into(),
+            ])
+            .into()
+        );
+        assert_eq!(
+            run::<FtSearch>(
+                &backend,
+                &[
+                    "ft.search",
+                    "idx",
+                    "@price:[2 +inf]",
+                    "nocontent",
+                    "sortby",
+                    "price",
+                    "desc"
+                ]
+            )?,
+            RespArray::new([RespFrame::Integer(2), bulk("item:1"), bulk("item:3")]).into()
+        );
+        assert!(run::<FtSearch>(&backend, &["ft.search", "idx", "*", "sortby", "title"]).is_err());
+
+        // the index follows HSET, HDEL, expired fields and DEL
+        hset(&backend, "item:3", &[("title", "apple pie")]);
+        backend.hdel("item:1", &["title".to_string()]);
+        backend.hexpire_at("item:2", "title", now_millis() - 1, ExpireCondition::Always);
+        assert_eq!(
+            found_keys(run::<FtSearch>(
+                &backend,
+                &["ft.search", "idx", "apple", "nocontent"]
+            )?),
+            [bulk("item:3")]
+        );
+        backend.del("item:3");
+        assert_eq!(
+            run::<FtSearch>(&backend, &["ft.search", "idx", "apple", "limit", "0", "0"])?,
+            RespArray::new([RespFrame::Integer(0)]).into()
+        );
+
+        let RespFrame::Map(info) = run::<FtInfo>(&backend, &["ft.info", "idx"])? else {
+            panic!("expected a map");
+        };
+        assert_eq!(info.get("num_docs"), Some(&RespFrame::Integer(2)));
+        run::<FtDropIndex>(&backend, &["ft.dropindex", "idx", "dd"])?;
+        assert!(!backend.hmap.contains_key("item:1"));
+        assert!(backend.hmap.contains_key("other:1"));
+        assert!(run::<FtInfo>(&backend, &["ft.info", "idx"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ft_aggregate() -> Result<()> {
+        let backend = Backend::new();
+        run::<FtCreate>(
+            &backend,
+            &[
+                "ft.create",
+                "idx",
+                "schema",
+                "kind",
+                "tag",
+                "price",
+                "numeric",
+                "name",
+                "text",
+            ],
+        )?;
+        for (key, kind, price) in [
+            ("a", "fruit", "3"),
+            ("b", "fruit", "5"),
+            ("c", "veg", "2"),
+            ("d", "fruit", "1"),
+        ] {
+            hset(
+                &backend,
+                key,
+                &[("kind", kind), ("price", price), ("name", key)],
+            );
+        }
+        assert_eq!(
+            run::<FtAggregate>(
+                &backend,
+                &[
+                    "ft.aggregate",
+                    "idx",
+                    "*",
+                    "groupby",
+                    "1",
+                    "@kind",
+                    "reduce",
+                    "count",
+                    "0",
+                    "as",
+                    "n",
+                    "reduce",
+                    "sum",
+                    "1",
+                    "@price",
+                    "sortby",
+                    "2",
+                    "@n",
+                    "desc",
+                ]
+            )?,
+            RespArray::new([
+                RespFrame::Integer(2),
+                RespArray::new([
+                    bulk("kind"),
+                    bulk("fruit"),
+                    bulk("n"),
+                    bulk("3"),
+                    bulk("__generated_aliassumprice"),
+                    bulk("9"),
+                ])
+                .into(),
+                RespArray::new([
+                    bulk("kind"),
+                    bulk("veg"),
+                    bulk("n"),
+                    bulk("1"),
+                    bulk("__generated_aliassumprice"),
+                    bulk("2"),
+                ])
+                .into(),
+            ])
+            .into()
+        );
+        assert_eq!(
+            run::<FtAggregate>(
+                &backend,
+                &[
+                    "ft.aggregate",
+                    "idx",
+                    "@kind:{fruit}",
+                    "load",
+                    "1",
+                    "@name",
+                    "sortby",
+                    "2",
+                    "@name",
+                    "desc",
+                    "limit",
+                    "0",
+                    "2",
+                ]
+            )?,
+            RespArray::new([
+                RespFrame::Integer(2),
+                RespArray::new([bulk("name"), bulk("d")]).into(),
+                RespArray::new([bulk("name"), bulk("b")]).into(),
+            ])
+            .into()
+        );
+        Ok(())
+    }
+}
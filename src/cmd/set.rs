@@ -0,0 +1,657 @@
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+use crate::array::RespArray;
+use crate::backend::{Backend, NOTIFY_SET};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    bulk_array, extract_args, extract_count, extract_random_count, extract_string, extract_strings,
+    notify_miss, validate_command, validate_command_at_least, CommandError, CommandExecutor,
+};
+use crate::frame::RespFrame;
+use crate::null::RespNull;
+use crate::set::RespSet;
+
+#[derive(Debug)]
+pub struct SAdd {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SRem {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SMembers {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct SIsMember {
+    key: String,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct SMIsMember {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SCard {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct SPop {
+    key: String,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct SRandMember {
+    key: String,
+    count: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct SMove {
+    src: String,
+    dst: String,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct SInter {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SUnion {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SDiff {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SInterStore {
+    dst: String,
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SUnionStore {
+    dst: String,
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SDiffStore {
+    dst: String,
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SInterCard {
+    keys: Vec<String>,
+    limit: usize,
+}
+
+impl CommandExecutor for SAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(RespFrame::Integer(
+            backend.set_add(&self.key, self.members) as i64
+        ))
+    }
+}
+
+impl CommandExecutor for SRem {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(RespFrame::Integer(
+            backend.set_remove(&self.key, &self.members) as i64,
+        ))
+    }
+}
+
+impl CommandExecutor for SMembers {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        notify_miss(backend, &self.key, backend.set.contains_key(&self.key));
+        Ok(set_reply(backend.set_members(&self.key)))
+    }
+}
+
+impl CommandExecutor for SIsMember {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let found = backend
+            .set
+            .get(&self.key)
+            .is_some_and(|set| set.contains(&self.member));
+        Ok(RespFrame::Integer(found as i64))
+    }
+}
+
+impl CommandExecutor for SMIsMember {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let set = backend.set.get(&self.key);
+        let ret = self
+            .members
+            .iter()
+            .map(|m| RespFrame::Integer(set.as_ref().is_some_and(|set| set.contains(m)) as i64))
+            .collect::<Vec<_>>();
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for SCard {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let len = backend.set.get(&self.key).map(|set| set.len()).unwrap_or(0);
+        Ok(RespFrame::Integer(len as i64))
+    }
+}
+
+impl CommandExecutor for SPop {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let popped = match backend.set.get_mut(&self.key) {
+            Some(mut set) => {
+                let count = self.count.unwrap_or(1).min(set.len());
+                let picked: Vec<String> = set
+                    .iter()
+                    .choose_multiple(&mut rand::thread_rng(), count)
+                    .into_iter()
+                    .cloned()
+                    .collect();
+                for member in &picked {
+                    set.remove(member);
+                }
+                picked
+            }
+            None => vec![],
+        };
+        if !popped.is_empty() {
+            backend.notify(NOTIFY_SET, "spop", &self.key);
+        }
+        backend.set_remove_if_empty(&self.key);
+
+        match self.count {
+            Some(_) => Ok(set_reply(popped)),
+            None => Ok(popped
+                .into_iter()
+                .next()
+                .map(|m| BulkString::from(m).into())
+                .unwrap_or(RespFrame::Null(RespNull))),
+        }
+    }
+}
+
+impl CommandExecutor for SRandMember {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let set = backend.set.get(&self.key);
+        let mut rng = rand::thread_rng();
+        match (set, self.count) {
+            (Some(set), None) => Ok(set
+                .iter()
+                .choose(&mut rng)
+                .map(|m| BulkString::from(m.as_str()).into())
+                .unwrap_or(RespFrame::Null(RespNull))),
+            (None, None) => Ok(RespFrame::Null(RespNull)),
+            (None, Some(_)) => Ok(RespArray::new([]).into()),
+            // a positive count returns distinct members, a negative one allows repeats
+            (Some(set), Some(count)) if count >= 0 => {
+                let count = (count as usize).min(set.len());
+                let picked = set.iter().choose_multiple(&mut rng, count);
+                Ok(bulk_array(picked.into_iter().cloned()))
+            }
+            (Some(set), Some(count)) => {
+                let members: Vec<&String> = set.iter().collect();
+                let picked = (0..count.unsigned_abs())
+                    .map(|_| members[rng.gen_range(0..members.len())].clone());
+                Ok(bulk_array(picked))
+            }
+        }
+    }
+}
+
+impl CommandExecutor for SMove {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        if self.src == self.dst {
+            let found = backend
+                .set
+                .get(&self.src)
+                .is_some_and(|set| set.contains(&self.member));
+            return Ok(RespFrame::Integer(found as i64));
+        }
+        let moved = backend.set_move(&self.src, &self.dst, &self.member);
+        Ok(RespFrame::Integer(moved as i64))
+    }
+}
+
+impl CommandExecutor for SInter {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(set_reply(backend.set_inter(&self.keys)))
+    }
+}
+
+impl CommandExecutor for SUnion {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(set_reply(backend.set_union(&self.keys)))
+    }
+}
+
+impl CommandExecutor for SDiff {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(set_reply(backend.set_diff(&self.keys)))
+    }
+}
+
+impl CommandExecutor for SInterStore {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let members = backend.set_inter(&self.keys);
+        Ok(RespFrame::Integer(
+            backend.set_store(&self.dst, members, "sinterstore") as i64,
+        ))
+    }
+}
+
+impl CommandExecutor for SUnionStore {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let members = backend.set_union(&self.keys);
+        Ok(RespFrame::Integer(
+            backend.set_store(&self.dst, members, "sunionstore") as i64,
+        ))
+    }
+}
+
+impl CommandExecutor for SDiffStore {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let members = backend.set_diff(&self.keys);
+        Ok(RespFrame::Integer(
+            backend.set_store(&self.dst, members, "sdiffstore") as i64,
+        ))
+    }
+}
+
+impl CommandExecutor for SInterCard {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let len = backend.set_inter(&self.keys).len();
+        let len = if self.limit == 0 {
+            len
+        } else {
+            len.min(self.limit)
+        };
+        Ok(RespFrame::Integer(len as i64))
+    }
+}
+
+fn set_reply(members: impl IntoIterator<Item = String>) -> RespFrame {
+    RespSet::new(
+        members
+            .into_iter()
+            .map(|m| BulkString::from(m).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+fn parse_key_members(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(String, Vec<String>), CommandError> {
+    validate_command_at_least(&value, &[name], 2)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    Ok((key, extract_strings(args)?))
+}
+
+fn parse_keys(value: RespArray, name: &'static str) -> Result<Vec<String>, CommandError> {
+    validate_command_at_least(&value, &[name], 1)?;
+    extract_strings(extract_args(value, 1)?.into_iter())
+}
+
+fn parse_key(value: RespArray, name: &'static str) -> Result<String, CommandError> {
+    validate_command(&value, &[name], 1)?;
+    extract_string(extract_args(value, 1)?.into_iter().next())
+}
+
+fn parse_key_count(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(String, Option<RespFrame>), CommandError> {
+    validate_command_at_least(&value, &[name], 1)?;
+    if value.len() > 3 {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have at most 2 argument",
+            name
+        )));
+    }
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    Ok((key, args.next()))
+}
+
+impl TryFrom<RespArray> for SAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(value, "sadd")?;
+        Ok(SAdd { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(value, "srem")?;
+        Ok(SRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SMembers {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SMembers {
+            key: parse_key(value, "smembers")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SIsMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["sismember"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SIsMember {
+            key: extract_string(args.next())?,
+            member: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SMIsMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(value, "smismember")?;
+        Ok(SMIsMember { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SCard {
+            key: parse_key(value, "scard")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_key_count(value, "spop")?;
+        let count = count.map(|count| extract_count(Some(count))).transpose()?;
+        Ok(SPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SRandMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_key_count(value, "srandmember")?;
+        let count = count
+            .map(|count| extract_random_count(Some(count)))
+            .transpose()?;
+        Ok(SRandMember { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SMove {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["smove"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SMove {
+            src: extract_string(args.next())?,
+            dst: extract_string(args.next())?,
+            member: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SInter {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SInter {
+            keys: parse_keys(value, "sinter")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SUnion {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SUnion {
+            keys: parse_keys(value, "sunion")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SDiff {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SDiff {
+            keys: parse_keys(value, "sdiff")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SInterStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (dst, keys) = parse_key_members(value, "sinterstore")?;
+        Ok(SInterStore { dst, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SUnionStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (dst, keys) = parse_key_members(value, "sunionstore")?;
+        Ok(SUnionStore { dst, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SDiffStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (dst, keys) = parse_key_members(value, "sdiffstore")?;
+        Ok(SDiffStore { dst, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SInterCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["sintercard"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let num_keys = extract_count(args.next())?;
+        if num_keys == 0 {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ));
+        }
+        let keys = (0..num_keys)
+            .map(|_| extract_string(args.next()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let limit = match args.next() {
+            Some(option) => match extract_string(Some(option))?.to_ascii_uppercase().as_str() {
+                "LIMIT" => extract_count(args.next())?,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            None => 0,
+        };
+        Ok(SInterCard { keys, limit })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    fn sorted(frame: RespFrame) -> Vec<RespFrame> {
+        let mut members = match frame {
+            RespFrame::Set(set) => set.0,
+            RespFrame::Array(array) => array.0,
+            frame => vec![frame],
+        };
+        members.sort_by_key(|m| format!("{:?}", m));
+        members
+    }
+
+    #[test]
+    fn test_sintercard_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$10\r\nsintercard\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$5\r\nlimit\r\n$1\r\n3\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SInterCard = frame.try_into()?;
+        assert_eq!(result.keys, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(result.limit, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sadd_srem_smembers_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = SAdd {
+            key: "set".to_string(),
+            members: vec!["a".to_string(), "b".to_string(), "a".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(2));
+
+        let cmd = SMembers {
+            key: "set".to_string(),
+        };
+        assert_eq!(
+            sorted(cmd.execute(&backend)?),
+            vec![b"a".into(), b"b".into()]
+        );
+
+        let cmd = SMIsMember {
+            key: "set".to_string(),
+            members: vec!["a".to_string(), "c".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([RespFrame::Integer(1), RespFrame::Integer(0)]).into()
+        );
+
+        let cmd = SRem {
+            key: "set".to_string(),
+            members: vec!["a".to_string(), "b".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(2));
+        assert!(!backend.set.contains_key("set"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_algebra_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.set_add("a", vec!["1".to_string(), "2".to_string(), "3".to_string()]);
+        backend.set_add("b", vec!["2".to_string(), "3".to_string(), "4".to_string()]);
+        let keys = vec!["a".to_string(), "b".to_string()];
+
+        let cmd = SInter { keys: keys.clone() };
+        assert_eq!(
+            sorted(cmd.execute(&backend)?),
+            vec![b"2".into(), b"3".into()]
+        );
+        let cmd = SDiff { keys: keys.clone() };
+        assert_eq!(sorted(cmd.execute(&backend)?), vec![b"1".into()]);
+        let cmd = SUnionStore {
+            dst: "c".to_string(),
+            keys: keys.clone(),
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(4));
+        let cmd = SInterCard { keys, limit: 1 };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+
+        let cmd = SDiffStore {
+            dst: "c".to_string(),
+            keys: vec!["a".to_string(), "c".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(0));
+        assert!(!backend.set.contains_key("c"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_srandmember_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.set_add("set", vec!["a".to_string(), "b".to_string()]);
+
+        let cmd = SRandMember {
+            key: "set".to_string(),
+            count: Some(-5),
+        };
+        assert_eq!(sorted(cmd.execute(&backend)?).len(), 5);
+        let cmd = SRandMember {
+            key: "set".to_string(),
+            count: Some(5),
+        };
+        assert_eq!(
+            sorted(cmd.execute(&backend)?),
+            vec![b"a".into(), b"b".into()]
+        );
+        let cmd = SRandMember {
+            key: "set".to_string(),
+            count: Some(i64::MAX),
+        };
+        assert_eq!(sorted(cmd.execute(&backend)?).len(), 2);
+        let cmd = RespArray::new([
+            BulkString::new("srandmember").into(),
+            BulkString::new("set").into(),
+            BulkString::new(i64::MIN.to_string()).into(),
+        ]);
+        let err = SRandMember::try_from(cmd).unwrap_err();
+        assert!(err.to_string().contains("value is out of range"));
+
+        let cmd = SPop {
+            key: "set".to_string(),
+            count: Some(usize::MAX),
+        };
+        assert_eq!(
+            sorted(cmd.execute(&backend)?),
+            vec![b"a".into(), b"b".into()]
+        );
+        assert!(!backend.set.contains_key("set"));
+        Ok(())
+    }
+}
@@ -0,0 +1,1684 @@
+use std::collections::HashMap;
+use std::ops::Bound;
+use std::time::Duration;
+
+use crate::array::{RespArray, RespNullArray};
+use crate::backend::{
+    now_millis, Backend, ClaimOptions, ServeFn, Stream, StreamId, StreamIdSpec, StreamTrim,
+    NOTIFY_STREAM,
+};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_count, extract_int, extract_string, extract_strings, notify_miss,
+    validate_command, validate_command_at_least, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::map::RespMap;
+use crate::null::RespNull;
+
+#[derive(Debug)]
+pub struct XAdd {
+    key: String,
+    no_mkstream: bool,
+    trim: Option<TrimArgs>,
+    id: StreamIdSpec,
+    fields: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct XRange {
+    key: String,
+    start: Bound<StreamId>,
+    end: Bound<StreamId>,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct XRevRange {
+    key: String,
+    end: Bound<StreamId>,
+    start: Bound<StreamId>,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct XLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct XDel {
+    key: String,
+    ids: Vec<StreamId>,
+}
+
+#[derive(Debug)]
+pub struct XTrim {
+    key: String,
+    trim: TrimArgs,
+}
+
+#[derive(Debug)]
+pub struct XInfo {
+    sub: XInfoSub,
+}
+
+#[derive(Debug)]
+pub struct XRead {
+    count: Option<usize>,
+    /// `None` without BLOCK, `Some(None)` to block forever
+    block: Option<Option<Duration>>,
+    keys: Vec<String>,
+    ids: Vec<ReadFrom>,
+}
+
+#[derive(Debug)]
+pub struct XGroup {
+    sub: XGroupSub,
+}
+
+#[derive(Debug)]
+pub struct XReadGroup {
+    group: String,
+    consumer: String,
+    count: Option<usize>,
+    /// `None` without BLOCK, `Some(None)` to block forever
+    block: Option<Option<Duration>>,
+    no_ack: bool,
+    keys: Vec<String>,
+    /// `None` is `>`, the entries never delivered to the group
+    ids: Vec<Option<StreamId>>,
+}
+
+#[derive(Debug)]
+pub struct XAck {
+    key: String,
+    group: String,
+    ids: Vec<StreamId>,
+}
+
+#[derive(Debug)]
+pub struct XPending {
+    key: String,
+    group: String,
+    /// `None` asks for the summary
+    range: Option<PendingRange>,
+}
+
+#[derive(Debug)]
+pub struct XClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    ids: Vec<StreamId>,
+    /// IDLE, resolved against the clock when the command runs
+    idle: Option<u64>,
+    options: ClaimOptions,
+}
+
+#[derive(Debug)]
+pub struct XAutoClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    start: StreamId,
+    count: usize,
+    just_id: bool,
+}
+
+#[derive(Debug)]
+enum XInfoSub {
+    Stream(String),
+    Groups(String),
+    Consumers(String, String),
+}
+
+#[derive(Debug)]
+enum XGroupSub {
+    Create {
+        key: String,
+        group: String,
+        start: GroupStart,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    },
+    SetId {
+        key: String,
+        group: String,
+        start: GroupStart,
+        entries_read: Option<u64>,
+    },
+    Destroy {
+        key: String,
+        group: String,
+    },
+    CreateConsumer {
+        key: String,
+        group: String,
+        consumer: String,
+    },
+    DelConsumer {
+        key: String,
+        group: String,
+        consumer: String,
+    },
+}
+
+/// The ID after which a consumer group starts delivering.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GroupStart {
+    After(StreamId),
+    /// `$`, the last entry of the stream
+    Last,
+}
+
+/// `[IDLE min-idle-time] start end count [consumer]` of XPENDING.
+#[derive(Debug)]
+struct PendingRange {
+    min_idle: Option<u64>,
+    start: Bound<StreamId>,
+    end: Bound<StreamId>,
+    count: usize,
+    consumer: Option<String>,
+}
+
+/// `MAXLEN | MINID [= | ~] threshold [LIMIT count]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TrimArgs {
+    trim: StreamTrim,
+    limit: Option<usize>,
+}
+
+/// Where XREAD starts reading a stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReadFrom {
+    After(StreamId),
+    /// `$`, only entries added from now on
+    New,
+    /// `+`, the last entry
+    LastEntry,
+}
+
+impl CommandExecutor for XAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let created = !backend.stream.contains_key(&self.key);
+        let (id, trimmed) = {
+            let mut stream = match backend.stream.get_mut(&self.key) {
+                Some(stream) => stream,
+                None if self.no_mkstream => return Ok(RespFrame::Null(RespNull)),
+                None => backend.stream.entry(self.key.clone()).or_default(),
+            };
+            let id = stream
+                .add(self.id, self.fields)
+                .map_err(CommandError::InvalidArgument)?;
+            let trimmed = match self.trim {
+                Some(trim) => stream.trim(trim.trim, trim.limit),
+                None => 0,
+            };
+            (id, trimmed)
+        };
+        backend.notify_new(created, &self.key);
+        backend.notify(NOTIFY_STREAM, "xadd", &self.key);
+        if trimmed > 0 {
+            backend.notify(NOTIFY_STREAM, "xtrim", &self.key);
+        }
+        backend.signal_key_ready(&self.key);
+        Ok(BulkString::from(id.to_string()).into())
+    }
+}
+
+impl CommandExecutor for XRange {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let entries = match backend.stream.get(&self.key) {
+            Some(stream) => stream.range(self.start, self.end, false, self.count),
+            None => {
+                notify_miss(backend, &self.key, false);
+                vec![]
+            }
+        };
+        Ok(entries_reply(entries))
+    }
+}
+
+impl CommandExecutor for XRevRange {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let entries = match backend.stream.get(&self.key) {
+            Some(stream) => stream.range(self.start, self.end, true, self.count),
+            None => vec![],
+        };
+        Ok(entries_reply(entries))
+    }
+}
+
+impl CommandExecutor for XLen {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(RespFrame::Integer(backend.stream_len(&self.key) as i64))
+    }
+}
+
+impl CommandExecutor for XDel {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let deleted = match backend.stream.get_mut(&self.key) {
+            Some(mut stream) => stream.delete(&self.ids),
+            None => 0,
+        };
+        if deleted > 0 {
+            backend.notify(NOTIFY_STREAM, "xdel", &self.key);
+        }
+        Ok(RespFrame::Integer(deleted as i64))
+    }
+}
+
+impl CommandExecutor for XTrim {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let removed = match backend.stream.get_mut(&self.key) {
+            Some(mut stream) => stream.trim(self.trim.trim, self.trim.limit),
+            None => 0,
+        };
+        if removed > 0 {
+            backend.notify(NOTIFY_STREAM, "xtrim", &self.key);
+        }
+        Ok(RespFrame::Integer(removed as i64))
+    }
+}
+
+impl CommandExecutor for XInfo {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match self.sub {
+            XInfoSub::Stream(key) => {
+                let stream = backend.stream.get(&key).ok_or_else(no_such_key)?;
+                let entry = |entry: Option<(&StreamId, &Vec<RespFrame>)>| match entry {
+                    Some((id, fields)) => entry_reply(*id, fields.clone()),
+                    None => RespFrame::Null(RespNull),
+                };
+                let first_id = stream.first_entry().map(|(id, _)| *id).unwrap_or_default();
+
+                let mut info = RespMap::new();
+                info.insert(
+                    "length".to_string(),
+                    RespFrame::Integer(stream.len() as i64),
+                );
+                info.insert("last-generated-id".to_string(), id_reply(stream.last_id()));
+                info.insert(
+                    "max-deleted-entry-id".to_string(),
+                    id_reply(stream.max_deleted_id()),
+                );
+                info.insert(
+                    "entries-added".to_string(),
+                    RespFrame::Integer(stream.entries_added() as i64),
+                );
+                info.insert("recorded-first-entry-id".to_string(), id_reply(first_id));
+                info.insert(
+                    "groups".to_string(),
+                    RespFrame::Integer(stream.groups().count() as i64),
+                );
+                info.insert("first-entry".to_string(), entry(stream.first_entry()));
+                info.insert("last-entry".to_string(), entry(stream.last_entry()));
+                Ok(info.into())
+            }
+            XInfoSub::Groups(key) => {
+                let stream = backend.stream.get(&key).ok_or_else(no_such_key)?;
+                let groups = stream
+                    .groups()
+                    .map(|(name, group)| {
+                        let mut info = RespMap::new();
+                        info.insert("name".to_string(), BulkString::from(name.clone()).into());
+                        info.insert(
+                            "consumers".to_string(),
+                            RespFrame::Integer(group.consumers().count() as i64),
+                        );
+                        info.insert(
+                            "pending".to_string(),
+                            RespFrame::Integer(group.pending().len() as i64),
+                        );
+                        info.insert(
+                            "last-delivered-id".to_string(),
+                            id_reply(group.last_delivered_id()),
+                        );
+                        info.insert(
+                            "entries-read".to_string(),
+                            optional_integer(group.entries_read()),
+                        );
+                        info.insert("lag".to_string(), optional_integer(stream.group_lag(group)));
+                        info.into()
+                    })
+                    .collect::<Vec<RespFrame>>();
+                Ok(RespArray::new(groups).into())
+            }
+            XInfoSub::Consumers(key, group) => {
+                let stream = backend.stream.get(&key).ok_or_else(no_such_key)?;
+                let group = stream
+                    .group(&group)
+                    .ok_or_else(|| no_such_group(&key, &group))?;
+                let now = now_millis();
+                let consumers = group
+                    .consumers()
+                    .map(|(name, consumer)| {
+                        let inactive = consumer
+                            .active_time()
+                            .map(|at| now.saturating_sub(at) as i64)
+                            .unwrap_or(-1);
+                        let mut info = RespMap::new();
+                        info.insert("name".to_string(), BulkString::from(name.clone()).into());
+                        info.insert(
+                            "pending".to_string(),
+                            RespFrame::Integer(consumer.pending_count() as i64),
+                        );
+                        info.insert(
+                            "idle".to_string(),
+                            RespFrame::Integer(now.saturating_sub(consumer.seen_time()) as i64),
+                        );
+                        info.insert("inactive".to_string(), RespFrame::Integer(inactive));
+                        info.into()
+                    })
+                    .collect::<Vec<RespFrame>>();
+                Ok(RespArray::new(consumers).into())
+            }
+        }
+    }
+}
+
+// Without a connection that can park, XREAD BLOCK only returns what is already there.
+impl CommandExecutor for XRead {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let positions = self.resolve(backend);
+        Ok(read_streams(backend, &positions, self.count).unwrap_or_else(|| RespNullArray.into()))
+    }
+}
+
+impl XRead {
+    pub async fn block(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let Some(timeout) = self.block else {
+            return self.execute(backend);
+        };
+        // `$` has to be pinned before waiting, or the entries that wake us would be skipped
+        let positions = self.resolve(backend);
+        if let Some(reply) = read_streams(backend, &positions, self.count) {
+            return Ok(reply);
+        }
+
+        let keys = positions.iter().map(|(key, _)| key.clone()).collect();
+        let after: HashMap<String, StreamId> = positions.into_iter().collect();
+        let count = self.count;
+        let serve: ServeFn = Box::new(move |backend, key| {
+            let after = *after.get(key)?;
+            let entries = backend.stream_read_after(key, after, count);
+            (!entries.is_empty()).then(|| {
+                RespArray::new([RespArray::new([
+                    BulkString::from(key).into(),
+                    entries_reply(entries),
+                ])
+                .into()])
+                .into()
+            })
+        });
+        let reply = backend.block_on(keys, timeout, serve, None).await;
+        Ok(reply.unwrap_or_else(|| RespNullArray.into()))
+    }
+
+    /// Turns every requested position into the ID after which entries are returned.
+    fn resolve(&self, backend: &Backend) -> Vec<(String, StreamId)> {
+        self.keys
+            .iter()
+            .zip(&self.ids)
+            .map(|(key, from)| {
+                let stream = backend.stream.get(key);
+                let after = match from {
+                    ReadFrom::After(id) => *id,
+                    ReadFrom::New => stream.map(|s| s.last_id()).unwrap_or_default(),
+                    ReadFrom::LastEntry => stream
+                        .and_then(|s| s.last_entry().map(|(id, _)| *id))
+                        .and_then(|id| id.prev())
+                        .unwrap_or(StreamId::MAX),
+                };
+                (key.clone(), after)
+            })
+            .collect()
+    }
+}
+
+impl CommandExecutor for XGroup {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match self.sub {
+            XGroupSub::Create {
+                key,
+                group,
+                start,
+                mkstream,
+                entries_read,
+            } => {
+                let created = !backend.stream.contains_key(&key);
+                {
+                    let mut stream = match backend.stream.get_mut(&key) {
+                        Some(stream) => stream,
+                        None if mkstream => backend.stream.entry(key.clone()).or_default(),
+                        None => return Err(key_required()),
+                    };
+                    let id = start.resolve(&stream);
+                    if !stream.create_group(&group, id, entries_read) {
+                        return Err(CommandError::InvalidArgument(
+                            "BUSYGROUP Consumer Group name already exists".to_string(),
+                        ));
+                    }
+                }
+                backend.notify_new(created, &key);
+                backend.notify(NOTIFY_STREAM, "xgroup-create", &key);
+                Ok(RESP_OK.clone())
+            }
+            XGroupSub::SetId {
+                key,
+                group,
+                start,
+                entries_read,
+            } => {
+                let mut stream = backend.stream.get_mut(&key).ok_or_else(key_required)?;
+                let id = start.resolve(&stream);
+                if !stream.set_group_id(&group, id, entries_read) {
+                    return Err(no_such_group(&key, &group));
+                }
+                backend.notify(NOTIFY_STREAM, "xgroup-setid", &key);
+                Ok(RESP_OK.clone())
+            }
+            XGroupSub::Destroy { key, group } => {
+                let mut stream = backend.stream.get_mut(&key).ok_or_else(key_required)?;
+                let destroyed = stream.destroy_group(&group);
+                if destroyed {
+                    backend.notify(NOTIFY_STREAM, "xgroup-destroy", &key);
+                }
+                Ok(RespFrame::Integer(destroyed as i64))
+            }
+            XGroupSub::CreateConsumer {
+                key,
+                group,
+                consumer,
+            } => {
+                let mut stream = backend.stream.get_mut(&key).ok_or_else(key_required)?;
+                let created = stream
+                    .create_consumer(&group, &consumer)
+                    .ok_or_else(|| no_such_group(&key, &group))?;
+                if created {
+                    backend.notify(NOTIFY_STREAM, "xgroup-createconsumer", &key);
+                }
+                Ok(RespFrame::Integer(created as i64))
+            }
+            XGroupSub::DelConsumer {
+                key,
+                group,
+                consumer,
+            } => {
+                let mut stream = backend.stream.get_mut(&key).ok_or_else(key_required)?;
+                let pending = stream
+                    .delete_consumer(&group, &consumer)
+                    .ok_or_else(|| no_such_group(&key, &group))?;
+                backend.notify(NOTIFY_STREAM, "xgroup-delconsumer", &key);
+                Ok(RespFrame::Integer(pending as i64))
+            }
+        }
+    }
+}
+
+// Without a connection that can park, XREADGROUP BLOCK only returns what is already there.
+impl CommandExecutor for XReadGroup {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        self.check_groups(backend)?;
+        let ret = self
+            .keys
+            .iter()
+            .zip(&self.ids)
+            .filter_map(|(key, after)| {
+                let entries = backend.stream.get_mut(key).and_then(|mut stream| {
+                    stream.read_group(&self.group, &self.consumer, *after, self.count, self.no_ack)
+                })?;
+                // history reads always report their stream, even with nothing pending
+                if after.is_none() && entries.is_empty() {
+                    return None;
+                }
+                Some(
+                    RespArray::new([
+                        BulkString::from(key.clone()).into(),
+                        group_entries_reply(entries),
+                    ])
+                    .into(),
+                )
+            })
+            .collect::<Vec<RespFrame>>();
+        if ret.is_empty() {
+            return Ok(RespNullArray.into());
+        }
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl XReadGroup {
+    pub async fn block(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        // only a read of new entries for every stream can wait for more
+        let Some(timeout) = self.block.filter(|_| self.ids.iter().all(Option::is_none)) else {
+            return self.execute(backend);
+        };
+        self.check_groups(backend)?;
+        let (group, consumer, count, no_ack) = (self.group, self.consumer, self.count, self.no_ack);
+        let serve: ServeFn = Box::new(move |backend, key| {
+            let entries = backend
+                .stream
+                .get_mut(key)?
+                .read_group(&group, &consumer, None, count, no_ack)?;
+            (!entries.is_empty()).then(|| {
+                RespArray::new([RespArray::new([
+                    BulkString::from(key).into(),
+                    group_entries_reply(entries),
+                ])
+                .into()])
+                .into()
+            })
+        });
+        // entries read for a client that went away stay pending, as for any lost reply
+        let reply = backend.block_on(self.keys, timeout, serve, None).await;
+        Ok(reply.unwrap_or_else(|| RespNullArray.into()))
+    }
+
+    fn check_groups(&self, backend: &Backend) -> Result<(), CommandError> {
+        for key in &self.keys {
+            let exists = backend
+                .stream
+                .get(key)
+                .is_some_and(|stream| stream.group(&self.group).is_some());
+            if !exists {
+                return Err(CommandError::InvalidArgument(format!(
+                    "NOGROUP No such key '{}' or consumer group '{}' in XREADGROUP with GROUP option",
+                    key, self.group
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CommandExecutor for XAck {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let acked = match backend.stream.get_mut(&self.key) {
+            Some(mut stream) => stream.ack(&self.group, &self.ids),
+            None => 0,
+        };
+        Ok(RespFrame::Integer(acked as i64))
+    }
+}
+
+impl CommandExecutor for XPending {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let stream = backend.stream.get(&self.key);
+        let group = stream
+            .as_ref()
+            .and_then(|stream| stream.group(&self.group))
+            .ok_or_else(|| no_key_or_group(&self.key, &self.group))?;
+        let pending = group.pending();
+
+        let Some(range) = self.range else {
+            let (Some((first, _)), Some((last, _))) =
+                (pending.first_key_value(), pending.last_key_value())
+            else {
+                return Ok(RespArray::new([
+                    RespFrame::Integer(0),
+                    RespFrame::Null(RespNull),
+                    RespFrame::Null(RespNull),
+                    RespNullArray.into(),
+                ])
+                .into());
+            };
+            let consumers = group
+                .consumers()
+                .filter(|(_, consumer)| consumer.pending_count() > 0)
+                .map(|(name, consumer)| {
+                    RespArray::new([
+                        BulkString::from(name.clone()).into(),
+                        BulkString::from(consumer.pending_count().to_string()).into(),
+                    ])
+                    .into()
+                })
+                .collect::<Vec<RespFrame>>();
+            return Ok(RespArray::new([
+                RespFrame::Integer(pending.len() as i64),
+                id_reply(*first),
+                id_reply(*last),
+                RespArray::new(consumers).into(),
+            ])
+            .into());
+        };
+
+        let now = now_millis();
+        let entries = pending
+            .range((range.start, range.end))
+            .filter(|(_, entry)| {
+                range
+                    .consumer
+                    .as_ref()
+                    .is_none_or(|consumer| *consumer == entry.consumer)
+            })
+            .map(|(id, entry)| (id, entry, now.saturating_sub(entry.delivery_time)))
+            .filter(|(_, _, idle)| range.min_idle.is_none_or(|min_idle| *idle >= min_idle))
+            .take(range.count)
+            .map(|(id, entry, idle)| {
+                RespArray::new([
+                    id_reply(*id),
+                    BulkString::from(entry.consumer.clone()).into(),
+                    RespFrame::Integer(idle as i64),
+                    RespFrame::Integer(entry.delivery_count as i64),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(entries).into())
+    }
+}
+
+impl CommandExecutor for XClaim {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let mut options = self.options;
+        if let Some(idle) = self.idle {
+            options.delivery_time = Some(now_millis().saturating_sub(idle));
+        }
+        let claimed = backend
+            .stream
+            .get_mut(&self.key)
+            .and_then(|mut stream| {
+                stream.claim(
+                    &self.group,
+                    &self.consumer,
+                    self.min_idle,
+                    &self.ids,
+                    options,
+                )
+            })
+            .ok_or_else(|| no_key_or_group(&self.key, &self.group))?;
+        Ok(claimed_reply(claimed, options.just_id))
+    }
+}
+
+impl CommandExecutor for XAutoClaim {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let ret = backend
+            .stream
+            .get_mut(&self.key)
+            .and_then(|mut stream| {
+                stream.auto_claim(
+                    &self.group,
+                    &self.consumer,
+                    self.min_idle,
+                    self.start,
+                    self.count,
+                    self.just_id,
+                )
+            })
+            .ok_or_else(|| no_key_or_group(&self.key, &self.group))?;
+        let deleted = ret.deleted.into_iter().map(id_reply).collect::<Vec<_>>();
+        Ok(RespArray::new([
+            id_reply(ret.cursor),
+            claimed_reply(ret.claimed, self.just_id),
+            RespArray::new(deleted).into(),
+        ])
+        .into())
+    }
+}
+
+impl GroupStart {
+    fn resolve(&self, stream: &Stream) -> StreamId {
+        match self {
+            GroupStart::After(id) => *id,
+            GroupStart::Last => stream.last_id(),
+        }
+    }
+}
+
+/// Replies with `[[key, entries], ...]` for the streams that have entries after their position.
+fn read_streams(
+    backend: &Backend,
+    positions: &[(String, StreamId)],
+    count: Option<usize>,
+) -> Option<RespFrame> {
+    let ret = positions
+        .iter()
+        .filter_map(|(key, after)| {
+            let entries = backend.stream_read_after(key, *after, count);
+            (!entries.is_empty()).then(|| {
+                RespArray::new([BulkString::from(key.clone()).into(), entries_reply(entries)])
+                    .into()
+            })
+        })
+        .collect::<Vec<RespFrame>>();
+    (!ret.is_empty()).then(|| RespArray::new(ret).into())
+}
+
+pub(crate) fn entries_reply(entries: Vec<(StreamId, Vec<RespFrame>)>) -> RespFrame {
+    RespArray::new(
+        entries
+            .into_iter()
+            .map(|(id, fields)| entry_reply(id, fields))
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+pub(crate) fn entry_reply(id: StreamId, fields: Vec<RespFrame>) -> RespFrame {
+    RespArray::new([id_reply(id), RespArray::new(fields).into()]).into()
+}
+
+pub(crate) fn id_reply(id: StreamId) -> RespFrame {
+    BulkString::from(id.to_string()).into()
+}
+
+/// Like [`entries_reply`], with a nil in place of the fields of deleted entries.
+fn group_entries_reply(entries: Vec<(StreamId, Option<Vec<RespFrame>>)>) -> RespFrame {
+    RespArray::new(
+        entries
+            .into_iter()
+            .map(|(id, fields)| match fields {
+                Some(fields) => entry_reply(id, fields),
+                None => RespArray::new([id_reply(id), RespNullArray.into()]).into(),
+            })
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+fn claimed_reply(claimed: Vec<(StreamId, Vec<RespFrame>)>, just_id: bool) -> RespFrame {
+    if just_id {
+        RespArray::new(
+            claimed
+                .into_iter()
+                .map(|(id, _)| id_reply(id))
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    } else {
+        entries_reply(claimed)
+    }
+}
+
+fn optional_integer(value: Option<u64>) -> RespFrame {
+    match value {
+        Some(value) => RespFrame::Integer(value as i64),
+        None => RespFrame::Null(RespNull),
+    }
+}
+
+fn no_such_key() -> CommandError {
+    CommandError::InvalidArgument("no such key".to_string())
+}
+
+fn key_required() -> CommandError {
+    CommandError::InvalidArgument(
+        "The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically."
+            .to_string(),
+    )
+}
+
+fn no_such_group(key: &str, group: &str) -> CommandError {
+    CommandError::InvalidArgument(format!(
+        "NOGROUP No such consumer group '{}' for key name '{}'",
+        group, key
+    ))
+}
+
+fn no_key_or_group(key: &str, group: &str) -> CommandError {
+    CommandError::InvalidArgument(format!(
+        "NOGROUP No such key '{}' or consumer group '{}'",
+        key, group
+    ))
+}
+
+fn invalid_id() -> CommandError {
+    CommandError::InvalidArgument(
+        "Invalid stream ID specified as stream command argument".to_string(),
+    )
+}
+
+pub(crate) fn parse_id(arg: Option<RespFrame>) -> Result<StreamId, CommandError> {
+    extract_string(arg)?.parse().map_err(|_| invalid_id())
+}
+
+/// Parses a range end for XRANGE: `-`, `+`, an exclusive `(id` or an ID whose missing
+/// sequence is the lowest one for a start and the highest one for an end.
+pub(crate) fn parse_range_bound(
+    arg: Option<RespFrame>,
+    is_start: bool,
+) -> Result<Bound<StreamId>, CommandError> {
+    let arg = extract_string(arg)?;
+    match arg.as_str() {
+        "-" => return Ok(Bound::Unbounded),
+        "+" => return Ok(Bound::Unbounded),
+        _ => {}
+    }
+    let (exclusive, id) = match arg.strip_prefix('(') {
+        Some(id) => (true, id),
+        None => (false, arg.as_str()),
+    };
+    let id = match id.split_once('-') {
+        Some(_) => id.parse().map_err(|_| invalid_id())?,
+        None => {
+            let ms = id.parse().map_err(|_| invalid_id())?;
+            StreamId::new(ms, if is_start { 0 } else { u64::MAX })
+        }
+    };
+    Ok(if exclusive {
+        Bound::Excluded(id)
+    } else {
+        Bound::Included(id)
+    })
+}
+
+/// Parses the trimming options when `args` starts with MAXLEN or MINID.
+fn parse_trim(
+    args: &mut std::iter::Peekable<impl Iterator<Item = RespFrame>>,
+) -> Result<Option<TrimArgs>, CommandError> {
+    let strategy = match args.peek() {
+        Some(RespFrame::BulkString(arg)) => String::from_utf8_lossy(arg).to_ascii_uppercase(),
+        _ => return Ok(None),
+    };
+    if strategy != "MAXLEN" && strategy != "MINID" {
+        return Ok(None);
+    }
+    args.next();
+
+    let mut threshold = extract_string(args.next())?;
+    let mut approx = false;
+    if threshold == "~" || threshold == "=" {
+        approx = threshold == "~";
+        threshold = extract_string(args.next())?;
+    }
+    let trim = if strategy == "MAXLEN" {
+        let max_len = threshold.parse::<usize>().map_err(|_| {
+            CommandError::InvalidArgument("The MAXLEN argument must be >= 0.".to_string())
+        })?;
+        StreamTrim::MaxLen(max_len)
+    } else {
+        StreamTrim::MinId(threshold.parse().map_err(|_| invalid_id())?)
+    };
+
+    let mut limit = None;
+    if let Some(RespFrame::BulkString(arg)) = args.peek() {
+        if arg.eq_ignore_ascii_case(b"LIMIT") {
+            args.next();
+            if !approx {
+                return Err(CommandError::InvalidArgument(
+                    "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+                ));
+            }
+            // LIMIT 0 lifts the limit
+            limit = Some(extract_count(args.next())?).filter(|limit| *limit > 0);
+        }
+    }
+    Ok(Some(TrimArgs { trim, limit }))
+}
+
+/// Parses the milliseconds of BLOCK, `None` when 0 asks to wait forever.
+fn parse_block(arg: Option<RespFrame>) -> Result<Option<Duration>, CommandError> {
+    let ms = u64::try_from(extract_int(arg)?)
+        .map_err(|_| CommandError::InvalidArgument("timeout is negative".to_string()))?;
+    Ok((ms > 0).then(|| Duration::from_millis(ms)))
+}
+
+/// Parses the min-idle-time of XCLAIM and XAUTOCLAIM, negative values count as 0.
+fn parse_min_idle(arg: Option<RespFrame>) -> Result<u64, CommandError> {
+    let min_idle = extract_string(arg)?.parse::<i64>().map_err(|_| {
+        CommandError::InvalidArgument("Invalid min-idle-time argument for XCLAIM".to_string())
+    })?;
+    Ok(min_idle.max(0) as u64)
+}
+
+fn parse_count(
+    args: &mut std::iter::Peekable<impl Iterator<Item = RespFrame>>,
+) -> Result<Option<usize>, CommandError> {
+    match args.next() {
+        None => Ok(None),
+        Some(arg) if extract_string(Some(arg.clone()))?.eq_ignore_ascii_case("COUNT") => {
+            let count = extract_int(args.next())?;
+            if args.next().is_some() {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            }
+            // a count below 1 returns nothing
+            Ok(Some(usize::try_from(count).unwrap_or(0)))
+        }
+        Some(_) => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
+impl TryFrom<RespArray> for XAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xadd"], 4)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let mut no_mkstream = false;
+        let mut trim = None;
+        loop {
+            if let Some(RespFrame::BulkString(arg)) = args.peek() {
+                if arg.eq_ignore_ascii_case(b"NOMKSTREAM") {
+                    no_mkstream = true;
+                    args.next();
+                    continue;
+                }
+            }
+            match parse_trim(&mut args)? {
+                Some(parsed) => trim = Some(parsed),
+                None => break,
+            }
+        }
+
+        let id = extract_string(args.next())?;
+        let id = match id.as_str() {
+            "*" => StreamIdSpec::Auto,
+            id => match id.strip_suffix("-*") {
+                Some(ms) => StreamIdSpec::AutoSeq(ms.parse().map_err(|_| invalid_id())?),
+                None => StreamIdSpec::Explicit(id.parse().map_err(|_| invalid_id())?),
+            },
+        };
+        let fields: Vec<RespFrame> = args.collect();
+        if fields.is_empty() || !fields.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'xadd' command".to_string(),
+            ));
+        }
+        Ok(XAdd {
+            key,
+            no_mkstream,
+            trim,
+            id,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xrange"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let start = parse_range_bound(args.next(), true)?;
+        let end = parse_range_bound(args.next(), false)?;
+        let count = parse_count(&mut args)?;
+        Ok(XRange {
+            key,
+            start,
+            end,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRevRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xrevrange"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let end = parse_range_bound(args.next(), false)?;
+        let start = parse_range_bound(args.next(), true)?;
+        let count = parse_count(&mut args)?;
+        Ok(XRevRange {
+            key,
+            end,
+            start,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xlen"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(XLen {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XDel {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xdel"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let ids = args
+            .map(|arg| parse_id(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XDel { key, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XTrim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xtrim"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let trim = parse_trim(&mut args)?
+            .ok_or_else(|| CommandError::InvalidArgument("syntax error".to_string()))?;
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(XTrim { key, trim })
+    }
+}
+
+impl TryFrom<RespArray> for XInfo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xinfo"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let sub = match extract_string(args.next())?.to_ascii_uppercase().as_str() {
+            "STREAM" => XInfoSub::Stream(extract_string(args.next())?),
+            "GROUPS" => XInfoSub::Groups(extract_string(args.next())?),
+            "CONSUMERS" => {
+                XInfoSub::Consumers(extract_string(args.next())?, extract_string(args.next())?)
+            }
+            sub => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'",
+                    sub
+                )))
+            }
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(XInfo { sub })
+    }
+}
+
+impl TryFrom<RespArray> for XRead {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xread"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let mut count = None;
+        let mut block = None;
+        loop {
+            match extract_string(args.next())?.to_ascii_uppercase().as_str() {
+                // a negative count is 0, which like XREADGROUP's means no limit
+                "COUNT" => {
+                    count = Some(usize::try_from(extract_int(args.next())?).unwrap_or(0))
+                        .filter(|count| *count > 0)
+                }
+                "BLOCK" => block = Some(parse_block(args.next())?),
+                "STREAMS" => break,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        let rest: Vec<String> = args
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        if rest.is_empty() || !rest.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified."
+                    .to_string(),
+            ));
+        }
+        let (keys, ids) = rest.split_at(rest.len() / 2);
+        let ids = ids
+            .iter()
+            .map(|id| match id.as_str() {
+                "$" => Ok(ReadFrom::New),
+                "+" => Ok(ReadFrom::LastEntry),
+                id => id.parse().map(ReadFrom::After).map_err(|_| invalid_id()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XRead {
+            count,
+            block,
+            keys: keys.to_vec(),
+            ids,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XGroup {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xgroup"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let sub = extract_string(args.next())?.to_ascii_uppercase();
+        let key = extract_string(args.next())?;
+        let group = extract_string(args.next())?;
+        let sub = match sub.as_str() {
+            "CREATE" | "SETID" => {
+                let start = match extract_string(args.next())?.as_str() {
+                    "$" => GroupStart::Last,
+                    id => GroupStart::After(id.parse().map_err(|_| invalid_id())?),
+                };
+                let mut mkstream = false;
+                let mut entries_read = None;
+                while let Some(arg) = args.next() {
+                    match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                        "MKSTREAM" if sub == "CREATE" => mkstream = true,
+                        "ENTRIESREAD" => {
+                            entries_read =
+                                Some(u64::try_from(extract_int(args.next())?).map_err(|_| {
+                                    CommandError::InvalidArgument(
+                                        "value for ENTRIESREAD must be positive".to_string(),
+                                    )
+                                })?)
+                        }
+                        _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+                    }
+                }
+                if sub == "CREATE" {
+                    XGroupSub::Create {
+                        key,
+                        group,
+                        start,
+                        mkstream,
+                        entries_read,
+                    }
+                } else {
+                    XGroupSub::SetId {
+                        key,
+                        group,
+                        start,
+                        entries_read,
+                    }
+                }
+            }
+            "DESTROY" => XGroupSub::Destroy { key, group },
+            "CREATECONSUMER" => XGroupSub::CreateConsumer {
+                key,
+                group,
+                consumer: extract_string(args.next())?,
+            },
+            "DELCONSUMER" => XGroupSub::DelConsumer {
+                key,
+                group,
+                consumer: extract_string(args.next())?,
+            },
+            sub => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'",
+                    sub
+                )))
+            }
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(XGroup { sub })
+    }
+}
+
+impl TryFrom<RespArray> for XReadGroup {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xreadgroup"], 6)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        if !extract_string(args.next())?.eq_ignore_ascii_case("GROUP") {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let group = extract_string(args.next())?;
+        let consumer = extract_string(args.next())?;
+        let mut count = None;
+        let mut block = None;
+        let mut no_ack = false;
+        loop {
+            match extract_string(args.next())?.to_ascii_uppercase().as_str() {
+                // COUNT 0 reads everything
+                "COUNT" => count = Some(extract_count(args.next())?).filter(|count| *count > 0),
+                "BLOCK" => block = Some(parse_block(args.next())?),
+                "NOACK" => no_ack = true,
+                "STREAMS" => break,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        let rest = extract_strings(args)?;
+        if rest.is_empty() || !rest.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "Unbalanced 'xreadgroup' list of streams: for each stream key an ID or '>' must be specified."
+                    .to_string(),
+            ));
+        }
+        let (keys, ids) = rest.split_at(rest.len() / 2);
+        let ids = ids
+            .iter()
+            .map(|id| match id.as_str() {
+                ">" => Ok(None),
+                id => id.parse().map(Some).map_err(|_| invalid_id()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XReadGroup {
+            group,
+            consumer,
+            count,
+            block,
+            no_ack,
+            keys: keys.to_vec(),
+            ids,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAck {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xack"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let group = extract_string(args.next())?;
+        let ids = args
+            .map(|arg| parse_id(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(XAck { key, group, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XPending {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xpending"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let group = extract_string(args.next())?;
+        if args.peek().is_none() {
+            return Ok(XPending {
+                key,
+                group,
+                range: None,
+            });
+        }
+
+        let mut start = extract_string(args.next())?;
+        let mut min_idle = None;
+        if start.eq_ignore_ascii_case("IDLE") {
+            min_idle = Some(extract_int(args.next())?.max(0) as u64);
+            start = extract_string(args.next())?;
+        }
+        let start = parse_range_bound(Some(BulkString::from(start).into()), true)?;
+        let end = parse_range_bound(args.next(), false)?;
+        let count = extract_count(args.next())?;
+        let consumer = args
+            .next()
+            .map(|arg| extract_string(Some(arg)))
+            .transpose()?;
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(XPending {
+            key,
+            group,
+            range: Some(PendingRange {
+                min_idle,
+                start,
+                end,
+                count,
+                consumer,
+            }),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XClaim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xclaim"], 5)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let group = extract_string(args.next())?;
+        let consumer = extract_string(args.next())?;
+        let min_idle = parse_min_idle(args.next())?;
+
+        // the IDs run up to the first option
+        let mut ids = vec![];
+        while let Some(RespFrame::BulkString(arg)) = args.peek() {
+            match String::from_utf8_lossy(arg).parse() {
+                Ok(id) => ids.push(id),
+                Err(_) if ids.is_empty() => return Err(invalid_id()),
+                Err(_) => break,
+            }
+            args.next();
+        }
+
+        let mut idle = None;
+        let mut options = ClaimOptions::default();
+        while let Some(arg) = args.next() {
+            let option = extract_string(Some(arg))?;
+            match option.to_ascii_uppercase().as_str() {
+                "IDLE" => idle = Some(extract_int(args.next())?.max(0) as u64),
+                "TIME" => options.delivery_time = Some(extract_int(args.next())?.max(0) as u64),
+                "RETRYCOUNT" => options.retry_count = Some(extract_int(args.next())?.max(0) as u64),
+                "FORCE" => options.force = true,
+                "JUSTID" => options.just_id = true,
+                "LASTID" => options.last_id = Some(parse_id(args.next())?),
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Unrecognized XCLAIM option '{}'",
+                        option
+                    )))
+                }
+            }
+        }
+        Ok(XClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            ids,
+            idle,
+            options,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAutoClaim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xautoclaim"], 5)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let group = extract_string(args.next())?;
+        let consumer = extract_string(args.next())?;
+        let min_idle = parse_min_idle(args.next())?;
+        let start = match parse_range_bound(args.next(), true)? {
+            Bound::Included(id) => id,
+            Bound::Excluded(id) => id.next().ok_or_else(invalid_id)?,
+            Bound::Unbounded => StreamId::MIN,
+        };
+
+        let mut count = 100;
+        let mut just_id = false;
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                "COUNT" => {
+                    count = extract_count(args.next())?;
+                    if count == 0 {
+                        return Err(CommandError::InvalidArgument(
+                            "COUNT must be > 0".to_string(),
+                        ));
+                    }
+                }
+                "JUSTID" => just_id = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(XAutoClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            start,
+            count,
+            just_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    fn decode(input: &[u8]) -> Result<RespArray> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(input);
+        Ok(RespArray::decode(&mut buf)?)
+    }
+
+    fn xadd(backend: &Backend, key: &str, id: &str) -> Result<RespFrame> {
+        let cmd: XAdd = args(&["xadd", key, id, "f", "v"]).try_into()?;
+        Ok(cmd.execute(backend)?)
+    }
+
+    #[test]
+    fn test_xadd_from_resp_array() -> Result<()> {
+        let cmd: XAdd = decode(
+            b"*9\r\n$4\r\nxadd\r\n$1\r\ns\r\n$10\r\nnomkstream\r\n$6\r\nmaxlen\r\n$1\r\n~\r\n$2\r\n10\r\n$3\r\n5-*\r\n$1\r\nf\r\n$1\r\nv\r\n",
+        )?
+        .try_into()?;
+        assert!(cmd.no_mkstream);
+        assert_eq!(
+            cmd.trim,
+            Some(TrimArgs {
+                trim: StreamTrim::MaxLen(10),
+                limit: None
+            })
+        );
+        assert_eq!(cmd.id, StreamIdSpec::AutoSeq(5));
+        assert_eq!(cmd.fields.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_xadd_xrange_command() -> Result<()> {
+        let backend = Backend::new();
+        for id in ["1-1", "1-2", "2-0", "3-5"] {
+            xadd(&backend, "s", id)?;
+        }
+        assert!(xadd(&backend, "s", "3-5").is_err());
+
+        let cmd: XRange =
+            decode(b"*6\r\n$6\r\nxrange\r\n$1\r\ns\r\n$4\r\n(1-1\r\n$1\r\n2\r\n$5\r\ncount\r\n$2\r\n10\r\n")?
+                .try_into()?;
+        let entries = |ids: &[(u64, u64)]| {
+            entries_reply(
+                ids.iter()
+                    .map(|(ms, seq)| (StreamId::new(*ms, *seq), vec![b"f".into(), b"v".into()]))
+                    .collect(),
+            )
+        };
+        assert_eq!(cmd.execute(&backend)?, entries(&[(1, 2), (2, 0)]));
+
+        let cmd = XRevRange {
+            key: "s".to_string(),
+            end: Bound::Unbounded,
+            start: Bound::Unbounded,
+            count: Some(1),
+        };
+        assert_eq!(cmd.execute(&backend)?, entries(&[(3, 5)]));
+
+        let cmd = XTrim {
+            key: "s".to_string(),
+            trim: TrimArgs {
+                trim: StreamTrim::MinId(StreamId::new(2, 0)),
+                limit: None,
+            },
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(2));
+        let cmd = XDel {
+            key: "s".to_string(),
+            ids: vec![StreamId::new(2, 0), StreamId::new(9, 9)],
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+        assert_eq!(backend.stream_len("s"), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xread_block_wakes_on_xadd() -> Result<()> {
+        let backend = Backend::new();
+        xadd(&backend, "s", "1-0")?;
+        let cmd: XRead = decode(
+            b"*6\r\n$5\r\nxread\r\n$5\r\nblock\r\n$4\r\n5000\r\n$7\r\nstreams\r\n$1\r\ns\r\n$1\r\n$\r\n",
+        )?
+        .try_into()?;
+        let handle = tokio::spawn({
+            let backend = backend.clone();
+            async move { cmd.block(&backend).await }
+        });
+        while backend.blocked_clients() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        xadd(&backend, "s", "2-0")?;
+        let expected = RespArray::new([RespArray::new([
+            BulkString::new("s").into(),
+            entries_reply(vec![(StreamId::new(2, 0), vec![b"f".into(), b"v".into()])]),
+        ])
+        .into()]);
+        assert_eq!(handle.await??, expected.into());
+        // reading does not consume entries
+        assert_eq!(backend.stream_len("s"), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xread_count_zero() -> Result<()> {
+        let backend = Backend::new();
+        xadd(&backend, "s", "1-0")?;
+        xadd(&backend, "s", "2-0")?;
+        for count in ["0", "-5"] {
+            let cmd: XRead =
+                args(&["xread", "count", count, "block", "0", "streams", "s", "0"]).try_into()?;
+            assert_eq!(cmd.count, None);
+            let reply = tokio::time::timeout(Duration::from_secs(1), cmd.block(&backend)).await??;
+            let expected = RespArray::new([RespArray::new([
+                BulkString::new("s").into(),
+                entries_reply(vec![
+                    (StreamId::new(1, 0), vec![b"f".into(), b"v".into()]),
+                    (StreamId::new(2, 0), vec![b"f".into(), b"v".into()]),
+                ]),
+            ])
+            .into()]);
+            assert_eq!(reply, expected.into());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_xreadgroup_from_resp_array() -> Result<()> {
+        let cmd: XReadGroup = decode(
+            b"*12\r\n$10\r\nxreadgroup\r\n$5\r\ngroup\r\n$1\r\ng\r\n$1\r\nc\r\n$5\r\ncount\r\n$1\r\n2\r\n$5\r\nnoack\r\n$7\r\nstreams\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\n>\r\n$1\r\n0\r\n",
+        )?
+        .try_into()?;
+        assert_eq!(cmd.count, Some(2));
+        assert!(cmd.no_ack);
+        assert_eq!(cmd.keys, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(cmd.ids, vec![None, Some(StreamId::MIN)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_xreadgroup_xack_xpending_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: XGroup = args(&["xgroup", "create", "s", "g", "$", "mkstream"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RESP_OK.clone());
+        let cmd: XGroup = args(&["xgroup", "create", "s", "g", "$"]).try_into()?;
+        assert!(cmd.execute(&backend).is_err());
+        xadd(&backend, "s", "1-0")?;
+        xadd(&backend, "s", "2-0")?;
+
+        let cmd: XReadGroup =
+            args(&["xreadgroup", "group", "g", "c", "streams", "s", ">"]).try_into()?;
+        let expected = RespArray::new([RespArray::new([
+            BulkString::new("s").into(),
+            entries_reply(vec![
+                (StreamId::new(1, 0), vec![b"f".into(), b"v".into()]),
+                (StreamId::new(2, 0), vec![b"f".into(), b"v".into()]),
+            ]),
+        ])
+        .into()]);
+        assert_eq!(cmd.execute(&backend)?, expected.into());
+        let cmd: XReadGroup =
+            args(&["xreadgroup", "group", "g", "c", "streams", "s", ">"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespNullArray.into());
+
+        let cmd: XAck = args(&["xack", "s", "g", "1-0"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+        let cmd: XPending = args(&["xpending", "s", "g"]).try_into()?;
+        let summary = RespArray::new([
+            RespFrame::Integer(1),
+            BulkString::new("2-0").into(),
+            BulkString::new("2-0").into(),
+            RespArray::new([RespArray::new([
+                BulkString::new("c").into(),
+                BulkString::new("1").into(),
+            ])
+            .into()])
+            .into(),
+        ]);
+        assert_eq!(cmd.execute(&backend)?, summary.into());
+
+        let cmd: XClaim = args(&["xclaim", "s", "g", "d", "0", "2-0", "justid"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([BulkString::new("2-0").into()]).into()
+        );
+        let cmd: XPending = args(&["xpending", "s", "g", "-", "+", "10", "d"]).try_into()?;
+        let RespFrame::Array(pending) = cmd.execute(&backend)? else {
+            panic!("expected an array");
+        };
+        assert_eq!(pending.len(), 1);
+
+        let cmd: XAutoClaim = args(&["xautoclaim", "s", "g", "c", "0", "-"]).try_into()?;
+        let RespFrame::Array(ret) = cmd.execute(&backend)? else {
+            panic!("expected an array");
+        };
+        assert_eq!(ret[0], BulkString::new("0-0").into());
+        assert_eq!(
+            ret[1],
+            entries_reply(vec![(StreamId::new(2, 0), vec![b"f".into(), b"v".into()])])
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xreadgroup_block_serves_every_group() -> Result<()> {
+        let backend = Backend::new();
+        for group in ["g1", "g2"] {
+            let cmd: XGroup =
+                args(&["xgroup", "create", "s", group, "$", "mkstream"]).try_into()?;
+            cmd.execute(&backend)?;
+        }
+        let mut handles = vec![];
+        for (group, consumer) in [("g1", "a"), ("g1", "b"), ("g2", "c")] {
+            let cmd: XReadGroup = args(&[
+                "xreadgroup",
+                "group",
+                group,
+                consumer,
+                "block",
+                "5000",
+                "streams",
+                "s",
+                ">",
+            ])
+            .try_into()?;
+            handles.push(tokio::spawn({
+                let backend = backend.clone();
+                async move { cmd.block(&backend).await }
+            }));
+            while backend.blocked_clients() < handles.len() {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        // the entry goes to the first reader of each group, the second one of g1 keeps waiting
+        xadd(&backend, "s", "1-0")?;
+        let third = handles.pop().unwrap();
+        let second = handles.pop().unwrap();
+        let first = handles.pop().unwrap();
+        assert!(matches!(first.await??, RespFrame::Array(_)));
+        assert!(matches!(third.await??, RespFrame::Array(_)));
+        assert_eq!(backend.blocked_clients(), 1);
+        xadd(&backend, "s", "2-0")?;
+        assert!(matches!(second.await??, RespFrame::Array(_)));
+        Ok(())
+    }
+}
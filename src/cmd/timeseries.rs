@@ -0,0 +1,1070 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use dashmap::mapref::entry::Entry;
+use dashmap::mapref::one::Ref;
+
+use crate::array::RespArray;
+use crate::backend::{
+    now_millis, Aggregation, Backend, BucketTimestamp, DuplicatePolicy, LabelFilter,
+    RangeAggregation, RangeQuery, TimeSeries, NOTIFY_MODULE,
+};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_float, extract_int, extract_string, validate_command,
+    validate_command_at_least, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::map::RespMap;
+use crate::null::RespNull;
+use crate::simple_error::SimpleError;
+
+#[derive(Debug)]
+pub struct TsCreate {
+    key: String,
+    options: SeriesOptions,
+}
+
+#[derive(Debug)]
+pub struct TsAdd {
+    key: String,
+    /// `None` for `*`, the current time
+    ts: Option<u64>,
+    value: f64,
+    options: SeriesOptions,
+}
+
+#[derive(Debug)]
+pub struct TsMAdd {
+    samples: Vec<(String, Option<u64>, f64)>,
+}
+
+#[derive(Debug)]
+pub struct TsIncrBy {
+    incr: TsIncr,
+}
+
+#[derive(Debug)]
+pub struct TsDecrBy {
+    incr: TsIncr,
+}
+
+#[derive(Debug)]
+pub struct TsGet {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct TsRange {
+    key: String,
+    query: RangeQuery,
+}
+
+#[derive(Debug)]
+pub struct TsRevRange {
+    key: String,
+    query: RangeQuery,
+}
+
+#[derive(Debug)]
+pub struct TsMRange {
+    query: RangeQuery,
+    multi: MultiOptions,
+}
+
+#[derive(Debug)]
+pub struct TsMRevRange {
+    query: RangeQuery,
+    multi: MultiOptions,
+}
+
+#[derive(Debug)]
+pub struct TsCreateRule {
+    source: String,
+    dest: String,
+    aggregation: Aggregation,
+    bucket: u64,
+    align: u64,
+}
+
+#[derive(Debug)]
+pub struct TsDeleteRule {
+    source: String,
+    dest: String,
+}
+
+#[derive(Debug)]
+pub struct TsInfo {
+    key: String,
+}
+
+/// The settings TS.CREATE takes, which TS.ADD and TS.INCRBY use to create missing series.
+#[derive(Debug, Default)]
+struct SeriesOptions {
+    retention: Option<u64>,
+    duplicate_policy: Option<DuplicatePolicy>,
+    labels: Vec<(String, String)>,
+    /// TS.ADD only, overrides the duplicate policy for this sample
+    on_duplicate: Option<DuplicatePolicy>,
+}
+
+#[derive(Debug)]
+struct TsIncr {
+    key: String,
+    delta: f64,
+    ts: Option<u64>,
+    options: SeriesOptions,
+}
+
+#[derive(Debug, Default)]
+struct MultiOptions {
+    with_labels: bool,
+    selected_labels: Option<Vec<String>>,
+    filters: Vec<LabelFilter>,
+}
+
+impl CommandExecutor for TsCreate {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match backend.timeseries.entry(self.key.clone()) {
+            Entry::Occupied(_) => Err(CommandError::InvalidArgument(
+                "TSDB: key already exists".to_string(),
+            )),
+            Entry::Vacant(entry) => {
+                entry.insert(self.options.into_series());
+                backend.notify_new(true, &self.key);
+                backend.notify(NOTIFY_MODULE, "ts.create", &self.key);
+                Ok(RESP_OK.clone())
+            }
+        }
+    }
+}
+
+impl CommandExecutor for TsAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let ts = self.ts.unwrap_or_else(now_millis);
+        let on_duplicate = self.options.on_duplicate;
+        let ts = backend
+            .ts_add(
+                &self.key,
+                Some(self.options.into_series()),
+                "ts.add",
+                |series| {
+                    series.add(ts, self.value, on_duplicate)?;
+                    Ok(ts)
+                },
+            )
+            .map_err(CommandError::InvalidArgument)?;
+        Ok(RespFrame::Integer(ts as i64))
+    }
+}
+
+impl CommandExecutor for TsMAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let results = self
+            .samples
+            .into_iter()
+            .map(|(key, ts, value)| {
+                let ts = ts.unwrap_or_else(now_millis);
+                let added = backend.ts_add(&key, None, "ts.add", |series| {
+                    series.add(ts, value, None)?;
+                    Ok(ts)
+                });
+                match added {
+                    Ok(ts) => RespFrame::Integer(ts as i64),
+                    Err(err) => SimpleError::new(err).into(),
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(RespArray::new(results).into())
+    }
+}
+
+impl CommandExecutor for TsIncrBy {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        self.incr.execute(backend, "ts.incrby")
+    }
+}
+
+impl CommandExecutor for TsDecrBy {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        self.incr.execute(backend, "ts.decrby")
+    }
+}
+
+impl TsIncr {
+    /// Adds a sample at `ts` (now by default) whose value is the last one plus `delta`.
+    fn execute(self, backend: &Backend, event: &str) -> Result<RespFrame, CommandError> {
+        let ts = self.ts.unwrap_or_else(now_millis);
+        let ts = backend
+            .ts_add(&self.key, Some(self.options.into_series()), event, |series| {
+                let last = series.last();
+                if last.is_some_and(|(last, _)| ts < last) {
+                    return Err("TSDB: timestamp must be equal to or higher than the maximum existing timestamp".to_string());
+                }
+                let value = last.map_or(0.0, |(_, value)| value) + self.delta;
+                series.add(ts, value, Some(DuplicatePolicy::Last))?;
+                Ok(ts)
+            })
+            .map_err(CommandError::InvalidArgument)?;
+        Ok(RespFrame::Integer(ts as i64))
+    }
+}
+
+impl CommandExecutor for TsGet {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let series = get_series(backend, &self.key)?;
+        Ok(series
+            .last()
+            .map_or_else(|| RespArray::new([]).into(), sample_reply))
+    }
+}
+
+impl CommandExecutor for TsRange {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let series = get_series(backend, &self.key)?;
+        Ok(samples_reply(series.query(&self.query)))
+    }
+}
+
+impl CommandExecutor for TsRevRange {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let series = get_series(backend, &self.key)?;
+        Ok(samples_reply(series.query(&self.query)))
+    }
+}
+
+impl CommandExecutor for TsMRange {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(mrange(backend, &self.query, &self.multi))
+    }
+}
+
+impl CommandExecutor for TsMRevRange {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(mrange(backend, &self.query, &self.multi))
+    }
+}
+
+impl CommandExecutor for TsCreateRule {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        backend
+            .ts_create_rule(
+                &self.source,
+                &self.dest,
+                self.aggregation,
+                self.bucket,
+                self.align,
+            )
+            .map_err(CommandError::InvalidArgument)?;
+        Ok(RESP_OK.clone())
+    }
+}
+
+impl CommandExecutor for TsDeleteRule {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        backend
+            .ts_delete_rule(&self.source, &self.dest)
+            .map_err(CommandError::InvalidArgument)?;
+        Ok(RESP_OK.clone())
+    }
+}
+
+impl CommandExecutor for TsInfo {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let series = get_series(backend, &self.key)?;
+        let timestamp =
+            |sample: Option<(u64, f64)>| RespFrame::Integer(sample.map_or(0, |s| s.0 as i64));
+        let mut info = RespMap::new();
+        info.insert(
+            "totalSamples".to_string(),
+            RespFrame::Integer(series.len() as i64),
+        );
+        info.insert("firstTimestamp".to_string(), timestamp(series.first()));
+        info.insert("lastTimestamp".to_string(), timestamp(series.last()));
+        info.insert(
+            "retentionTime".to_string(),
+            RespFrame::Integer(series.retention() as i64),
+        );
+        info.insert(
+            "duplicatePolicy".to_string(),
+            BulkString::new(series.duplicate_policy().name()).into(),
+        );
+        info.insert("labels".to_string(), labels_reply(series.labels().iter()));
+        info.insert(
+            "sourceKey".to_string(),
+            series.source().map_or(RespFrame::Null(RespNull), |source| {
+                BulkString::new(source).into()
+            }),
+        );
+        let rules = series
+            .rules()
+            .iter()
+            .map(|rule| {
+                RespArray::new([
+                    BulkString::new(rule.dest.as_str()).into(),
+                    RespFrame::Integer(rule.bucket as i64),
+                    BulkString::new(rule.aggregation.name().to_ascii_uppercase()).into(),
+                    RespFrame::Integer(rule.align as i64),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>();
+        info.insert("rules".to_string(), RespArray::new(rules).into());
+        Ok(info.into())
+    }
+}
+
+impl SeriesOptions {
+    fn into_series(self) -> TimeSeries {
+        TimeSeries::new(
+            self.retention.unwrap_or(0),
+            self.duplicate_policy.unwrap_or_default(),
+            self.labels,
+        )
+    }
+}
+
+fn get_series<'a>(
+    backend: &'a Backend,
+    key: &str,
+) -> Result<Ref<'a, String, TimeSeries>, CommandError> {
+    backend
+        .timeseries
+        .get(key)
+        .ok_or_else(|| CommandError::InvalidArgument("TSDB: the key does not exist".to_string()))
+}
+
+fn mrange(backend: &Backend, query: &RangeQuery, multi: &MultiOptions) -> RespFrame {
+    let mut series: Vec<(String, RespFrame)> = backend
+        .timeseries
+        .iter()
+        .filter(|entry| entry.matches(&multi.filters))
+        .map(|entry| {
+            let labels = match &multi.selected_labels {
+                Some(selected) => RespArray::new(
+                    selected
+                        .iter()
+                        .map(|label| {
+                            let value = entry
+                                .label(label)
+                                .map_or(RespFrame::Null(RespNull), |v| BulkString::new(v).into());
+                            RespArray::new([BulkString::new(label.as_str()).into(), value]).into()
+                        })
+                        .collect::<Vec<RespFrame>>(),
+                )
+                .into(),
+                None if multi.with_labels => labels_reply(entry.labels().iter()),
+                None => RespArray::new([]).into(),
+            };
+            let reply = RespArray::new([
+                BulkString::new(entry.key().as_str()).into(),
+                labels,
+                samples_reply(entry.query(query)),
+            ]);
+            (entry.key().clone(), reply.into())
+        })
+        .collect();
+    series.sort_by(|a, b| a.0.cmp(&b.0));
+    RespArray::new(
+        series
+            .into_iter()
+            .map(|(_, reply)| reply)
+            .collect::<Vec<_>>(),
+    )
+    .into()
+}
+
+fn sample_reply((ts, value): (u64, f64)) -> RespFrame {
+    RespArray::new([RespFrame::Integer(ts as i64), RespFrame::Double(value)]).into()
+}
+
+fn samples_reply(samples: Vec<(u64, f64)>) -> RespFrame {
+    RespArray::new(samples.into_iter().map(sample_reply).collect::<Vec<_>>()).into()
+}
+
+fn labels_reply<'a>(labels: impl Iterator<Item = &'a (String, String)>) -> RespFrame {
+    RespArray::new(
+        labels
+            .map(|(label, value)| {
+                RespArray::new([
+                    BulkString::new(label.as_str()).into(),
+                    BulkString::new(value.as_str()).into(),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+fn invalid(message: &str) -> CommandError {
+    CommandError::InvalidArgument(format!("TSDB: {}", message))
+}
+
+fn parse_u64(arg: Option<RespFrame>, what: &str) -> Result<u64, CommandError> {
+    u64::try_from(extract_int(arg)?).map_err(|_| invalid(&format!("invalid {}", what)))
+}
+
+/// A sample timestamp, `None` for `*`.
+fn parse_timestamp(arg: Option<RespFrame>) -> Result<Option<u64>, CommandError> {
+    let ts = extract_string(arg)?;
+    if ts == "*" {
+        return Ok(None);
+    }
+    ts.parse()
+        .map(Some)
+        .map_err(|_| invalid("invalid timestamp"))
+}
+
+/// A range bound, `-` and `+` being the earliest and latest timestamps.
+fn parse_bound(arg: Option<RespFrame>) -> Result<u64, CommandError> {
+    match extract_string(arg)?.as_str() {
+        "-" => Ok(0),
+        "+" => Ok(u64::MAX),
+        ts => ts.parse().map_err(|_| invalid("invalid timestamp")),
+    }
+}
+
+fn parse_policy(arg: Option<RespFrame>) -> Result<DuplicatePolicy, CommandError> {
+    DuplicatePolicy::parse(&extract_string(arg)?).ok_or_else(|| invalid("Unknown DUPLICATE_POLICY"))
+}
+
+/// Parses the series options that follow the key. Options other than RETENTION, ENCODING,
+/// CHUNK_SIZE, DUPLICATE_POLICY and LABELS go to `extra`, which returns whether it took them.
+fn parse_series_options(
+    mut args: impl Iterator<Item = RespFrame>,
+    allow_on_duplicate: bool,
+    mut extra: impl FnMut(&str, &mut dyn Iterator<Item = RespFrame>) -> Result<bool, CommandError>,
+) -> Result<SeriesOptions, CommandError> {
+    let mut options = SeriesOptions::default();
+    while let Some(arg) = args.next() {
+        let option = extract_string(Some(arg))?.to_ascii_uppercase();
+        match option.as_str() {
+            "RETENTION" => options.retention = Some(parse_u64(args.next(), "retention")?),
+            // samples are kept uncompressed in memory, these are accepted for compatibility
+            "ENCODING" => {
+                let encoding = extract_string(args.next())?.to_ascii_uppercase();
+                if encoding != "COMPRESSED" && encoding != "UNCOMPRESSED" {
+                    return Err(invalid("unknown encoding"));
+                }
+            }
+            "CHUNK_SIZE" => {
+                parse_u64(args.next(), "chunk size")?;
+            }
+            "DUPLICATE_POLICY" => options.duplicate_policy = Some(parse_policy(args.next())?),
+            "ON_DUPLICATE" if allow_on_duplicate => {
+                options.on_duplicate = Some(parse_policy(args.next())?)
+            }
+            "LABELS" => {
+                let rest = args.by_ref().collect::<Vec<_>>();
+                if rest.len() % 2 != 0 {
+                    return Err(invalid("wrong number of labels"));
+                }
+                let mut rest = rest.into_iter();
+                while let (Some(label), Some(value)) = (rest.next(), rest.next()) {
+                    options
+                        .labels
+                        .push((extract_string(Some(label))?, extract_string(Some(value))?));
+                }
+            }
+            option => {
+                if !extra(option, &mut args)? {
+                    return Err(CommandError::InvalidArgument("syntax error".to_string()));
+                }
+            }
+        }
+    }
+    Ok(options)
+}
+
+fn parse_incr(value: RespArray, name: &'static str, sign: f64) -> Result<TsIncr, CommandError> {
+    validate_command_at_least(&value, &[name], 2)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    let delta = extract_float(args.next())? * sign;
+    let mut ts = None;
+    let options = parse_series_options(args, false, |option, args| {
+        if option != "TIMESTAMP" {
+            return Ok(false);
+        }
+        ts = parse_timestamp(args.next())?;
+        Ok(true)
+    })?;
+    Ok(TsIncr {
+        key,
+        delta,
+        ts,
+        options,
+    })
+}
+
+/// Parses the range and its options, TS.MRANGE's label options are only accepted when `multi`
+/// is given.
+fn parse_range(
+    args: &mut Peekable<IntoIter<RespFrame>>,
+    reverse: bool,
+    mut multi: Option<&mut MultiOptions>,
+) -> Result<RangeQuery, CommandError> {
+    let mut query = RangeQuery {
+        from: parse_bound(args.next())?,
+        to: parse_bound(args.next())?,
+        reverse,
+        ..Default::default()
+    };
+    let mut align = None;
+    let (mut bucket_timestamp, mut empty) = (BucketTimestamp::default(), false);
+    while let Some(arg) = args.next() {
+        let option = extract_string(Some(arg))?.to_ascii_uppercase();
+        match (option.as_str(), multi.as_deref_mut()) {
+            ("LATEST", _) => {}
+            ("FILTER_BY_TS", _) => {
+                // the timestamps run until the next option
+                let mut filter = vec![];
+                while let Some(ts) = args.next_if(|arg| frame_to_u64(arg).is_some()) {
+                    filter.extend(frame_to_u64(&ts));
+                }
+                if filter.is_empty() {
+                    return Err(invalid("FILTER_BY_TS needs at least one timestamp"));
+                }
+                query.filter_ts = Some(filter);
+            }
+            ("FILTER_BY_VALUE", _) => {
+                let min = extract_float(args.next())?;
+                query.filter_value = Some((min, extract_float(args.next())?));
+            }
+            ("COUNT", _) => query.count = Some(parse_u64(args.next(), "count")? as usize),
+            ("ALIGN", _) => {
+                align = Some(
+                    match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+                        "-" | "start" => query.from,
+                        "+" | "end" => query.to,
+                        ts => ts.parse().map_err(|_| invalid("unknown ALIGN parameter"))?,
+                    },
+                );
+            }
+            ("AGGREGATION", _) => {
+                let aggregation = Aggregation::parse(&extract_string(args.next())?)
+                    .ok_or_else(|| invalid("Unknown aggregation type"))?;
+                let bucket = parse_u64(args.next(), "bucket duration")?;
+                if bucket == 0 {
+                    return Err(invalid("bucketDuration must be greater than zero"));
+                }
+                query.aggregation = Some(RangeAggregation {
+                    aggregation,
+                    bucket,
+                    align: 0,
+                    bucket_timestamp: BucketTimestamp::default(),
+                    empty: false,
+                });
+            }
+            ("BUCKETTIMESTAMP", _) => {
+                bucket_timestamp = match extract_string(args.next())?.to_ascii_lowercase().as_str()
+                {
+                    "-" | "start" => BucketTimestamp::Start,
+                    "+" | "end" => BucketTimestamp::End,
+                    "~" | "mid" => BucketTimestamp::Mid,
+                    _ => return Err(invalid("unknown BUCKETTIMESTAMP parameter")),
+                };
+            }
+            ("EMPTY", _) => empty = true,
+            ("WITHLABELS", Some(multi)) => multi.with_labels = true,
+            ("SELECTED_LABELS", Some(multi)) => {
+                let mut labels = vec![];
+                while let Some(label) = args.next_if(|arg| {
+                    !matches!(arg, RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"FILTER"))
+                }) {
+                    labels.push(extract_string(Some(label))?);
+                }
+                multi.selected_labels = Some(labels);
+            }
+            ("FILTER", Some(multi)) => {
+                // the filters run to the end of the command
+                for arg in args.by_ref() {
+                    multi
+                        .filters
+                        .push(parse_label_filter(&extract_string(Some(arg))?)?);
+                }
+            }
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+
+    match query.aggregation.as_mut() {
+        Some(aggregation) => {
+            aggregation.align = align.unwrap_or(0);
+            aggregation.bucket_timestamp = bucket_timestamp;
+            aggregation.empty = empty;
+        }
+        None if align.is_some() => {
+            return Err(invalid("ALIGN parameter can only be used with AGGREGATION"))
+        }
+        None if empty || bucket_timestamp != BucketTimestamp::Start => {
+            return Err(invalid(
+                "EMPTY and BUCKETTIMESTAMP can only be used with AGGREGATION",
+            ))
+        }
+        None => {}
+    }
+    Ok(query)
+}
+
+fn frame_to_u64(frame: &RespFrame) -> Option<u64> {
+    match frame {
+        RespFrame::BulkString(s) => std::str::from_utf8(s).ok()?.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Parses a label matcher: `l=v`, `l!=v`, `l=` (no label l), `l!=` (has label l), or a list
+/// of values such as `l=(v1,v2)`.
+fn parse_label_filter(filter: &str) -> Result<LabelFilter, CommandError> {
+    let (label, values, negate) = match filter.split_once("!=") {
+        Some((label, values)) => (label, values, true),
+        None => match filter.split_once('=') {
+            Some((label, values)) => (label, values, false),
+            None => return Err(invalid("failed parsing labels")),
+        },
+    };
+    if label.is_empty() {
+        return Err(invalid("failed parsing labels"));
+    }
+    let values = match values.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+        Some(list) => list.split(',').map(|v| v.trim().to_string()).collect(),
+        None if values.is_empty() => vec![],
+        None => vec![values.to_string()],
+    };
+    Ok(LabelFilter {
+        label: label.to_string(),
+        values,
+        negate,
+    })
+}
+
+fn parse_mrange(
+    value: RespArray,
+    name: &'static str,
+    reverse: bool,
+) -> Result<(RangeQuery, MultiOptions), CommandError> {
+    validate_command_at_least(&value, &[name], 3)?;
+    let mut args = extract_args(value, 1)?.into_iter().peekable();
+    let mut multi = MultiOptions::default();
+    let query = parse_range(&mut args, reverse, Some(&mut multi))?;
+    if multi.with_labels && multi.selected_labels.is_some() {
+        return Err(invalid(
+            "WITHLABELS and SELECTED_LABELS are mutually exclusive",
+        ));
+    }
+    // matching series must have a label, so at least one matcher has to require a value
+    if !multi
+        .filters
+        .iter()
+        .any(|filter| !filter.negate && !filter.values.is_empty())
+    {
+        return Err(invalid("please provide at least one matcher"));
+    }
+    Ok((query, multi))
+}
+
+fn parse_single_range(
+    value: RespArray,
+    name: &'static str,
+    reverse: bool,
+) -> Result<(String, RangeQuery), CommandError> {
+    validate_command_at_least(&value, &[name], 3)?;
+    let mut args = extract_args(value, 1)?.into_iter().peekable();
+    let key = extract_string(args.next())?;
+    Ok((key, parse_range(&mut args, reverse, None)?))
+}
+
+impl TryFrom<RespArray> for TsCreate {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["ts.create"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let options = parse_series_options(args, false, |_, _| Ok(false))?;
+        Ok(TsCreate { key, options })
+    }
+}
+
+impl TryFrom<RespArray> for TsAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["ts.add"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let ts = parse_timestamp(args.next())?;
+        let value = extract_float(args.next())?;
+        let options = parse_series_options(args, true, |_, _| Ok(false))?;
+        Ok(TsAdd {
+            key,
+            ts,
+            value,
+            options,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsMAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["ts.madd"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        if args.len() % 3 != 0 {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'ts.madd' command".to_string(),
+            ));
+        }
+        let mut samples = vec![];
+        while let Some(key) = args.next() {
+            samples.push((
+                extract_string(Some(key))?,
+                parse_timestamp(args.next())?,
+                extract_float(args.next())?,
+            ));
+        }
+        Ok(TsMAdd { samples })
+    }
+}
+
+impl TryFrom<RespArray> for TsIncrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(TsIncrBy {
+            incr: parse_incr(value, "ts.incrby", 1.0)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsDecrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(TsDecrBy {
+            incr: parse_incr(value, "ts.decrby", -1.0)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsGet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["ts.get"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        // LATEST only matters for compactions still being filled, which aren't reported
+        if let Some(arg) = args.next() {
+            if !extract_string(Some(arg))?.eq_ignore_ascii_case("latest") {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            }
+        }
+        Ok(TsGet { key })
+    }
+}
+
+impl TryFrom<RespArray> for TsRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, query) = parse_single_range(value, "ts.range", false)?;
+        Ok(TsRange { key, query })
+    }
+}
+
+impl TryFrom<RespArray> for TsRevRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, query) = parse_single_range(value, "ts.revrange", true)?;
+        Ok(TsRevRange { key, query })
+    }
+}
+
+impl TryFrom<RespArray> for TsMRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (query, multi) = parse_mrange(value, "ts.mrange", false)?;
+        Ok(TsMRange { query, multi })
+    }
+}
+
+impl TryFrom<RespArray> for TsMRevRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (query, multi) = parse_mrange(value, "ts.mrevrange", true)?;
+        Ok(TsMRevRange { query, multi })
+    }
+}
+
+impl TryFrom<RespArray> for TsCreateRule {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["ts.createrule"], 5)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let source = extract_string(args.next())?;
+        let dest = extract_string(args.next())?;
+        if !extract_string(args.next())?.eq_ignore_ascii_case("aggregation") {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let aggregation = Aggregation::parse(&extract_string(args.next())?)
+            .ok_or_else(|| invalid("Unknown aggregation type"))?;
+        let bucket = parse_u64(args.next(), "bucket duration")?;
+        if bucket == 0 {
+            return Err(invalid("bucketDuration must be greater than zero"));
+        }
+        let align = match args.next() {
+            Some(align) => parse_u64(Some(align), "align timestamp")?,
+            None => 0,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(TsCreateRule {
+            source,
+            dest,
+            aggregation,
+            bucket,
+            align,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsDeleteRule {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ts.deleterule"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(TsDeleteRule {
+            source: extract_string(args.next())?,
+            dest: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsInfo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ts.info"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(TsInfo {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    fn run<T>(backend: &Backend, cmd: &[&str]) -> Result<RespFrame>
+    where
+        T: TryFrom<RespArray, Error = CommandError> + CommandExecutor,
+    {
+        Ok(T::try_from(args(cmd))?.execute(backend)?)
+    }
+
+    fn samples(samples: &[(i64, f64)]) -> RespFrame {
+        RespArray::new(
+            samples
+                .iter()
+                .map(|(ts, v)| {
+                    RespArray::new([RespFrame::Integer(*ts), RespFrame::Double(*v)]).into()
+                })
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+
+    #[test]
+    fn test_ts_range_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*9\r\n$8\r\nts.range\r\n$1\r\nt\r\n$1\r\n-\r\n$1\r\n+\r\n$12\r\nFILTER_BY_TS\r\n$1\r\n1\r\n$1\r\n2\r\n$5\r\nCOUNT\r\n$1\r\n5\r\n");
+        let cmd: TsRange = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!((cmd.query.from, cmd.query.to), (0, u64::MAX));
+        assert_eq!(cmd.query.filter_ts, Some(vec![1, 2]));
+        assert_eq!(cmd.query.count, Some(5));
+
+        let cmd: Result<TsRange, _> = args(&["ts.range", "t", "-", "+", "align", "0"]).try_into();
+        assert!(cmd.is_err());
+        let cmd: Result<TsMRange, _> =
+            args(&["ts.mrange", "-", "+", "filter", "area!=eu"]).try_into();
+        assert!(cmd.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_add_range_commands() -> Result<()> {
+        let backend = Backend::new();
+        run::<TsCreate>(&backend, &["ts.create", "t", "duplicate_policy", "sum"])?;
+        assert!(run::<TsCreate>(&backend, &["ts.create", "t"]).is_err());
+        for (ts, value) in [
+            ("10", "1"),
+            ("15", "3"),
+            ("22", "5"),
+            ("22", "1"),
+            ("41", "7"),
+        ] {
+            run::<TsAdd>(&backend, &["ts.add", "t", ts, value])?;
+        }
+        assert_eq!(
+            run::<TsRange>(
+                &backend,
+                &["ts.range", "t", "-", "+", "filter_by_value", "3", "6"]
+            )?,
+            samples(&[(15, 3.0), (22, 6.0)])
+        );
+        assert_eq!(
+            run::<TsRevRange>(
+                &backend,
+                &[
+                    "ts.revrange",
+                    "t",
+                    "0",
+                    "100",
+                    "aggregation",
+                    "max",
+                    "10",
+                    "count",
+                    "2"
+                ]
+            )?,
+            samples(&[(40, 7.0), (20, 6.0)])
+        );
+        assert_eq!(
+            run::<TsRange>(
+                &backend,
+                &[
+                    "ts.range",
+                    "t",
+                    "12",
+                    "+",
+                    "align",
+                    "start",
+                    "aggregation",
+                    "sum",
+                    "10",
+                    "empty"
+                ]
+            )?,
+            samples(&[(12, 3.0), (22, 6.0), (32, 7.0)])
+        );
+
+        assert_eq!(
+            run::<TsIncrBy>(&backend, &["ts.incrby", "t", "2", "timestamp", "50"])?,
+            RespFrame::Integer(50)
+        );
+        assert!(run::<TsDecrBy>(&backend, &["ts.decrby", "t", "1", "timestamp", "49"]).is_err());
+        assert_eq!(
+            run::<TsGet>(&backend, &["ts.get", "t"])?,
+            RespArray::new([RespFrame::Integer(50), RespFrame::Double(9.0)]).into()
+        );
+        assert_eq!(
+            run::<TsMAdd>(&backend, &["ts.madd", "t", "60", "1", "missing", "60", "1"])?,
+            RespArray::new([
+                RespFrame::Integer(60),
+                SimpleError::new("TSDB: the key does not exist").into()
+            ])
+            .into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_rule_and_mrange_commands() -> Result<()> {
+        let backend = Backend::new();
+        run::<TsCreate>(
+            &backend,
+            &["ts.create", "cpu:1", "labels", "area", "eu", "host", "1"],
+        )?;
+        run::<TsCreate>(
+            &backend,
+            &["ts.create", "cpu:2", "labels", "area", "us", "host", "2"],
+        )?;
+        run::<TsCreate>(
+            &backend,
+            &["ts.create", "cpu:1:avg", "labels", "area", "eu"],
+        )?;
+        run::<TsCreateRule>(
+            &backend,
+            &[
+                "ts.createrule",
+                "cpu:1",
+                "cpu:1:avg",
+                "aggregation",
+                "avg",
+                "10",
+            ],
+        )?;
+        for (ts, value) in [("1", "1"), ("2", "3"), ("11", "5"), ("21", "7")] {
+            run::<TsAdd>(&backend, &["ts.add", "cpu:1", ts, value])?;
+            run::<TsAdd>(&backend, &["ts.add", "cpu:2", ts, value])?;
+        }
+        assert_eq!(
+            run::<TsRange>(&backend, &["ts.range", "cpu:1:avg", "-", "+"])?,
+            samples(&[(0, 2.0), (10, 5.0)])
+        );
+
+        let reply = run::<TsMRange>(
+            &backend,
+            &[
+                "ts.mrange",
+                "0",
+                "5",
+                "selected_labels",
+                "host",
+                "filter",
+                "area=(eu,us)",
+                "host!=",
+            ],
+        )?;
+        let series = |key: &str, host: &str, samples: RespFrame| -> RespFrame {
+            RespArray::new([
+                BulkString::new(key).into(),
+                RespArray::new([RespArray::new([
+                    BulkString::new("host").into(),
+                    BulkString::new(host).into(),
+                ])
+                .into()])
+                .into(),
+                samples,
+            ])
+            .into()
+        };
+        assert_eq!(
+            reply,
+            RespArray::new([
+                series("cpu:1", "1", samples(&[(1, 1.0), (2, 3.0)])),
+                series("cpu:2", "2", samples(&[(1, 1.0), (2, 3.0)])),
+            ])
+            .into()
+        );
+
+        let reply = run::<TsInfo>(&backend, &["ts.info", "cpu:1:avg"])?;
+        let RespFrame::Map(info) = reply else {
+            panic!("expected a map");
+        };
+        assert_eq!(
+            info.get("sourceKey"),
+            Some(&BulkString::new("cpu:1").into())
+        );
+        run::<TsDeleteRule>(&backend, &["ts.deleterule", "cpu:1", "cpu:1:avg"])?;
+        assert!(run::<TsDeleteRule>(&backend, &["ts.deleterule", "cpu:1", "cpu:1:avg"]).is_err());
+        Ok(())
+    }
+}
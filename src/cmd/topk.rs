@@ -0,0 +1,273 @@
+use dashmap::mapref::entry::Entry;
+
+use crate::array::RespArray;
+use crate::backend::{
+    Backend, TopK, NOTIFY_MODULE, TOPK_DEFAULT_DECAY, TOPK_DEFAULT_DEPTH, TOPK_DEFAULT_WIDTH,
+    TOPK_MAX_BUCKETS, TOPK_MAX_K,
+};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_float, extract_int, extract_string, validate_command,
+    validate_command_at_least, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::map::RespMap;
+use crate::null::RespNull;
+
+#[derive(Debug)]
+pub struct TopKReserve {
+    key: String,
+    k: usize,
+    width: usize,
+    depth: usize,
+    decay: f64,
+}
+
+#[derive(Debug)]
+pub struct TopKAdd {
+    key: String,
+    items: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct TopKList {
+    key: String,
+    with_count: bool,
+}
+
+#[derive(Debug)]
+pub struct TopKInfo {
+    key: String,
+}
+
+impl CommandExecutor for TopKReserve {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        match backend.topk.entry(self.key.clone()) {
+            Entry::Occupied(_) => Err(CommandError::InvalidArgument(
+                "TopK: key already exists".to_string(),
+            )),
+            Entry::Vacant(entry) => {
+                entry.insert(TopK::new(self.k, self.width, self.depth, self.decay));
+                backend.notify_new(true, &self.key);
+                backend.notify(NOTIFY_MODULE, "topk.reserve", &self.key);
+                Ok(RESP_OK.clone())
+            }
+        }
+    }
+}
+
+impl CommandExecutor for TopKAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let mut topk = backend.topk.get_mut(&self.key).ok_or_else(missing_key)?;
+        let expelled = self
+            .items
+            .iter()
+            .map(|item| match topk.incr_by(item, 1) {
+                Some(expelled) => BulkString::new(expelled).into(),
+                None => RespFrame::Null(RespNull),
+            })
+            .collect::<Vec<_>>();
+        drop(topk);
+        backend.notify(NOTIFY_MODULE, "topk.add", &self.key);
+        Ok(RespArray::new(expelled).into())
+    }
+}
+
+impl CommandExecutor for TopKList {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let topk = backend.topk.get(&self.key).ok_or_else(missing_key)?;
+        let mut reply = vec![];
+        for (item, count) in topk.list() {
+            reply.push(BulkString::new(item).into());
+            if self.with_count {
+                reply.push(RespFrame::Integer(count as i64));
+            }
+        }
+        Ok(RespArray::new(reply).into())
+    }
+}
+
+impl CommandExecutor for TopKInfo {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let topk = backend.topk.get(&self.key).ok_or_else(missing_key)?;
+        let mut info = RespMap::new();
+        info.insert("k".to_string(), RespFrame::Integer(topk.k() as i64));
+        info.insert("width".to_string(), RespFrame::Integer(topk.width() as i64));
+        info.insert("depth".to_string(), RespFrame::Integer(topk.depth() as i64));
+        info.insert("decay".to_string(), RespFrame::Double(topk.decay()));
+        Ok(info.into())
+    }
+}
+
+fn missing_key() -> CommandError {
+    CommandError::InvalidArgument("TopK: key does not exist".to_string())
+}
+
+impl TryFrom<RespArray> for TopKReserve {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["topk.reserve"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let k = positive(extract_int(args.next())?, "k")?;
+        if k > TOPK_MAX_K {
+            return Err(CommandError::InvalidArgument(
+                "TopK: k is too large".to_string(),
+            ));
+        }
+        let (width, depth, decay) = match args.len() {
+            0 => (TOPK_DEFAULT_WIDTH, TOPK_DEFAULT_DEPTH, TOPK_DEFAULT_DECAY),
+            3 => {
+                let width = positive(extract_int(args.next())?, "width")?;
+                let depth = positive(extract_int(args.next())?, "depth")?;
+                let decay = extract_float(args.next())?;
+                if !(decay > 0.0 && decay <= 1.0) {
+                    return Err(CommandError::InvalidArgument(
+                        "TopK: decay must be between 0 and 1".to_string(),
+                    ));
+                }
+                (width, depth, decay)
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "wrong number of arguments for 'topk.reserve' command".to_string(),
+                ))
+            }
+        };
+        if width
+            .checked_mul(depth)
+            .is_none_or(|buckets| buckets > TOPK_MAX_BUCKETS)
+        {
+            return Err(CommandError::InvalidArgument(
+                "TopK: width*depth is too large".to_string(),
+            ));
+        }
+        Ok(TopKReserve {
+            key,
+            k,
+            width,
+            depth,
+            decay,
+        })
+    }
+}
+
+fn positive(value: i64, name: &str) -> Result<usize, CommandError> {
+    usize::try_from(value)
+        .ok()
+        .filter(|value| *value > 0)
+        .ok_or_else(|| CommandError::InvalidArgument(format!("TopK: invalid {}", name)))
+}
+
+impl TryFrom<RespArray> for TopKAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["topk.add"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let items = args
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(TopKAdd { key, items })
+    }
+}
+
+impl TryFrom<RespArray> for TopKList {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["topk.list"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let with_count = match args.next() {
+            None => false,
+            Some(arg) if args.len() == 0 => {
+                if !extract_string(Some(arg))?.eq_ignore_ascii_case("withcount") {
+                    return Err(CommandError::InvalidArgument("syntax error".to_string()));
+                }
+                true
+            }
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(TopKList { key, with_count })
+    }
+}
+
+impl TryFrom<RespArray> for TopKInfo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["topk.info"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(TopKInfo {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    #[test]
+    fn test_topk_reserve_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$12\r\ntopk.reserve\r\n$1\r\nt\r\n$1\r\n3\r\n$2\r\n50\r\n$1\r\n4\r\n$3\r\n0.8\r\n",
+        );
+        let cmd: TopKReserve = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!((cmd.k, cmd.width, cmd.depth, cmd.decay), (3, 50, 4, 0.8));
+
+        let cmd: TopKReserve = args(&["topk.reserve", "t", "3"]).try_into()?;
+        assert_eq!(cmd.width, TOPK_DEFAULT_WIDTH);
+        let cmd: Result<TopKReserve, _> = args(&["topk.reserve", "t", "3", "50"]).try_into();
+        assert!(cmd.is_err());
+
+        let too_large = [
+            args(&["topk.reserve", "t", "9223372036854775807"]),
+            args(&["topk.reserve", "t", "3", "4294967296", "4294967296", "0.9"]),
+            args(&["topk.reserve", "t", "3", "1000000", "1000", "0.9"]),
+        ];
+        for cmd in too_large {
+            let err = TopKReserve::try_from(cmd).unwrap_err();
+            assert!(err.to_string().contains("too large"), "{}", err);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_topk_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: TopKAdd = args(&["topk.add", "t", "a"]).try_into()?;
+        assert!(cmd.execute(&backend).is_err());
+
+        let cmd: TopKReserve = args(&["topk.reserve", "t", "1", "50", "4", "0.9"]).try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RESP_OK.clone());
+        let cmd: TopKAdd = args(&["topk.add", "t", "a", "a", "b", "b", "b"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([
+                RespFrame::Null(RespNull),
+                RespFrame::Null(RespNull),
+                RespFrame::Null(RespNull),
+                RespFrame::Null(RespNull),
+                BulkString::new("a").into(),
+            ])
+            .into()
+        );
+        let cmd: TopKList = args(&["topk.list", "t", "withcount"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([BulkString::new("b").into(), RespFrame::Integer(3)]).into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+use crate::array::{RespArray, RespNullArray};
+use crate::backend::{Backend, Watcher};
+use crate::cmd::{
+    connection_only, extract_args, extract_strings, validate_command, validate_command_at_least,
+    Command, CommandError, CommandExecutor, CommandFlag, RESP_OK,
+};
+use crate::frame::RespFrame;
+use crate::simple_error::SimpleError;
+use crate::simple_string::SimpleString;
+
+#[derive(Debug)]
+pub struct Multi;
+
+#[derive(Debug)]
+pub struct Exec;
+
+#[derive(Debug)]
+pub struct Discard;
+
+#[derive(Debug)]
+pub struct Watch {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Unwatch;
+
+/// The commands a connection queued since MULTI.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    commands: Vec<Command>,
+    /// a command failed to queue, so EXEC discards the whole transaction
+    aborted: bool,
+}
+
+impl CommandExecutor for Multi {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("MULTI"))
+    }
+}
+
+impl CommandExecutor for Exec {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("EXEC"))
+    }
+}
+
+impl CommandExecutor for Discard {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("DISCARD"))
+    }
+}
+
+impl CommandExecutor for Watch {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("WATCH"))
+    }
+}
+
+impl CommandExecutor for Unwatch {
+    fn execute(self, _backend: &Backend) -> Result<RespFrame, CommandError> {
+        Err(connection_only("UNWATCH"))
+    }
+}
+
+impl Watch {
+    pub fn apply(self, watcher: &mut Watcher) {
+        for key in &self.keys {
+            watcher.watch(key);
+        }
+    }
+}
+
+impl Transaction {
+    /// Queues a command for EXEC. Commands that don't parse or can't run inside a transaction
+    /// fail the transaction, a nested MULTI is only refused.
+    pub fn queue(&mut self, cmd: Result<Command, CommandError>) -> Result<RespFrame, CommandError> {
+        let cmd = cmd.inspect_err(|_| self.aborted = true)?;
+        match cmd {
+            Command::Multi(_) => Err(CommandError::InvalidArgument(
+                "MULTI calls can not be nested".to_string(),
+            )),
+            Command::Watch(_) => Err(CommandError::InvalidArgument(
+                "WATCH inside MULTI is not allowed".to_string(),
+            )),
+            cmd if cmd.has_flag(CommandFlag::NoMulti) => {
+                self.aborted = true;
+                Err(CommandError::InvalidArgument(
+                    "Command not allowed inside a transaction".to_string(),
+                ))
+            }
+            cmd => {
+                self.commands.push(cmd);
+                Ok(SimpleString::new("QUEUED").into())
+            }
+        }
+    }
+
+    /// Runs the queued commands while no other client can execute any, replying with an array
+    /// of their replies, errors included. Blocking commands don't wait, like they would in
+    /// redis. A watched key that was modified makes it a null array instead, either way the
+    /// keys are unwatched.
+    pub async fn exec(
+        self,
+        backend: &Backend,
+        watcher: &mut Watcher,
+    ) -> Result<RespFrame, CommandError> {
+        if self.aborted {
+            watcher.unwatch();
+            return Ok(SimpleError::new(
+                "EXECABORT Transaction discarded because of previous errors.",
+            )
+            .into());
+        }
+        let _exclusive = backend
+            .exec_exclusive()
+            .await
+            .map_err(CommandError::InvalidArgument)?;
+        // writers flag watchers while holding the lock shared, so none is missed here
+        let dirty = watcher.is_dirty();
+        watcher.unwatch();
+        if dirty {
+            return Ok(RespNullArray.into());
+        }
+        let replies = self
+            .commands
+            .into_iter()
+            .map(|cmd| match cmd {
+                // EXEC unwatches every key anyway
+                Command::Unwatch(_) => RESP_OK.clone(),
+                cmd => cmd
+                    .execute(backend)
+                    .unwrap_or_else(|e| SimpleError::new(e.to_string()).into()),
+            })
+            .collect::<Vec<_>>();
+        Ok(RespArray::new(replies).into())
+    }
+}
+
+impl TryFrom<RespArray> for Multi {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["multi"], 0)?;
+        Ok(Multi)
+    }
+}
+
+impl TryFrom<RespArray> for Exec {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["exec"], 0)?;
+        Ok(Exec)
+    }
+}
+
+impl TryFrom<RespArray> for Discard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["discard"], 0)?;
+        Ok(Discard)
+    }
+}
+
+impl TryFrom<RespArray> for Watch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["watch"], 1)?;
+        let keys = extract_strings(extract_args(value, 1)?.into_iter())?;
+        Ok(Watch { keys })
+    }
+}
+
+impl TryFrom<RespArray> for Unwatch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["unwatch"], 0)?;
+        Ok(Unwatch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::bulk_string::BulkString;
+    use crate::cmd::args;
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    #[test]
+    fn test_transaction_try_from() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$5\r\nmulti\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let _: Multi = frame.try_into()?;
+
+        assert!(Exec::try_from(RespArray::new([BulkString::new("exec").into()])).is_ok());
+        assert!(Command::try_from(args(&["discard", "x"])).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_exec() -> Result<()> {
+        let backend = Backend::new();
+        let mut transaction = Transaction::default();
+        let queued: RespFrame = SimpleString::new("QUEUED").into();
+        assert_eq!(
+            transaction.queue(Command::try_from(args(&["set", "k", "v"])))?,
+            queued
+        );
+        assert_eq!(
+            transaction.queue(Command::try_from(args(&["lset", "missing", "0", "a"])))?,
+            queued
+        );
+        assert_eq!(
+            transaction.queue(Command::try_from(args(&["get", "k"])))?,
+            queued
+        );
+        assert_eq!(
+            transaction.queue(Command::try_from(args(&["unwatch"])))?,
+            queued
+        );
+        // a nested MULTI is refused without failing the transaction
+        assert!(transaction
+            .queue(Command::try_from(args(&["multi"])))
+            .is_err());
+        assert!(backend.get("k").is_none());
+
+        let mut watcher = Watcher::new(&backend);
+        let RespFrame::Array(replies) = transaction.exec(&backend, &mut watcher).await? else {
+            panic!("EXEC must reply with an array");
+        };
+        assert_eq!(replies.len(), 4);
+        assert_eq!(replies[0], RESP_OK.clone());
+        // runtime errors are replied per command, the others still run
+        assert!(matches!(&replies[1], RespFrame::Error(_)));
+        assert_eq!(replies[2], BulkString::new("v").into());
+        assert_eq!(replies[3], RESP_OK.clone());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_abort() -> Result<()> {
+        let backend = Backend::new();
+        let mut transaction = Transaction::default();
+        transaction.queue(Command::try_from(args(&["set", "k", "v"])))?;
+        assert!(transaction
+            .queue(Command::try_from(args(&["get"])))
+            .is_err());
+        assert!(transaction
+            .queue(Command::try_from(args(&["subscribe", "c"])))
+            .is_err());
+
+        let reply = transaction
+            .exec(&backend, &mut Watcher::new(&backend))
+            .await?;
+        assert_eq!(
+            reply,
+            SimpleError::new("EXECABORT Transaction discarded because of previous errors.").into()
+        );
+        assert!(backend.get("k").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_watch() -> Result<()> {
+        let backend = Backend::new();
+        let mut watcher = Watcher::new(&backend);
+        let watch = |watcher: &mut Watcher, key: &str| -> Result<()> {
+            match Command::try_from(args(&["watch", key]))? {
+                Command::Watch(watch) => watch.apply(watcher),
+                cmd => panic!("unexpected command {:?}", cmd),
+            }
+            Ok(())
+        };
+        let transaction = || -> Result<Transaction> {
+            let mut transaction = Transaction::default();
+            transaction.queue(Command::try_from(args(&["set", "k", "mine"])))?;
+            Ok(transaction)
+        };
+
+        // an untouched key lets EXEC through
+        watch(&mut watcher, "k")?;
+        Command::try_from(args(&["hset", "h", "f", "v"]))?.execute(&backend)?;
+        assert!(matches!(
+            transaction()?.exec(&backend, &mut watcher).await?,
+            RespFrame::Array(_)
+        ));
+
+        // EXEC unwatched the key, so this write doesn't count
+        Command::try_from(args(&["set", "k", "theirs"]))?.execute(&backend)?;
+        watch(&mut watcher, "h")?;
+        Command::try_from(args(&["hdel", "h", "f"]))?.execute(&backend)?;
+        assert_eq!(
+            transaction()?.exec(&backend, &mut watcher).await?,
+            RespNullArray.into()
+        );
+        assert_eq!(backend.get("k"), Some(BulkString::new("theirs").into()));
+
+        watch(&mut watcher, "missing")?;
+        Command::try_from(args(&["flushall"]))?.execute(&backend)?;
+        assert_eq!(
+            transaction()?.exec(&backend, &mut watcher).await?,
+            RespNullArray.into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,664 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use serde_json::Value;
+
+use crate::array::RespArray;
+use crate::backend::{
+    Backend, Metric, VectorFilter, VectorSet, NOTIFY_GENERIC, NOTIFY_MODULE,
+    VSET_DEFAULT_EF_CONSTRUCTION, VSET_DEFAULT_M,
+};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_bytes, extract_count, extract_float, extract_string, validate_command,
+    validate_command_at_least, CommandError, CommandExecutor,
+};
+use crate::frame::RespFrame;
+use crate::map::RespMap;
+use crate::null::RespNull;
+
+/// How many candidates VSIM considers, unless set with EF.
+const VSIM_DEFAULT_EF: usize = 100;
+
+#[derive(Debug)]
+pub struct VAdd {
+    key: String,
+    vector: Vec<f32>,
+    element: String,
+    attributes: Option<Value>,
+    metric: Option<Metric>,
+    m: Option<usize>,
+    ef: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct VRem {
+    key: String,
+    element: String,
+}
+
+#[derive(Debug)]
+pub struct VSim {
+    key: String,
+    query: VectorQuery,
+    with_scores: bool,
+    with_attributes: bool,
+    count: usize,
+    ef: usize,
+    filter: Option<VectorFilter>,
+    filter_ef: Option<usize>,
+    /// TRUTH, a full scan instead of the graph search
+    exact: bool,
+}
+
+#[derive(Debug)]
+pub struct VCard {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct VDim {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct VEmb {
+    key: String,
+    element: String,
+}
+
+#[derive(Debug)]
+pub struct VInfo {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct VSetAttr {
+    key: String,
+    element: String,
+    /// `None` removes the attributes
+    attributes: Option<Value>,
+}
+
+#[derive(Debug)]
+pub struct VGetAttr {
+    key: String,
+    element: String,
+}
+
+#[derive(Debug)]
+enum VectorQuery {
+    /// ELE, the vector of an element of the set
+    Element(String),
+    Vector(Vec<f32>),
+}
+
+impl CommandExecutor for VAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let created = !backend.vset.contains_key(&self.key);
+        let added = {
+            let mut set = backend.vset.entry(self.key.clone()).or_insert_with(|| {
+                VectorSet::new(
+                    self.vector.len(),
+                    self.metric.unwrap_or(Metric::Cosine),
+                    self.m.unwrap_or(VSET_DEFAULT_M),
+                    self.ef.unwrap_or(VSET_DEFAULT_EF_CONSTRUCTION),
+                )
+            });
+            if let Some(metric) = self.metric {
+                if metric != set.metric() {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "the set uses the {} metric",
+                        set.metric().name()
+                    )));
+                }
+            }
+            set.add(&self.element, self.vector, self.attributes)
+                .map_err(CommandError::InvalidArgument)?
+        };
+        backend.notify_new(created, &self.key);
+        backend.notify(NOTIFY_MODULE, "vadd", &self.key);
+        Ok(RespFrame::Integer(added as i64))
+    }
+}
+
+impl CommandExecutor for VRem {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let removed = backend
+            .vset
+            .get_mut(&self.key)
+            .is_some_and(|mut set| set.remove(&self.element));
+        if removed {
+            backend.notify(NOTIFY_MODULE, "vrem", &self.key);
+        }
+        if backend
+            .vset
+            .remove_if(&self.key, |_, set| set.is_empty())
+            .is_some()
+        {
+            backend.notify(NOTIFY_GENERIC, "del", &self.key);
+        }
+        Ok(RespFrame::Integer(removed as i64))
+    }
+}
+
+impl CommandExecutor for VSim {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let Some(set) = backend.vset.get(&self.key) else {
+            return Ok(RespArray::new([]).into());
+        };
+        let query = match self.query {
+            VectorQuery::Element(element) => set
+                .vector(&element)
+                .ok_or_else(|| {
+                    CommandError::InvalidArgument("element not found in set".to_string())
+                })?
+                .to_vec(),
+            VectorQuery::Vector(vector) => vector,
+        };
+        if query.len() != set.dim() {
+            return Err(CommandError::InvalidArgument(format!(
+                "Vector dimension mismatch - got {} but set has {}",
+                query.len(),
+                set.dim()
+            )));
+        }
+        let found = set.search(
+            &query,
+            self.count,
+            self.ef,
+            self.filter.as_ref(),
+            self.filter_ef.unwrap_or(self.count * 100),
+            self.exact,
+        );
+        let mut reply = vec![];
+        for (element, score) in found {
+            if self.with_attributes {
+                let attributes = attributes_reply(set.attributes(&element));
+                reply.push(BulkString::new(element).into());
+                if self.with_scores {
+                    reply.push(RespFrame::Double(score));
+                }
+                reply.push(attributes);
+            } else {
+                reply.push(BulkString::new(element).into());
+                if self.with_scores {
+                    reply.push(RespFrame::Double(score));
+                }
+            }
+        }
+        Ok(RespArray::new(reply).into())
+    }
+}
+
+impl CommandExecutor for VCard {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let len = backend.vset.get(&self.key).map_or(0, |set| set.len());
+        Ok(RespFrame::Integer(len as i64))
+    }
+}
+
+impl CommandExecutor for VDim {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let set = backend
+            .vset
+            .get(&self.key)
+            .ok_or_else(|| CommandError::InvalidArgument("key does not exist".to_string()))?;
+        Ok(RespFrame::Integer(set.dim() as i64))
+    }
+}
+
+impl CommandExecutor for VEmb {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let vector = backend
+            .vset
+            .get(&self.key)
+            .and_then(|set| set.vector(&self.element).map(<[f32]>::to_vec));
+        Ok(match vector {
+            Some(vector) => RespArray::new(
+                vector
+                    .into_iter()
+                    .map(|value| RespFrame::Double(value as f64))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            None => RespFrame::Null(RespNull),
+        })
+    }
+}
+
+impl CommandExecutor for VInfo {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let Some(set) = backend.vset.get(&self.key) else {
+            return Ok(RespFrame::Null(RespNull));
+        };
+        let mut info = RespMap::new();
+        info.insert("quant-type".to_string(), BulkString::new("f32").into());
+        info.insert(
+            "distance-metric".to_string(),
+            BulkString::new(set.metric().name()).into(),
+        );
+        info.insert(
+            "vector-dim".to_string(),
+            RespFrame::Integer(set.dim() as i64),
+        );
+        info.insert("size".to_string(), RespFrame::Integer(set.len() as i64));
+        info.insert(
+            "max-level".to_string(),
+            RespFrame::Integer(set.max_level() as i64),
+        );
+        info.insert("hnsw-m".to_string(), RespFrame::Integer(set.m() as i64));
+        Ok(info.into())
+    }
+}
+
+impl CommandExecutor for VSetAttr {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let updated = backend
+            .vset
+            .get_mut(&self.key)
+            .is_some_and(|mut set| set.set_attributes(&self.element, self.attributes));
+        if updated {
+            backend.notify(NOTIFY_MODULE, "vsetattr", &self.key);
+        }
+        Ok(RespFrame::Integer(updated as i64))
+    }
+}
+
+impl CommandExecutor for VGetAttr {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let Some(set) = backend.vset.get(&self.key) else {
+            return Ok(RespFrame::Null(RespNull));
+        };
+        Ok(attributes_reply(set.attributes(&self.element)))
+    }
+}
+
+fn attributes_reply(attributes: Option<&Value>) -> RespFrame {
+    attributes.map_or(RespFrame::Null(RespNull), |attributes| {
+        BulkString::new(attributes.to_string()).into()
+    })
+}
+
+fn parse_attributes(arg: Option<RespFrame>) -> Result<Option<Value>, CommandError> {
+    let attributes = extract_string(arg)?;
+    if attributes.is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(&attributes)
+        .map(Some)
+        .map_err(|_| CommandError::InvalidArgument("attributes must be valid JSON".to_string()))
+}
+
+fn parse_positive(arg: Option<RespFrame>, name: &str) -> Result<usize, CommandError> {
+    let value = extract_count(arg)?;
+    if value == 0 {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} must be positive",
+            name
+        )));
+    }
+    Ok(value)
+}
+
+/// A vector given as `FP32 <little endian f32 blob>` or `VALUES <n> <v1> .. <vn>`, when `kind`
+/// is one of those keywords.
+fn parse_vector(
+    kind: &str,
+    args: &mut Peekable<IntoIter<RespFrame>>,
+) -> Result<Option<Vec<f32>>, CommandError> {
+    let vector: Vec<f32> = match kind {
+        "FP32" => {
+            let blob = extract_bytes(args.next())?;
+            if blob.is_empty() || blob.len() % 4 != 0 {
+                return Err(CommandError::InvalidArgument(
+                    "FP32 blob length must be a positive multiple of 4".to_string(),
+                ));
+            }
+            blob.chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect()
+        }
+        "VALUES" => {
+            let dim = parse_positive(args.next(), "vector dimension")?;
+            (0..dim)
+                .map(|_| extract_float(args.next()).map(|value| value as f32))
+                .collect::<Result<_, _>>()?
+        }
+        _ => return Ok(None),
+    };
+    if vector.iter().any(|value| !value.is_finite()) {
+        return Err(CommandError::InvalidArgument(
+            "vector values must be finite".to_string(),
+        ));
+    }
+    Ok(Some(vector))
+}
+
+fn parse_key_element(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(String, String), CommandError> {
+    validate_command(&value, &[name], 2)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    Ok((extract_string(args.next())?, extract_string(args.next())?))
+}
+
+fn parse_key(value: RespArray, name: &'static str) -> Result<String, CommandError> {
+    validate_command(&value, &[name], 1)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    extract_string(args.next())
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+impl TryFrom<RespArray> for VAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["vadd"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let kind = extract_string(args.next())?.to_ascii_uppercase();
+        if kind == "REDUCE" {
+            return Err(CommandError::InvalidArgument(
+                "REDUCE is not supported".to_string(),
+            ));
+        }
+        let vector = parse_vector(&kind, &mut args)?.ok_or_else(syntax_error)?;
+        let mut add = VAdd {
+            key,
+            vector,
+            element: extract_string(args.next())?,
+            attributes: None,
+            metric: None,
+            m: None,
+            ef: None,
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                "CAS" | "NOQUANT" => {}
+                "Q8" | "BIN" => {
+                    return Err(CommandError::InvalidArgument(
+                        "only NOQUANT vectors are supported".to_string(),
+                    ))
+                }
+                "EF" => add.ef = Some(parse_positive(args.next(), "EF")?),
+                "M" => add.m = Some(parse_positive(args.next(), "M")?),
+                "SETATTR" => add.attributes = parse_attributes(args.next())?,
+                "METRIC" => {
+                    let metric = extract_string(args.next())?;
+                    add.metric = Some(Metric::parse(&metric).ok_or_else(|| {
+                        CommandError::InvalidArgument(format!("unknown metric `{}`", metric))
+                    })?);
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(add)
+    }
+}
+
+impl TryFrom<RespArray> for VRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, element) = parse_key_element(value, "vrem")?;
+        Ok(VRem { key, element })
+    }
+}
+
+impl TryFrom<RespArray> for VSim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["vsim"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let kind = extract_string(args.next())?.to_ascii_uppercase();
+        let query = match kind.as_str() {
+            "ELE" => VectorQuery::Element(extract_string(args.next())?),
+            _ => VectorQuery::Vector(parse_vector(&kind, &mut args)?.ok_or_else(syntax_error)?),
+        };
+        let mut sim = VSim {
+            key,
+            query,
+            with_scores: false,
+            with_attributes: false,
+            count: 10,
+            ef: VSIM_DEFAULT_EF,
+            filter: None,
+            filter_ef: None,
+            exact: false,
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_uppercase().as_str() {
+                "WITHSCORES" => sim.with_scores = true,
+                "WITHATTRIBS" => sim.with_attributes = true,
+                "COUNT" => sim.count = parse_positive(args.next(), "COUNT")?,
+                "EF" => sim.ef = parse_positive(args.next(), "EF")?,
+                "FILTER" => {
+                    let filter = VectorFilter::parse(&extract_string(args.next())?)
+                        .map_err(CommandError::InvalidArgument)?;
+                    sim.filter = Some(filter);
+                }
+                "FILTER-EF" => sim.filter_ef = Some(parse_positive(args.next(), "FILTER-EF")?),
+                "TRUTH" => sim.exact = true,
+                "NOTHREAD" => {}
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(sim)
+    }
+}
+
+impl TryFrom<RespArray> for VCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(VCard {
+            key: parse_key(value, "vcard")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for VDim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(VDim {
+            key: parse_key(value, "vdim")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for VEmb {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, element) = parse_key_element(value, "vemb")?;
+        Ok(VEmb { key, element })
+    }
+}
+
+impl TryFrom<RespArray> for VInfo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(VInfo {
+            key: parse_key(value, "vinfo")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for VSetAttr {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["vsetattr"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(VSetAttr {
+            key: extract_string(args.next())?,
+            element: extract_string(args.next())?,
+            attributes: parse_attributes(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for VGetAttr {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, element) = parse_key_element(value, "vgetattr")?;
+        Ok(VGetAttr { key, element })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::{args, bulk};
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    fn run<T>(backend: &Backend, cmd: &[&str]) -> Result<RespFrame, CommandError>
+    where
+        T: TryFrom<RespArray, Error = CommandError> + CommandExecutor,
+    {
+        T::try_from(args(cmd))?.execute(backend)
+    }
+
+    #[test]
+    fn test_vadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*6\r\n$4\r\nvadd\r\n$1\r\nv\r\n$4\r\nFP32\r\n$8\r\n\x00\x00\x80\x3f\x00\x00\x00\xc0\r\n$1\r\na\r\n$7\r\nNOQUANT\r\n");
+        let cmd: VAdd = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.vector, [1.0, -2.0]);
+        assert_eq!(cmd.element, "a");
+
+        let cmd: VAdd = args(&[
+            "vadd",
+            "v",
+            "values",
+            "2",
+            "1",
+            "0.5",
+            "b",
+            "setattr",
+            r#"{"n": 1}"#,
+            "metric",
+            "l2",
+        ])
+        .try_into()?;
+        assert_eq!(cmd.vector, [1.0, 0.5]);
+        assert_eq!(cmd.attributes, Some(serde_json::json!({"n": 1})));
+        assert_eq!(cmd.metric, Some(Metric::L2));
+
+        let cmd: Result<VAdd, _> = args(&["vadd", "v", "values", "2", "1", "b"]).try_into();
+        assert!(cmd.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_vector_set_commands() -> Result<()> {
+        let backend = Backend::new();
+        for (element, x, y, attributes) in [
+            ("a", "1", "0", r#"{"kind": "x", "n": 1}"#),
+            ("b", "0.9", "0.1", r#"{"kind": "y", "n": 2}"#),
+            ("c", "0", "1", r#"{"kind": "x", "n": 3}"#),
+        ] {
+            assert_eq!(
+                run::<VAdd>(
+                    &backend,
+                    &["vadd", "v", "values", "2", x, y, element, "setattr", attributes]
+                )?,
+                RespFrame::Integer(1)
+            );
+        }
+        assert_eq!(
+            run::<VAdd>(&backend, &["vadd", "v", "values", "2", "0", "2", "c"])?,
+            RespFrame::Integer(0)
+        );
+        assert!(run::<VAdd>(&backend, &["vadd", "v", "values", "1", "1", "d"]).is_err());
+        assert!(run::<VAdd>(
+            &backend,
+            &["vadd", "v", "values", "2", "1", "1", "d", "metric", "ip"]
+        )
+        .is_err());
+
+        assert_eq!(
+            run::<VSim>(&backend, &["vsim", "v", "ele", "a", "count", "2"])?,
+            RespArray::new([bulk("a"), bulk("b")]).into()
+        );
+        assert_eq!(
+            run::<VSim>(
+                &backend,
+                &[
+                    "vsim",
+                    "v",
+                    "values",
+                    "2",
+                    "1",
+                    "0",
+                    "filter",
+                    ".kind == \"x\"",
+                    "withscores",
+                    "withattribs",
+                    "truth",
+                ]
+            )?,
+            RespArray::new([
+                bulk("a"),
+                RespFrame::Double(1.0),
+                bulk(r#"{"kind":"x","n":1}"#),
+                bulk("c"),
+                RespFrame::Double(0.5),
+                bulk(r#"{"kind":"x","n":3}"#),
+            ])
+            .into()
+        );
+        assert!(run::<VSim>(&backend, &["vsim", "v", "ele", "missing"]).is_err());
+
+        assert_eq!(
+            run::<VEmb>(&backend, &["vemb", "v", "c"])?,
+            RespArray::new([RespFrame::Double(0.0), RespFrame::Double(2.0)]).into()
+        );
+        assert_eq!(
+            run::<VGetAttr>(&backend, &["vgetattr", "v", "c"])?,
+            bulk(r#"{"kind":"x","n":3}"#)
+        );
+        run::<VSetAttr>(&backend, &["vsetattr", "v", "c", ""])?;
+        assert_eq!(
+            run::<VGetAttr>(&backend, &["vgetattr", "v", "c"])?,
+            RespFrame::Null(RespNull)
+        );
+
+        let RespFrame::Map(info) = run::<VInfo>(&backend, &["vinfo", "v"])? else {
+            panic!("expected a map");
+        };
+        assert_eq!(info.get("size"), Some(&RespFrame::Integer(3)));
+        assert_eq!(info.get("distance-metric"), Some(&bulk("cosine")));
+        assert_eq!(
+            run::<VDim>(&backend, &["vdim", "v"])?,
+            RespFrame::Integer(2)
+        );
+
+        for element in ["a", "b", "c"] {
+            assert_eq!(
+                run::<VRem>(&backend, &["vrem", "v", element])?,
+                RespFrame::Integer(1)
+            );
+        }
+        assert_eq!(
+            run::<VCard>(&backend, &["vcard", "v"])?,
+            RespFrame::Integer(0)
+        );
+        assert!(!backend.vset.contains_key("v"));
+        Ok(())
+    }
+}
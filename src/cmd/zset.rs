@@ -0,0 +1,1858 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::array::{RespArray, RespNullArray};
+use crate::backend::{Backend, LexBound, RestoreFn, ScoreBound, ServeFn, SortedSet, NOTIFY_ZSET};
+use crate::bulk_string::BulkString;
+use crate::cmd::{
+    extract_args, extract_count, extract_int, extract_random_count, extract_string,
+    extract_strings, extract_timeout, normalize_range, notify_miss, validate_command,
+    validate_command_at_least, CommandError, CommandExecutor,
+};
+use crate::frame::RespFrame;
+use crate::null::RespNull;
+
+#[derive(Debug)]
+pub struct ZAdd {
+    key: String,
+    flags: ZAddFlags,
+    pairs: Vec<(f64, String)>,
+}
+
+#[derive(Debug, Default)]
+struct ZAddFlags {
+    nx: bool,
+    xx: bool,
+    gt: bool,
+    lt: bool,
+    ch: bool,
+    incr: bool,
+}
+
+#[derive(Debug)]
+pub struct ZIncrBy {
+    key: String,
+    increment: f64,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct ZRem {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ZScore {
+    key: String,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct ZMScore {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ZRank {
+    key: String,
+    member: String,
+    with_score: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRevRank {
+    key: String,
+    member: String,
+    with_score: bool,
+}
+
+#[derive(Debug)]
+pub struct ZCard {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct ZCount {
+    key: String,
+    min: ScoreBound,
+    max: ScoreBound,
+}
+
+#[derive(Debug)]
+pub struct ZLexCount {
+    key: String,
+    min: LexBound,
+    max: LexBound,
+}
+
+#[derive(Debug)]
+pub struct ZRange {
+    key: String,
+    query: RangeQuery,
+    with_scores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRangeStore {
+    dst: String,
+    src: String,
+    query: RangeQuery,
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByRank {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByScore {
+    key: String,
+    min: ScoreBound,
+    max: ScoreBound,
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByLex {
+    key: String,
+    min: LexBound,
+    max: LexBound,
+}
+
+#[derive(Debug)]
+pub struct ZPopMin {
+    key: String,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ZPopMax {
+    key: String,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ZMPop {
+    keys: Vec<String>,
+    max: bool,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct BZPopMin {
+    keys: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BZPopMax {
+    keys: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BZMPop {
+    keys: Vec<String>,
+    max: bool,
+    count: usize,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct ZRandMember {
+    key: String,
+    count: Option<i64>,
+    with_scores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZUnion {
+    op: ZSetOp,
+    with_scores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZInter {
+    op: ZSetOp,
+    with_scores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZDiff {
+    op: ZSetOp,
+    with_scores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZUnionStore {
+    dst: String,
+    op: ZSetOp,
+}
+
+#[derive(Debug)]
+pub struct ZInterStore {
+    dst: String,
+    op: ZSetOp,
+}
+
+#[derive(Debug)]
+pub struct ZDiffStore {
+    dst: String,
+    op: ZSetOp,
+}
+
+/// Which elements a ZRANGE family command selects.
+#[derive(Debug)]
+struct RangeQuery {
+    by: RangeBy,
+    rev: bool,
+    offset: usize,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+enum RangeBy {
+    Rank(i64, i64),
+    Score(ScoreBound, ScoreBound),
+    Lex(LexBound, LexBound),
+}
+
+/// The inputs of ZUNION, ZINTER and ZDIFF and their STORE forms.
+#[derive(Debug)]
+struct ZSetOp {
+    keys: Vec<String>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Aggregate {
+    Sum,
+    Min,
+    Max,
+}
+
+impl CommandExecutor for ZAdd {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let flags = self.flags;
+        let mut added = 0;
+        let mut changed = 0;
+        let mut incr_result = None;
+        let created = {
+            let mut zset = backend.zset.entry(self.key.clone()).or_default();
+            let created = zset.is_empty();
+            for (score, member) in self.pairs {
+                match zset.score(&member) {
+                    Some(old) => {
+                        if flags.nx {
+                            continue;
+                        }
+                        let score = if flags.incr { old + score } else { score };
+                        if score.is_nan() {
+                            return Err(CommandError::InvalidArgument(
+                                "resulting score is not a number (NaN)".to_string(),
+                            ));
+                        }
+                        if (flags.gt && score <= old) || (flags.lt && score >= old) {
+                            continue;
+                        }
+                        if score != old {
+                            zset.insert(member, score);
+                            changed += 1;
+                        }
+                        incr_result = Some(score);
+                    }
+                    None => {
+                        if flags.xx {
+                            continue;
+                        }
+                        zset.insert(member, score);
+                        added += 1;
+                        incr_result = Some(score);
+                    }
+                }
+            }
+            created
+        };
+        // only a key created above can be empty, so dropping it is not a deletion
+        backend.zset.remove_if(&self.key, |_, zset| zset.is_empty());
+        if added + changed > 0 {
+            backend.notify_new(created && added > 0, &self.key);
+            let event = if flags.incr { "zincr" } else { "zadd" };
+            backend.notify(NOTIFY_ZSET, event, &self.key);
+        }
+        if added > 0 {
+            backend.signal_key_ready(&self.key);
+        }
+
+        if flags.incr {
+            return Ok(incr_result
+                .map(RespFrame::Double)
+                .unwrap_or(RespFrame::Null(RespNull)));
+        }
+        let ret = if flags.ch { added + changed } else { added };
+        Ok(RespFrame::Integer(ret))
+    }
+}
+
+impl CommandExecutor for ZIncrBy {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let (score, created) = {
+            let mut zset = backend.zset.entry(self.key.clone()).or_default();
+            let created = zset.is_empty();
+            let score = zset.score(&self.member).unwrap_or(0.0) + self.increment;
+            if !score.is_nan() {
+                zset.insert(self.member, score);
+            }
+            (score, created)
+        };
+        if score.is_nan() {
+            backend.zset.remove_if(&self.key, |_, zset| zset.is_empty());
+            return Err(CommandError::InvalidArgument(
+                "resulting score is not a number (NaN)".to_string(),
+            ));
+        }
+        backend.notify_new(created, &self.key);
+        backend.notify(NOTIFY_ZSET, "zincr", &self.key);
+        backend.signal_key_ready(&self.key);
+        Ok(RespFrame::Double(score))
+    }
+}
+
+impl CommandExecutor for ZRem {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let removed = match backend.zset.get_mut(&self.key) {
+            Some(mut zset) => self.members.iter().filter(|m| zset.remove(m)).count(),
+            None => 0,
+        };
+        if removed > 0 {
+            backend.notify(NOTIFY_ZSET, "zrem", &self.key);
+        }
+        backend.zset_remove_if_empty(&self.key);
+        Ok(RespFrame::Integer(removed as i64))
+    }
+}
+
+impl CommandExecutor for ZScore {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let score = match backend.zset.get(&self.key) {
+            Some(zset) => zset.score(&self.member),
+            None => {
+                notify_miss(backend, &self.key, false);
+                None
+            }
+        };
+        Ok(score_reply(score))
+    }
+}
+
+impl CommandExecutor for ZMScore {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let zset = backend.zset.get(&self.key);
+        let ret = self
+            .members
+            .iter()
+            .map(|m| score_reply(zset.as_ref().and_then(|zset| zset.score(m))))
+            .collect::<Vec<_>>();
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for ZRank {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(rank_reply(
+            backend,
+            &self.key,
+            &self.member,
+            false,
+            self.with_score,
+        ))
+    }
+}
+
+impl CommandExecutor for ZRevRank {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(rank_reply(
+            backend,
+            &self.key,
+            &self.member,
+            true,
+            self.with_score,
+        ))
+    }
+}
+
+impl CommandExecutor for ZCard {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(RespFrame::Integer(backend.zset_len(&self.key) as i64))
+    }
+}
+
+impl CommandExecutor for ZCount {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let count = backend.zset.get(&self.key).map_or(0, |zset| {
+            let (start, end) = zset.score_range(self.min, self.max);
+            end - start
+        });
+        Ok(RespFrame::Integer(count as i64))
+    }
+}
+
+impl CommandExecutor for ZLexCount {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let count = backend.zset.get(&self.key).map_or(0, |zset| {
+            let (start, end) = zset.lex_range(&self.min, &self.max);
+            end - start
+        });
+        Ok(RespFrame::Integer(count as i64))
+    }
+}
+
+impl CommandExecutor for ZRange {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let elements = match backend.zset.get(&self.key) {
+            Some(zset) => self.query.collect(&zset),
+            None => {
+                notify_miss(backend, &self.key, false);
+                vec![]
+            }
+        };
+        Ok(elements_reply(elements, self.with_scores))
+    }
+}
+
+impl CommandExecutor for ZRangeStore {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let elements = match backend.zset.get(&self.src) {
+            Some(zset) => self.query.collect(&zset),
+            None => vec![],
+        };
+        let len = backend.zset_store(&self.dst, to_sorted_set(elements), "zrangestore");
+        Ok(RespFrame::Integer(len as i64))
+    }
+}
+
+impl CommandExecutor for ZRemRangeByRank {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let query = RangeQuery::new(RangeBy::Rank(self.start, self.stop));
+        Ok(remove_range(backend, &self.key, query, "zremrangebyrank"))
+    }
+}
+
+impl CommandExecutor for ZRemRangeByScore {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let query = RangeQuery::new(RangeBy::Score(self.min, self.max));
+        Ok(remove_range(backend, &self.key, query, "zremrangebyscore"))
+    }
+}
+
+impl CommandExecutor for ZRemRangeByLex {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let query = RangeQuery::new(RangeBy::Lex(self.min, self.max));
+        Ok(remove_range(backend, &self.key, query, "zremrangebylex"))
+    }
+}
+
+impl CommandExecutor for ZPopMin {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let popped = backend.zset_pop(&self.key, false, self.count.unwrap_or(1));
+        Ok(elements_reply(popped, true))
+    }
+}
+
+impl CommandExecutor for ZPopMax {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let popped = backend.zset_pop(&self.key, true, self.count.unwrap_or(1));
+        Ok(elements_reply(popped, true))
+    }
+}
+
+impl CommandExecutor for ZMPop {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(
+            serve_first(backend, &self.keys, mpop_serve(self.max, self.count))
+                .unwrap_or_else(|| RespNullArray.into()),
+        )
+    }
+}
+
+// Like the blocking list pops, these time out right away when they can't park.
+impl CommandExecutor for BZPopMin {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(serve_first(backend, &self.keys, pop_serve(false))
+            .unwrap_or_else(|| RespNullArray.into()))
+    }
+}
+
+impl CommandExecutor for BZPopMax {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(serve_first(backend, &self.keys, pop_serve(true))
+            .unwrap_or_else(|| RespNullArray.into()))
+    }
+}
+
+impl CommandExecutor for BZMPop {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        Ok(
+            serve_first(backend, &self.keys, mpop_serve(self.max, self.count))
+                .unwrap_or_else(|| RespNullArray.into()),
+        )
+    }
+}
+
+impl BZPopMin {
+    pub async fn block(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let reply = backend
+            .block_on(
+                self.keys,
+                self.timeout,
+                pop_serve(false),
+                Some(pop_restore()),
+            )
+            .await;
+        Ok(reply.unwrap_or_else(|| RespNullArray.into()))
+    }
+}
+
+impl BZPopMax {
+    pub async fn block(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let reply = backend
+            .block_on(
+                self.keys,
+                self.timeout,
+                pop_serve(true),
+                Some(pop_restore()),
+            )
+            .await;
+        Ok(reply.unwrap_or_else(|| RespNullArray.into()))
+    }
+}
+
+impl BZMPop {
+    pub async fn block(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let serve = mpop_serve(self.max, self.count);
+        let reply = backend
+            .block_on(self.keys, self.timeout, serve, Some(mpop_restore()))
+            .await;
+        Ok(reply.unwrap_or_else(|| RespNullArray.into()))
+    }
+}
+
+impl CommandExecutor for ZRandMember {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let zset = backend.zset.get(&self.key);
+        let mut rng = rand::thread_rng();
+        let Some(zset) = zset else {
+            return Ok(match self.count {
+                Some(_) => RespArray::new([]).into(),
+                None => RespFrame::Null(RespNull),
+            });
+        };
+        let pick = |rank: usize| {
+            zset.get_by_rank(rank)
+                .map(|(m, s)| (m.to_string(), s))
+                .expect("rank is within the sorted set")
+        };
+        let len = zset.len();
+        match self.count {
+            None => {
+                let (member, _) = pick(rng.gen_range(0..len));
+                Ok(BulkString::from(member).into())
+            }
+            // a positive count returns distinct members, a negative one allows repeats
+            Some(count) if count >= 0 => {
+                let ranks = rand::seq::index::sample(&mut rng, len, (count as usize).min(len));
+                let elements = ranks.into_iter().map(pick).collect();
+                Ok(elements_reply(elements, self.with_scores))
+            }
+            Some(count) => {
+                let elements = (0..count.unsigned_abs())
+                    .map(|_| pick(rng.gen_range(0..len)))
+                    .collect();
+                Ok(elements_reply(elements, self.with_scores))
+            }
+        }
+    }
+}
+
+impl CommandExecutor for ZUnion {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let zset = to_sorted_set(self.op.union(backend));
+        Ok(elements_reply(sorted_elements(&zset), self.with_scores))
+    }
+}
+
+impl CommandExecutor for ZInter {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let zset = to_sorted_set(self.op.inter(backend));
+        Ok(elements_reply(sorted_elements(&zset), self.with_scores))
+    }
+}
+
+impl CommandExecutor for ZDiff {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let zset = to_sorted_set(self.op.diff(backend));
+        Ok(elements_reply(sorted_elements(&zset), self.with_scores))
+    }
+}
+
+impl CommandExecutor for ZUnionStore {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let zset = to_sorted_set(self.op.union(backend));
+        Ok(RespFrame::Integer(
+            backend.zset_store(&self.dst, zset, "zunionstore") as i64,
+        ))
+    }
+}
+
+impl CommandExecutor for ZInterStore {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let zset = to_sorted_set(self.op.inter(backend));
+        Ok(RespFrame::Integer(
+            backend.zset_store(&self.dst, zset, "zinterstore") as i64,
+        ))
+    }
+}
+
+impl CommandExecutor for ZDiffStore {
+    fn execute(self, backend: &Backend) -> Result<RespFrame, CommandError> {
+        let zset = to_sorted_set(self.op.diff(backend));
+        Ok(RespFrame::Integer(
+            backend.zset_store(&self.dst, zset, "zdiffstore") as i64,
+        ))
+    }
+}
+
+impl Backend {
+    /// Pops up to `count` elements with the lowest (or with `max` the highest) scores.
+    pub fn zset_pop(&self, key: &str, max: bool, count: usize) -> Vec<(String, f64)> {
+        let popped = match self.zset.get_mut(key) {
+            Some(mut zset) => {
+                let len = zset.len();
+                let count = count.min(len);
+                let (start, end) = if max { (len - count, len) } else { (0, count) };
+                let popped: Vec<(String, f64)> = zset
+                    .range(start, end, max)
+                    .map(|(m, s)| (m.to_string(), s))
+                    .collect();
+                for (member, _) in &popped {
+                    zset.remove(member);
+                }
+                popped
+            }
+            None => vec![],
+        };
+        if !popped.is_empty() {
+            let event = if max { "zpopmax" } else { "zpopmin" };
+            self.notify(NOTIFY_ZSET, event, key);
+        }
+        self.zset_remove_if_empty(key);
+        popped
+    }
+
+    /// Adds back members a blocked client popped but never received.
+    pub(crate) fn zset_restore(&self, key: &str, members: Vec<(String, f64)>) {
+        if members.is_empty() {
+            return;
+        }
+        let created = {
+            let mut zset = self.zset.entry(key.to_string()).or_default();
+            let created = zset.is_empty();
+            for (member, score) in members {
+                zset.insert(member, score);
+            }
+            created
+        };
+        self.notify_new(created, key);
+        self.notify(NOTIFY_ZSET, "zadd", key);
+        self.signal_key_ready(key);
+    }
+}
+
+impl RangeQuery {
+    fn new(by: RangeBy) -> Self {
+        Self {
+            by,
+            rev: false,
+            offset: 0,
+            count: None,
+        }
+    }
+
+    fn collect(&self, zset: &SortedSet) -> Vec<(String, f64)> {
+        let len = zset.len();
+        let (start, end) = match &self.by {
+            RangeBy::Rank(start, stop) => match normalize_range(*start, *stop, len) {
+                // with REV the indexes count from the highest score
+                Some((start, stop)) if self.rev => (len - 1 - stop, len - start),
+                Some((start, stop)) => (start, stop + 1),
+                None => return vec![],
+            },
+            RangeBy::Score(min, max) => zset.score_range(*min, *max),
+            RangeBy::Lex(min, max) => zset.lex_range(min, max),
+        };
+        zset.range(start, end, self.rev)
+            .skip(self.offset)
+            .take(self.count.unwrap_or(usize::MAX))
+            .map(|(m, s)| (m.to_string(), s))
+            .collect()
+    }
+}
+
+impl ZSetOp {
+    /// Loads the weighted members of every input key, plain sets count as a score of 1.
+    fn inputs(&self, backend: &Backend) -> Vec<Option<HashMap<String, f64>>> {
+        self.keys
+            .iter()
+            .zip(&self.weights)
+            .map(|(key, weight)| {
+                let weighted = |score: f64| {
+                    let score = score * weight;
+                    // inf * 0 is NaN, redis counts it as 0
+                    if score.is_nan() {
+                        0.0
+                    } else {
+                        score
+                    }
+                };
+                if let Some(zset) = backend.zset.get(key) {
+                    return Some(
+                        zset.iter()
+                            .map(|(m, s)| (m.to_string(), weighted(s)))
+                            .collect(),
+                    );
+                }
+                backend
+                    .set
+                    .get(key)
+                    .map(|set| set.iter().map(|m| (m.clone(), weighted(1.0))).collect())
+            })
+            .collect()
+    }
+
+    fn union(&self, backend: &Backend) -> HashMap<String, f64> {
+        let mut ret: HashMap<String, f64> = HashMap::new();
+        for input in self.inputs(backend).into_iter().flatten() {
+            for (member, score) in input {
+                ret.entry(member)
+                    .and_modify(|acc| *acc = self.aggregate.apply(*acc, score))
+                    .or_insert(score);
+            }
+        }
+        ret
+    }
+
+    fn inter(&self, backend: &Backend) -> HashMap<String, f64> {
+        let mut inputs = self.inputs(backend).into_iter();
+        let Some(Some(mut ret)) = inputs.next() else {
+            return HashMap::new();
+        };
+        for input in inputs {
+            let Some(input) = input else {
+                return HashMap::new();
+            };
+            ret.retain(|member, acc| match input.get(member) {
+                Some(score) => {
+                    *acc = self.aggregate.apply(*acc, *score);
+                    true
+                }
+                None => false,
+            });
+        }
+        ret
+    }
+
+    fn diff(&self, backend: &Backend) -> HashMap<String, f64> {
+        let mut inputs = self.inputs(backend).into_iter();
+        let Some(Some(mut ret)) = inputs.next() else {
+            return HashMap::new();
+        };
+        for input in inputs.flatten() {
+            ret.retain(|member, _| !input.contains_key(member));
+        }
+        ret
+    }
+}
+
+impl Aggregate {
+    fn apply(&self, acc: f64, score: f64) -> f64 {
+        match self {
+            Aggregate::Sum => {
+                let sum = acc + score;
+                // inf + -inf is NaN, redis counts it as 0
+                if sum.is_nan() {
+                    0.0
+                } else {
+                    sum
+                }
+            }
+            Aggregate::Min => acc.min(score),
+            Aggregate::Max => acc.max(score),
+        }
+    }
+}
+
+fn serve_first(backend: &Backend, keys: &[String], serve: ServeFn) -> Option<RespFrame> {
+    keys.iter().find_map(|key| serve(backend, key))
+}
+
+/// Replies with `[key, member, score]` for BZPOPMIN and BZPOPMAX.
+fn pop_serve(max: bool) -> ServeFn {
+    Box::new(move |backend, key| {
+        let (member, score) = backend.zset_pop(key, max, 1).pop()?;
+        Some(
+            RespArray::new([
+                BulkString::from(key).into(),
+                BulkString::from(member).into(),
+                RespFrame::Double(score),
+            ])
+            .into(),
+        )
+    })
+}
+
+/// Replies with `[key, [[member, score], ...]]` for ZMPOP and BZMPOP.
+fn mpop_serve(max: bool, count: usize) -> ServeFn {
+    Box::new(move |backend, key| {
+        let popped = backend.zset_pop(key, max, count);
+        if popped.is_empty() {
+            return None;
+        }
+        let elements = popped
+            .into_iter()
+            .map(|(member, score)| {
+                RespArray::new([BulkString::from(member).into(), RespFrame::Double(score)]).into()
+            })
+            .collect::<Vec<RespFrame>>();
+        Some(
+            RespArray::new([
+                BulkString::from(key).into(),
+                RespArray::new(elements).into(),
+            ])
+            .into(),
+        )
+    })
+}
+
+/// Adds the member of a [`pop_serve`] reply back with its score.
+fn pop_restore() -> RestoreFn {
+    Box::new(|backend, key, reply| {
+        if let RespFrame::Array(RespArray(mut reply)) = reply {
+            let members = member_score(reply.split_off(1)).into_iter().collect();
+            backend.zset_restore(key, members);
+        }
+    })
+}
+
+fn mpop_restore() -> RestoreFn {
+    Box::new(|backend, key, reply| {
+        if let RespFrame::Array(RespArray(mut reply)) = reply {
+            if let Some(RespFrame::Array(RespArray(elements))) = reply.pop() {
+                let members = elements
+                    .into_iter()
+                    .filter_map(|element| match element {
+                        RespFrame::Array(RespArray(pair)) => member_score(pair),
+                        _ => None,
+                    })
+                    .collect();
+                backend.zset_restore(key, members);
+            }
+        }
+    })
+}
+
+/// Reads a `[member, score]` pair back from a reply.
+fn member_score(pair: Vec<RespFrame>) -> Option<(String, f64)> {
+    match <[RespFrame; 2]>::try_from(pair).ok()? {
+        [RespFrame::BulkString(BulkString(member)), RespFrame::Double(score)] => {
+            Some((String::from_utf8(member).ok()?, score))
+        }
+        _ => None,
+    }
+}
+
+fn remove_range(backend: &Backend, key: &str, query: RangeQuery, event: &str) -> RespFrame {
+    let removed = match backend.zset.get_mut(key) {
+        Some(mut zset) => {
+            let elements = query.collect(&zset);
+            for (member, _) in &elements {
+                zset.remove(member);
+            }
+            elements.len()
+        }
+        None => 0,
+    };
+    if removed > 0 {
+        backend.notify(NOTIFY_ZSET, event, key);
+    }
+    backend.zset_remove_if_empty(key);
+    RespFrame::Integer(removed as i64)
+}
+
+fn rank_reply(
+    backend: &Backend,
+    key: &str,
+    member: &str,
+    rev: bool,
+    with_score: bool,
+) -> RespFrame {
+    let Some(zset) = backend.zset.get(key) else {
+        return RespFrame::Null(RespNull);
+    };
+    let (Some(rank), Some(score)) = (zset.rank(member), zset.score(member)) else {
+        return RespFrame::Null(RespNull);
+    };
+    let rank = if rev { zset.len() - 1 - rank } else { rank };
+    if with_score {
+        RespArray::new([RespFrame::Integer(rank as i64), RespFrame::Double(score)]).into()
+    } else {
+        RespFrame::Integer(rank as i64)
+    }
+}
+
+fn score_reply(score: Option<f64>) -> RespFrame {
+    score
+        .map(RespFrame::Double)
+        .unwrap_or(RespFrame::Null(RespNull))
+}
+
+/// Replies with the members, each followed by its score as a double when `with_scores` is set.
+pub(crate) fn elements_reply(elements: Vec<(String, f64)>, with_scores: bool) -> RespFrame {
+    let mut ret = Vec::with_capacity(elements.len() * if with_scores { 2 } else { 1 });
+    for (member, score) in elements {
+        ret.push(BulkString::from(member).into());
+        if with_scores {
+            ret.push(RespFrame::Double(score));
+        }
+    }
+    RespArray::new(ret).into()
+}
+
+fn to_sorted_set(elements: impl IntoIterator<Item = (String, f64)>) -> SortedSet {
+    let mut zset = SortedSet::new();
+    for (member, score) in elements {
+        zset.insert(member, score);
+    }
+    zset
+}
+
+fn sorted_elements(zset: &SortedSet) -> Vec<(String, f64)> {
+    zset.iter().map(|(m, s)| (m.to_string(), s)).collect()
+}
+
+/// Parses a score such as `1.5`, `-inf` or `+inf`.
+pub(crate) fn parse_score(arg: Option<RespFrame>) -> Result<f64, CommandError> {
+    let score: f64 = extract_string(arg)?
+        .parse()
+        .map_err(|_| CommandError::InvalidArgument("value is not a valid float".to_string()))?;
+    if score.is_nan() {
+        return Err(CommandError::InvalidArgument(
+            "value is not a valid float".to_string(),
+        ));
+    }
+    Ok(score)
+}
+
+/// Parses a ZRANGEBYSCORE style bound, where a `(` prefix makes it exclusive.
+pub(crate) fn parse_score_bound(arg: Option<RespFrame>) -> Result<ScoreBound, CommandError> {
+    let arg = extract_string(arg)?;
+    let invalid = || CommandError::InvalidArgument("min or max is not a float".to_string());
+    let (exclusive, score) = match arg.strip_prefix('(') {
+        Some(score) => (true, score),
+        None => (false, arg.as_str()),
+    };
+    let score: f64 = score.parse().map_err(|_| invalid())?;
+    if score.is_nan() {
+        return Err(invalid());
+    }
+    Ok(if exclusive {
+        ScoreBound::Exclusive(score)
+    } else {
+        ScoreBound::Inclusive(score)
+    })
+}
+
+fn parse_lex_bound(arg: Option<RespFrame>) -> Result<LexBound, CommandError> {
+    let arg = extract_string(arg)?;
+    match arg.as_str() {
+        "-" => Ok(LexBound::Min),
+        "+" => Ok(LexBound::Max),
+        _ => match (arg.strip_prefix('['), arg.strip_prefix('(')) {
+            (Some(member), _) => Ok(LexBound::Inclusive(member.to_string())),
+            (_, Some(member)) => Ok(LexBound::Exclusive(member.to_string())),
+            _ => Err(CommandError::InvalidArgument(
+                "min or max not valid string range item".to_string(),
+            )),
+        },
+    }
+}
+
+fn extract_option(arg: RespFrame) -> Result<String, CommandError> {
+    Ok(extract_string(Some(arg))?.to_ascii_uppercase())
+}
+
+fn parse_key(value: RespArray, name: &'static str) -> Result<String, CommandError> {
+    validate_command(&value, &[name], 1)?;
+    extract_string(extract_args(value, 1)?.into_iter().next())
+}
+
+fn parse_key_members(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(String, Vec<String>), CommandError> {
+    validate_command_at_least(&value, &[name], 2)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    Ok((key, extract_strings(args)?))
+}
+
+fn parse_rank(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(String, String, bool), CommandError> {
+    validate_command_at_least(&value, &[name], 2)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    let member = extract_string(args.next())?;
+    let with_score = match args.next() {
+        Some(arg) if extract_option(arg.clone())? == "WITHSCORE" => true,
+        Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        None => false,
+    };
+    if args.next().is_some() {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+    Ok((key, member, with_score))
+}
+
+fn parse_pop(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(String, Option<usize>), CommandError> {
+    validate_command_at_least(&value, &[name], 1)?;
+    if value.len() > 3 {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have at most 2 argument",
+            name
+        )));
+    }
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    let count = args
+        .next()
+        .map(|arg| extract_count(Some(arg)))
+        .transpose()?;
+    Ok((key, count))
+}
+
+fn parse_blocking_pop(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<String>, Option<Duration>), CommandError> {
+    validate_command_at_least(&value, &[name], 2)?;
+    let mut args = extract_args(value, 1)?;
+    let timeout = extract_timeout(args.pop())?;
+    Ok((extract_strings(args.into_iter())?, timeout))
+}
+
+/// Parses the `numkeys key [key ...] MIN|MAX [COUNT count]` tail shared by ZMPOP and BZMPOP.
+fn parse_mpop_args(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<(Vec<String>, bool, usize), CommandError> {
+    let num_keys = extract_count(args.next())?;
+    if num_keys == 0 {
+        return Err(CommandError::InvalidArgument(
+            "numkeys should be greater than 0".to_string(),
+        ));
+    }
+    let keys = (0..num_keys)
+        .map(|_| extract_string(args.next()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let max = match args.next().map(extract_option).transpose()?.as_deref() {
+        Some("MIN") => false,
+        Some("MAX") => true,
+        _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+    };
+    let count = match args.next() {
+        Some(option) => match extract_option(option)?.as_str() {
+            "COUNT" => match extract_count(args.next())? {
+                0 => {
+                    return Err(CommandError::InvalidArgument(
+                        "count should be greater than 0".to_string(),
+                    ))
+                }
+                count => count,
+            },
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        },
+        None => 1,
+    };
+    if args.next().is_some() {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+    Ok((keys, max, count))
+}
+
+/// Parses `start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count]` plus, when allowed,
+/// `WITHSCORES`.
+fn parse_range_query(
+    args: &mut impl Iterator<Item = RespFrame>,
+    allow_with_scores: bool,
+) -> Result<(RangeQuery, bool), CommandError> {
+    let (start, stop) = match (args.next(), args.next()) {
+        (Some(start), Some(stop)) => (start, stop),
+        _ => {
+            return Err(CommandError::InvalidArgument(
+                "missing start or stop".to_string(),
+            ))
+        }
+    };
+    let (mut by_score, mut by_lex, mut rev, mut with_scores) = (false, false, false, false);
+    let mut limit = None;
+    while let Some(arg) = args.next() {
+        match extract_option(arg)?.as_str() {
+            "BYSCORE" => by_score = true,
+            "BYLEX" => by_lex = true,
+            "REV" => rev = true,
+            "WITHSCORES" if allow_with_scores => with_scores = true,
+            "LIMIT" => {
+                let offset = extract_int(args.next())?;
+                let count = extract_int(args.next())?;
+                limit = Some((offset, count));
+            }
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+    if by_score && by_lex {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+    if with_scores && by_lex {
+        return Err(CommandError::InvalidArgument(
+            "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+        ));
+    }
+
+    // with REV the range is given from the highest to the lowest bound
+    let (min, max) = if rev && (by_score || by_lex) {
+        (Some(stop), Some(start))
+    } else {
+        (Some(start), Some(stop))
+    };
+    let by = if by_score {
+        RangeBy::Score(parse_score_bound(min)?, parse_score_bound(max)?)
+    } else if by_lex {
+        RangeBy::Lex(parse_lex_bound(min)?, parse_lex_bound(max)?)
+    } else {
+        if limit.is_some() {
+            return Err(CommandError::InvalidArgument(
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                    .to_string(),
+            ));
+        }
+        RangeBy::Rank(extract_int(min)?, extract_int(max)?)
+    };
+
+    let mut query = RangeQuery::new(by);
+    query.rev = rev;
+    if let Some((offset, count)) = limit {
+        // a negative offset selects nothing, a negative count everything after the offset
+        query.offset = usize::try_from(offset).unwrap_or(usize::MAX);
+        query.count = usize::try_from(count).ok();
+    }
+    Ok((query, with_scores))
+}
+
+/// Parses `numkeys key [key ...] [WEIGHTS weight ...] [AGGREGATE SUM|MIN|MAX]` plus, when
+/// allowed, `WITHSCORES`. ZDIFF only takes the keys.
+fn parse_set_op(
+    args: &mut impl Iterator<Item = RespFrame>,
+    allow_weights: bool,
+    allow_with_scores: bool,
+) -> Result<(ZSetOp, bool), CommandError> {
+    let num_keys = extract_count(args.next())?;
+    if num_keys == 0 {
+        return Err(CommandError::InvalidArgument(
+            "at least 1 input key is needed".to_string(),
+        ));
+    }
+    let keys = (0..num_keys)
+        .map(|_| extract_string(args.next()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut op = ZSetOp {
+        weights: vec![1.0; keys.len()],
+        keys,
+        aggregate: Aggregate::Sum,
+    };
+    let mut with_scores = false;
+    while let Some(arg) = args.next() {
+        match extract_option(arg)?.as_str() {
+            "WEIGHTS" if allow_weights => {
+                for weight in op.weights.iter_mut() {
+                    *weight = parse_score(args.next()).map_err(|_| {
+                        CommandError::InvalidArgument("weight value is not a float".to_string())
+                    })?;
+                }
+            }
+            "AGGREGATE" if allow_weights => {
+                op.aggregate = match args.next().map(extract_option).transpose()?.as_deref() {
+                    Some("SUM") => Aggregate::Sum,
+                    Some("MIN") => Aggregate::Min,
+                    Some("MAX") => Aggregate::Max,
+                    _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+                }
+            }
+            "WITHSCORES" if allow_with_scores => with_scores = true,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+    Ok((op, with_scores))
+}
+
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zadd"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let mut flags = ZAddFlags::default();
+        while let Some(RespFrame::BulkString(arg)) = args.peek() {
+            match arg.to_ascii_uppercase().as_slice() {
+                b"NX" => flags.nx = true,
+                b"XX" => flags.xx = true,
+                b"GT" => flags.gt = true,
+                b"LT" => flags.lt = true,
+                b"CH" => flags.ch = true,
+                b"INCR" => flags.incr = true,
+                _ => break,
+            }
+            args.next();
+        }
+        if flags.nx && flags.xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if (flags.gt && flags.lt) || (flags.nx && (flags.gt || flags.lt)) {
+            return Err(CommandError::InvalidArgument(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+            ));
+        }
+
+        let rest: Vec<RespFrame> = args.collect();
+        if rest.is_empty() || !rest.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        if flags.incr && rest.len() != 2 {
+            return Err(CommandError::InvalidArgument(
+                "INCR option supports a single increment-element pair".to_string(),
+            ));
+        }
+        let mut rest = rest.into_iter();
+        let mut pairs = Vec::new();
+        while let Some(score) = rest.next() {
+            pairs.push((parse_score(Some(score))?, extract_string(rest.next())?));
+        }
+        Ok(ZAdd { key, flags, pairs })
+    }
+}
+
+impl TryFrom<RespArray> for ZIncrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zincrby"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZIncrBy {
+            key: extract_string(args.next())?,
+            increment: parse_score(args.next())?,
+            member: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(value, "zrem")?;
+        Ok(ZRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZScore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zscore"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZScore {
+            key: extract_string(args.next())?,
+            member: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZMScore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(value, "zmscore")?;
+        Ok(ZMScore { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZRank {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, member, with_score) = parse_rank(value, "zrank")?;
+        Ok(ZRank {
+            key,
+            member,
+            with_score,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRevRank {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, member, with_score) = parse_rank(value, "zrevrank")?;
+        Ok(ZRevRank {
+            key,
+            member,
+            with_score,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(ZCard {
+            key: parse_key(value, "zcard")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zcount"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZCount {
+            key: extract_string(args.next())?,
+            min: parse_score_bound(args.next())?,
+            max: parse_score_bound(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZLexCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zlexcount"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZLexCount {
+            key: extract_string(args.next())?,
+            min: parse_lex_bound(args.next())?,
+            max: parse_lex_bound(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zrange"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let (query, with_scores) = parse_range_query(&mut args, true)?;
+        Ok(ZRange {
+            key,
+            query,
+            with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRangeStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zrangestore"], 4)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let dst = extract_string(args.next())?;
+        let src = extract_string(args.next())?;
+        let (query, _) = parse_range_query(&mut args, false)?;
+        Ok(ZRangeStore { dst, src, query })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRangeByRank {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zremrangebyrank"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZRemRangeByRank {
+            key: extract_string(args.next())?,
+            start: extract_int(args.next())?,
+            stop: extract_int(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRangeByScore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zremrangebyscore"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZRemRangeByScore {
+            key: extract_string(args.next())?,
+            min: parse_score_bound(args.next())?,
+            max: parse_score_bound(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRangeByLex {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zremrangebylex"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZRemRangeByLex {
+            key: extract_string(args.next())?,
+            min: parse_lex_bound(args.next())?,
+            max: parse_lex_bound(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMin {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_pop(value, "zpopmin")?;
+        Ok(ZPopMin { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMax {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_pop(value, "zpopmax")?;
+        Ok(ZPopMax { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for ZMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zmpop"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (keys, max, count) = parse_mpop_args(&mut args)?;
+        Ok(ZMPop { keys, max, count })
+    }
+}
+
+impl TryFrom<RespArray> for BZPopMin {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = parse_blocking_pop(value, "bzpopmin")?;
+        Ok(BZPopMin { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BZPopMax {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = parse_blocking_pop(value, "bzpopmax")?;
+        Ok(BZPopMax { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BZMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["bzmpop"], 4)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let timeout = extract_timeout(args.next())?;
+        let (keys, max, count) = parse_mpop_args(&mut args)?;
+        Ok(BZMPop {
+            keys,
+            max,
+            count,
+            timeout,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRandMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zrandmember"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let count = args
+            .next()
+            .map(|arg| extract_random_count(Some(arg)))
+            .transpose()?;
+        let with_scores = match args.next() {
+            Some(arg) if extract_option(arg.clone())? == "WITHSCORES" => true,
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            None => false,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(ZRandMember {
+            key,
+            count,
+            with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZUnion {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zunion"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (op, with_scores) = parse_set_op(&mut args, true, true)?;
+        Ok(ZUnion { op, with_scores })
+    }
+}
+
+impl TryFrom<RespArray> for ZInter {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zinter"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (op, with_scores) = parse_set_op(&mut args, true, true)?;
+        Ok(ZInter { op, with_scores })
+    }
+}
+
+impl TryFrom<RespArray> for ZDiff {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zdiff"], 2)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (op, with_scores) = parse_set_op(&mut args, false, true)?;
+        Ok(ZDiff { op, with_scores })
+    }
+}
+
+impl TryFrom<RespArray> for ZUnionStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zunionstore"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let dst = extract_string(args.next())?;
+        let (op, _) = parse_set_op(&mut args, true, false)?;
+        Ok(ZUnionStore { dst, op })
+    }
+}
+
+impl TryFrom<RespArray> for ZInterStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zinterstore"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let dst = extract_string(args.next())?;
+        let (op, _) = parse_set_op(&mut args, true, false)?;
+        Ok(ZInterStore { dst, op })
+    }
+}
+
+impl TryFrom<RespArray> for ZDiffStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zdiffstore"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let dst = extract_string(args.next())?;
+        let (op, _) = parse_set_op(&mut args, false, false)?;
+        Ok(ZDiffStore { dst, op })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::decode::RespDecode;
+
+    use super::*;
+
+    fn decode(input: &[u8]) -> Result<RespArray> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(input);
+        Ok(RespArray::decode(&mut buf)?)
+    }
+
+    fn with_scores(elements: &[(&str, f64)]) -> RespFrame {
+        elements_reply(
+            elements.iter().map(|(m, s)| (m.to_string(), *s)).collect(),
+            true,
+        )
+    }
+
+    fn leaderboard(backend: &Backend) -> Result<()> {
+        let cmd: ZAdd = decode(
+            b"*8\r\n$4\r\nzadd\r\n$5\r\nboard\r\n$1\r\n1\r\n$1\r\na\r\n$1\r\n2\r\n$1\r\nb\r\n$4\r\n+inf\r\n$1\r\nc\r\n",
+        )?
+        .try_into()?;
+        assert_eq!(cmd.execute(backend)?, RespFrame::Integer(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zadd_flags_command() -> Result<()> {
+        let backend = Backend::new();
+        leaderboard(&backend)?;
+
+        let cmd: ZAdd = decode(
+            b"*8\r\n$4\r\nzadd\r\n$5\r\nboard\r\n$2\r\ngt\r\n$2\r\nch\r\n$1\r\n0\r\n$1\r\na\r\n$1\r\n5\r\n$1\r\nb\r\n",
+        )?
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+
+        let cmd: ZAdd = decode(
+            b"*6\r\n$4\r\nzadd\r\n$5\r\nboard\r\n$2\r\nxx\r\n$4\r\nincr\r\n$3\r\n1.5\r\n$1\r\nb\r\n",
+        )?
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Double(6.5));
+
+        let cmd: ZAdd = decode(
+            b"*6\r\n$4\r\nzadd\r\n$5\r\nboard\r\n$2\r\nxx\r\n$4\r\nincr\r\n$1\r\n1\r\n$1\r\nz\r\n",
+        )?
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Null(RespNull));
+
+        let cmd = ZRank {
+            key: "board".to_string(),
+            member: "b".to_string(),
+            with_score: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([RespFrame::Integer(1), RespFrame::Double(6.5)]).into()
+        );
+        let cmd = ZRevRank {
+            key: "board".to_string(),
+            member: "c".to_string(),
+            with_score: false,
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_command() -> Result<()> {
+        let backend = Backend::new();
+        leaderboard(&backend)?;
+
+        let cmd: ZRange = decode(
+            b"*5\r\n$6\r\nzrange\r\n$5\r\nboard\r\n$1\r\n0\r\n$2\r\n-1\r\n$10\r\nwithscores\r\n",
+        )?
+        .try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            with_scores(&[("a", 1.0), ("b", 2.0), ("c", f64::INFINITY)])
+        );
+
+        let cmd: ZRange = decode(
+            b"*10\r\n$6\r\nzrange\r\n$5\r\nboard\r\n$4\r\n+inf\r\n$2\r\n(1\r\n$7\r\nbyscore\r\n$3\r\nrev\r\n$5\r\nlimit\r\n$1\r\n1\r\n$1\r\n5\r\n$10\r\nwithscores\r\n",
+        )?
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend)?, with_scores(&[("b", 2.0)]));
+
+        let cmd: ZRange =
+            decode(b"*5\r\n$6\r\nzrange\r\n$5\r\nboard\r\n$1\r\n0\r\n$1\r\n0\r\n$3\r\nrev\r\n")?
+                .try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([BulkString::new("c").into()]).into()
+        );
+
+        let cmd = ZCount {
+            key: "board".to_string(),
+            min: ScoreBound::Exclusive(1.0),
+            max: ScoreBound::Inclusive(f64::INFINITY),
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zremrange_zpop_command() -> Result<()> {
+        let backend = Backend::new();
+        leaderboard(&backend)?;
+
+        let cmd = ZPopMax {
+            key: "board".to_string(),
+            count: None,
+        };
+        assert_eq!(cmd.execute(&backend)?, with_scores(&[("c", f64::INFINITY)]));
+
+        let cmd = ZRemRangeByScore {
+            key: "board".to_string(),
+            min: ScoreBound::Inclusive(f64::NEG_INFINITY),
+            max: ScoreBound::Exclusive(2.0),
+        };
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(1));
+
+        let cmd = ZPopMin {
+            key: "board".to_string(),
+            count: Some(10),
+        };
+        assert_eq!(cmd.execute(&backend)?, with_scores(&[("b", 2.0)]));
+        assert!(!backend.zset.contains_key("board"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bzmpop_from_resp_array() -> Result<()> {
+        let cmd: BZMPop = decode(
+            b"*8\r\n$6\r\nbzmpop\r\n$1\r\n1\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$3\r\nmax\r\n$5\r\ncount\r\n$1\r\n3\r\n",
+        )?
+        .try_into()?;
+        assert_eq!(cmd.keys, vec!["a".to_string(), "b".to_string()]);
+        assert!(cmd.max);
+        assert_eq!(cmd.count, 3);
+        assert_eq!(cmd.timeout, Some(Duration::from_secs(1)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrandmember_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: ZAdd =
+            decode(b"*4\r\n$4\r\nzadd\r\n$1\r\nz\r\n$1\r\n1\r\n$1\r\na\r\n")?.try_into()?;
+        cmd.execute(&backend)?;
+
+        let cmd: ZRandMember =
+            decode(b"*3\r\n$11\r\nzrandmember\r\n$1\r\nz\r\n$2\r\n-3\r\n")?.try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new(vec![BulkString::new("a").into(); 3]).into()
+        );
+        let cmd: ZRandMember =
+            decode(b"*3\r\n$11\r\nzrandmember\r\n$1\r\nz\r\n$19\r\n9223372036854775807\r\n")?
+                .try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new(vec![BulkString::new("a").into()]).into()
+        );
+        let err = ZRandMember::try_from(decode(
+            b"*3\r\n$11\r\nzrandmember\r\n$1\r\nz\r\n$20\r\n-9223372036854775808\r\n",
+        )?)
+        .unwrap_err();
+        assert!(err.to_string().contains("value is out of range"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zmpop_count_zero() -> Result<()> {
+        let err = ZMPop::try_from(decode(
+            b"*6\r\n$5\r\nzmpop\r\n$1\r\n1\r\n$1\r\na\r\n$3\r\nmin\r\n$5\r\ncount\r\n$1\r\n0\r\n",
+        )?)
+        .unwrap_err();
+        assert!(err.to_string().contains("count should be greater than 0"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bzpopmin_wakes_on_zadd() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = BZPopMin {
+            keys: vec!["jobs".to_string()],
+            timeout: Some(Duration::from_secs(5)),
+        };
+        let handle = tokio::spawn({
+            let backend = backend.clone();
+            async move { cmd.block(&backend).await }
+        });
+        while backend.blocked_clients() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let cmd: ZAdd = decode(
+            b"*6\r\n$4\r\nzadd\r\n$4\r\njobs\r\n$1\r\n5\r\n$1\r\nb\r\n$1\r\n1\r\n$1\r\na\r\n",
+        )?
+        .try_into()?;
+        cmd.execute(&backend)?;
+        assert_eq!(
+            handle.await??,
+            RespArray::new([
+                BulkString::new("jobs").into(),
+                BulkString::new("a").into(),
+                RespFrame::Double(1.0),
+            ])
+            .into()
+        );
+        assert_eq!(backend.zset_len("jobs"), 1);
+
+        let cmd = BZPopMax {
+            keys: vec!["empty".to_string()],
+            timeout: Some(Duration::from_millis(10)),
+        };
+        assert_eq!(cmd.block(&backend).await?, RespNullArray.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zunion_zinter_command() -> Result<()> {
+        let backend = Backend::new();
+        leaderboard(&backend)?;
+        backend.set_add("plain", vec!["a".to_string(), "d".to_string()]);
+
+        let cmd: ZInter = decode(
+            b"*8\r\n$6\r\nzinter\r\n$1\r\n2\r\n$5\r\nboard\r\n$5\r\nplain\r\n$7\r\nweights\r\n$1\r\n2\r\n$1\r\n3\r\n$10\r\nwithscores\r\n",
+        )?
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend)?, with_scores(&[("a", 5.0)]));
+
+        let cmd: ZUnionStore = decode(
+            b"*7\r\n$11\r\nzunionstore\r\n$3\r\ndst\r\n$1\r\n2\r\n$5\r\nboard\r\n$5\r\nplain\r\n$9\r\naggregate\r\n$3\r\nmax\r\n",
+        )?
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend)?, RespFrame::Integer(4));
+        assert_eq!(backend.zset.get("dst").unwrap().score("a"), Some(1.0));
+
+        let cmd: ZDiff = decode(b"*4\r\n$5\r\nzdiff\r\n$1\r\n2\r\n$5\r\nboard\r\n$5\r\nplain\r\n")?
+            .try_into()?;
+        assert_eq!(
+            cmd.execute(&backend)?,
+            RespArray::new([BulkString::new("b").into(), BulkString::new("c").into()]).into()
+        );
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ use tokio::net::TcpListener;
 use tracing::{info, warn};
 
 use simple_redis::backend::Backend;
+use simple_redis::cmd::{load_functions, load_plugins};
 use simple_redis::network;
 
 #[tokio::main]
@@ -15,6 +16,18 @@ async fn main() -> Result<()> {
 
     let listener = TcpListener::bind(addr).await?;
     let backend = Backend::new();
+    // plugins are loaded from the directory in SIMPLE_REDIS_PLUGIN_DIR, if set
+    if let Some(dir) = std::env::var_os("SIMPLE_REDIS_PLUGIN_DIR") {
+        for name in load_plugins(&backend, dir)? {
+            info!("loaded plugin {}", name);
+        }
+    }
+    // function libraries are kept in the file in SIMPLE_REDIS_FUNCTION_FILE, if set
+    if let Some(path) = std::env::var_os("SIMPLE_REDIS_FUNCTION_FILE") {
+        let count = load_functions(&backend, path)?;
+        info!("loaded {} function libraries", count);
+    }
+    tokio::spawn(backend.clone().hmap_active_expire());
     loop {
         let (stream, remote_addr) = listener.accept().await?;
         info!("accepted connection from {}", remote_addr);
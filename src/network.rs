@@ -6,10 +6,10 @@ use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
 use tracing::info;
 
-use crate::backend::Backend;
-use crate::cmd::{Command, CommandExecutor};
+use crate::backend::{Backend, Subscriber, Watcher};
+use crate::cmd::{is_blocking, Command, Transaction, SUBSCRIBED_COMMANDS};
 use crate::decode::RespDecode;
-use crate::frame::RespFrame;
+use crate::frame::{RespFrame, RespProtocol};
 use crate::resp::encode::RespEncode;
 use crate::simple_string::SimpleString;
 use crate::RespError;
@@ -25,28 +25,74 @@ pub struct RedisRequest {
 
 #[derive(Debug)]
 pub struct RedisResponse {
-    frame: RespFrame,
+    /// a reply per channel for the (un)subscribe commands, a single one otherwise
+    frames: Vec<RespFrame>,
+    /// set by QUIT, the connection closes once the frames are sent
+    close: bool,
+}
+
+/// Per connection state that outlives a single request.
+#[derive(Debug)]
+struct Session {
+    protocol: RespProtocol,
+    subscriber: Subscriber,
+    /// set between MULTI and EXEC or DISCARD
+    transaction: Option<Transaction>,
+    watcher: Watcher,
 }
 
 pub async fn stream_handler(tcp_stream: TcpStream, backend: Backend) -> Result<()> {
     // how to get a frame from the tcp stream?
 
     let mut framed = Framed::new(tcp_stream, RespFrameCodec {});
+    let (subscriber, mut messages) = Subscriber::new(&backend);
+    let mut session = Session {
+        protocol: RespProtocol::default(),
+        subscriber,
+        transaction: None,
+        watcher: Watcher::new(&backend),
+    };
     loop {
-        match framed.next().await {
+        // published messages go out between replies, never in the middle of one
+        let next = tokio::select! {
+            next = framed.next() => next,
+            Some(event) = messages.recv() => {
+                let frame = session.subscriber.receive(event);
+                framed.send(frame.into_protocol(session.protocol)).await?;
+                continue;
+            }
+        };
+        match next {
             Some(Ok(frame)) => {
                 info!("received frame: {:?}", frame);
+                let blocking = is_blocking(&frame);
                 let request = RedisRequest {
                     frame,
                     backend: backend.clone(),
                 };
-                // call request_handler with the frame
-                let response = request_handler(request).await?;
+                // call request_handler with the frame. A blocked client may hang up meanwhile,
+                // any other command is answered even if the client stopped writing
+                let response = if blocking {
+                    tokio::select! {
+                        biased;
+                        response = request_handler(request, &mut session) => response?,
+                        _ = wait_for_disconnect(framed.get_ref()) => {
+                            framed.flush().await?;
+                            return Ok(());
+                        }
+                    }
+                } else {
+                    request_handler(request, &mut session).await?
+                };
                 info!("sending response: {:?}", response);
                 // send the response back to the tcp stream
 
-                framed.send(response.frame).await?;
-                // send the response back to the tcp stream
+                for frame in response.frames {
+                    framed.send(frame.into_protocol(session.protocol)).await?;
+                }
+                if response.close {
+                    return Ok(());
+                }
             }
             Some(Err(e)) => framed.send(SimpleString::new(e.to_string()).into()).await?,
             None => return Ok(()),
@@ -54,21 +100,146 @@ pub async fn stream_handler(tcp_stream: TcpStream, backend: Backend) -> Result<(
     }
 }
 
-async fn request_handler(requset: RedisRequest) -> Result<RedisResponse> {
+async fn request_handler(requset: RedisRequest, session: &mut Session) -> Result<RedisResponse> {
     let (frame, backend) = (requset.frame, requset.backend);
-    match Command::try_from(frame) {
-        Ok(cmd) => {
-            info!("Executing command: {:?}", cmd);
-            match cmd.execute(&backend) {
-                Ok(frame) => Ok(RedisResponse { frame }),
-                Err(e) => Ok(RedisResponse {
-                    frame: SimpleString::new(e.to_string()).into(),
-                }),
+    if let Some(name) = restricted_command(&frame, session) {
+        return Ok(RedisResponse::error(format!(
+            "Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+            name
+        )));
+    }
+    let cmd = Command::try_from(frame);
+    // QUIT and RESET act on the connection right away, even in a transaction
+    match cmd {
+        Ok(Command::Quit(_)) => {
+            return Ok(RedisResponse {
+                frames: vec![SimpleString::new("OK").into()],
+                close: true,
+            })
+        }
+        Ok(Command::Reset(_)) => {
+            session.reset();
+            return Ok(RedisResponse::new(SimpleString::new("RESET").into()));
+        }
+        _ => {}
+    }
+    // between MULTI and EXEC commands are queued instead of executed
+    if let Some(mut transaction) = session.transaction.take() {
+        let reply = match cmd {
+            Ok(Command::Exec(_)) => transaction.exec(&backend, &mut session.watcher).await,
+            Ok(Command::Discard(_)) => {
+                session.watcher.unwatch();
+                Ok(SimpleString::new("OK").into())
             }
+            cmd => {
+                let reply = transaction.queue(cmd);
+                session.transaction = Some(transaction);
+                reply
+            }
+        };
+        return match reply {
+            Ok(frame) => Ok(RedisResponse::new(frame)),
+            Err(e) => Ok(RedisResponse::error(e.to_string())),
+        };
+    }
+    match cmd {
+        Ok(mut cmd) => {
+            if let Command::Hello(hello) = &mut cmd {
+                session.protocol = hello.negotiate(session.protocol);
+            }
+            info!("Executing command: {:?}", cmd);
+            let subscriber = &mut session.subscriber;
+            let frames = match cmd {
+                Command::Subscribe(cmd) => cmd.apply(subscriber),
+                Command::Unsubscribe(cmd) => cmd.apply(subscriber),
+                Command::PSubscribe(cmd) => cmd.apply(subscriber),
+                Command::PUnsubscribe(cmd) => cmd.apply(subscriber),
+                Command::SSubscribe(cmd) => match cmd.check_slot(&backend) {
+                    Ok(()) => cmd.apply(subscriber),
+                    Err(e) => return Ok(RedisResponse::error(e.to_string())),
+                },
+                Command::SUnsubscribe(cmd) => cmd.apply(subscriber),
+                Command::Multi(_) => {
+                    session.transaction = Some(Transaction::default());
+                    vec![SimpleString::new("OK").into()]
+                }
+                Command::Watch(cmd) => {
+                    cmd.apply(&mut session.watcher);
+                    vec![SimpleString::new("OK").into()]
+                }
+                Command::Unwatch(_) => {
+                    session.watcher.unwatch();
+                    vec![SimpleString::new("OK").into()]
+                }
+                Command::Exec(_) => return Ok(RedisResponse::error("EXEC without MULTI".into())),
+                Command::Discard(_) => {
+                    return Ok(RedisResponse::error("DISCARD without MULTI".into()))
+                }
+                // a subscribed RESP2 connection only receives arrays
+                Command::Ping(cmd)
+                    if session.protocol == RespProtocol::Resp2 && subscriber.is_subscribed() =>
+                {
+                    vec![cmd.subscribed_reply()]
+                }
+                cmd => match cmd.execute_blocking(&backend).await {
+                    Ok(frame) => vec![frame],
+                    Err(e) => return Ok(RedisResponse::error(e.to_string())),
+                },
+            };
+            Ok(RedisResponse {
+                frames,
+                close: false,
+            })
         }
-        Err(e) => Ok(RedisResponse {
-            frame: SimpleString::new(e.to_string()).into(),
-        }),
+        Err(e) => Ok(RedisResponse::error(e.to_string())),
+    }
+}
+
+/// RESP2 has no push type, so a subscribed RESP2 connection only carries messages and may only
+/// send the commands that manage its subscriptions. Returns the name of a command that isn't
+/// allowed.
+fn restricted_command(frame: &RespFrame, session: &Session) -> Option<String> {
+    if session.protocol != RespProtocol::Resp2 || !session.subscriber.is_subscribed() {
+        return None;
+    }
+    let RespFrame::Array(array) = frame else {
+        return None;
+    };
+    let Some(RespFrame::BulkString(name)) = array.first() else {
+        return None;
+    };
+    let name = String::from_utf8_lossy(name.as_ref()).to_ascii_lowercase();
+    (!SUBSCRIBED_COMMANDS.contains(&name.as_str())).then_some(name)
+}
+
+impl Session {
+    fn reset(&mut self) {
+        self.protocol = RespProtocol::default();
+        self.subscriber.unsubscribe_all();
+        self.transaction = None;
+        self.watcher.unwatch();
+    }
+}
+
+impl RedisResponse {
+    fn new(frame: RespFrame) -> Self {
+        Self {
+            frames: vec![frame],
+            close: false,
+        }
+    }
+
+    fn error(message: String) -> Self {
+        Self::new(SimpleString::new(message).into())
+    }
+}
+
+/// Resolves once the peer closed the connection. Pipelined requests are left in the socket.
+async fn wait_for_disconnect(stream: &TcpStream) {
+    let mut buf = [0u8; 1];
+    match stream.peek(&mut buf).await {
+        Ok(0) | Err(_) => {}
+        Ok(_) => std::future::pending().await,
     }
 }
 
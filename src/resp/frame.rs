@@ -7,6 +7,7 @@ use crate::decode::RespDecode;
 use crate::encode::RespEncode;
 use crate::map::RespMap;
 use crate::null::RespNull;
+use crate::push::RespPush;
 use crate::set::RespSet;
 use crate::simple_error::SimpleError;
 use crate::simple_string::SimpleString;
@@ -27,6 +28,7 @@ pub enum RespFrame {
     Double(f64),
     Map(RespMap),
     Set(RespSet),
+    Push(RespPush),
 }
 
 impl RespDecode for RespFrame {
@@ -83,6 +85,10 @@ impl RespDecode for RespFrame {
                 let frame = RespSet::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'>') => {
+                let frame = RespPush::decode(buf)?;
+                Ok(frame.into())
+            }
             None => Err(RespError::NotComplete),
             _ => Err(RespError::InvalidFrameType(format!(
                 "expect_length: unknown frame type : {:?}",
@@ -97,6 +103,7 @@ impl RespDecode for RespFrame {
             Some(b'*') => RespArray::expect_length(buf),
             Some(b'~') => RespSet::expect_length(buf),
             Some(b'%') => RespMap::expect_length(buf),
+            Some(b'>') => RespPush::expect_length(buf),
             Some(b'$') => BulkString::expect_length(buf),
             Some(b':') => i64::expect_length(buf),
             Some(b'+') => SimpleString::expect_length(buf),
@@ -125,3 +132,124 @@ impl<const N: usize> From<&[u8; N]> for RespFrame {
         BulkString(s.to_vec()).into()
     }
 }
+
+/// The protocol a connection negotiated with `HELLO`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RespProtocol {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+impl RespFrame {
+    /// Rewrites the frame for `protocol`: RESP2 clients can't parse the RESP3 only types, so
+    /// sets, maps and pushes become arrays, doubles bulk strings, booleans integers and nulls null
+    /// bulk strings. RESP3 clients get a single null type.
+    pub fn into_protocol(self, protocol: RespProtocol) -> RespFrame {
+        match (self, protocol) {
+            (RespFrame::Array(array), _) => RespArray::new(
+                array
+                    .0
+                    .into_iter()
+                    .map(|v| v.into_protocol(protocol))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            (RespFrame::Set(set), RespProtocol::Resp2) => RespArray::new(
+                set.0
+                    .into_iter()
+                    .map(|v| v.into_protocol(protocol))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            (RespFrame::Set(set), RespProtocol::Resp3) => RespSet::new(
+                set.0
+                    .into_iter()
+                    .map(|v| v.into_protocol(protocol))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            (RespFrame::Push(push), RespProtocol::Resp2) => RespArray::new(
+                push.0
+                    .into_iter()
+                    .map(|v| v.into_protocol(protocol))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            (RespFrame::Push(push), RespProtocol::Resp3) => RespPush::new(
+                push.0
+                    .into_iter()
+                    .map(|v| v.into_protocol(protocol))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            (RespFrame::Map(map), RespProtocol::Resp2) => RespArray::new(
+                map.0
+                    .into_iter()
+                    .flat_map(|(k, v)| [BulkString::from(k).into(), v.into_protocol(protocol)])
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            (RespFrame::Map(map), RespProtocol::Resp3) => {
+                let mut ret = RespMap::new();
+                for (k, v) in map.0 {
+                    ret.insert(k, v.into_protocol(protocol));
+                }
+                ret.into()
+            }
+            (RespFrame::Double(f), RespProtocol::Resp2) => {
+                BulkString::from(format_double(f)).into()
+            }
+            (RespFrame::Boolean(b), RespProtocol::Resp2) => RespFrame::Integer(b as i64),
+            (RespFrame::Null(_), RespProtocol::Resp2) => RespNullBulkString.into(),
+            (RespFrame::NullArray(_) | RespFrame::NullBulkString(_), RespProtocol::Resp3) => {
+                RespNull.into()
+            }
+            (frame, _) => frame,
+        }
+    }
+}
+
+/// Formats a double the way redis prints scores, e.g. `1.5`, `10` or `inf`.
+pub fn format_double(f: f64) -> String {
+    if f.is_infinite() {
+        return if f > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    f.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_into_resp2() {
+        let mut map = RespMap::new();
+        map.insert("score".to_string(), 1.5.into());
+        map.insert("flag".to_string(), true.into());
+        let frame: RespFrame = RespSet::new([map.into(), RespNull.into()]).into();
+        assert_eq!(
+            frame.into_protocol(RespProtocol::Resp2),
+            RespArray::new([
+                RespArray::new([
+                    BulkString::new("flag").into(),
+                    RespFrame::Integer(1),
+                    BulkString::new("score").into(),
+                    BulkString::new("1.5").into(),
+                ])
+                .into(),
+                RespNullBulkString.into(),
+            ])
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_frame_into_resp3() {
+        let frame: RespFrame = RespArray::new([RespNullArray.into(), 1.5.into()]).into();
+        assert_eq!(
+            frame.into_protocol(RespProtocol::Resp3),
+            RespArray::new([RespNull.into(), 1.5.into()]).into()
+        );
+    }
+}
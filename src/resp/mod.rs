@@ -17,6 +17,7 @@ pub mod frame;
 pub mod integer;
 pub mod map;
 pub mod null;
+pub mod push;
 pub mod set;
 pub mod simple_error;
 pub mod simple_string;
@@ -104,7 +105,7 @@ pub(crate) fn calc_total_length(
     let mut total = end + CRLF_LEN;
     let mut data = &buf[total..];
     match prefix {
-        "*" | "~" => {
+        "*" | "~" | ">" => {
             for _ in 0..len {
                 let len = RespFrame::expect_length(data)?;
                 data = &data[len..];
@@ -0,0 +1,99 @@
+use std::ops::Deref;
+
+use bytes::{Buf, BytesMut};
+
+use crate::resp::decode::{RespDecode, CRLF_LEN};
+use crate::resp::encode::{RespEncode, BUF_CAP};
+use crate::resp::frame::RespFrame;
+use crate::resp::{calc_total_length, parse_length};
+use crate::RespError;
+
+/// Out of band data the server sends to RESP3 clients, e.g. pub/sub messages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RespPush(pub(crate) Vec<RespFrame>);
+
+impl RespPush {
+    pub fn new(v: impl Into<Vec<RespFrame>>) -> Self {
+        RespPush(v.into())
+    }
+}
+
+impl Deref for RespPush {
+    type Target = Vec<RespFrame>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// - push: "><length>\r\n"
+impl RespEncode for RespPush {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(BUF_CAP);
+        buf.extend_from_slice(&format!(">{}\r\n", self.len()).into_bytes());
+        for frame in &self.0 {
+            buf.extend_from_slice(&frame.encode());
+        }
+        buf
+    }
+}
+
+impl RespDecode for RespPush {
+    const PREFIX: &'static str = ">";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
+        if buf.len() < total_len {
+            return Err(RespError::NotComplete);
+        }
+        buf.advance(end + CRLF_LEN);
+
+        let mut frames = Vec::with_capacity(len);
+        for _ in 0..len {
+            frames.push(RespFrame::decode(buf)?);
+        }
+        Ok(RespPush::new(frames))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        calc_total_length(buf, end, len, Self::PREFIX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bulk_string::BulkString;
+
+    use super::*;
+
+    #[test]
+    fn test_push_encode() {
+        let frame: RespFrame = RespPush::new([
+            BulkString::new("message").into(),
+            BulkString::new("news").into(),
+            BulkString::new("hi").into(),
+        ])
+        .into();
+        assert_eq!(
+            frame.encode(),
+            b">3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n"
+        );
+    }
+
+    #[test]
+    fn test_push_decode() -> anyhow::Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b">2\r\n$9\r\nsubscribe\r\n");
+        let ret = RespFrame::decode(&mut buf);
+        assert_eq!(ret.unwrap_err(), RespError::NotComplete);
+
+        buf.extend_from_slice(b":1\r\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            RespPush::new([b"subscribe".into(), RespFrame::Integer(1)]).into()
+        );
+        Ok(())
+    }
+}